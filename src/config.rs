@@ -1,39 +1,124 @@
+mod migrations;
+
 use anyhow::{anyhow, Context, Result};
+use log::warn;
+pub use migrations::SCHEMA_VERSION;
 use serde::{Deserialize, Serialize};
 use serde_yaml;
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 /// All the configuration options for the vault wrapped in a single struct
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Config {
+    /// The version of the config, so files written for an older cahlter can be upgraded. Files
+    /// without one are from before versions
+    #[serde(default)]
+    pub schema_version: u32,
     pub general: General,
     pub appearance: Appearance,
+    #[serde(default)]
+    pub markdown: Markdown,
+    #[serde(default)]
+    pub review: Review,
+    #[serde(default)]
+    pub assets: Assets,
+    #[serde(default)]
+    pub code_blocks: CodeBlocks,
+    #[serde(default)]
+    pub search: Search,
+    #[serde(default)]
+    pub reading_time: ReadingTime,
+    #[serde(default)]
+    pub contributors: Contributors,
+    #[serde(default)]
+    pub feed: Feed,
+    #[serde(default)]
+    pub robots: Robots,
+    #[serde(default)]
+    pub social_cards: SocialCards,
+    #[serde(default)]
+    pub exercises: Exercises,
+    #[serde(default)]
+    pub summary: Summary,
+    #[serde(default)]
+    pub footer: Footer,
+    #[serde(default)]
+    pub private: Private,
+    #[serde(default)]
+    pub print: Print,
+    /// Pages that moved, from their old path to the new one, both relative to the build dir (e.g.
+    /// `setup.html: guide/setup.html`). The new one may also be an absolute url. The build writes
+    /// a page at each old path that sends readers on, and adds the chapters it notices moved.
+    #[serde(default)]
+    pub redirects: BTreeMap<String, String>,
     pub links: Vec<Link>,
     pub languages: Vec<Language>,
 }
 
 impl Config {
-    /// Read a config file from disk and parse it
+    /// Read a config file from disk and parse it. Files written for an older cahlter are upgraded
+    /// on the fly, but left as they are.
     pub fn from_disk<P>(path: P) -> Result<Config>
     where
         P: AsRef<Path>,
     {
-        let file =
-            std::fs::read_to_string(path).with_context(|| "Failed to read the config file.")?;
+        let (config, version) = read_migrated(&path)?;
+
+        if version < SCHEMA_VERSION {
+            warn!(
+                "{} is at version {version} of the config. Run cahlter migrate to upgrade it",
+                path.as_ref().display()
+            );
+        }
+
+        serde_yaml::from_value(config).with_context(|| "Failed to parse the config file")
+    }
+
+    /// Upgrades a config file written for an older cahlter and returns the version it was at, or
+    /// `None` if it was already current. Options the file leaves out stay that way.
+    pub fn migrate_file<P>(path: P) -> Result<Option<u32>>
+    where
+        P: AsRef<Path>,
+    {
+        let (config, version) = read_migrated(&path)?;
+
+        if version == SCHEMA_VERSION {
+            return Ok(None);
+        }
 
-        let config: Config =
-            serde_yaml::from_str(&file).with_context(|| "Failed to parse the config file")?;
+        // Anything the current version can't read shouldn't be written
+        serde_yaml::from_value::<Config>(config.clone())
+            .with_context(|| "Failed to parse the config file")?;
+        let serialized = serde_yaml::to_string(&config)
+            .with_context(|| anyhow!("Failed to serialize the config file"))?;
+        std::fs::write(path, serialized)
+            .with_context(|| anyhow!("Failed to write the config file"))?;
 
-        Ok(config)
+        Ok(Some(version))
     }
 
     /// Update the config with the values from another config
     pub fn update(&mut self, other: Config) {
         self.general = other.general;
         self.appearance = other.appearance;
+        self.markdown = other.markdown;
+        self.review = other.review;
+        self.assets = other.assets;
+        self.code_blocks = other.code_blocks;
+        self.search = other.search;
+        self.reading_time = other.reading_time;
+        self.contributors = other.contributors;
+        self.feed = other.feed;
+        self.redirects = other.redirects;
         self.links = other.links;
         self.languages = other.languages;
+        self.summary = other.summary;
+        self.footer = other.footer;
+        self.private = other.private;
+        self.print = other.print;
     }
 
     /// Saves the config in the given path
@@ -51,6 +136,19 @@ impl Config {
     }
 }
 
+/// The config file upgraded to the current version, and the version it was at
+pub(crate) fn read_migrated<P>(path: P) -> Result<(serde_yaml::Value, u32)>
+where
+    P: AsRef<Path>,
+{
+    let file = std::fs::read_to_string(path).with_context(|| "Failed to read the config file.")?;
+    let mut config: serde_yaml::Value =
+        serde_yaml::from_str(&file).with_context(|| "Failed to parse the config file")?;
+    let version = migrations::migrate(&mut config)?;
+
+    Ok((config, version))
+}
+
 impl Default for Config {
     fn default() -> Config {
         let general = General {
@@ -63,22 +161,49 @@ impl Default for Config {
             src_dir: PathBuf::from("src"),
             build_dir: PathBuf::from("build"),
             use_default: true,
+            site_url: None,
+            base_path: String::new(),
+            language: default_language(),
         };
 
         let appearance = Appearance {
             custom: vec![],
             default_theme: String::from("gruvbox"),
             themes: vec!["gruvbox".to_string(), "catppuccin".to_string()],
+            metadata_banner: false,
+            highlight_theme: default_highlight_theme(),
+            accents: BTreeMap::new(),
+            favicon: None,
+            favicon_variants: false,
+            page_toc: default_page_toc(),
+            last_updated: false,
+            light_variants: default_light_variants(),
+            head_include: None,
         };
 
-        let config = Config {
+        Config {
+            schema_version: SCHEMA_VERSION,
             general,
             appearance,
+            markdown: Markdown::default(),
+            review: Review::default(),
+            assets: Assets::default(),
+            code_blocks: CodeBlocks::default(),
+            search: Search::default(),
+            reading_time: ReadingTime::default(),
+            contributors: Contributors::default(),
+            feed: Feed::default(),
+            robots: Robots::default(),
+            social_cards: SocialCards::default(),
+            exercises: Exercises::default(),
+            summary: Summary::default(),
+            footer: Footer::default(),
+            private: Private::default(),
+            print: Print::default(),
+            redirects: BTreeMap::new(),
             links: vec![],
             languages: vec![],
-        };
-
-        config
+        }
     }
 }
 
@@ -101,20 +226,546 @@ pub struct General {
     pub use_default: bool,
     pub build_dir: PathBuf,
     pub src_dir: PathBuf,
+    /// Where the site is published (e.g. https://example.com/docs). The sitemap.xml, robots.txt
+    /// and canonical urls are only written when it's set, since search engines need absolute urls.
+    #[serde(default)]
+    pub site_url: Option<String>,
+    /// Where the site is served from, when it isn't the root of the domain (e.g. /docs). Every
+    /// url in the pages starts with it
+    #[serde(default)]
+    pub base_path: String,
+    /// The language the pages are written in, as a code (e.g. `en`, `pt-br`). Each of the
+    /// `languages` is written in the one of its dir (see [`Language::code`])
+    #[serde(default = "default_language")]
+    pub language: String,
 }
 
 /// Appearance options for the generated site
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Appearance {
-    /// Paths to custom CSS files
+    /// Paths to custom CSS and JS files, relative to the vault. Globs (e.g. `css/*.css`) stand
+    /// for every file they match, in alphabetical order
     pub custom: Vec<String>,
-    /// The theme that should be used by default
+    /// The theme that should be used by default. Either a built in one or one in the themes dir
     pub default_theme: String,
-    /// All available themes
+    /// The built in themes readers can pick. The ones in the themes dir are always there
     pub themes: Vec<String>,
+    /// Should the front matter metadata (status, owner and review date) be shown above the content?
+    #[serde(default)]
+    pub metadata_banner: bool,
+    /// The colors used for the code blocks (gruvbox or catppuccin). Custom css can add more
+    #[serde(default = "default_highlight_theme")]
+    pub highlight_theme: String,
+    /// Colors for sections, by their title (e.g. `Reference: "#d65d0e"`). They mark the section in
+    /// the sidebar and the header of its chapters
+    #[serde(default)]
+    pub accents: BTreeMap<String, String>,
+    /// The icon of the browser tabs, relative to the vault (e.g. `assets/icon.png`)
+    #[serde(default)]
+    pub favicon: Option<PathBuf>,
+    /// Should the sizes browsers and phones usually ask for be drawn from the favicon? It has to
+    /// be a PNG or an SVG
+    #[serde(default)]
+    pub favicon_variants: bool,
+    /// Should the sections of each page be listed next to its content?
+    #[serde(default = "default_page_toc")]
+    pub page_toc: bool,
+    /// Should the footer say when each chapter was last updated? It's the date of its last
+    /// commit or, outside of a git repository, the last time its file was modified
+    #[serde(default)]
+    pub last_updated: bool,
+    /// The light variant of each theme that has one (e.g. `gruvbox: gruvbox-light`). Themes are
+    /// dark, and readers who prefer light ones get the variant instead
+    #[serde(default = "default_light_variants")]
+    pub light_variants: BTreeMap<String, String>,
+    /// A file, relative to the vault, whose html goes in the `<head>` of every page as it is (e.g.
+    /// web fonts, verification tags or analytics)
+    #[serde(default)]
+    pub head_include: Option<PathBuf>,
+}
+
+fn default_page_toc() -> bool {
+    true
+}
+
+fn default_light_variants() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        (String::from("gruvbox"), String::from("gruvbox-light")),
+        (String::from("catppuccin"), String::from("catppuccin-latte")),
+    ])
+}
+
+fn default_language() -> String {
+    String::from("en")
+}
+
+fn default_highlight_theme() -> String {
+    String::from("gruvbox")
+}
+
+/// Options for how the markdown files are turned into HTML
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Markdown {
+    /// How many levels the headings should be demoted (e.g. 1 turns H1 into H2). Useful when
+    /// the page title should be the only H1
+    pub heading_offset: u8,
+    /// Should the chapter title be added as a heading when the content doesn't start with one?
+    pub inject_title: bool,
+    /// Syntax that goes beyond CommonMark
+    pub extensions: Extensions,
+    /// Should using a variable that isn't in the variables file fail the build? Otherwise it's
+    /// left as it is
+    pub strict_variables: bool,
+    /// Should a `requires` of the front matter that doesn't point to exactly one chapter fail the
+    /// build? Otherwise it's left out of the page
+    pub strict_requires: bool,
+    /// How the text of the headings is turned into their ids
+    pub slug: Slug,
+    /// The passes run over the markdown of every chapter, in this order. Leaving one out turns it
+    /// off (e.g. without `highlight`, code blocks stay plain)
+    pub filters: Vec<Filter>,
+}
+
+impl Default for Markdown {
+    fn default() -> Markdown {
+        Markdown {
+            heading_offset: 0,
+            inject_title: false,
+            extensions: Extensions::default(),
+            strict_variables: false,
+            strict_requires: false,
+            slug: Slug::default(),
+            filters: Filter::ALL.to_vec(),
+        }
+    }
+}
+
+/// The passes the renderer has built in
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    /// Block quotes with an attribution or marked as pull quotes become figures
+    Quotes,
+    /// `{{name}}` is replaced with the value of the variable
+    Variables,
+    /// `{{#name arguments}}` is replaced with what the shortcode renders to
+    Shortcodes,
+    /// Code blocks are highlighted, or turned into diagrams and quizzes
+    Highlight,
+    /// Headings go down by `heading_offset` levels
+    DemoteHeadings,
+    /// The title is added in front of chapters that don't start with a heading, if
+    /// `inject_title` is on
+    InjectTitle,
+    /// Relative links point to the pages their chapters become
+    Links,
+    /// Images ending with `#light` or `#dark` only show with themes of that kind
+    ThemedImages,
+}
+
+impl Filter {
+    /// Every filter, in the order they run by default
+    pub const ALL: [Filter; 8] = [
+        Filter::Quotes,
+        Filter::Variables,
+        Filter::Shortcodes,
+        Filter::Highlight,
+        Filter::DemoteHeadings,
+        Filter::InjectTitle,
+        Filter::Links,
+        Filter::ThemedImages,
+    ];
+}
+
+/// How the ids of the headings are made from their text
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Slug {
+    /// Letters of any script are kept (e.g. `café-日本`)
+    #[default]
+    Unicode,
+    /// Accents are dropped and anything else that isn't ASCII is left out (e.g. `cafe`)
+    Ascii,
+}
+
+/// Markdown extensions that can be turned on and off. The GitHub flavored ones are on by default
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Extensions {
+    /// Tables (e.g. `| a | b |`)
+    pub tables: bool,
+    /// References to notes at the end of the page (e.g. `[^1]`)
+    pub footnotes: bool,
+    /// Crossed out text (e.g. `~~gone~~`)
+    pub strikethrough: bool,
+    /// Checkboxes in lists (e.g. `- [x] done`)
+    pub task_lists: bool,
+    /// Turns straight quotes into curly ones and -- into dashes
+    pub smart_punctuation: bool,
+}
+
+impl Default for Extensions {
+    fn default() -> Extensions {
+        Extensions {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            task_lists: true,
+            smart_punctuation: false,
+        }
+    }
+}
+
+/// Options for keeping the content up to date
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Review {
+    /// How many days can pass after a chapter's `reviewed` date before it's overdue
+    pub max_age: u32,
+    /// Should the pages of overdue chapters carry an "outdated" badge? Chapters that were never
+    /// reviewed don't, since there's no telling how old they are
+    pub outdated_badge: bool,
+}
+
+impl Default for Review {
+    fn default() -> Review {
+        Review {
+            max_age: 180,
+            outdated_badge: false,
+        }
+    }
+}
+
+/// Options for the files other than chapters (images, attachments, etc.) found in the src dir
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Assets {
+    /// Extensions of the files that should be copied (e.g. png, pdf). If empty, every file that
+    /// isn't markdown is copied
+    pub extensions: Vec<String>,
+    /// Files that shouldn't be copied, relative to the src dir. `*` matches anything (e.g. drafts/*)
+    pub ignore: Vec<String>,
+    /// Should the assets be symlinked instead of copied? Saves time and space with large files
+    pub symlink: bool,
+    /// Should the assets be hard linked when the src and build dir are in the same filesystem?
+    /// Almost as cheap as symlinks, but the build dir can still be moved around. Off by default,
+    /// since anything that edits the linked files in the build dir edits the src dir too
+    pub hard_links: bool,
+}
+
+/// Extras added around the code blocks
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct CodeBlocks {
+    /// Should there be a button that copies the code to the clipboard?
+    pub copy_button: bool,
+    /// Should the lines be numbered?
+    pub line_numbers: bool,
+    /// Should code blocks with files pulled by `{{#include path}}` link to a page with the whole
+    /// file?
+    pub source_view: bool,
+}
+
+impl Default for CodeBlocks {
+    fn default() -> CodeBlocks {
+        CodeBlocks {
+            copy_button: true,
+            line_numbers: false,
+            source_view: false,
+        }
+    }
+}
+
+/// Options for the search box in the header
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Search {
+    /// Should the build write a search index and the pages show the search box?
+    pub enabled: bool,
+    /// Words left out of the index, since almost every chapter has them
+    pub stop_words: Vec<String>,
+    /// How many characters of a chapter are shown under its title in the results
+    pub max_excerpt_length: usize,
+}
+
+impl Default for Search {
+    fn default() -> Search {
+        let stop_words = [
+            "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it", "of",
+            "on", "or", "that", "the", "this", "to", "with",
+        ];
+
+        Search {
+            enabled: true,
+            stop_words: stop_words.iter().map(|word| word.to_string()).collect(),
+            max_excerpt_length: 160,
+        }
+    }
+}
+
+/// Options for the estimate of how long each chapter takes to read
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct ReadingTime {
+    /// Should the pages show how many words the chapter has and how long it takes to read?
+    pub enabled: bool,
+    /// How many words are read in a minute
+    pub words_per_minute: usize,
+}
+
+impl Default for ReadingTime {
+    fn default() -> ReadingTime {
+        ReadingTime {
+            enabled: false,
+            words_per_minute: 200,
+        }
+    }
+}
+
+/// Options for the list of people who wrote each chapter, taken from its git history
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Contributors {
+    /// Should the bottom of each page list who committed to its chapter?
+    pub enabled: bool,
+    /// Where the repository is hosted (e.g. https://github.com/owner/repo). On GitHub and GitLab,
+    /// contributors get the avatars of their accounts
+    pub repository: Option<String>,
+}
+
+/// Options for the Atom feed of the chapters, for vaults used as notes or changelogs
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Feed {
+    /// Should the build write a feed.xml?
+    pub enabled: bool,
+    /// The title of the feed. The title of the vault is used if there is none
+    pub title: Option<String>,
+    /// How many of the newest chapters are in the feed
+    pub limit: usize,
+    /// Should the entries have the whole chapter instead of its description?
+    pub full_content: bool,
+}
+
+impl Default for Feed {
+    fn default() -> Feed {
+        Feed {
+            enabled: false,
+            title: None,
+            limit: 20,
+            full_content: false,
+        }
+    }
+}
+
+/// Options for the social cards, the images shown next to the links to a page
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct SocialCards {
+    /// Should the build draw a card for each chapter?
+    pub enabled: bool,
+    /// An SVG, relative to the vault, to draw the cards from instead of the default one.
+    /// `{{title}}` becomes the title of the chapter and `{{site}}` the title of the vault. The
+    /// font and colors below fill `{{font}}`, `{{background}}`, `{{foreground}}` and `{{accent}}`
+    pub template: Option<PathBuf>,
+    /// The font of the text (e.g. Inter). It has to be installed wherever the vault is built
+    pub font: Option<String>,
+    /// The colors of the card. Those left out follow the default theme
+    pub background: Option<String>,
+    pub foreground: Option<String>,
+    pub accent: Option<String>,
+    /// Where the cards are written, inside the build dir
+    pub dir: String,
+}
+
+impl Default for SocialCards {
+    fn default() -> SocialCards {
+        SocialCards {
+            enabled: false,
+            template: None,
+            font: None,
+            background: None,
+            foreground: None,
+            accent: None,
+            dir: String::from("social"),
+        }
+    }
+}
+
+/// Options for the `::: exercise` and `::: solution` blocks of the chapters
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Exercises {
+    pub solutions: Solutions,
+}
+
+/// Where the solutions to the exercises end up
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Solutions {
+    /// In a page of their own, linked from each exercise
+    #[default]
+    Appendix,
+    /// Nowhere, unless the vault is built with `--with-solutions`
+    Hidden,
+}
+
+/// How the chapters of the vault are found
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Summary {
+    pub strategy: Strategy,
+    /// The file the `single-file` strategy makes chapters of, relative to the source dir
+    pub single_file: Option<PathBuf>,
+}
+
+/// Where the summary comes from
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// The summary file, or the file tree if there is none
+    #[default]
+    Auto,
+    /// Only the summary file, which has to be there
+    File,
+    /// Only the file tree, even if there is a summary file
+    Tree,
+    /// The headings of `single_file`
+    SingleFile,
+    /// The summary file, followed by what the file tree finds that isn't in it
+    Merged,
+}
+
+/// What goes at the bottom of every page, besides the link to the sitemap
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Footer {
+    /// A line about who owns the content (e.g. `© 2024 Obi-Wan Kenobi`)
+    pub copyright: Option<String>,
+    /// Links shown next to the one to the sitemap
+    pub links: Vec<Link>,
+    /// A file with html that goes at the end of the footer, as it is, relative to the vault
+    pub html: Option<PathBuf>,
+}
+
+/// What kind of build it is. Only release builds are meant to be published
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    #[default]
+    Release,
+    /// Builds of work in progress, like the ones of `build --since` for pull requests
+    Preview,
+    /// Builds of `serve`
+    Dev,
+}
+
+impl FromStr for Profile {
+    type Err = anyhow::Error;
+
+    fn from_str(profile: &str) -> Result<Profile> {
+        match profile {
+            "release" => Ok(Profile::Release),
+            "preview" => Ok(Profile::Preview),
+            "dev" => Ok(Profile::Dev),
+            _ => anyhow::bail!("Unknown profile {profile}. Use release, preview or dev"),
+        }
+    }
+}
+
+/// Which profiles search engines may index, going by the robots.txt written next to the
+/// sitemap.xml. The others disallow everything, so previews don't end up in search results.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Robots {
+    pub release: bool,
+    pub preview: bool,
+    pub dev: bool,
+}
+
+impl Robots {
+    pub fn allows_indexing(&self, profile: Profile) -> bool {
+        match profile {
+            Profile::Release => self.release,
+            Profile::Preview => self.preview,
+            Profile::Dev => self.dev,
+        }
+    }
+}
+
+impl Default for Robots {
+    fn default() -> Robots {
+        Robots {
+            release: true,
+            preview: false,
+            dev: false,
+        }
+    }
 }
 
-/// Holds a link that should be displayed in the header
+/// What happens to the chapters marked `private: true` in each profile. The passphrase of the
+/// encrypted ones comes from an environment variable, so it never ends up in the config.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Private {
+    pub release: PrivateMode,
+    pub preview: PrivateMode,
+    pub dev: PrivateMode,
+    /// The environment variable with the passphrase (e.g. `CAHLTER_PASSPHRASE`)
+    pub passphrase_env: String,
+}
+
+impl Private {
+    pub fn mode(&self, profile: Profile) -> PrivateMode {
+        match profile {
+            Profile::Release => self.release,
+            Profile::Preview => self.preview,
+            Profile::Dev => self.dev,
+        }
+    }
+}
+
+impl Default for Private {
+    fn default() -> Private {
+        Private {
+            release: PrivateMode::Exclude,
+            preview: PrivateMode::Encrypt,
+            dev: PrivateMode::Plain,
+            passphrase_env: String::from("CAHLTER_PASSPHRASE"),
+        }
+    }
+}
+
+/// How private chapters are built
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivateMode {
+    /// Like any other chapter
+    Plain,
+    /// Their pages ask for the passphrase and decrypt themselves in the browser. Their text is
+    /// left out of the search index, the feed and the other formats
+    Encrypt,
+    /// Not at all, as if they weren't in the vault
+    Exclude,
+}
+
+/// Options for the page with every chapter in the order of the summary, for printing the vault
+/// like a book
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Print {
+    /// Should the build write a print.html and the sidebar link to it?
+    pub enabled: bool,
+}
+
+impl Default for Print {
+    fn default() -> Print {
+        Print { enabled: true }
+    }
+}
+
+/// Holds a link that should be displayed in the header or the footer
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Link {
     /// The link's name. If there is no icon available, this will be displayed as label
@@ -130,10 +781,22 @@ pub struct Link {
 pub struct Language {
     /// The language's name (e.g. English, pt-br, etc.)
     pub name: String,
-    /// Path to a directory containing the translated markdown files
+    /// Path to a directory containing the translated markdown files, relative to the vault. It's
+    /// laid over the source dir, so files that weren't translated yet come from there
     pub path: String,
 }
 
+impl Language {
+    /// Where the language is built inside the build dir. It's the name of its directory (e.g.
+    /// `translations/pt-br` is built into `build/pt-br`)
+    pub fn code(&self) -> String {
+        Path::new(&self.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -183,6 +846,47 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn it_should_upgrade_an_old_config_file() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let config_path = temp_dir.path().join("test_config.yml");
+        let mut config = serde_yaml::to_value(Config::default())?;
+        let mapping = config.as_mapping_mut().unwrap();
+
+        mapping.remove("schema_version");
+        mapping.remove("robots");
+        mapping["general"]
+            .as_mapping_mut()
+            .unwrap()
+            .insert("allow_indexing".into(), false.into());
+        std::fs::write(&config_path, serde_yaml::to_string(&config)?)?;
+
+        assert!(!Config::from_disk(&config_path)?.robots.release);
+        assert!(std::fs::read_to_string(&config_path)?.contains("allow_indexing"));
+
+        assert_eq!(Config::migrate_file(&config_path)?, Some(0));
+        let file = std::fs::read_to_string(&config_path)?;
+        assert!(!file.contains("allow_indexing"));
+        assert!(file.contains("schema_version: 1"));
+        assert_eq!(Config::migrate_file(&config_path)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_read_a_config_file_without_optional_sections() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let config_path = temp_dir.path().join("test_config.yml");
+        let mut config = serde_yaml::to_value(Config::default())?;
+
+        config.as_mapping_mut().unwrap().remove("markdown");
+        std::fs::write(&config_path, serde_yaml::to_string(&config)?)?;
+
+        assert_eq!(Config::from_disk(&config_path)?, Config::default());
+
+        Ok(())
+    }
+
     #[test]
     fn it_should_update_a_config() -> Result<()> {
         let mut config = Config::default();
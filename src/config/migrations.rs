@@ -0,0 +1,89 @@
+//! Upgrades config files written for older versions of cahlter. Each change to the config that
+//! would break the files out there gets a migration, and bumps [`SCHEMA_VERSION`].
+
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+
+/// The version of the config files this build of cahlter reads and writes
+pub const SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+// MIGRATIONS[i] takes a config from version i to i + 1. Files from before versions are at 0.
+const MIGRATIONS: [fn(&mut Mapping); SCHEMA_VERSION as usize] = [move_allow_indexing];
+
+/// Upgrades the config to [`SCHEMA_VERSION`] and returns the version it was at. Configs written
+/// by a newer cahlter are refused, since there's no telling what their options mean.
+pub fn migrate(config: &mut Value) -> Result<u32> {
+    let config = config
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow!("The config file should be a mapping"))?;
+    let version = match config.get(SCHEMA_VERSION_KEY) {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow!("Invalid {SCHEMA_VERSION_KEY} {version:?}"))?,
+    };
+
+    if version > SCHEMA_VERSION {
+        anyhow::bail!(
+            "The config is at version {version}, but this cahlter only knows up to \
+             {SCHEMA_VERSION}. Update cahlter to read it"
+        );
+    }
+
+    for migration in MIGRATIONS[version as usize..].iter() {
+        migration(config);
+    }
+    config.insert(Value::from(SCHEMA_VERSION_KEY), Value::from(SCHEMA_VERSION));
+
+    Ok(version)
+}
+
+// `general.allow_indexing` became `robots.release`, when robots.txt started to depend on the kind
+// of build
+fn move_allow_indexing(config: &mut Mapping) {
+    let Some(allow_indexing) = config
+        .get_mut("general")
+        .and_then(Value::as_mapping_mut)
+        .and_then(|general| general.remove("allow_indexing"))
+    else {
+        return;
+    };
+
+    let robots = config
+        .entry(Value::from("robots"))
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+
+    if let Some(robots) = robots.as_mapping_mut() {
+        robots
+            .entry(Value::from("release"))
+            .or_insert(allow_indexing);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_migrate_old_configs() -> Result<()> {
+        let mut config: Value =
+            serde_yaml::from_str("general:\n  title: Vault\n  allow_indexing: false\n")?;
+
+        assert_eq!(migrate(&mut config)?, 0);
+        assert_eq!(
+            config,
+            serde_yaml::from_str::<Value>(
+                "general:\n  title: Vault\nrobots:\n  release: false\nschema_version: 1\n"
+            )?
+        );
+        assert_eq!(migrate(&mut config)?, SCHEMA_VERSION);
+
+        let mut newer: Value = serde_yaml::from_str("schema_version: 99\n")?;
+        assert!(migrate(&mut newer).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+//! The few git commands cahlter needs. They run the `git` binary, so it has to be installed.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The version of git that's installed (e.g. `git version 2.43.0`). It fails if there's none.
+pub fn version() -> Result<String> {
+    run(Path::new("."), &["--version"])
+}
+
+/// Runs `git pull --ff-only` in the dir. Anything but a fast-forward needs a person to look at it,
+/// so it fails instead.
+pub fn pull<P>(dir: P) -> Result<String>
+where
+    P: AsRef<Path>,
+{
+    run(dir.as_ref(), &["pull", "--ff-only"])
+}
+
+/// Every file that changed since `git_ref`, relative to the dir and only inside it. Uncommitted
+/// and untracked files count too. The ref is never read as an option, even if it looks like one.
+pub fn changed_files<P>(dir: P, git_ref: &str) -> Result<Vec<PathBuf>>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let changed = run(
+        dir,
+        &[
+            "diff",
+            "--name-only",
+            "--relative",
+            "--end-of-options",
+            git_ref,
+            "--",
+        ],
+    )?;
+    let untracked = run(dir, &["ls-files", "--others", "--exclude-standard"])?;
+
+    Ok(changed
+        .lines()
+        .chain(untracked.lines())
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// When the file was last committed. It returns `None` if it never was.
+pub fn last_commit_date<P>(file: P) -> Result<Option<DateTime<FixedOffset>>>
+where
+    P: AsRef<Path>,
+{
+    let file = file.as_ref();
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let date = run(dir, &["log", "-1", "--format=%cI", "--", &name])?;
+
+    match date.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(DateTime::parse_from_rfc3339(&date).with_context(
+            || anyhow!("git gave an invalid date for {}: {date}", file.display()),
+        )?)),
+    }
+}
+
+/// When each file inside the dir was last committed, by its path relative to the dir. Files that
+/// were never committed are left out. A single `git log` finds them all, which is much faster
+/// than asking for each file in large vaults.
+pub fn last_commit_dates<P>(dir: P) -> Result<HashMap<PathBuf, DateTime<FixedOffset>>>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let mut dates = HashMap::new();
+
+    for (date, files) in log(dir, "%cI")? {
+        let date = DateTime::parse_from_rfc3339(&date)
+            .with_context(|| anyhow!("git gave an invalid date in {}: {date}", dir.display()))?;
+
+        // Newer commits come first, so the first date of a file is its last one
+        for file in files {
+            dates.entry(file).or_insert(date);
+        }
+    }
+
+    Ok(dates)
+}
+
+/// Someone who wrote a commit, as the `.mailmap` of the repository says
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Author {
+    pub name: String,
+    pub email: String,
+}
+
+/// Who committed to each file inside the dir, by its path relative to the dir. The ones with the
+/// most commits come first. Like [`last_commit_dates`], it's a single `git log`.
+pub fn authors<P>(dir: P) -> Result<HashMap<PathBuf, Vec<Author>>>
+where
+    P: AsRef<Path>,
+{
+    let mut commits: HashMap<PathBuf, Vec<(Author, usize)>> = HashMap::new();
+
+    for (author, files) in log(dir.as_ref(), "%aN%x09%aE")? {
+        let (name, email) = author.split_once('\t').unwrap_or((&author, ""));
+        let author = Author {
+            name: name.to_string(),
+            email: email.to_string(),
+        };
+
+        for file in files {
+            let authors = commits.entry(file).or_default();
+
+            match authors.iter_mut().find(|(known, _)| *known == author) {
+                Some((_, count)) => *count += 1,
+                None => authors.push((author.clone(), 1)),
+            }
+        }
+    }
+
+    Ok(commits
+        .into_iter()
+        .map(|(file, mut authors)| {
+            // Stable, so ties keep the one who committed last first
+            authors.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            (
+                file,
+                authors.into_iter().map(|(author, _)| author).collect(),
+            )
+        })
+        .collect())
+}
+
+// Every commit, newest first, as its `format` and the files it touched relative to the dir
+fn log(dir: &Path, format: &str) -> Result<Vec<(String, Vec<PathBuf>)>> {
+    // Each commit is its format, marked with a NUL, followed by the files it touched. Names that
+    // aren't ASCII would be quoted otherwise.
+    let output = run(
+        dir,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "log",
+            &format!("--format=%x00{format}"),
+            "--name-only",
+            "--relative",
+            "--",
+        ],
+    )?;
+    let mut commits: Vec<(String, Vec<PathBuf>)> = Vec::new();
+
+    for line in output.lines().filter(|line| !line.is_empty()) {
+        match (line.strip_prefix('\0'), commits.last_mut()) {
+            (Some(commit), _) => commits.push((commit.to_string(), Vec::new())),
+            (None, Some((_, files))) => files.push(PathBuf::from(line)),
+            (None, None) => {}
+        }
+    }
+
+    Ok(commits)
+}
+
+/// Where the dir is inside its repository (e.g. `docs/`), or an empty path if it's the root
+pub fn prefix<P>(dir: P) -> Result<PathBuf>
+where
+    P: AsRef<Path>,
+{
+    Ok(PathBuf::from(run(
+        dir.as_ref(),
+        &["rev-parse", "--show-prefix"],
+    )?))
+}
+
+fn run(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .with_context(|| anyhow!("Failed to run git in {}", dir.display()))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed in {}: {}",
+            args.join(" "),
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
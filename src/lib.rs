@@ -1,7 +1,13 @@
 pub mod config;
+pub mod git;
 pub mod renderer;
+pub mod rpc;
+pub mod static_files;
 pub mod summary;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod util;
 pub mod vault;
+pub mod webhook;
 
-pub use vault::content::{Chapter, Content, Item, Section};
+pub use vault::content::{Chapter, Content, FrontMatter, Item, Section};
@@ -1,23 +1,45 @@
-use anyhow::Result;
-use cahlter::vault::Vault;
-use clap::{Arg, ArgMatches, Command};
+use anyhow::{anyhow, Context, Result};
+use async_std::task;
+use cahlter::config::{Config, Profile, SCHEMA_VERSION};
+use cahlter::renderer::{self, LIVE_RELOAD_ENDPOINT};
+use cahlter::static_files::StaticFiles;
+use cahlter::vault::{
+    self, diff_pages, query_terms, BuildReport, PageDiff, Problem, Replacement, Severity, Vault,
+    WatchEvent, CONFIG_FILE,
+};
+use cahlter::webhook::{self, WEBHOOK_ENDPOINT};
+use cahlter::{git, rpc, util};
+use chrono::Local;
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use colored::Colorize;
 use human_panic::setup_panic;
 use is_terminal::IsTerminal;
-use log::{error, info, kv, warn, Level};
+use log::{error, info, kv, warn, Level, LevelFilter};
 use std::env;
+use std::fs::{self, File, OpenOptions};
 use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 const DEFAULT_PORT: &str = "8080";
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 fn setup_logging() {
+    // Requests are logged by `RequestLog`, so tide's own logs would only add noise
     if !std::io::stdout().is_terminal() {
-        env_logger::init();
+        env_logger::Builder::from_default_env()
+            .filter_module("tide::log", LevelFilter::Off)
+            .init();
         return;
     }
 
     let env = env_logger::Env::default().default_filter_or("info");
     env_logger::Builder::from_env(env)
+        .filter_module("tide::log", LevelFilter::Off)
         .format(|buf, record| match record.level() {
             Level::Error => writeln!(buf, "❌ {}: {}", "error".red().bold(), record.args(),),
             Level::Warn => writeln!(buf, "⚠️ {}: {}", "warn".yellow().bold(), record.args()),
@@ -27,8 +49,7 @@ fn setup_logging() {
                 record
                     .key_values()
                     .get(kv::Key::from("emoji"))
-                    .unwrap_or(kv::Value::from(""))
-                    .to_string(),
+                    .unwrap_or(kv::Value::from("")),
                 record.args()
             ),
             _ => writeln!(buf, "{} {}", record.level(), record.args()),
@@ -41,21 +62,270 @@ fn cli() -> Command {
         .about("A minimalistic static web site generator")
         .subcommand_required(true)
         .subcommand(Command::new("init").arg(Arg::new("vault_path").help("The vault's path")))
-        .subcommand(Command::new("build").arg(Arg::new("vault_path").help("The vault's path")))
+        .subcommand(
+            Command::new("build")
+                .arg(
+                    Arg::new("diff")
+                        .long("diff")
+                        .action(ArgAction::SetTrue)
+                        .help("Show how the pages changed since the previous build"),
+                )
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .value_name("GIT_REF")
+                        .help("Only render the chapters that changed since this git ref (e.g. origin/main)"),
+                )
+                .arg(
+                    Arg::new("with_solutions")
+                        .long("with-solutions")
+                        .action(ArgAction::SetTrue)
+                        .help("Show the solutions to the exercises, even if the config hides them"),
+                )
+                .arg(
+                    Arg::new("no_cache")
+                        .long("no-cache")
+                        .action(ArgAction::SetTrue)
+                        .help("Don't use or fill the cache of highlighted code"),
+                )
+                .arg(profile_arg().help(
+                    "What kind of build it is (release, or preview with --since). It decides what robots.txt allows",
+                ))
+                .arg(audience_arg())
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Look for missing chapters, conflicting outputs and broken links")
+                .arg(
+                    Arg::new("anchors_diff")
+                        .long("anchors-diff")
+                        .action(ArgAction::SetTrue)
+                        .help(
+                            "Also list the heading anchors that disappeared since the last build",
+                        ),
+                )
+                .arg(
+                    Arg::new("annotations")
+                        .long("annotations")
+                        .value_parser(["github"])
+                        .help("Print the problems as annotations for pull requests on this platform"),
+                )
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Look for anything wrong with the vault or what it needs, and how to fix it")
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export the vault to other formats")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("epub")
+                        .about("Write the chapters as an EPUB book, with the summary as its table of contents")
+                        .arg(
+                            Arg::new("output")
+                                .long("output")
+                                .short('o')
+                                .help("Where to write the book. By default, book.epub in the build dir"),
+                        )
+                        .arg(profile_arg().help(
+                            "What kind of build it is (release by default). It decides which private chapters go in the book",
+                        ))
+                        .arg(audience_arg())
+                        .arg(Arg::new("vault_path").help("The vault's path")),
+                )
+                .subcommand(
+                    Command::new("html")
+                        .about("Write the chapters, or one of them, as a single html file with everything it needs inside")
+                        .arg(
+                            Arg::new("chapter")
+                                .long("chapter")
+                                .help("The chapter to export, relative to the vault (e.g. src/intro.md). By default, every chapter"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .long("output")
+                                .short('o')
+                                .help("Where to write the file. By default, inside the vault"),
+                        )
+                        .arg(profile_arg().help(
+                            "What kind of build it is (release by default). It decides which private chapters go in the file",
+                        ))
+                        .arg(audience_arg())
+                        .arg(Arg::new("vault_path").help("The vault's path")),
+                ),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("Format the markdown of the chapters the same way")
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(ArgAction::SetTrue)
+                        .help("Fail if a chapter isn't formatted, without changing it"),
+                )
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Upgrade the config of the vault to the current version")
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("relink")
+                .about("Find the links to chapters that moved and point them to where they are now")
+                .arg(
+                    Arg::new("write")
+                        .long("write")
+                        .action(ArgAction::SetTrue)
+                        .help("Rewrite the links in the source files"),
+                )
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("replace")
+                .about("Replace text in every chapter, leaving code blocks and inline code alone")
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .required(true)
+                        .help("The text to look for"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .required(true)
+                        .help("What it's replaced by"),
+                )
+                .arg(
+                    Arg::new("dry_run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Only show how the chapters would change"),
+                )
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("report")
+                .about("List the chapters that are overdue for review")
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Look for chapters in the search index of the vault")
+                .arg(Arg::new("query").required(true).help("The words to look for"))
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("10")
+                        .help("How many chapters to show at most"),
+                )
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Count the chapters, words, images and links of the vault")
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                )
+                .arg(Arg::new("vault_path").help("The vault's path")),
+        )
+        .subcommand(
+            Command::new("summary")
+                .about("Work with the vault's summary file")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("generate")
+                        .about("Write a summary file based on the file tree")
+                        .arg(
+                            Arg::new("force")
+                                .long("force")
+                                .action(ArgAction::SetTrue)
+                                .help("Overwrite an existing summary"),
+                        )
+                        .arg(Arg::new("vault_path").help("The vault's path")),
+                ),
+        )
         .subcommand(
             Command::new("serve")
                 .arg(Arg::new("port").long("port"))
-                .arg(Arg::new("vault_path").help("The vault's path")),
+                .arg(
+                    Arg::new("live_reload")
+                        .long("live-reload")
+                        .action(ArgAction::SetTrue)
+                        .help("Refresh the browser after each rebuild. Meant for development only"),
+                )
+                .arg(
+                    Arg::new("access_log")
+                        .long("access-log")
+                        .value_name("FILE")
+                        .help("Append every request to this file"),
+                )
+                .arg(
+                    Arg::new("rpc").long("rpc").action(ArgAction::SetTrue).help(
+                        "Answer JSON-RPC requests (render, summary, diagnostics) for editors",
+                    ),
+                )
+                .arg(
+                    Arg::new("webhook_secret")
+                        .long("webhook-secret")
+                        .value_name("SECRET")
+                        .help(format!(
+                            "Rebuild when a POST to {WEBHOOK_ENDPOINT} proves it knows this secret, instead of watching the files"
+                        )),
+                )
+                .arg(
+                    Arg::new("git_pull")
+                        .long("git-pull")
+                        .action(ArgAction::SetTrue)
+                        .requires("webhook_secret")
+                        .help("Run git pull in the vault before each webhook rebuild"),
+                )
+                .arg(profile_arg().help(
+                    "What kind of build it is (dev by default). It decides what robots.txt allows",
+                ))
+                .arg(audience_arg())
+                .arg(
+                    Arg::new("mount")
+                        .long("mount")
+                        .num_args(1..)
+                        .action(ArgAction::Append)
+                        .help("Where each vault is served from (e.g. /a /b), in the same order. A single vault is served from general.base_path by default"),
+                )
+                .arg(
+                    Arg::new("vault_path")
+                        .num_args(1..)
+                        .help("The vault's path. Several vaults can be served at once"),
+                ),
         )
 }
 
+fn audience_arg() -> Arg {
+    Arg::new("audience")
+        .long("audience")
+        .help("Who the site is for (e.g. internal). Blocks marked with ::: only for other audiences are left out")
+}
+
+fn profile_arg() -> Arg {
+    Arg::new("profile")
+        .long("profile")
+        .value_parser(["release", "preview", "dev"])
+}
+
 #[async_std::main]
 async fn main() -> Result<()> {
     setup_logging();
     setup_panic!();
 
     if let Err(e) = run().await {
-        error!("{}", e);
+        error!("{:#}", e);
         std::process::exit(1);
     }
 
@@ -67,6 +337,24 @@ async fn run() -> Result<()> {
     match matches.subcommand() {
         Some(("init", submatches)) => init(submatches)?,
         Some(("build", submatches)) => build(submatches)?,
+        Some(("check", submatches)) => check(submatches)?,
+        Some(("doctor", submatches)) => doctor(submatches)?,
+        Some(("export", submatches)) => match submatches.subcommand() {
+            Some(("epub", submatches)) => export_epub(submatches)?,
+            Some(("html", submatches)) => export_html(submatches)?,
+            _ => unreachable!(),
+        },
+        Some(("fmt", submatches)) => fmt(submatches)?,
+        Some(("migrate", submatches)) => migrate(submatches)?,
+        Some(("relink", submatches)) => relink(submatches)?,
+        Some(("replace", submatches)) => replace(submatches)?,
+        Some(("report", submatches)) => report(submatches)?,
+        Some(("search", submatches)) => search(submatches)?,
+        Some(("stats", submatches)) => stats(submatches)?,
+        Some(("summary", submatches)) => match submatches.subcommand() {
+            Some(("generate", submatches)) => generate_summary(submatches)?,
+            _ => unreachable!(),
+        },
         Some(("serve", submatches)) => serve(submatches).await?,
         _ => unreachable!(),
     };
@@ -74,20 +362,29 @@ async fn run() -> Result<()> {
     Ok(())
 }
 
-fn init(matches: &ArgMatches) -> Result<()> {
-    info!(emoji= "⚙️"; "Preparing the vault...");
+/// Relative paths are resolved from the current dir
+fn vault_path(matches: &ArgMatches) -> PathBuf {
     let vault_path = matches
         .get_one::<String>("vault_path")
-        .and_then(|s| Some(s.as_str()))
+        .map(|s| s.as_str())
         .unwrap_or(".");
 
-    let mut vault = match vault_path.starts_with("/") {
-        true => Vault::new(vault_path),
+    resolve_path(vault_path)
+}
+
+fn resolve_path(vault_path: &str) -> PathBuf {
+    match vault_path.starts_with("/") {
+        true => PathBuf::from(vault_path),
         false => {
             let current_dir = env::current_dir().expect("Could not get the current dir");
-            Vault::new(current_dir.join(vault_path))
+            current_dir.join(vault_path)
         }
-    };
+    }
+}
+
+fn init(matches: &ArgMatches) -> Result<()> {
+    info!(emoji= "⚙️"; "Preparing the vault...");
+    let mut vault = Vault::new(vault_path(matches));
 
     info!(
         emoji="⚡"; "Initializing the vault at {}...",
@@ -103,41 +400,463 @@ fn init(matches: &ArgMatches) -> Result<()> {
 
 fn build(matches: &ArgMatches) -> Result<()> {
     info!(emoji = "💿"; "Reading the vault...");
-    let vault_path = matches
-        .get_one::<String>("vault_path")
-        .and_then(|s| Some(s.as_str()))
-        .unwrap_or(".");
+    let mut vault = Vault::from_disk(vault_path(matches))?;
+    vault.profile = match matches.get_one::<String>("profile") {
+        Some(profile) => profile.parse()?,
+        None if matches.contains_id("since") => Profile::Preview,
+        None => Profile::Release,
+    };
+    vault.with_solutions = matches.get_flag("with_solutions");
+    vault.no_cache = matches.get_flag("no_cache");
+    vault.audience = matches.get_one::<String>("audience").cloned();
 
-    let mut vault = match vault_path.starts_with("/") {
-        true => Vault::from_disk(vault_path)?,
-        false => {
-            let current_dir = env::current_dir().expect("Could not get the current dir");
-            Vault::from_disk(current_dir.join(vault_path))?
-        }
+    let before = match matches.get_flag("diff") {
+        true => Some(vault.built_pages()?),
+        false => None,
     };
 
     info!(emoji = "🏗️"; "Building...");
-    vault.build()?;
+    match matches.get_one::<String>("since") {
+        Some(git_ref) => {
+            let report = vault.build_since(git_ref)?;
+            info!(
+                emoji = "🌿";
+                "Rendered the {} chapter(s) that changed since {}, out of {}",
+                report.chapters - report.cached,
+                git_ref,
+                report.chapters
+            );
+        }
+        None => {
+            vault.build()?;
+        }
+    }
+
+    if let Some(before) = before {
+        print_page_diffs(&diff_pages(&before, &vault.built_pages()?));
+    }
 
     info!(emoji = "✅"; "Done");
     Ok(())
 }
 
-async fn serve(matches: &ArgMatches) -> Result<()> {
+fn print_page_diffs(diffs: &[PageDiff]) {
+    if diffs.is_empty() {
+        info!(emoji = "🟰"; "No page changed");
+        return;
+    }
+
+    for diff in diffs.iter() {
+        match diff {
+            PageDiff::Added(path) => println!("{} {}", "added".green().bold(), path.display()),
+            PageDiff::Removed(path) => println!("{} {}", "removed".red().bold(), path.display()),
+            PageDiff::Changed(path, lines) => {
+                println!("{} {}", "changed".yellow().bold(), path.display());
+
+                print_diff_lines(lines);
+            }
+        }
+    }
+}
+
+fn print_diff_lines(lines: &str) {
+    for line in lines.lines() {
+        match line.starts_with('-') {
+            true => println!("  {}", line.red()),
+            false => println!("  {}", line.green()),
+        }
+    }
+}
+
+fn check(matches: &ArgMatches) -> Result<()> {
     info!(emoji = "💿"; "Reading the vault...");
-    let vault_path = matches
-        .get_one::<String>("vault_path")
-        .and_then(|s| Some(s.as_str()))
-        .unwrap_or(".");
+    let vault = Vault::from_disk(vault_path(matches))?;
 
-    let vault = match vault_path.starts_with("/") {
-        true => Vault::from_disk(vault_path)?,
+    info!(emoji = "🔍"; "Checking...");
+    let mut problems = vault.check()?;
+
+    if matches.get_flag("anchors_diff") {
+        problems.extend(vault.check_anchors()?);
+    }
+
+    match matches.get_one::<String>("annotations").map(|s| s.as_str()) {
+        Some("github") => {
+            // Outside of a repository, the paths relative to the vault are the best guess
+            let prefix = git::prefix(&vault.path).unwrap_or_default();
+
+            for problem in problems.iter() {
+                println!("{}", problem.github_annotation(&prefix));
+            }
+        }
+        _ => {
+            for problem in problems.iter() {
+                error!("{}", problem);
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        anyhow::bail!("Found {} problem(s)", problems.len());
+    }
+
+    info!(emoji = "✅"; "No problems found");
+    Ok(())
+}
+
+fn doctor(matches: &ArgMatches) -> Result<()> {
+    info!(emoji = "🩺"; "Examining the vault...");
+    let diagnoses = vault::doctor(vault_path(matches));
+
+    for diagnosis in diagnoses.iter() {
+        match diagnosis.severity {
+            Severity::Error => error!("{}", diagnosis),
+            Severity::Warning => warn!("{}", diagnosis),
+        }
+    }
+
+    let errors = diagnoses
+        .iter()
+        .filter(|diagnosis| diagnosis.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        anyhow::bail!(
+            "Found {errors} error(s) and {} warning(s)",
+            diagnoses.len() - errors
+        );
+    }
+
+    match diagnoses.is_empty() {
+        true => info!(emoji = "✅"; "The vault is healthy"),
         false => {
-            let current_dir = env::current_dir().expect("Could not get the current dir");
-            Vault::from_disk(current_dir.join(vault_path))?
+            info!(emoji = "✅"; "Nothing that breaks the build, but {} warning(s)", diagnoses.len())
         }
+    }
+
+    Ok(())
+}
+
+fn export_epub(matches: &ArgMatches) -> Result<()> {
+    info!(emoji = "💿"; "Reading the vault...");
+    let mut vault = Vault::from_disk(vault_path(matches))?;
+    vault.profile = matches
+        .get_one::<String>("profile")
+        .map_or(Ok(Profile::Release), |profile| profile.parse())?;
+    vault.audience = matches.get_one::<String>("audience").cloned();
+    let output = matches
+        .get_one::<String>("output")
+        .map(|output| resolve_path(output));
+
+    info!(emoji = "📚"; "Exporting the book...");
+    let path = vault.export_epub(output.as_deref())?;
+
+    info!(emoji = "✅"; "Book written to {}", path.display());
+    Ok(())
+}
+
+fn export_html(matches: &ArgMatches) -> Result<()> {
+    info!(emoji = "💿"; "Reading the vault...");
+    let mut vault = Vault::from_disk(vault_path(matches))?;
+    vault.profile = matches
+        .get_one::<String>("profile")
+        .map_or(Ok(Profile::Release), |profile| profile.parse())?;
+    vault.audience = matches.get_one::<String>("audience").cloned();
+    let chapter = matches.get_one::<String>("chapter").map(PathBuf::from);
+    let output = matches
+        .get_one::<String>("output")
+        .map(|output| resolve_path(output));
+
+    info!(emoji = "📦"; "Exporting...");
+    let path = vault.export_html(chapter.as_deref(), output.as_deref())?;
+
+    info!(emoji = "✅"; "Page written to {}", path.display());
+    Ok(())
+}
+
+fn fmt(matches: &ArgMatches) -> Result<()> {
+    info!(emoji = "💿"; "Reading the vault...");
+    let vault = Vault::from_disk(vault_path(matches))?;
+
+    if matches.get_flag("check") {
+        let unformatted = vault.unformatted()?;
+
+        for path in unformatted.iter() {
+            error!("{} isn't formatted", path.display());
+        }
+
+        if !unformatted.is_empty() {
+            anyhow::bail!("Run cahlter fmt to format {} chapter(s)", unformatted.len());
+        }
+
+        info!(emoji = "✅"; "Every chapter is formatted");
+        return Ok(());
+    }
+
+    let formatted = vault.format()?;
+    for path in formatted.iter() {
+        println!("{}", path.display());
+    }
+
+    info!(emoji = "✅"; "Formatted {} chapter(s)", formatted.len());
+    Ok(())
+}
+
+fn migrate(matches: &ArgMatches) -> Result<()> {
+    let path = vault_path(matches).join(CONFIG_FILE);
+
+    match Config::migrate_file(&path)? {
+        Some(version) => info!(
+            emoji = "✅";
+            "Upgraded {} from version {version} to {SCHEMA_VERSION}",
+            path.display()
+        ),
+        None => info!(emoji = "🟰"; "{} is already at version {SCHEMA_VERSION}", path.display()),
+    }
+
+    Ok(())
+}
+
+fn relink(matches: &ArgMatches) -> Result<()> {
+    info!(emoji = "💿"; "Reading the vault...");
+    let vault = Vault::from_disk(vault_path(matches))?;
+
+    let links = match matches.get_flag("write") {
+        true => vault.relink()?,
+        false => vault.moved_links()?,
     };
 
+    if links.is_empty() {
+        info!(emoji = "✅"; "No links to moved chapters");
+        return Ok(());
+    }
+
+    for link in links.iter() {
+        println!("{}", link);
+    }
+
+    match matches.get_flag("write") {
+        true => info!(emoji = "✅"; "Fixed {} link(s)", links.len()),
+        false => info!(emoji = "🔗"; "Run again with --write to fix {} link(s)", links.len()),
+    }
+
+    Ok(())
+}
+
+fn replace(matches: &ArgMatches) -> Result<()> {
+    info!(emoji = "💿"; "Reading the vault...");
+    let vault = Vault::from_disk(vault_path(matches))?;
+    let from = matches
+        .get_one::<String>("from")
+        .expect("The text to replace is required");
+    let to = matches
+        .get_one::<String>("to")
+        .expect("The replacement is required");
+
+    if matches.get_flag("dry_run") {
+        let replacements = vault.replacements(from, to)?;
+        print_replacements(&replacements);
+
+        if !replacements.is_empty() {
+            info!(emoji = "📝"; "Run again without --dry-run to make the replacements");
+        }
+        return Ok(());
+    }
+
+    // Only the problems the replacements brought are worth telling about
+    let before = vault.check()?;
+    let replacements = vault.replace(from, to)?;
+    print_replacements(&replacements);
+
+    let problems: Vec<Problem> = vault
+        .check()?
+        .into_iter()
+        .filter(|problem| !before.contains(problem))
+        .collect();
+    for problem in problems.iter() {
+        error!("{}", problem);
+    }
+
+    if !problems.is_empty() {
+        anyhow::bail!("The replacements caused {} problem(s)", problems.len());
+    }
+
+    Ok(())
+}
+
+fn print_replacements(replacements: &[Replacement]) {
+    if replacements.is_empty() {
+        info!(emoji = "🟰"; "Nothing to replace");
+        return;
+    }
+
+    for replacement in replacements.iter() {
+        println!(
+            "{} {} ({})",
+            "changed".yellow().bold(),
+            replacement.path.display(),
+            replacement.count
+        );
+        print_diff_lines(&replacement.diff);
+    }
+
+    let count: usize = replacements
+        .iter()
+        .map(|replacement| replacement.count)
+        .sum();
+    info!(emoji = "✅"; "{count} replacement(s) in {} chapter(s)", replacements.len());
+}
+
+fn report(matches: &ArgMatches) -> Result<()> {
+    info!(emoji = "💿"; "Reading the vault...");
+    let vault = Vault::from_disk(vault_path(matches))?;
+    let overdue = vault.overdue_reviews(Local::now().date_naive())?;
+
+    if overdue.is_empty() {
+        info!(emoji = "✅"; "Everything was reviewed in the last {} days", vault.config.review.max_age);
+        return Ok(());
+    }
+
+    info!(emoji = "📋"; "{} chapter(s) overdue for review:", overdue.len());
+    for review in overdue.iter() {
+        println!("{}", review);
+    }
+    if vault.config.review.outdated_badge {
+        info!("The pages of the ones that were reviewed at some point are marked as outdated");
+    }
+
+    Ok(())
+}
+
+fn search(matches: &ArgMatches) -> Result<()> {
+    let vault = Vault::from_disk(vault_path(matches))?;
+    let query = matches
+        .get_one::<String>("query")
+        .expect("The query is required");
+    let limit = *matches
+        .get_one::<usize>("limit")
+        .expect("The limit has a default");
+    let documents = vault.search(query)?;
+
+    if documents.is_empty() {
+        info!(emoji = "🔍"; "Nothing matches \"{}\"", query);
+        return Ok(());
+    }
+
+    let terms = query_terms(query);
+    for document in documents.iter().take(limit) {
+        println!(
+            "{} {}",
+            document.title.bold(),
+            document.url.display().to_string().dimmed()
+        );
+        println!("  {}", highlight(&document.excerpt, &terms));
+    }
+
+    if documents.len() > limit {
+        info!(emoji = "🔍"; "{} more chapter(s) match. Use --limit to see them", documents.len() - limit);
+    }
+
+    Ok(())
+}
+
+// The words of the excerpt that the terms start, like the search matched them
+fn highlight(excerpt: &str, terms: &[String]) -> String {
+    excerpt
+        .split(' ')
+        .map(|word| {
+            let bare = word
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+
+            match terms.iter().any(|term| bare.starts_with(term.as_str())) {
+                true => word.yellow().bold().to_string(),
+                false => word.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn stats(matches: &ArgMatches) -> Result<()> {
+    let vault = Vault::from_disk(vault_path(matches))?;
+    let stats = vault.stats()?;
+
+    if matches.get_one::<String>("output").map(|s| s.as_str()) == Some("json") {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:<40} {:>8} {:>8} {:>8}",
+        "Chapter", "Words", "Images", "Links"
+    );
+    for chapter in stats.chapters.iter() {
+        println!(
+            "{:<40} {:>8} {:>8} {:>8}",
+            chapter.title, chapter.counts.words, chapter.counts.images, chapter.counts.links
+        );
+    }
+
+    if !stats.sections.is_empty() {
+        println!("\n{:<40} {:>8} {:>8}", "Section", "Chapters", "Words");
+        for section in stats.sections.iter() {
+            println!(
+                "{:<40} {:>8} {:>8}",
+                section.title, section.chapters, section.counts.words
+            );
+        }
+    }
+
+    println!(
+        "\n{} chapters, {} words, {} images and {} links",
+        stats.chapters.len(),
+        stats.total.words,
+        stats.total.images,
+        stats.total.links
+    );
+
+    if let Some(growth) = stats.growth {
+        println!(
+            "{:+} chapters and {:+} words since the last build",
+            growth.chapters, growth.words
+        );
+    }
+
+    Ok(())
+}
+
+fn generate_summary(matches: &ArgMatches) -> Result<()> {
+    info!(emoji = "💿"; "Reading the vault...");
+    let vault = Vault::from_disk(vault_path(matches))?;
+
+    info!(emoji = "📝"; "Generating the summary...");
+    let path = vault.generate_summary(matches.get_flag("force"))?;
+
+    info!(emoji = "✅"; "Summary written to {}", path.display());
+    Ok(())
+}
+
+async fn serve(matches: &ArgMatches) -> Result<()> {
+    let live_reload = matches.get_flag("live_reload");
+    let profile: Profile = matches
+        .get_one::<String>("profile")
+        .map_or(Ok(Profile::Dev), |profile| profile.parse())?;
+    let mut vaults = Vec::new();
+
+    for (path, mount) in vault_mounts(matches)? {
+        info!(emoji = "💿"; "Reading {}...", path.display());
+        let mut vault = Vault::from_disk(path)?;
+        vault.live_reload = live_reload;
+        vault.profile = profile;
+        vault.audience = matches.get_one::<String>("audience").cloned();
+        if let Some(mount) = mount {
+            vault.base_path = mount;
+        }
+
+        info!(emoji = "🏗️"; "Building...");
+        vault.build()?;
+        vaults.push(vault);
+    }
+
     let mut app = tide::new();
     let port = match matches.get_one::<String>("port") {
         Some(p) => p,
@@ -147,8 +866,340 @@ async fn serve(matches: &ArgMatches) -> Result<()> {
         }
     };
 
+    let builds = Arc::new(AtomicUsize::new(0));
+    if live_reload {
+        let builds = builds.clone();
+        app.at(LIVE_RELOAD_ENDPOINT)
+            .get(tide::sse::endpoint(move |_, sender| {
+                notify_rebuild(builds.clone(), sender)
+            }));
+    }
+
+    let access_log = match matches.get_one::<String>("access_log") {
+        Some(path) => Some(Mutex::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| anyhow!("Failed to open the access log at {path}"))?,
+        )),
+        None => None,
+    };
+
     info!(emoji = "🌐"; "Starting the server");
-    app.at("/").serve_dir(vault.build_dir())?;
-    app.listen("127.0.0.1:".to_string() + &port).await?;
+    app.with(RequestLog { access_log });
+    app.with(ErrorPages {
+        mounts: vaults
+            .iter()
+            .map(|vault| (vault.base_path.clone(), vault.build_dir()))
+            .collect(),
+    });
+
+    for vault in vaults {
+        if matches.get_flag("rpc") {
+            let rpc = Rpc {
+                path: vault.path.clone(),
+                base_path: vault.base_path.clone(),
+                vault: Arc::new(Mutex::new(None)),
+            };
+
+            app.at(&format!("{}{}", vault.base_path, rpc::RPC_ENDPOINT))
+                .post(move |request| rpc_endpoint(request, rpc.clone()));
+        }
+
+        // HEAD requests are routed to the GET endpoint by tide
+        app.at(&format!("{}/*", vault.base_path))
+            .get(StaticFiles::new(&vault.base_path, vault.build_dir())?);
+
+        let builds = builds.clone();
+        match matches.get_one::<String>("webhook_secret") {
+            Some(secret) => {
+                let webhook = Webhook {
+                    endpoint: format!("{}{}", vault.base_path, WEBHOOK_ENDPOINT),
+                    secret: secret.clone(),
+                    git_pull: matches.get_flag("git_pull"),
+                    vault: Arc::new(Mutex::new(vault)),
+                    builds,
+                };
+
+                app.at(&webhook.endpoint.clone())
+                    .post(move |request| webhook_endpoint(request, webhook.clone()));
+            }
+            None => {
+                thread::spawn(move || watch(vault, builds));
+            }
+        }
+    }
+
+    app.listen("127.0.0.1:".to_string() + port).await?;
+    Ok(())
+}
+
+/// Pairs each vault with the path it's served from. A single vault is served from the base path of
+/// its config (`None`) unless a mount is given.
+fn vault_mounts(matches: &ArgMatches) -> Result<Vec<(PathBuf, Option<String>)>> {
+    let paths: Vec<PathBuf> = match matches.get_many::<String>("vault_path") {
+        Some(paths) => paths.map(|path| resolve_path(path)).collect(),
+        None => vec![resolve_path(".")],
+    };
+    let mounts: Vec<Option<String>> = match matches.get_many::<String>("mount") {
+        Some(mounts) => mounts.map(|mount| Some(util::base_path(mount))).collect(),
+        None if paths.len() == 1 => vec![None],
+        None => anyhow::bail!("Use --mount to tell where each of the vaults should be served from"),
+    };
+
+    if mounts.len() != paths.len() {
+        anyhow::bail!(
+            "Got {} vaults but {} mounts. Every vault needs its own mount",
+            paths.len(),
+            mounts.len()
+        );
+    }
+
+    for (i, mount) in mounts.iter().enumerate() {
+        if mounts[..i].contains(mount) {
+            anyhow::bail!(
+                "More than one vault is mounted at {}",
+                mount.as_deref().unwrap_or("/")
+            );
+        }
+    }
+
+    Ok(paths.into_iter().zip(mounts).collect())
+}
+
+/// The vault editors preview through the JSON-RPC endpoint. Chapters are read on every request,
+/// so the answers are never out of date, but the vault is only read again when its config changes.
+#[derive(Clone)]
+struct Rpc {
+    path: PathBuf,
+    base_path: String,
+    /// The vault, along with when its config was modified
+    vault: Arc<Mutex<Option<(SystemTime, Vault)>>>,
+}
+
+impl Rpc {
+    fn handle(&self, body: &str) -> rpc::Response {
+        let mut cached = self.vault.lock().unwrap();
+
+        match self.load(&mut cached) {
+            Ok(vault) => rpc::handle(vault, body),
+            Err(e) => {
+                rpc::Response::error(serde_json::Value::Null, rpc::SERVER_ERROR, e.to_string())
+            }
+        }
+    }
+
+    fn load<'a>(&self, cached: &'a mut Option<(SystemTime, Vault)>) -> Result<&'a Vault> {
+        let modified = fs::metadata(self.path.join(CONFIG_FILE))
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| anyhow!("Failed to read {}", CONFIG_FILE))?;
+
+        if cached.as_ref().map(|(cached, _)| *cached) != Some(modified) {
+            let mut vault = Vault::from_disk(&self.path)?;
+            vault.base_path = self.base_path.clone();
+            *cached = Some((modified, vault));
+        }
+
+        Ok(&cached.as_ref().expect("The vault was just read").1)
+    }
+}
+
+async fn rpc_endpoint(mut request: tide::Request<()>, rpc: Rpc) -> tide::Result {
+    let body = request.body_string().await?;
+    // Rendering blocks, so it gets a thread of its own
+    let response = task::spawn_blocking(move || rpc.handle(&body)).await;
+
+    Ok(tide::Body::from_json(&response)?.into())
+}
+
+/// Everything a vault needs to be rebuilt by the webhook
+#[derive(Clone)]
+struct Webhook {
+    endpoint: String,
+    secret: String,
+    git_pull: bool,
+    /// Locked during builds, so requests that arrive in the middle of one wait for it
+    vault: Arc<Mutex<Vault>>,
+    builds: Arc<AtomicUsize>,
+}
+
+impl Webhook {
+    fn rebuild(&self) -> Result<BuildReport> {
+        let mut vault = self.vault.lock().unwrap();
+
+        if self.git_pull {
+            let output = git::pull(&vault.path)?;
+            info!(emoji = "⬇️"; "{}", output);
+        }
+
+        let report = vault.rebuild()?;
+        self.builds.fetch_add(1, Ordering::SeqCst);
+
+        Ok(report)
+    }
+}
+
+async fn webhook_endpoint(mut request: tide::Request<()>, webhook: Webhook) -> tide::Result {
+    let body = request.body_bytes().await?;
+    let header = |name: &str| {
+        request
+            .header(name)
+            .map(|values| values.last().as_str().to_string())
+    };
+    let token = webhook::TOKEN_HEADERS.iter().find_map(|name| header(name));
+
+    if !webhook::is_authorized(
+        &webhook.secret,
+        header(webhook::SIGNATURE_HEADER).as_deref(),
+        token.as_deref(),
+        &body,
+    ) {
+        warn!(
+            "Rejected a request to {} without the secret",
+            webhook.endpoint
+        );
+        return Ok(tide::Response::new(tide::StatusCode::Unauthorized));
+    }
+
+    info!(emoji = "🔄"; "Webhook received. Rebuilding...");
+    // Builds block, so they get a thread of their own
+    match task::spawn_blocking(move || webhook.rebuild()).await {
+        Ok(report) => {
+            info!(emoji = "✅"; "Done in {:?}", report.duration);
+            Ok(tide::Body::from_json(&serde_json::json!({
+                "chapters": report.chapters,
+                "cached": report.cached,
+            }))?
+            .into())
+        }
+        Err(e) => Err(tide::Error::from_str(
+            tide::StatusCode::InternalServerError,
+            format!("{e:#}"),
+        )),
+    }
+}
+
+/// Logs the method, path, status and duration of every request and, if there is an access log,
+/// appends them to it
+struct RequestLog {
+    access_log: Option<Mutex<File>>,
+}
+
+#[tide::utils::async_trait]
+impl tide::Middleware<()> for RequestLog {
+    async fn handle(&self, request: tide::Request<()>, next: tide::Next<'_, ()>) -> tide::Result {
+        let method = request.method().to_string();
+        let path = request.url().path().to_string();
+        let start = Instant::now();
+        let response = next.run(request).await;
+        let status: u16 = response.status().into();
+        let duration = start.elapsed();
+
+        info!(
+            emoji = "📨",
+            method = method.as_str(),
+            path = path.as_str(),
+            status = status,
+            duration_ms = duration.as_millis() as u64;
+            "{} {} {} {:?}", method, path, status, duration
+        );
+
+        if let Some(access_log) = &self.access_log {
+            let line = format!(
+                "{} {} {} {} {}ms\n",
+                Local::now().to_rfc3339(),
+                method,
+                path,
+                status,
+                duration.as_millis()
+            );
+
+            if let Err(e) = access_log.lock().unwrap().write_all(line.as_bytes()) {
+                warn!("Failed to write to the access log: {}", e);
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+/// Logs failed requests and answers them with the error pages of the vault instead of tide's
+/// plain text responses
+struct ErrorPages {
+    /// The build dir of each vault, by the path it's served from
+    mounts: Vec<(String, PathBuf)>,
+}
+
+impl ErrorPages {
+    // The most specific mount wins, so /docs/api/x is handled by /docs/api instead of /docs
+    fn build_dir(&self, path: &str) -> Option<&PathBuf> {
+        self.mounts
+            .iter()
+            .filter(|(mount, _)| {
+                mount.is_empty() || path == mount || path.starts_with(&format!("{mount}/"))
+            })
+            .max_by_key(|(mount, _)| mount.len())
+            .map(|(_, build_dir)| build_dir)
+    }
+}
+
+#[tide::utils::async_trait]
+impl tide::Middleware<()> for ErrorPages {
+    async fn handle(&self, request: tide::Request<()>, next: tide::Next<'_, ()>) -> tide::Result {
+        let path = request.url().path().to_string();
+        let mut response = next.run(request).await;
+        let status = response.status();
+
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(response);
+        }
+
+        match response.error() {
+            Some(e) => error!("{} {}: {}", status, path, e),
+            None => error!("{} {}", status, path),
+        }
+
+        let Some(build_dir) = self.build_dir(&path) else {
+            return Ok(response);
+        };
+
+        let page = build_dir.join(renderer::error_page_path(status.into()));
+        if let Ok(page) = async_std::fs::read_to_string(page).await {
+            response.set_body(page);
+            response.set_content_type(tide::http::mime::HTML);
+        }
+
+        Ok(response)
+    }
+}
+
+/// Rebuilds the vault whenever something changes. Every successful build bumps `builds`, so
+/// live-reload clients know it's time to refresh.
+fn watch(mut vault: Vault, builds: Arc<AtomicUsize>) {
+    vault.watch(WATCH_INTERVAL, |event| {
+        match event {
+            WatchEvent::ChangeDetected => info!(emoji = "🔄"; "Changes detected. Rebuilding..."),
+            WatchEvent::RebuildStarted => {}
+            WatchEvent::RebuildFinished(report) => {
+                builds.fetch_add(1, Ordering::SeqCst);
+                info!(emoji = "✅"; "Done in {:?}", report.duration);
+            }
+            WatchEvent::Error(e) => error!("{:#}", e),
+        }
+
+        ControlFlow::Continue(())
+    })
+}
+
+/// Waits for the next successful build and tells the client to reload
+async fn notify_rebuild(builds: Arc<AtomicUsize>, sender: tide::sse::Sender) -> tide::Result<()> {
+    let seen = builds.load(Ordering::SeqCst);
+
+    while builds.load(Ordering::SeqCst) == seen {
+        task::sleep(WATCH_INTERVAL).await;
+    }
+
+    sender.send("reload", "", None).await?;
     Ok(())
 }
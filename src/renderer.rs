@@ -1,11 +1,134 @@
 mod askama_renderer;
+pub mod audiences;
+pub mod encryption;
+pub mod epub;
+pub mod exercises;
+pub mod favicon;
+pub mod fences;
+pub mod filters;
+mod highlight;
+pub mod includes;
+pub(crate) mod markdown;
+pub mod outputs;
+mod pdf;
+pub mod shortcodes;
+mod snippets;
+pub mod social;
+pub mod variables;
 
-use crate::config::Config;
+use crate::config::{Config, PrivateMode};
+use crate::util;
+use crate::vault::{Contributors, LastUpdated, Theme};
 use crate::Chapter;
 use crate::Content;
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 pub use askama_renderer::AskamaRenderer;
-use std::path::PathBuf;
+use chrono::{Local, NaiveDate};
+use highlight::HighlightCache;
+use std::path::{Path, PathBuf};
+use variables::Variables;
+
+/// Path of the server-sent events endpoint used by the live-reload client
+pub const LIVE_RELOAD_ENDPOINT: &str = "/__livereload";
+
+/// Where the Atom feed is written, inside the build dir. Pages link to it when it's enabled.
+pub const FEED_FILE: &str = "feed.xml";
+
+/// Where the page showing how everything a chapter can have looks is written, inside the build
+/// dir. It's hidden, since nothing links to it.
+pub const THEME_PREVIEW_FILE: &str = "theme-preview.html";
+
+/// Where the page with every chapter, for printing, is written inside the build dir. The sidebar
+/// links to it when it's enabled.
+pub const PRINT_FILE: &str = "print.html";
+
+/// Pages rendered for the errors the server may answer with, along with what they tell the reader
+pub const ERROR_PAGES: [(u16, &str); 3] = [
+    (403, "You don't have permission to see this page"),
+    (404, "There is nothing here. The page may have moved"),
+    (500, "Something went wrong while serving this page"),
+];
+
+/// The file, inside the build dir, of the page shown for a given status code
+pub fn error_page_path(status: u16) -> PathBuf {
+    PathBuf::from(format!("{status}.html"))
+}
+
+/// The social card of a page, inside the build dir (e.g. `social/setup/install.png`, with `dir`
+/// being `social`)
+pub fn social_card_path<P, Q>(dir: P, page: Q) -> PathBuf
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    dir.as_ref().join(page).with_extension("png")
+}
+
+/// Where a chapter is written inside the build dir. The output mirrors the source, so
+/// `src/chapter1/intro.md` becomes `chapter1/intro.html`. Number prefixes, which are only there
+/// for ordering, are dropped: `src/02_setup/01-install.md` becomes `setup/install.html`.
+pub fn output_path<P, Q>(content: P, src_dir: Q) -> Result<PathBuf>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let content = content.as_ref();
+
+    content
+        .strip_prefix(src_dir)
+        .map(|path| util::strip_number_prefixes(path).with_extension("html"))
+        .with_context(|| anyhow!("Failed to create the url for {}", content.display()))
+}
+
+/// The chapters a `requires` of the front matter may point to. It's either the page of the chapter
+/// without the extension (e.g. `guide/setup`) or only its name (e.g. `setup`), which more than one
+/// chapter may have.
+pub fn required_chapters<'a, P>(
+    chapters: &'a [Chapter],
+    src_dir: P,
+    requirement: &str,
+) -> Result<Vec<&'a Chapter>>
+where
+    P: AsRef<Path>,
+{
+    let requirement = Path::new(requirement.trim().trim_matches('/')).with_extension("");
+    let mut pages = Vec::new();
+
+    for chapter in chapters.iter().filter(|chapter| chapter.anchor.is_none()) {
+        let page = output_path(&chapter.content, &src_dir)?.with_extension("");
+
+        // The summary may list the same file more than once
+        if !pages.iter().any(|(other, _)| *other == page) {
+            pages.push((page, chapter));
+        }
+    }
+
+    let whole: Vec<&Chapter> = pages
+        .iter()
+        .filter(|(page, _)| *page == requirement)
+        .map(|(_, chapter)| *chapter)
+        .collect();
+    if !whole.is_empty() {
+        return Ok(whole);
+    }
+
+    Ok(pages
+        .iter()
+        .filter(|(page, _)| page.file_name() == Some(requirement.as_os_str()))
+        .map(|(_, chapter)| *chapter)
+        .collect())
+}
+
+/// A language the site is built in, for the switcher in the header
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteLanguage {
+    pub name: String,
+    /// Where the language is served from (e.g. /docs/pt-br). The default one is at the base path.
+    pub base_path: String,
+    /// Every page it has, relative to where it's served from. The first one is where readers land
+    /// when a page wasn't translated.
+    pub pages: Vec<PathBuf>,
+}
 
 pub trait Renderer {
     fn render(&self, chapter: &Chapter) -> Result<String>;
@@ -17,6 +140,43 @@ pub struct RendererContext {
     config: Config,
     // src_dir so we can strip from the chapter content and get a proper url.
     src_dir: PathBuf,
+    // Should the pages listen for rebuilds and refresh themselves? Only makes sense while serving.
+    live_reload: bool,
+    // Where the site is served from (e.g. /docs). Empty means the root.
+    base_path: String,
+    // Where `{{#snippet name}}` looks for its files. Without it, snippets are left as text.
+    snippets_dir: Option<PathBuf>,
+    // What `{{name}}` is replaced with in the chapters
+    variables: Variables,
+    // Where `{{#include path}}` resolves the source pages from. Without it, there are none.
+    source_root: Option<PathBuf>,
+    // Should exercises link to their solutions? Without it, solutions are nowhere to be seen.
+    solutions: bool,
+    // When each chapter was last updated, by its file. Chapters that aren't in it have no date.
+    last_updated: LastUpdated,
+    // Who committed to each chapter, by its file. Without them, pages list no one.
+    contributors: Contributors,
+    // Where highlighted code is kept between builds. Without it, every block is highlighted.
+    highlight_cache: Option<HighlightCache>,
+    // The languages the site is built in. Pages only link to them if there's more than one.
+    languages: Vec<SiteLanguage>,
+    // Html that goes in the head of every page, as it is
+    head_include: String,
+    // Html that goes at the end of the footer of every page, as it is
+    footer_html: String,
+    // The stylesheets and scripts of `appearance.custom`, with the globs expanded. Pages load
+    // them from the root of the site.
+    custom_files: Vec<PathBuf>,
+    // Which readers the pages are for. Blocks for other audiences are left out (see
+    // [`audiences`])
+    audience: Option<String>,
+    // How the pages of private chapters are written and, to encrypt them, the passphrase
+    private: PrivateMode,
+    passphrase: Option<String>,
+    // The themes of the vault, besides the ones built in. Pages load all of them to switch freely.
+    themes: Vec<Theme>,
+    // What chapters are outdated is decided by how long ago they were reviewed
+    today: NaiveDate,
 }
 
 impl RendererContext {
@@ -25,6 +185,151 @@ impl RendererContext {
             content,
             config,
             src_dir,
+            live_reload: false,
+            base_path: String::new(),
+            snippets_dir: None,
+            variables: Variables::new(),
+            source_root: None,
+            solutions: false,
+            last_updated: LastUpdated::new(),
+            contributors: Contributors::new(),
+            highlight_cache: None,
+            languages: Vec::new(),
+            head_include: String::new(),
+            footer_html: String::new(),
+            custom_files: Vec::new(),
+            audience: None,
+            private: PrivateMode::Plain,
+            passphrase: None,
+            themes: Vec::new(),
+            today: Local::now().date_naive(),
         }
     }
+
+    /// Inject a small client into every page that reloads it after each rebuild
+    pub fn with_live_reload(mut self, live_reload: bool) -> Self {
+        self.live_reload = live_reload;
+        self
+    }
+
+    /// Make every url in the pages start with `base_path`, so the site can be served from
+    /// somewhere other than `/` (e.g. `/docs`)
+    pub fn with_base_path<S>(mut self, base_path: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.base_path = util::base_path(base_path);
+        self
+    }
+
+    /// Let chapters insert the files of `snippets_dir` with `{{#snippet name}}`
+    pub fn with_snippets_dir<P>(mut self, snippets_dir: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.snippets_dir = Some(snippets_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Replace `{{name}}` with the value of the variable in every chapter
+    pub fn with_variables(mut self, variables: Variables) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    /// Link code blocks with files pulled by `{{#include path}}` to a page with the whole file.
+    /// Those pages mirror where the files are relative to `root`.
+    pub fn with_source_root<P>(mut self, root: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.source_root = Some(root.as_ref().to_path_buf());
+        self
+    }
+
+    /// Decide which chapters are outdated as if it were `today`
+    pub fn with_today(mut self, today: NaiveDate) -> Self {
+        self.today = today;
+        self
+    }
+
+    /// Link exercises to their solutions, in the page rendered by
+    /// [`AskamaRenderer::render_solutions`]
+    pub fn with_solutions(mut self, solutions: bool) -> Self {
+        self.solutions = solutions;
+        self
+    }
+
+    /// Say in the footer of each chapter when it was last updated
+    pub fn with_last_updated(mut self, last_updated: LastUpdated) -> Self {
+        self.last_updated = last_updated;
+        self
+    }
+
+    /// List who committed to each chapter at the bottom of its page
+    pub fn with_contributors(mut self, contributors: Contributors) -> Self {
+        self.contributors = contributors;
+        self
+    }
+
+    /// Let readers switch to the same page in the other languages. The one served from the base
+    /// path is the current one.
+    pub fn with_languages(mut self, languages: Vec<SiteLanguage>) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    /// Put `html` in the head of every page, after everything else there
+    pub fn with_head_include<S>(mut self, html: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.head_include = html.into();
+        self
+    }
+
+    /// Put `html` at the end of the footer of every page
+    pub fn with_footer_html<S>(mut self, html: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.footer_html = html.into();
+        self
+    }
+
+    /// Load the stylesheets and scripts in every page. They're copied to the root of the site,
+    /// so only their names matter.
+    pub fn with_custom_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.custom_files = files;
+        self
+    }
+
+    /// Keep the blocks of the chapters for `audience`, and leave out the others
+    pub fn with_audience(mut self, audience: Option<String>) -> Self {
+        self.audience = audience;
+        self
+    }
+
+    /// Write the pages of private chapters the way `mode` says. Encrypting them takes the
+    /// passphrase, which the pages ask readers for.
+    pub fn with_private(mut self, mode: PrivateMode, passphrase: Option<String>) -> Self {
+        self.private = mode;
+        self.passphrase = passphrase;
+        self
+    }
+
+    /// Load the themes of the vault in every page and list them in the theme picker
+    pub fn with_themes(mut self, themes: Vec<Theme>) -> Self {
+        self.themes = themes;
+        self
+    }
+
+    /// Keep highlighted code in `dir`, so the next builds can skip highlighting what didn't change
+    pub fn with_highlight_cache<P>(mut self, dir: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.highlight_cache = Some(HighlightCache::new(dir));
+        self
+    }
 }
@@ -1,15 +1,69 @@
-use super::{Renderer, RendererContext};
-use crate::config::Link;
-use crate::{Chapter, Item, Section};
+use super::audiences;
+use super::encryption;
+use super::exercises::{self, Appendix, SOLUTIONS_FILE};
+use super::favicon::{self, Favicon};
+use super::filters::{self, MarkdownFilter};
+use super::includes::{self, SourceView};
+use super::markdown::Heading;
+use super::{
+    fences, highlight, markdown, shortcodes, snippets, Renderer, RendererContext, FEED_FILE,
+    LIVE_RELOAD_ENDPOINT, PRINT_FILE, THEME_PREVIEW_FILE,
+};
+use crate::config::{Filter, Link, PrivateMode};
+use crate::git::Author;
+use crate::vault::{Counts, SITEMAP_FILE, THEMES_DIR, THEME_CSS, THEME_JS};
+use crate::{util, Chapter, Content, FrontMatter, Item, Section};
 use anyhow::{anyhow, Context, Result};
 use askama::Template;
+use chrono::NaiveDate;
+use log::warn;
+use pulldown_cmark::Event;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// The markdown of the theme preview (see [`AskamaRenderer::render_theme_preview`])
+const THEME_PREVIEW: &str = include_str!("../../templates/theme_preview.md");
+
+/// What the theme preview highlights with each code theme
+const THEME_PREVIEW_CODE: &str = "// Greets whoever is there
+fn greet(name: &str) -> String {
+    format!(\"Hello there, {name}\")
+}
+";
+
 #[derive(Template)]
 #[template(path = "header.html")]
 struct Header<'a> {
     links: &'a Vec<Link>,
+    base_path: &'a str,
+    /// Is there a search index to look things up in?
+    search: bool,
+    /// Where the page is in each language, if the site has more than one
+    languages: &'a [LanguageOption<'a>],
+    /// Is there a light variant to switch to?
+    schemes: bool,
+}
+
+#[derive(Template)]
+#[template(path = "footer.html")]
+struct Footer<'a> {
+    base_path: &'a str,
+    links: &'a [Link],
+    copyright: Option<&'a str>,
+    /// When the chapter was last updated, if the config shows it
+    last_updated: Option<NaiveDate>,
+    /// Html from the config that goes at the end, as it is
+    html: &'a str,
+}
+
+/// A language in the switcher of the header
+struct LanguageOption<'a> {
+    name: &'a str,
+    /// The same page in the language or, if it wasn't translated, its first page
+    url: String,
+    current: bool,
 }
 
 #[derive(Template)]
@@ -17,31 +71,270 @@ struct Header<'a> {
 struct Sidebar<'a> {
     title: &'a String,
     table_of_contents: &'a String,
+    /// The page with every chapter, if the config has it
+    print: Option<String>,
 }
 
 #[derive(Template)]
 #[template(path = "sidebar/chapter.html", escape = "none")]
 struct SidebarChapter<'a> {
     title: &'a String,
+    icon: Option<Icon<'a>>,
     subchapters: &'a String,
-    target: &'a String,
+    /// Drafts have nowhere to go
+    target: Option<&'a str>,
+    /// Is the target outside of the vault?
+    external: bool,
+    /// The accent of the section it's in. Subchapters inherit it.
+    accent: Option<&'a str>,
 }
 
 #[derive(Template)]
 #[template(path = "sidebar/section.html")]
 struct SidebarSection<'a> {
     title: &'a String,
+    accent: Option<&'a str>,
+    chapters: &'a String,
+}
+
+#[derive(Template)]
+#[template(path = "sitemap.html", escape = "none")]
+struct Sitemap<'a> {
+    title: &'a String,
+    items: &'a String,
+}
+
+#[derive(Template)]
+#[template(path = "sitemap/chapter.html")]
+struct SitemapChapter<'a> {
+    title: &'a String,
+    icon: Option<Icon<'a>>,
+    target: Option<&'a str>,
+    external: bool,
+    description: &'a Option<String>,
+    subchapters: &'a String,
+}
+
+#[derive(Template)]
+#[template(path = "sitemap/section.html")]
+struct SitemapSection<'a> {
+    title: &'a String,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorPage<'a> {
+    base_path: &'a str,
+    status: u16,
+    message: &'a str,
+}
+
+/// Asks for the passphrase of a private chapter and puts its page in place once decrypted (see
+/// [`encryption::encrypt`])
+#[derive(Template)]
+#[template(path = "private.html")]
+struct PrivatePage<'a> {
+    base_path: &'a str,
+    lang: &'a str,
+    title: &'a str,
+    theme: &'a str,
+    salt: &'a str,
+    iv: &'a str,
+    iterations: u32,
+    /// The whole page, encrypted
+    content: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "redirect.html")]
+struct Redirect<'a> {
+    lang: &'a str,
+    target: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "metadata.html")]
+struct Metadata<'a> {
+    /// Should the status, owner and review date be shown?
+    banner: bool,
+    status: &'a Option<String>,
+    owner: &'a Option<String>,
+    reviewed: &'a Option<NaiveDate>,
+    /// How many days passed since the chapter was reviewed, if it's overdue
+    outdated: Option<i64>,
+}
+
+#[derive(Template)]
+#[template(path = "prerequisites.html")]
+struct Prerequisites<'a> {
+    /// The title and the target of each chapter
+    chapters: &'a [(String, String)],
+}
+
+#[derive(Template)]
+#[template(path = "page_toc.html")]
+struct PageToc<'a> {
+    headings: &'a [&'a Heading],
+}
+
+#[derive(Template)]
+#[template(path = "shortcuts.html")]
+struct ShortcutList<'a> {
+    base_path: &'a str,
+    sitemap: &'a str,
+    shortcuts: &'a [Shortcut],
+}
+
+/// A key readers can press. The script only listens to the keys the help lists
+struct Shortcut {
+    key: &'static str,
+    /// What the script does (e.g. `search`)
+    action: &'static str,
+    description: &'static str,
+}
+
+#[derive(Template)]
+#[template(path = "contributors.html")]
+struct ContributorList<'a> {
+    contributors: &'a [Contributor<'a>],
+}
+
+/// Someone who committed to a chapter
+struct Contributor<'a> {
+    name: &'a str,
+    /// Only there when the repository is on a forge that has them
+    avatar: Option<String>,
 }
 
 #[derive(Template)]
 #[template(path = "index.html", escape = "none")]
 struct Page<'a> {
+    base_path: &'a str,
+    /// The language of the build (e.g. `en`)
+    lang: &'a str,
+    title: &'a str,
+    /// The title without the one of the vault, for the previews of links to the page
+    page_title: &'a str,
+    /// The title of the vault
+    site_name: &'a str,
+    /// What the page is about, from the front matter or else the description of the vault
+    description: Option<&'a str>,
+    /// The absolute url of the page, so search engines know where it really lives
+    canonical: Option<&'a str>,
+    /// The social card of the page
+    image: Option<&'a str>,
+    /// The front matter of the chapter, with any custom keys
+    front_matter: &'a FrontMatter,
+    /// The accent of the section the chapter is in
+    accent: Option<&'a str>,
     theme: &'a String,
     header: &'a String,
     sidebar: &'a String,
+    metadata: &'a String,
     content: &'a String,
+    /// The headings of the content, next to it
+    page_toc: &'a String,
+    /// Who committed to the chapter, below its content
+    contributors: &'a String,
+    footer: &'a String,
+    /// The help with the keyboard shortcuts
+    shortcuts: &'a String,
     custom_css: &'a Vec<String>,
-    themes: &'a Vec<String>,
+    /// The scripts of `appearance.custom`, after the others
+    custom_js: &'a [String],
+    /// Html from the config that goes at the end of the head
+    head_include: &'a str,
+    /// The favicon and the sizes drawn from it
+    favicons: &'a [Favicon],
+    /// The themes in the picker, built in ones first
+    themes: &'a [ThemeOption<'a>],
+    /// The stylesheets of the themes of the vault
+    theme_css: &'a [String],
+    /// The scripts of the themes of the vault that have one
+    theme_scripts: &'a [String],
+    live_reload: Option<&'a str>,
+    /// The Atom feed of the vault, if it has one
+    feed: Option<&'a str>,
+    /// Extra scripts needed by the content (e.g. to draw diagrams)
+    scripts: &'a [&'a str],
+    /// Only there when the config shows it
+    reading_time: Option<ReadingTime>,
+    /// Should search engines leave the page out?
+    hidden: bool,
+}
+
+/// A theme readers can pick
+struct ThemeOption<'a> {
+    name: &'a str,
+    /// What readers who prefer light themes get instead
+    light: Option<&'a str>,
+}
+
+/// How much there is to read in a chapter
+struct ReadingTime {
+    words: usize,
+    minutes: usize,
+}
+
+/// Everything that changes from page to page. The rest (header, sidebar, styles, etc.) is the
+/// same for every page.
+#[derive(Default)]
+struct PageParts<'a> {
+    title: &'a str,
+    /// The page, relative to the build dir. Error pages are served from any path, so they have none
+    path: Option<PathBuf>,
+    front_matter: FrontMatter,
+    /// The accent of the section the chapter is in
+    accent: Option<&'a str>,
+    metadata: String,
+    content: String,
+    scripts: Vec<&'a str>,
+    /// The social card, relative to the build dir
+    image: Option<PathBuf>,
+    /// The headings of the content, for the table of contents of the page
+    headings: Vec<Heading>,
+    /// What the chapter counts, as it was written. Only chapters have them
+    counts: Option<Counts>,
+    /// When the chapter was last updated
+    last_updated: Option<NaiveDate>,
+    /// Who committed to the chapter
+    contributors: &'a [Author],
+    /// Should search engines leave the page out?
+    hidden: bool,
+}
+
+/// The markdown of a chapter, once rendered
+struct Body<'a> {
+    html: String,
+    /// Extra scripts needed by the content (e.g. to draw diagrams)
+    scripts: Vec<&'a str>,
+    headings: Vec<Heading>,
+}
+
+/// What goes before the title of a chapter
+enum Icon<'a> {
+    /// A tabler icon, drawn by its font
+    Named(&'a str),
+    /// Anything else, like an emoji, is shown as it is
+    Text(&'a str),
+}
+
+impl<'a> Icon<'a> {
+    fn new(icon: &'a str) -> Option<Self> {
+        let icon = icon.trim();
+
+        match icon.is_empty() {
+            true => None,
+            false
+                if icon
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') =>
+            {
+                Some(Icon::Named(icon))
+            }
+            false => Some(Icon::Text(icon)),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,11 +347,54 @@ impl AskamaRenderer {
         Self { context }
     }
 
-    pub fn render_header(&self) -> Result<String> {
+    /// Renders the header of a page, relative to the build dir. Pages without one (e.g. error pages)
+    /// link to the first page of each language.
+    pub fn render_header(&self, page: Option<&Path>) -> Result<String> {
         let links = self.context.config.links.clone();
-        let header = Header { links: &links };
+        let languages: Vec<LanguageOption> = match self.context.languages.len() > 1 {
+            true => self
+                .context
+                .languages
+                .iter()
+                .map(|language| {
+                    let target = page
+                        .filter(|page| language.pages.iter().any(|known| known == page))
+                        .or(language.pages.first().map(PathBuf::as_path))
+                        .map(|page| page.to_string_lossy().replace('\\', "/"))
+                        .unwrap_or_default();
+
+                    LanguageOption {
+                        name: &language.name,
+                        url: format!("{}/{target}", language.base_path),
+                        current: language.base_path == self.context.base_path,
+                    }
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+        let header = Header {
+            links: &links,
+            base_path: &self.context.base_path,
+            search: self.context.config.search.enabled,
+            languages: &languages,
+            schemes: !self.context.config.appearance.light_variants.is_empty(),
+        };
+
+        render_template(&header, "header.html")
+    }
+
+    /// Renders the footer of a page, with the date the chapter was last updated if it has one
+    pub fn render_footer(&self, last_updated: Option<NaiveDate>) -> Result<String> {
+        let config = &self.context.config.footer;
+        let footer = Footer {
+            base_path: &self.context.base_path,
+            links: &config.links,
+            copyright: config.copyright.as_deref(),
+            last_updated,
+            html: &self.context.footer_html,
+        };
 
-        Ok(header.render()?)
+        render_template(&footer, "footer.html")
     }
 
     // The template engine makes difficult to renderer the sidebar, so we do the heavy lifting here
@@ -70,10 +406,10 @@ impl AskamaRenderer {
         for item in items.iter() {
             match item {
                 Item::Chapter(chapter) => {
-                    table_of_contents.push_str(&self.render_sidebar_chapter(&chapter)?)
+                    table_of_contents.push_str(&self.render_sidebar_chapter(chapter, None)?)
                 }
                 Item::Section(section) => {
-                    table_of_contents.push_str(&self.render_sidebar_section(&section)?)
+                    table_of_contents.push_str(&self.render_sidebar_section(section)?)
                 }
             }
         }
@@ -81,116 +417,1745 @@ impl AskamaRenderer {
         let sidebar = Sidebar {
             title: &title,
             table_of_contents: &table_of_contents,
+            print: self
+                .context
+                .config
+                .print
+                .enabled
+                .then(|| format!("{}/{PRINT_FILE}", self.context.base_path)),
         };
 
-        Ok(sidebar.render()?)
+        render_template(&sidebar, "sidebar.html")
     }
 
-    fn render_sidebar_chapter(&self, chapter: &Chapter) -> Result<String> {
+    fn render_sidebar_chapter(&self, chapter: &Chapter, accent: Option<&str>) -> Result<String> {
         // We don't care about indentation here. The css class takes care of it.
         let subchapters = chapter
             .subchapters
             .iter()
-            .map(|chapter| self.render_sidebar_chapter(&chapter))
+            .map(|item| match item {
+                Item::Chapter(chapter) => self.render_sidebar_chapter(chapter, None),
+                Item::Section(section) => self.render_sidebar_section(section),
+            })
             .collect::<Result<Vec<String>>>()?
             .join("");
 
-        let target = self.get_chapter_target(chapter.content.clone())?;
-        let title = match self.context.config.general.enumerate {
-            true => format!("{} {}", chapter.number, chapter.title),
-            false => chapter.title.clone(),
-        };
+        let target = self.chapter_target(chapter)?;
+        let title = self.chapter_title(chapter);
+        let front_matter = self.front_matter(chapter)?;
 
         let sidebar_chapter = SidebarChapter {
             title: &title,
+            icon: front_matter.icon.as_deref().and_then(Icon::new),
             subchapters: &subchapters,
-            target: &target,
+            target: target.as_deref(),
+            external: chapter.url.is_some(),
+            accent,
         };
 
-        Ok(sidebar_chapter.render()?)
+        render_template(&sidebar_chapter, "sidebar/chapter.html")
+    }
+
+    // Drafts have nowhere to go. External chapters go wherever they point to.
+    fn chapter_target(&self, chapter: &Chapter) -> Result<Option<String>> {
+        match (&chapter.url, chapter.draft) {
+            (Some(url), _) => Ok(Some(url.clone())),
+            (None, true) => Ok(None),
+            (None, false) => {
+                let target = self.get_chapter_target(chapter.content.clone())?;
+
+                match &chapter.anchor {
+                    Some(anchor) => Ok(Some(format!("{target}#{anchor}"))),
+                    None => Ok(Some(target)),
+                }
+            }
+        }
+    }
+
+    // Drafts, external and missing chapters have nothing to read from
+    fn front_matter(&self, chapter: &Chapter) -> Result<FrontMatter> {
+        match chapter.content.is_file() {
+            true => FrontMatter::from_disk(&chapter.content),
+            false => Ok(FrontMatter::default()),
+        }
+    }
+
+    // Chapters without a number (e.g. prefaces) are never enumerated
+    fn chapter_title(&self, chapter: &Chapter) -> String {
+        match self.context.config.general.enumerate && !chapter.number.is_empty() {
+            true => format!("{} {}", chapter.number, chapter.title),
+            false => chapter.title.clone(),
+        }
     }
 
     fn get_chapter_target(&self, path: PathBuf) -> Result<String> {
-        Ok("/".to_string()
-            + path
-                .strip_prefix(&self.context.src_dir)
-                .and_then(|url| Ok(url.with_extension("html")))
-                .with_context(|| anyhow!("Failed to create the url for {}", path.display()))?
+        Ok(self.context.base_path.clone()
+            + "/"
+            + super::output_path(path, &self.context.src_dir)?
                 .to_string_lossy()
                 .as_ref())
     }
 
-    fn render_sidebar_section(&self, section: &Section) -> Result<String> {
-        let sidebar_section = SidebarSection {
-            title: &section.title,
+    // Nothing is shown if the banner is disabled or there is nothing to show, unless the chapter
+    // is outdated
+    fn render_metadata(&self, front_matter: &FrontMatter) -> Result<String> {
+        let banner = self.context.config.appearance.metadata_banner && front_matter.has_metadata();
+        let review = &self.context.config.review;
+        let outdated = front_matter
+            .reviewed
+            .map(|reviewed| (self.context.today - reviewed).num_days())
+            .filter(|&age| review.outdated_badge && age > review.max_age.into());
+
+        if !banner && outdated.is_none() {
+            return Ok(String::new());
+        }
+
+        let metadata = Metadata {
+            banner,
+            status: &front_matter.status,
+            owner: &front_matter.owner,
+            reviewed: &front_matter.reviewed,
+            outdated,
         };
 
-        Ok(sidebar_section.render()?)
+        render_template(&metadata, "metadata.html")
     }
-}
 
-impl Renderer for AskamaRenderer {
-    fn render(&self, chapter: &Chapter) -> Result<String> {
-        let header = self.render_header()?;
-        let sidebar = self.render_sidebar()?;
-        let mut custom_css = Vec::new();
+    // The chapters to read first, resolved against the summary. Those that aren't exactly one
+    // chapter fail the build in strict mode and are left out otherwise.
+    fn render_prerequisites(&self, front_matter: &FrontMatter) -> Result<String> {
+        if front_matter.requires.is_empty() {
+            return Ok(String::new());
+        }
 
-        for css in self.context.config.appearance.custom.iter() {
-            let file_name = Path::new(css)
-                .file_name()
-                .with_context(|| anyhow!("Failed to extract the file name from {css}"))?;
+        let all_chapters = self.context.content.all_chapters();
+        let mut chapters = Vec::new();
+
+        for requirement in front_matter.requires.iter() {
+            let message = match super::required_chapters(
+                &all_chapters,
+                &self.context.src_dir,
+                requirement,
+            )?[..]
+            {
+                [chapter] => {
+                    if let Some(target) = self.chapter_target(chapter)? {
+                        chapters.push((self.chapter_title(chapter), target));
+                    }
+                    continue;
+                }
+                [] => format!("The required chapter {requirement} isn't in the summary"),
+                _ => format!(
+                    "The required chapter {requirement} could be more than one. Use its whole path"
+                ),
+            };
 
-            custom_css.push("/".to_string() + &file_name.to_string_lossy().to_string());
+            match self.context.config.markdown.strict_requires {
+                true => anyhow::bail!(message),
+                false => warn!("{message}"),
+            }
         }
 
-        let markdown = fs::read_to_string(&chapter.content)
-            .with_context(|| anyhow!("Failed to read contents of {}", chapter.content.display()))?;
-        let parser = pulldown_cmark::Parser::new(&markdown);
-        let mut html = String::new();
+        if chapters.is_empty() {
+            return Ok(String::new());
+        }
 
-        pulldown_cmark::html::push_html(&mut html, parser);
+        render_template(
+            &Prerequisites {
+                chapters: &chapters,
+            },
+            "prerequisites.html",
+        )
+    }
 
-        let index = Page {
+    /// Renders `markdown` as if it were the contents of the chapter's file
+    pub fn render_markdown(&self, chapter: &Chapter, markdown: &str) -> Result<String> {
+        self.render_chapter(chapter, markdown).with_context(|| {
+            anyhow!(
+                "Failed to render the chapter {} ({})",
+                chapter.title,
+                chapter.content.display()
+            )
+        })
+    }
+
+    /// Renders only the html of the chapter's markdown, without the page around it (e.g. for
+    /// feeds)
+    pub fn render_content(&self, chapter: &Chapter, markdown: &str) -> Result<String> {
+        let (front_matter, markdown) = FrontMatter::extract(markdown)
+            .with_context(|| anyhow!("Invalid front matter in {}", chapter.content.display()))?;
+
+        Ok(self.render_body(chapter, &front_matter, markdown)?.html)
+    }
+
+    fn render_chapter(&self, chapter: &Chapter, markdown: &str) -> Result<String> {
+        let (front_matter, markdown) = FrontMatter::extract(markdown)
+            .with_context(|| anyhow!("Invalid front matter in {}", chapter.content.display()))?;
+        let metadata =
+            self.render_metadata(&front_matter)? + &self.render_prerequisites(&front_matter)?;
+        let body = self.render_body(chapter, &front_matter, markdown)?;
+        let path = super::output_path(&chapter.content, &self.context.src_dir)?;
+        let counts = Counts::from_markdown(markdown);
+        let title = self.chapter_title(chapter);
+        let private = front_matter.private;
+
+        let page = self.render_page(PageParts {
+            title: &title,
+            image: self
+                .context
+                .config
+                .social_cards
+                .enabled
+                .then(|| super::social_card_path(&self.context.config.social_cards.dir, &path)),
+            path: Some(path),
+            accent: self
+                .context
+                .content
+                .section_of(chapter)
+                .and_then(|section| self.accent(section)),
+            front_matter,
+            metadata,
+            content: body.html,
+            scripts: body.scripts,
+            headings: body.headings,
+            counts: Some(counts),
+            last_updated: self
+                .context
+                .last_updated
+                .get(&chapter.content)
+                .map(|date| date.date_naive()),
+            contributors: self
+                .context
+                .contributors
+                .get(&chapter.content)
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+            hidden: false,
+        })?;
+
+        match private {
+            true => self.render_private(&title, page),
+            false => Ok(page),
+        }
+    }
+
+    // Private chapters are written the way the profile of the build says
+    fn render_private(&self, title: &str, page: String) -> Result<String> {
+        let passphrase = match self.context.private {
+            PrivateMode::Plain => return Ok(page),
+            PrivateMode::Encrypt => self.context.passphrase.as_deref().with_context(|| {
+                anyhow!(
+                    "Private chapters are encrypted, but {} has no passphrase",
+                    self.context.config.private.passphrase_env
+                )
+            })?,
+            PrivateMode::Exclude => anyhow::bail!("Private chapters are left out of this build"),
+        };
+        let encrypted = encryption::encrypt(page.as_bytes(), passphrase)?;
+        let private = PrivatePage {
+            base_path: &self.context.base_path,
+            lang: &self.context.config.general.language,
+            title,
             theme: &self.context.config.appearance.default_theme,
-            header: &header,
-            sidebar: &sidebar,
-            content: &html,
-            custom_css: &custom_css,
-            themes: &self.context.config.appearance.themes,
+            salt: &encrypted.salt,
+            iv: &encrypted.iv,
+            iterations: encryption::ITERATIONS,
+            content: &encrypted.data,
         };
 
-        return Ok(index.render()?);
+        render_template(&private, "private.html")
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use anyhow::Result;
-    use std::path::PathBuf;
-    use tempfile::tempdir;
+    // Pulls the snippets and included files into the markdown, and leaves out the blocks for
+    // other audiences
+    fn expand<'a>(&self, chapter: &Chapter, markdown: &'a str) -> Result<Cow<'a, str>> {
+        let markdown = match &self.context.snippets_dir {
+            Some(dir) => Cow::Owned(snippets::snippets(markdown, dir)?),
+            None => Cow::Borrowed(markdown),
+        };
+        let markdown = match markdown.contains("{{#include ") {
+            true => {
+                let source_view = self.context.source_root.as_ref().map(|root| SourceView {
+                    root,
+                    base_path: &self.context.base_path,
+                });
+                let dir = chapter.content.parent().unwrap_or(Path::new(""));
 
-    #[test]
-    fn test_get_chapter_target() -> Result<()> {
-        let tempdir = tempdir()?;
-        let context = RendererContext::new(
-            crate::Content::new(tempdir.path())?,
-            crate::config::Config::default(),
-            PathBuf::from("/some/dir/src"),
+                Cow::Owned(includes::includes(&markdown, dir, source_view.as_ref())?)
+            }
+            false => markdown,
+        };
+        // Before the exercises, which can be inside of the blocks
+        let markdown = match markdown.contains(":::") {
+            true => Cow::Owned(audiences::audiences(
+                &markdown,
+                self.context.audience.as_deref(),
+            )?),
+            false => markdown,
+        };
+
+        Ok(markdown)
+    }
+
+    // The html of the markdown, without the front matter, and what else the page needs from it
+    fn render_body(
+        &self,
+        chapter: &Chapter,
+        front_matter: &FrontMatter,
+        markdown: &str,
+    ) -> Result<Body<'_>> {
+        let markdown = self.expand(chapter, markdown)?;
+        let markdown = match markdown.contains(":::") {
+            true => {
+                let url = format!("{}/{SOLUTIONS_FILE}", self.context.base_path);
+                let page = super::output_path(&chapter.content, &self.context.src_dir)?;
+                let appendix = Appendix {
+                    url: &url,
+                    page: &page,
+                };
+
+                Cow::Owned(exercises::exercises(
+                    &markdown,
+                    self.context.solutions.then_some(&appendix),
+                )?)
+            }
+            false => markdown,
+        };
+        let options = markdown::options(&self.context.config.markdown.extensions);
+        let events =
+            markdown::merge_text(pulldown_cmark::Parser::new_ext(&markdown, options).collect());
+        // No filter touches the languages of the fences, so the scripts can be found first
+        let scripts = fences::scripts(&events, &fences::FENCE_TRANSFORMS);
+        let events = self.filter(chapter, front_matter, events)?;
+        let (events, headings) = markdown::headings(events, self.context.config.markdown.slug);
+        let mut html = String::new();
+
+        pulldown_cmark::html::push_html(&mut html, events.into_iter());
+
+        Ok(Body {
+            html,
+            scripts,
+            headings,
+        })
+    }
+
+    // Runs the markdown filters of the config over the events of the chapter, in its order
+    fn filter<'a>(
+        &self,
+        chapter: &Chapter,
+        front_matter: &FrontMatter,
+        events: Vec<Event<'a>>,
+    ) -> Result<Vec<Event<'a>>> {
+        let config = &self.context.config;
+        // Links point to sources, so they are resolved from where the chapter is in the src dir
+        let base = chapter
+            .content
+            .strip_prefix(&self.context.src_dir)
+            .unwrap_or(Path::new(""));
+        let mut filters: Vec<Box<dyn MarkdownFilter + '_>> = Vec::new();
+
+        for filter in config.markdown.filters.iter() {
+            filters.push(match filter {
+                Filter::Quotes => Box::new(filters::Quotes),
+                Filter::Variables => Box::new(filters::Interpolate {
+                    variables: &self.context.variables,
+                    strict: config.markdown.strict_variables,
+                }),
+                Filter::Shortcodes => Box::new(filters::Shortcodes(&shortcodes::SHORTCODES)),
+                Filter::Highlight => Box::new(filters::Highlight {
+                    theme: &config.appearance.highlight_theme,
+                    options: &config.code_blocks,
+                    transforms: &fences::FENCE_TRANSFORMS,
+                    cache: self.context.highlight_cache.as_ref(),
+                }),
+                Filter::DemoteHeadings => Box::new(filters::DemoteHeadings(
+                    front_matter
+                        .heading_offset
+                        .unwrap_or(config.markdown.heading_offset),
+                )),
+                Filter::InjectTitle if config.markdown.inject_title => {
+                    Box::new(filters::InjectTitle(self.chapter_title(chapter)))
+                }
+                Filter::InjectTitle => continue,
+                Filter::Links => Box::new(filters::Links {
+                    base: base.parent().unwrap_or(Path::new("")).to_path_buf(),
+                    root: &self.context.base_path,
+                }),
+                Filter::ThemedImages => Box::new(filters::ThemedImages),
+            });
+        }
+
+        let filters: Vec<&dyn MarkdownFilter> = filters.iter().map(|filter| &**filter).collect();
+        filters::run(events, &filters)
+    }
+
+    /// Renders a page listing every chapter, with their descriptions
+    pub fn render_sitemap(&self) -> Result<String> {
+        let mut items = String::new();
+        let mut in_list = false;
+
+        for item in self.context.content.summary.items.iter() {
+            match item {
+                Item::Chapter(chapter) => {
+                    if !in_list {
+                        items.push_str("<ul class=\"sitemap__list\">");
+                        in_list = true;
+                    }
+
+                    items.push_str(&self.render_sitemap_chapter(chapter)?);
+                }
+                Item::Section(section) => {
+                    if in_list {
+                        items.push_str("</ul>");
+                        in_list = false;
+                    }
+
+                    let sitemap_section = SitemapSection {
+                        title: &section.title,
+                    };
+
+                    items.push_str(&render_template(&sitemap_section, "sitemap/section.html")?);
+
+                    if !section.chapters.is_empty() {
+                        items.push_str("<ul class=\"sitemap__list\">");
+                        for chapter in section.chapters.iter() {
+                            items.push_str(&self.render_sitemap_chapter(chapter)?);
+                        }
+                        items.push_str("</ul>");
+                    }
+                }
+            }
+        }
+
+        if in_list {
+            items.push_str("</ul>");
+        }
+
+        let sitemap = Sitemap {
+            title: &self.context.config.general.title,
+            items: &items,
+        };
+
+        self.render_page(PageParts {
+            title: "Sitemap",
+            path: Some(PathBuf::from(SITEMAP_FILE)),
+            content: render_template(&sitemap, "sitemap.html")?,
+            ..Default::default()
+        })
+    }
+
+    fn render_sitemap_chapter(&self, chapter: &Chapter) -> Result<String> {
+        let subchapters = chapter
+            .subchapters
+            .iter()
+            .map(|item| match item {
+                Item::Chapter(chapter) => self.render_sitemap_chapter(chapter),
+                Item::Section(section) => self.render_sitemap_subsection(section),
+            })
+            .collect::<Result<Vec<String>>>()?
+            .join("");
+        let front_matter = self.front_matter(chapter)?;
+        let target = self.chapter_target(chapter)?;
+
+        let sitemap_chapter = SitemapChapter {
+            title: &self.chapter_title(chapter),
+            icon: front_matter.icon.as_deref().and_then(Icon::new),
+            target: target.as_deref(),
+            external: chapter.url.is_some(),
+            description: &front_matter.description,
+            subchapters: &subchapters,
+        };
+
+        render_template(&sitemap_chapter, "sitemap/chapter.html")
+    }
+
+    // Sections under a chapter are an item of its list, holding their own
+    fn render_sitemap_subsection(&self, section: &Section) -> Result<String> {
+        let sitemap_section = SitemapSection {
+            title: &section.title,
+        };
+        let chapters = section
+            .chapters
+            .iter()
+            .map(|chapter| self.render_sitemap_chapter(chapter))
+            .collect::<Result<Vec<String>>>()?
+            .join("");
+
+        Ok(format!(
+            "<li class=\"sitemap__group\">{}<ul class=\"sitemap__list\">{chapters}</ul></li>",
+            render_template(&sitemap_section, "sitemap/section.html")?
+        ))
+    }
+
+    /// Renders the page the server answers with when something goes wrong
+    pub fn render_error(&self, status: u16, message: &str) -> Result<String> {
+        let error = ErrorPage {
+            base_path: &self.context.base_path,
+            status,
+            message,
+        };
+
+        self.render_page(PageParts {
+            title: &status.to_string(),
+            content: render_template(&error, "error.html")?,
+            ..Default::default()
+        })
+    }
+
+    /// Renders the page with the whole file that an `{{#include path}}` pulled code from (see
+    /// [`includes::source_page`])
+    pub fn render_source<P>(&self, file: P, page: PathBuf) -> Result<String>
+    where
+        P: AsRef<Path>,
+    {
+        let file = file.as_ref();
+        let code = fs::read_to_string(file)
+            .with_context(|| anyhow!("Failed to read contents of {}", file.display()))?;
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let language = file.extension().unwrap_or_default().to_string_lossy();
+        let listing = markdown::source_listing(
+            &code,
+            &language,
+            &self.context.config.appearance.highlight_theme,
         );
-        let renderer = AskamaRenderer::new(context);
-        let tests = vec![
-            ("/some/dir/src/file.txt", "/file.html"),
-            ("/some/dir/src/dir/file", "/dir/file.html"),
-        ];
 
-        for test in tests.iter() {
-            assert_eq!(
-                renderer.get_chapter_target(PathBuf::from(test.0))?,
-                test.1.to_string()
+        let title = util::escape_html(&name);
+
+        self.render_page(PageParts {
+            title: &name,
+            path: Some(page),
+            content: format!("<h1>{title}</h1>\n{listing}"),
+            ..Default::default()
+        })
+    }
+
+    /// Renders a page that sends the reader somewhere else. The target is either a page relative
+    /// to the build dir (e.g. `guide/setup.html`) or an absolute url.
+    pub fn render_redirect(&self, target: &str) -> Result<String> {
+        let target = match target.contains("://") || target.starts_with('/') {
+            true => target.to_string(),
+            false => {
+                let escaped = util::escape_href(target);
+
+                format!("{}/{escaped}", self.context.base_path)
+            }
+        };
+
+        let redirect = Redirect {
+            lang: &self.context.config.general.language,
+            target: &target,
+        };
+
+        render_template(&redirect, "redirect.html")
+    }
+
+    /// Renders the page for missing paths from a markdown file, instead of the default one (see
+    /// [`crate::summary::NOT_FOUND_FILE_NAME`]). Its links work from any path.
+    pub fn render_not_found<P>(&self, path: P) -> Result<String>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let markdown = fs::read_to_string(path)
+            .with_context(|| anyhow!("Failed to read contents of {}", path.display()))?;
+        let (front_matter, markdown) = FrontMatter::extract(&markdown)
+            .with_context(|| anyhow!("Invalid front matter in {}", path.display()))?;
+        let title = front_matter.title.as_deref().unwrap_or("Page not found");
+        let chapter = Chapter::new(title, "", path, Vec::new());
+        let body = self.render_body(&chapter, &front_matter, markdown)?;
+
+        self.render_page(PageParts {
+            title,
+            content: body.html,
+            scripts: body.scripts,
+            headings: body.headings,
+            ..Default::default()
+        })
+    }
+
+    /// Renders the page with the solutions to the exercises of every chapter. It returns `None` if
+    /// there are none. Private chapters are left out, unless they're shown as they are.
+    pub fn render_solutions(&self) -> Result<Option<String>> {
+        let mut content = String::from("<h1>Solutions</h1>\n");
+        let mut scripts = Vec::new();
+        let mut solved = false;
+
+        for chapter in self.context.content.all_chapters().iter() {
+            if !chapter.content.is_file() {
+                continue;
+            }
+
+            let file = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+            let (front_matter, body) = FrontMatter::extract(&file).with_context(|| {
+                anyhow!("Invalid front matter in {}", chapter.content.display())
+            })?;
+            if front_matter.private && self.context.private != PrivateMode::Plain {
+                continue;
+            }
+
+            let solutions = exercises::solutions(&self.expand(chapter, body)?)
+                .with_context(|| anyhow!("Invalid exercises in {}", chapter.content.display()))?;
+            if solutions.is_empty() {
+                continue;
+            }
+            solved = true;
+
+            let page = super::output_path(&chapter.content, &self.context.src_dir)?;
+            let url = util::escape_href(&self.get_chapter_target(chapter.content.clone())?);
+            // Starting with a heading keeps the title from being injected. Links in the solutions
+            // resolve from the chapter, just like they would in it.
+            let mut markdown = format!("## {}\n", self.chapter_title(chapter));
+
+            for solution in solutions {
+                markdown.push_str(&format!(
+                    "\n<h3 class=\"solution__title\" id=\"{}\"><a href=\"{url}#exercise-{n}\">Exercise {n}</a></h3>\n\n{}\n",
+                    exercises::solution_id(&page, solution.exercise),
+                    solution.markdown,
+                    n = solution.exercise,
+                ));
+            }
+
+            let body = self.render_body(chapter, &FrontMatter::default(), &markdown)?;
+            content.push_str(&body.html);
+            scripts.extend(body.scripts);
+        }
+
+        if !solved {
+            return Ok(None);
+        }
+
+        scripts.sort();
+        scripts.dedup();
+
+        self.render_page(PageParts {
+            title: "Solutions",
+            path: Some(PathBuf::from(SOLUTIONS_FILE)),
+            content,
+            scripts,
+            ..Default::default()
+        })
+        .map(Some)
+    }
+
+    /// Renders the page with every chapter, one after the other in the order of the summary, for
+    /// printing the vault like a book. Each chapter starts on a new sheet. `content` has the
+    /// chapters that go into it.
+    pub fn render_print(&self, content: &Content) -> Result<String> {
+        // The dialog would open again after every rebuild while serving
+        self.render_chapters_page(content, "Print", !self.context.live_reload)
+    }
+
+    /// Like [`AskamaRenderer::render_print`], but the page is meant to be read as it is, so it
+    /// doesn't open the print dialog
+    pub fn render_all_chapters(&self, content: &Content) -> Result<String> {
+        self.render_chapters_page(content, &self.context.config.general.title, false)
+    }
+
+    fn render_chapters_page(&self, content: &Content, title: &str, dialog: bool) -> Result<String> {
+        let mut html = String::new();
+        let mut scripts = Vec::new();
+        let mut printed = HashSet::new();
+
+        for item in content.summary.items.iter() {
+            let chapters = match item {
+                Item::Chapter(chapter) => std::slice::from_ref(chapter),
+                Item::Section(section) => {
+                    let title = util::escape_html(&section.title);
+                    html.push_str(&format!("<h1 class=\"print__section\">{title}</h1>\n"));
+
+                    section.chapters.as_slice()
+                }
+            };
+
+            for chapter in chapters {
+                self.render_print_chapter(chapter, &mut printed, &mut html, &mut scripts)?;
+            }
+        }
+
+        scripts.sort();
+        scripts.dedup();
+        if dialog {
+            html.push_str(
+                "<script>window.addEventListener(\"load\", () => window.print());</script>\n",
             );
         }
 
+        self.render_page(PageParts {
+            title,
+            path: Some(PathBuf::from(PRINT_FILE)),
+            content: html,
+            scripts,
+            hidden: true,
+            ..Default::default()
+        })
+    }
+
+    // Chapters that point to the same file (e.g. with anchors) are only printed once. Drafts,
+    // external chapters and the ones only readers with the passphrase can see are left out.
+    fn render_print_chapter<'a>(
+        &'a self,
+        chapter: &'a Chapter,
+        printed: &mut HashSet<&'a Path>,
+        content: &mut String,
+        scripts: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        if chapter.url.is_none()
+            && !chapter.draft
+            && chapter.content.is_file()
+            && printed.insert(&chapter.content)
+        {
+            let file = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+            let (front_matter, markdown) = FrontMatter::extract(&file).with_context(|| {
+                anyhow!("Invalid front matter in {}", chapter.content.display())
+            })?;
+
+            if !front_matter.private || self.context.private == PrivateMode::Plain {
+                let body = self
+                    .render_body(chapter, &front_matter, markdown)
+                    .with_context(|| {
+                        anyhow!(
+                            "Failed to render the chapter {} ({})",
+                            chapter.title,
+                            chapter.content.display()
+                        )
+                    })?;
+
+                content.push_str(&format!(
+                    "<article class=\"print__chapter\">\n{}</article>\n",
+                    body.html
+                ));
+                scripts.extend(body.scripts);
+            }
+        }
+
+        for subchapter in chapter.children() {
+            self.render_print_chapter(subchapter, printed, content, scripts)?;
+        }
+
+        Ok(())
+    }
+
+    /// Renders the page with every element a chapter can have, so the effect of the appearance
+    /// is seen at a glance. The components show even if no chapter would have them.
+    pub fn render_theme_preview(&self) -> Result<String> {
+        let path = self.context.src_dir.join("theme-preview.md");
+        let chapter = Chapter::new("Theme preview", "", &path, Vec::new());
+        let mut body = self.render_body(&chapter, &FrontMatter::default(), THEME_PREVIEW)?;
+
+        let current = &self.context.config.appearance.highlight_theme;
+        let mut themes: Vec<&str> = highlight::THEMES.to_vec();
+        if !themes.contains(&current.as_str()) {
+            themes.push(current);
+        }
+
+        body.html
+            .push_str("<h2 id=\"code-themes\">Code themes</h2>\n");
+        body.headings.push(Heading {
+            level: 2,
+            text: String::from("Code themes"),
+            anchor: String::from("code-themes"),
+        });
+        for theme in themes {
+            let name = match theme == current {
+                true => format!("{theme} (current)"),
+                false => theme.to_string(),
+            };
+            let title = util::escape_html(&name);
+
+            body.html.push_str(&format!(
+                "<h3>{title}</h3>\n{}",
+                markdown::source_listing(THEME_PREVIEW_CODE, "rust", theme)
+            ));
+        }
+
+        let front_matter = FrontMatter {
+            status: Some(String::from("reviewed")),
+            owner: Some(String::from("Obi-Wan Kenobi")),
+            reviewed: Some(self.context.today),
+            ..Default::default()
+        };
+        let metadata = Metadata {
+            banner: true,
+            status: &front_matter.status,
+            owner: &front_matter.owner,
+            reviewed: &front_matter.reviewed,
+            outdated: Some(self.context.config.review.max_age.into()),
+        };
+        let prerequisites = Prerequisites {
+            chapters: &[(String::from("A chapter to read first"), String::from("#"))],
+        };
+        let contributors = [Author {
+            name: String::from("Obi-Wan Kenobi"),
+            email: String::from("obi@wan.com"),
+        }];
+
+        self.render_page(PageParts {
+            title: "Theme preview",
+            path: Some(PathBuf::from(THEME_PREVIEW_FILE)),
+            metadata: render_template(&metadata, "metadata.html")?
+                + &render_template(&prerequisites, "prerequisites.html")?,
+            front_matter,
+            content: body.html,
+            scripts: body.scripts,
+            headings: body.headings,
+            counts: Some(Counts::from_markdown(THEME_PREVIEW)),
+            last_updated: Some(self.context.today),
+            contributors: &contributors,
+            hidden: true,
+            ..Default::default()
+        })
+    }
+
+    // Wraps the content with everything else in the page (header, sidebar, styles, etc.)
+    fn render_page(&self, parts: PageParts) -> Result<String> {
+        let header = self.render_header(parts.path.as_deref())?;
+        let sidebar = self.render_sidebar()?;
+        let mut custom_css = Vec::new();
+        let mut custom_js = Vec::new();
+
+        for file in self.context.custom_files.iter() {
+            let file_name = file.file_name().with_context(|| {
+                anyhow!("Failed to extract the file name from {}", file.display())
+            })?;
+            let url = format!("{}/{}", self.context.base_path, file_name.to_string_lossy());
+
+            match file.extension().is_some_and(|extension| extension == "js") {
+                true => custom_js.push(url),
+                false => custom_css.push(url),
+            }
+        }
+
+        let title = match self.context.config.general.title.is_empty() {
+            true => parts.title.to_string(),
+            false => format!("{} - {}", parts.title, self.context.config.general.title),
+        };
+        let canonical = match (&self.context.config.general.site_url, &parts.path) {
+            (Some(_), Some(path)) => Some(self.url(path)),
+            _ => None,
+        };
+        let image = parts.image.as_ref().map(|image| self.url(image));
+
+        let general = &self.context.config.general;
+        let description = parts
+            .front_matter
+            .description
+            .as_deref()
+            .or((!general.desc.is_empty()).then_some(general.desc.as_str()));
+
+        let favicons = match &self.context.config.appearance.favicon {
+            Some(favicon) => {
+                favicon::favicons(favicon, self.context.config.appearance.favicon_variants)?
+            }
+            None => Vec::new(),
+        };
+
+        let mut themes = self.context.config.appearance.themes.clone();
+        let mut theme_css = Vec::new();
+        let mut theme_scripts = Vec::new();
+        for theme in self.context.themes.iter() {
+            let dir = format!("{}/{THEMES_DIR}/{}", self.context.base_path, theme.name);
+
+            if !themes.contains(&theme.name) {
+                themes.push(theme.name.clone());
+            }
+            theme_css.push(format!("{dir}/{THEME_CSS}"));
+            if theme.script {
+                theme_scripts.push(format!("{dir}/{THEME_JS}"));
+            }
+        }
+
+        let light_variants = &self.context.config.appearance.light_variants;
+        let themes: Vec<ThemeOption> = themes
+            .iter()
+            .map(|theme| ThemeOption {
+                name: theme,
+                light: light_variants.get(theme).map(String::as_str),
+            })
+            .collect();
+
+        let page_toc = self.render_page_toc(&parts.headings)?;
+        let shortcuts = self.render_shortcuts(!page_toc.is_empty(), themes.len())?;
+        let contributors = self.render_contributors(parts.contributors)?;
+        let footer = self.render_footer(parts.last_updated)?;
+        let reading = &self.context.config.reading_time;
+        let reading_time = parts
+            .counts
+            .filter(|_| reading.enabled)
+            .map(|counts| ReadingTime {
+                words: counts.words,
+                minutes: counts.reading_time(reading.words_per_minute),
+            });
+
+        let index = Page {
+            base_path: &self.context.base_path,
+            lang: &general.language,
+            title: &title,
+            page_title: parts.title,
+            site_name: &general.title,
+            description,
+            canonical: canonical.as_deref(),
+            image: image.as_deref(),
+            front_matter: &parts.front_matter,
+            accent: parts.accent,
+            theme: &self.context.config.appearance.default_theme,
+            header: &header,
+            sidebar: &sidebar,
+            metadata: &parts.metadata,
+            content: &parts.content,
+            page_toc: &page_toc,
+            shortcuts: &shortcuts,
+            contributors: &contributors,
+            footer: &footer,
+            custom_css: &custom_css,
+            custom_js: &custom_js,
+            head_include: &self.context.head_include,
+            favicons: &favicons,
+            themes: &themes,
+            theme_css: &theme_css,
+            theme_scripts: &theme_scripts,
+            live_reload: self.context.live_reload.then_some(LIVE_RELOAD_ENDPOINT),
+            feed: self.context.config.feed.enabled.then_some(FEED_FILE),
+            scripts: &parts.scripts,
+            reading_time,
+            hidden: parts.hidden,
+        };
+
+        render_template(&index, "index.html")
+    }
+
+    fn render_contributors(&self, authors: &[Author]) -> Result<String> {
+        if authors.is_empty() {
+            return Ok(String::new());
+        }
+
+        let repository = self.context.config.contributors.repository.as_deref();
+        let contributors: Vec<Contributor> = authors
+            .iter()
+            .map(|author| Contributor {
+                name: &author.name,
+                avatar: repository.and_then(|repository| avatar(repository, &author.email)),
+            })
+            .collect();
+
+        render_template(
+            &ContributorList {
+                contributors: &contributors,
+            },
+            "contributors.html",
+        )
+    }
+
+    // Only the features the page has get a shortcut, so the help never lists a key that does
+    // nothing
+    fn render_shortcuts(&self, page_toc: bool, themes: usize) -> Result<String> {
+        let mut shortcuts = vec![Shortcut {
+            key: "?",
+            action: "help",
+            description: "Show this help",
+        }];
+
+        if self.context.config.search.enabled {
+            shortcuts.push(Shortcut {
+                key: "/",
+                action: "search",
+                description: "Search",
+            });
+        }
+        if page_toc {
+            shortcuts.push(Shortcut {
+                key: "n",
+                action: "next-section",
+                description: "Go to the next section",
+            });
+            shortcuts.push(Shortcut {
+                key: "p",
+                action: "previous-section",
+                description: "Go to the previous section",
+            });
+        }
+        shortcuts.push(Shortcut {
+            key: "s",
+            action: "sitemap",
+            description: "Open the sitemap",
+        });
+        if themes > 1 {
+            shortcuts.push(Shortcut {
+                key: "t",
+                action: "theme",
+                description: "Switch to the next theme",
+            });
+        }
+        if !self.context.config.appearance.light_variants.is_empty() {
+            shortcuts.push(Shortcut {
+                key: "d",
+                action: "scheme",
+                description: "Switch between light and dark",
+            });
+        }
+        if self.context.languages.len() > 1 {
+            shortcuts.push(Shortcut {
+                key: "l",
+                action: "language",
+                description: "Pick another language",
+            });
+        }
+
+        render_template(
+            &ShortcutList {
+                base_path: &self.context.base_path,
+                sitemap: SITEMAP_FILE,
+                shortcuts: &shortcuts,
+            },
+            "shortcuts.html",
+        )
+    }
+
+    // Only the sections and their subsections are listed, since H1 is usually the title. A single
+    // one isn't worth a table of contents.
+    fn render_page_toc(&self, headings: &[Heading]) -> Result<String> {
+        let headings: Vec<&Heading> = headings
+            .iter()
+            .filter(|heading| (2..=3).contains(&heading.level))
+            .collect();
+
+        if !self.context.config.appearance.page_toc || headings.len() < 2 {
+            return Ok(String::new());
+        }
+
+        render_template(
+            &PageToc {
+                headings: &headings,
+            },
+            "page_toc.html",
+        )
+    }
+
+    // The url of a file of the build dir. It's only absolute if the site url is known
+    fn url(&self, path: &Path) -> String {
+        let mut url = match &self.context.config.general.site_url {
+            Some(site_url) => format!("{}/", site_url.trim_end_matches('/')),
+            None => format!("{}/", self.context.base_path),
+        };
+        url.push_str(&util::escape_href(&util::url_path(path)));
+
+        url
+    }
+
+    // Sections are groups the reader can collapse, with their chapters inside
+    fn render_sidebar_section(&self, section: &Section) -> Result<String> {
+        let accent = self.accent(section);
+        let chapters = section
+            .chapters
+            .iter()
+            .map(|chapter| self.render_sidebar_chapter(chapter, accent))
+            .collect::<Result<Vec<String>>>()?
+            .join("");
+
+        let sidebar_section = SidebarSection {
+            title: &section.title,
+            accent,
+            chapters: &chapters,
+        };
+
+        render_template(&sidebar_section, "sidebar/section.html")
+    }
+
+    // Colors end up inside a style attribute, so anything that isn't a plain color is ignored
+    fn accent(&self, section: &Section) -> Option<&str> {
+        self.context
+            .config
+            .appearance
+            .accents
+            .get(&section.title)
+            .map(|accent| accent.trim())
+            .filter(|accent| {
+                !accent.is_empty()
+                    && accent
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "#(),.% -".contains(c))
+            })
+    }
+}
+
+// Askama checks every variable when the templates are compiled, so a missing or misspelled one
+// never gets this far. What fails here is a value that couldn't be formatted, and the error says
+// in which template.
+// The avatar of whoever has the email, on the forge the repository is hosted in. Only GitHub and
+// GitLab, including the ones people host themselves, look them up by email.
+fn avatar(repository: &str, email: &str) -> Option<String> {
+    let (scheme, rest) = repository.split_once("://")?;
+    let host = rest.split('/').next()?;
+    let email = util::percent_encode(email);
+
+    match host {
+        "github.com" => Some(format!(
+            "https://avatars.githubusercontent.com/u/e?email={email}&s=48"
+        )),
+        host if host.contains("gitlab") => Some(format!(
+            "{scheme}://{host}/api/v4/avatar?email={email}&size=48"
+        )),
+        _ => None,
+    }
+}
+
+fn render_template<T>(template: &T, path: &str) -> Result<String>
+where
+    T: Template,
+{
+    template
+        .render()
+        .with_context(|| anyhow!("Failed to render the template {path}"))
+}
+
+impl Renderer for AskamaRenderer {
+    fn render(&self, chapter: &Chapter) -> Result<String> {
+        let markdown = fs::read_to_string(&chapter.content)
+            .with_context(|| anyhow!("Failed to read contents of {}", chapter.content.display()))?;
+
+        self.render_markdown(chapter, &markdown)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_get_chapter_target() -> Result<()> {
+        let tempdir = tempdir()?;
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            PathBuf::from("/some/dir/src"),
+        );
+        let renderer = AskamaRenderer::new(context);
+        let tests = [
+            ("/some/dir/src/file.txt", "/file.html"),
+            ("/some/dir/src/dir/file", "/dir/file.html"),
+        ];
+
+        for test in tests.iter() {
+            assert_eq!(
+                renderer.get_chapter_target(PathBuf::from(test.0))?,
+                test.1.to_string()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_render_the_metadata_banner() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("chapter1.md");
+        fs::write(
+            &chapter_path,
+            "---\nstatus: draft\nowner: Obi-Wan\n---\n# Hello there",
+        )?;
+
+        let chapter = Chapter::new("Chapter1", "1", &chapter_path, vec![]);
+        let mut config = crate::config::Config::default();
+        config.appearance.metadata_banner = true;
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        );
+        let page = AskamaRenderer::new(context).render(&chapter)?;
+
+        assert!(page.contains("metadata__status--draft"));
+        assert!(page.contains("Obi-Wan"));
+        assert!(!page.contains("status: draft"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_mark_the_outdated_chapters() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("chapter1.md");
+        fs::write(
+            &chapter_path,
+            "---\nreviewed: 2024-01-01\n---\n# Hello there",
+        )?;
+
+        let chapter = Chapter::new("Chapter1", "1", &chapter_path, vec![]);
+        let mut config = crate::config::Config::default();
+        config.review.max_age = 29;
+        config.review.outdated_badge = true;
+        let render = |today| -> Result<String> {
+            let context = RendererContext::new(
+                crate::Content::new(tempdir.path())?,
+                config.clone(),
+                tempdir.path().to_path_buf(),
+            )
+            .with_today(NaiveDate::from_ymd_opt(2024, 1, today).unwrap_or_default());
+
+            AskamaRenderer::new(context).render(&chapter)
+        };
+
+        let page = render(31)?;
+        assert!(page.contains("title=\"Last reviewed 30 days ago\""));
+        // The banner is off, so the badge is all there is
+        assert!(!page.contains("Reviewed on"));
+        assert!(!render(30)?.contains("metadata__outdated"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_expose_the_front_matter_to_the_page() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("chapter1.md");
+        fs::write(
+            &chapter_path,
+            "---\ndescription: Hello <there>\n---\n# General Kenobi",
+        )?;
+
+        let chapter = Chapter::new("Chapter1", "1", &chapter_path, vec![]);
+        let mut config = crate::config::Config::default();
+        config.general.title = "Star Wars".to_string();
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        );
+        let page = AskamaRenderer::new(context).render(&chapter)?;
+
+        assert!(page.contains("<title>Chapter1 - Star Wars</title>"));
+        assert!(page.contains("<meta name=\"description\" content=\"Hello &lt;there&gt;\" />"));
+        assert!(!page.contains("description:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_say_what_failed_to_render() -> Result<()> {
+        struct Broken;
+
+        impl std::fmt::Display for Broken {
+            fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                Err(std::fmt::Error)
+            }
+        }
+
+        #[derive(Template)]
+        #[template(source = "{{ broken }}", ext = "txt")]
+        struct BrokenTemplate {
+            broken: Broken,
+        }
+
+        let error = render_template(&BrokenTemplate { broken: Broken }, "broken.txt").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to render the template broken.txt"
+        );
+
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("chapter1.md");
+        let chapter = Chapter::new("Chapter1", "1", &chapter_path, vec![]);
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        );
+        let error = AskamaRenderer::new(context)
+            .render_markdown(&chapter, "---\nreviewed: yesterday\n---\n")
+            .unwrap_err();
+
+        assert!(format!("{error:#}").starts_with(&format!(
+            "Failed to render the chapter Chapter1 ({}): Invalid front matter",
+            chapter_path.display()
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_render_the_sitemap() -> Result<()> {
+        let tempdir = tempdir()?;
+        fs::write(
+            tempdir.path().join("summary.md"),
+            "[Intro](./intro.md)\n# Part 1\n- [Chapter 1](./chapter1.md)\n    - [Chapter 1.1](./chapter1.1.md)",
+        )?;
+        fs::write(
+            tempdir.path().join("chapter1.md"),
+            "---\ndescription: Where the fun begins\n---\n",
+        )?;
+
+        let mut config = crate::config::Config::default();
+        config.general.title = String::from("Obi & <Anakin>");
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        );
+        let sitemap = AskamaRenderer::new(context).render_sitemap()?;
+
+        assert!(sitemap.contains("<h1>Obi &amp; &lt;Anakin&gt;</h1>"));
+        assert!(sitemap.contains("href=\"/intro.html\""));
+        assert!(sitemap.contains("Part 1"));
+        assert!(sitemap.contains("Where the fun begins"));
+        assert!(sitemap.contains("href=\"/chapter1.1.html\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_show_chapter_icons() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("chapter1.md");
+        fs::write(
+            tempdir.path().join("summary.md"),
+            "- [Chapter 1](./chapter1.md)\n- [Chapter 2](./chapter2.md)\n- [Chapter 3]()",
+        )?;
+        fs::write(&chapter_path, "---\nicon: rocket\n---\n# Hello there")?;
+        fs::write(
+            tempdir.path().join("chapter2.md"),
+            "---\nicon: \"<🚀>\"\n---\n",
+        )?;
+
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        );
+        let renderer = AskamaRenderer::new(context);
+        let page = renderer.render(&Chapter::new("Chapter 1", "1", &chapter_path, vec![]))?;
+        let sitemap = renderer.render_sitemap()?;
+
+        for html in [page, sitemap] {
+            assert!(html.contains("<i class=\"ti ti-rocket icon\"></i>Chapter 1"));
+            assert!(html.contains("<span class=\"icon\">&lt;🚀&gt;</span>Chapter 2"));
+            assert!(!html.contains("</span>Chapter 3"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_link_external_chapters() -> Result<()> {
+        let tempdir = tempdir()?;
+        fs::write(
+            tempdir.path().join("summary.md"),
+            "- [Rust Book](https://doc.rust-lang.org/book?a=1&b=2)",
+        )?;
+
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        );
+        let renderer = AskamaRenderer::new(context);
+        let page = renderer.render_error(404, "Not found")?;
+        let sitemap = renderer.render_sitemap()?;
+
+        for html in [page, sitemap] {
+            assert!(html.contains(
+                "href=\"https://doc.rust-lang.org/book?a=1&amp;b=2\" target=\"_blank\" rel=\"noopener noreferrer\""
+            ));
+            assert!(html.contains("ti-external-link"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_group_chapters_in_collapsible_sections() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("intro.md");
+        fs::write(
+            tempdir.path().join("summary.md"),
+            "[Intro](./intro.md)\n# Part 1\n- [Chapter 1](./chapter1.md)\n- [Chapter 2](./chapter2.md)",
+        )?;
+        fs::write(&chapter_path, "# Hello there")?;
+
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        );
+        let page = AskamaRenderer::new(context).render(&Chapter::new(
+            "Intro",
+            "",
+            &chapter_path,
+            vec![],
+        ))?;
+        let group = &page[page.find("<details").unwrap()..page.find("</details>").unwrap()];
+
+        assert!(group.contains("data-section=\"Part 1\" open>"));
+        assert!(group.contains("<summary class=\"table-of-contents__section\">Part 1</summary>"));
+        assert!(group.contains("Chapter 1") && group.contains("Chapter 2"));
+        assert!(!group.contains("Intro"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_color_sections_with_their_accent() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("chapter1.md");
+        fs::write(
+            tempdir.path().join("summary.md"),
+            "[Intro](./intro.md)\n# Part 1\n- [Chapter 1](./chapter1.md)\n# Part 2\n- [Chapter 2](./chapter2.md)",
+        )?;
+        fs::write(&chapter_path, "# Hello there")?;
+        fs::write(tempdir.path().join("intro.md"), "# General Kenobi")?;
+
+        let mut config = crate::config::Config::default();
+        config
+            .appearance
+            .accents
+            .insert("Part 1".to_string(), "#e06c75".to_string());
+        config
+            .appearance
+            .accents
+            .insert("Part 2".to_string(), "red\" onclick=\"alert(1)".to_string());
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        );
+        let renderer = AskamaRenderer::new(context);
+        let page = renderer.render(&Chapter::new("Chapter 1", "1", &chapter_path, vec![]))?;
+
+        assert!(page.contains("class=\"container container--accent\" style=\"--accent: #e06c75\""));
+        assert!(page
+            .contains("table-of-contents__section--accent\" style=\"--accent: #e06c75\">Part 1"));
+        assert!(!page.contains("onclick"));
+
+        let intro = renderer.render(&Chapter::new(
+            "Intro",
+            "",
+            tempdir.path().join("intro.md"),
+            vec![],
+        ))?;
+        assert!(intro.contains("<div class=\"container\">"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_describe_the_page_for_link_previews() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("setup.md");
+        let chapter = Chapter::new("Setup & more", "1", &chapter_path, vec![]);
+        let mut config = crate::config::Config::default();
+        config.general.title = "Guide".to_string();
+        config.general.desc = "All about it".to_string();
+        config.general.site_url = Some("https://example.com".to_string());
+        let renderer = AskamaRenderer::new(RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        ));
+
+        let page = renderer.render_markdown(&chapter, "# Setup")?;
+        assert!(page.contains("<meta property=\"og:title\" content=\"Setup &amp; more\" />"));
+        assert!(page.contains("<meta property=\"og:site_name\" content=\"Guide\" />"));
+        assert!(page.contains("<meta property=\"og:description\" content=\"All about it\" />"));
+        assert!(page
+            .contains("<meta property=\"og:url\" content=\"https://example.com/setup.html\" />"));
+        assert!(page.contains("<meta name=\"twitter:card\" content=\"summary\" />"));
+
+        let page =
+            renderer.render_markdown(&chapter, "---\ndescription: Just this\n---\n# Setup")?;
+        assert!(page.contains("<meta property=\"og:description\" content=\"Just this\" />"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_prefix_every_url_with_the_base_path() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("chapter1.md");
+        fs::write(&chapter_path, "[Next](./chapter2.md)")?;
+
+        let chapter = Chapter::new("Chapter1", "1", &chapter_path, vec![]);
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        )
+        .with_base_path("docs/");
+        let page = AskamaRenderer::new(context).render(&chapter)?;
+
+        assert!(page.contains("href=\"/docs/chapter2.html\""));
+        assert!(page.contains("href=\"/docs/main.css\""));
+        assert!(page.contains("href=\"/docs/sitemap.html\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_render_an_error_page() -> Result<()> {
+        let tempdir = tempdir()?;
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        );
+        let page = AskamaRenderer::new(context).render_error(403, "<Nope>")?;
+
+        assert!(page.contains("403"));
+        assert!(page.contains("&lt;Nope&gt;"));
+        assert!(page.contains("class=\"sidebar"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_load_the_mermaid_script_when_needed() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter = Chapter::new("Chapter1", "1", tempdir.path().join("chapter1.md"), vec![]);
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        );
+        let renderer = AskamaRenderer::new(context);
+
+        let page = renderer.render_markdown(&chapter, "```mermaid\ngraph TD;\n```")?;
+        assert!(page.contains("<pre class=\"mermaid\">"));
+        assert!(page.contains("src=\"/mermaid.min.js\""));
+
+        let page = renderer.render_markdown(&chapter, "# Hello there")?;
+        assert!(!page.contains("mermaid.min.js"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_show_how_long_the_chapter_takes_to_read() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter = Chapter::new("Chapter1", "1", tempdir.path().join("chapter1.md"), vec![]);
+        let mut config = crate::config::Config::default();
+        let markdown =
+            "---\nowner: Someone Else\n---\n# Hello there\n\nGeneral Kenobi, you are a bold one";
+
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config.clone(),
+            tempdir.path().to_path_buf(),
+        );
+        let page = AskamaRenderer::new(context).render_markdown(&chapter, markdown)?;
+        assert!(!page.contains("class=\"reading-time\""));
+
+        config.reading_time.enabled = true;
+        config.reading_time.words_per_minute = 4;
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        );
+        let page = AskamaRenderer::new(context).render_markdown(&chapter, markdown)?;
+        assert!(page.contains("</i> 3 min read · 9 words</p>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_run_the_filters_of_the_config() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter = Chapter::new("Chapter1", "1", tempdir.path().join("chapter1.md"), vec![]);
+        let mut config = crate::config::Config::default();
+        let markdown = "# Hi\n\n[Setup](setup.md)\n\n```rust\nfn main() {}\n```";
+        let render = |config: &crate::config::Config| {
+            let context = RendererContext::new(
+                crate::Content::new(tempdir.path())?,
+                config.clone(),
+                tempdir.path().to_path_buf(),
+            );
+            AskamaRenderer::new(context).render_content(&chapter, markdown)
+        };
+
+        let html = render(&config)?;
+        assert!(html.contains("href=\"/setup.html\""));
+        assert!(html.contains("<div class=\"code-block\">"));
+
+        config.markdown.filters = vec![Filter::Links];
+        let html = render(&config)?;
+        assert!(html.contains("href=\"/setup.html\""));
+        assert!(html.contains("<pre><code class=\"language-rust\">fn main() {}"));
+
+        config.markdown.filters = vec![];
+        assert!(render(&config)?.contains("href=\"setup.md\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_let_the_front_matter_override_the_heading_offset() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter = Chapter::new("Chapter1", "1", tempdir.path().join("chapter1.md"), vec![]);
+        let mut config = crate::config::Config::default();
+        config.markdown.heading_offset = 1;
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        );
+        let renderer = AskamaRenderer::new(context);
+
+        assert!(renderer.render_content(&chapter, "# Hi")?.contains("<h2"));
+        assert!(renderer
+            .render_content(&chapter, "---\nheading_offset: 0\n---\n# Hi")?
+            .contains("<h1"));
+        assert!(renderer
+            .render_content(&chapter, "---\nheading_offset: 2\n---\n# Hi")?
+            .contains("<h3"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_say_when_the_chapter_was_last_updated() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter = Chapter::new("Chapter1", "1", tempdir.path().join("chapter1.md"), vec![]);
+        let date = chrono::DateTime::parse_from_rfc3339("2024-03-01T10:00:00Z")?.to_utc();
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        );
+
+        let page = AskamaRenderer::new(context.clone()).render_markdown(&chapter, "# Hi")?;
+        assert!(!page.contains("footer__updated"));
+
+        let context = context.with_last_updated([(chapter.content.clone(), date)].into());
+        let page = AskamaRenderer::new(context).render_markdown(&chapter, "# Hi")?;
+        assert!(page.contains("</i>Last updated on 2024-03-01"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_list_who_committed_to_the_chapter() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter = Chapter::new("Chapter1", "1", tempdir.path().join("chapter1.md"), vec![]);
+        let mut config = crate::config::Config::default();
+        config.contributors.repository = Some(String::from("https://github.com/obi/wan"));
+        let author = Author {
+            name: String::from("Obi <Wan>"),
+            email: String::from("obi+wan@example.com"),
+        };
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        )
+        .with_contributors([(chapter.content.clone(), vec![author])].into());
+
+        let page = AskamaRenderer::new(context).render_markdown(&chapter, "# Hi")?;
+        assert!(page.contains("e?email=obi%2Bwan%40example.com&amp;s=48"));
+        assert!(page.contains("Obi &lt;Wan&gt;"));
+
+        assert_eq!(
+            avatar("https://gitlab.example.com/obi/wan", "obi@wan.com"),
+            Some(String::from(
+                "https://gitlab.example.com/api/v4/avatar?email=obi%40wan.com&size=48"
+            ))
+        );
+        assert_eq!(avatar("https://codeberg.org/obi/wan", "obi@wan.com"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_list_the_sections_of_the_page() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter = Chapter::new("Chapter1", "1", tempdir.path().join("chapter1.md"), vec![]);
+        let mut config = crate::config::Config::default();
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config.clone(),
+            tempdir.path().to_path_buf(),
+        );
+        let markdown = "# Title\n\n## Install\n\n### With *cargo*\n\n#### Details";
+
+        let page = AskamaRenderer::new(context.clone()).render_markdown(&chapter, markdown)?;
+        assert!(page.contains("<h2 id=\"install\">Install<a class=\"heading__permalink\""));
+        assert!(page.contains("page-toc__item--h2\">\n            <a class=\"page-toc__link\" href=\"#install\">Install</a>"));
+        assert!(page.contains("href=\"#with-cargo\">With cargo</a>"));
+        assert!(!page.contains("page-toc__link\" href=\"#title\""));
+        assert!(!page.contains("page-toc__link\" href=\"#details\""));
+
+        let page = AskamaRenderer::new(context.clone()).render_markdown(&chapter, "## Only")?;
+        assert!(!page.contains("class=\"page-toc\""));
+
+        config.appearance.page_toc = false;
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        );
+        let page = AskamaRenderer::new(context).render_markdown(&chapter, markdown)?;
+        assert!(!page.contains("class=\"page-toc\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_list_the_shortcuts_of_enabled_features() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter = Chapter::new("Chapter1", "1", tempdir.path().join("chapter1.md"), vec![]);
+        let mut config = crate::config::Config::default();
+        config.search.enabled = false;
+        config.appearance.themes = vec![String::from("gruvbox")];
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            config,
+            tempdir.path().to_path_buf(),
+        );
+        let renderer = AskamaRenderer::new(context);
+
+        let page = renderer.render_markdown(
+            &chapter,
+            "# Title
+
+## Install
+
+## Usage",
+        )?;
+        assert!(page.contains("<tr data-key=\"?\" data-action=\"help\">"));
+        assert!(page.contains("<tr data-key=\"n\" data-action=\"next-section\">"));
+        assert!(page.contains("<tr data-key=\"s\" data-action=\"sitemap\">"));
+        assert!(!page.contains("data-action=\"search\""));
+        assert!(!page.contains("data-action=\"theme\""));
+        assert!(!page.contains("data-action=\"language\""));
+
+        let page = renderer.render_markdown(&chapter, "# Title")?;
+        assert!(!page.contains("data-action=\"next-section\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_inject_the_live_reload_client_only_when_enabled() -> Result<()> {
+        let tempdir = tempdir()?;
+        let chapter_path = tempdir.path().join("chapter1.md");
+        fs::write(&chapter_path, "# Hello there")?;
+
+        let chapter = Chapter::new("Chapter1", "1", &chapter_path, vec![]);
+        let context = RendererContext::new(
+            crate::Content::new(tempdir.path())?,
+            crate::config::Config::default(),
+            tempdir.path().to_path_buf(),
+        );
+
+        let page = AskamaRenderer::new(context.clone()).render(&chapter)?;
+        assert!(!page.contains(LIVE_RELOAD_ENDPOINT));
+
+        let page = AskamaRenderer::new(context.with_live_reload(true)).render(&chapter)?;
+        assert!(page.contains(LIVE_RELOAD_ENDPOINT));
+
         Ok(())
     }
 }
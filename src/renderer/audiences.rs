@@ -0,0 +1,115 @@
+//! Parts of a chapter only some readers get, written as containers in the chapters:
+//!
+//! ```markdown
+//! ::: only internal
+//! The staging cluster lives at staging.example.com
+//! :::
+//! ```
+//!
+//! A build for an audience (e.g. `build --audience internal`) keeps the blocks for it, without
+//! their markers, and leaves out the others. Builds without an audience leave out every block, so
+//! forgetting to pass one never publishes more than it should.
+
+use super::includes::{self, LineKind};
+use anyhow::Result;
+
+const OPENING: &str = ":::";
+
+const ONLY: &str = "only";
+
+// A container that's open
+struct Container {
+    /// Is it a block for some audience?
+    only: bool,
+    /// Is what's inside of it kept?
+    keep: bool,
+    /// Where it starts, counting from 0
+    line: usize,
+}
+
+/// Leaves out the blocks that aren't for the audience. Other containers (e.g. exercises) can be
+/// inside of them or around them.
+pub fn audiences(markdown: &str, audience: Option<&str>) -> Result<String> {
+    let mut output = String::with_capacity(markdown.len());
+    let mut open: Vec<Container> = Vec::new();
+
+    for (number, (line, kind)) in includes::lines(markdown).into_iter().enumerate() {
+        let visible = open.iter().all(|container| container.keep);
+        let marker = match kind {
+            LineKind::Code | LineKind::Closing => None,
+            _ => line.trim().strip_prefix(OPENING).map(str::trim),
+        };
+
+        // The markers of the blocks are left out, along with what they leave out
+        let is_block_marker = match marker
+            .map(|marker| marker.split_whitespace().collect::<Vec<_>>())
+        {
+            Some(words) if words.first() == Some(&ONLY) => {
+                if words.len() == 1 {
+                    anyhow::bail!("The block at line {} isn't for any audience. List them after {OPENING} {ONLY}", number + 1);
+                }
+
+                open.push(Container {
+                    only: true,
+                    keep: audience.is_some_and(|audience| words[1..].contains(&audience)),
+                    line: number,
+                });
+                true
+            }
+            Some(words) if words.is_empty() => open.pop().is_some_and(|container| container.only),
+            Some(_) => {
+                open.push(Container {
+                    only: false,
+                    keep: true,
+                    line: number,
+                });
+                false
+            }
+            None => false,
+        };
+
+        if visible && !is_block_marker {
+            output.push_str(line);
+        }
+    }
+
+    if let Some(container) = open.iter().find(|container| container.only) {
+        anyhow::bail!(
+            "The block at line {} isn't closed with {OPENING}",
+            container.line + 1
+        );
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CHAPTER: &str = "# Deploying\n\n::: only internal partners\nAsk Obi for the keys\n\n::: exercise\nDeploy it\n:::\n:::\n\n::: only public\nOpen a ticket\n:::\n\n```\n::: only internal\n```\n";
+
+    #[test]
+    fn it_should_keep_the_blocks_of_the_audience() -> Result<()> {
+        assert_eq!(
+            audiences(CHAPTER, Some("internal"))?,
+            "# Deploying\n\nAsk Obi for the keys\n\n::: exercise\nDeploy it\n:::\n\n\n```\n::: only internal\n```\n"
+        );
+        assert_eq!(
+            audiences(CHAPTER, Some("public"))?,
+            "# Deploying\n\n\nOpen a ticket\n\n```\n::: only internal\n```\n"
+        );
+        assert_eq!(
+            audiences(CHAPTER, None)?,
+            "# Deploying\n\n\n\n```\n::: only internal\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reject_blocks_without_an_audience_or_an_end() {
+        assert!(audiences("::: only\nHello there\n:::\n", None).is_err());
+        assert!(audiences("::: only internal\nHello there\n", None).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::Aes256Gcm;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha256;
+
+/// How many rounds of PBKDF2 the key is derived with. Browsers take a fraction of a second for
+/// them, but guessing passphrases gets that much slower.
+pub const ITERATIONS: u32 = 100_000;
+
+/// What a page becomes once encrypted, encoded in base64 so it can go in the html. The data ends
+/// with the authentication tag, which is what the Web Crypto API expects.
+pub struct Encrypted {
+    pub salt: String,
+    pub iv: String,
+    pub data: String,
+}
+
+/// Encrypts the data with AES-256-GCM and a key derived from the passphrase with
+/// PBKDF2-HMAC-SHA256. Each call has its own salt and iv, so the same page never looks the same.
+pub fn encrypt(data: &[u8], passphrase: &str) -> Result<Encrypted> {
+    let mut salt = [0; 16];
+    let mut iv = [0; 12];
+    getrandom::getrandom(&mut salt).map_err(|err| anyhow!("Failed to generate a salt: {err}"))?;
+    getrandom::getrandom(&mut iv).map_err(|err| anyhow!("Failed to generate an iv: {err}"))?;
+
+    let key = derive_key(passphrase.as_bytes(), &salt, ITERATIONS);
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(&key));
+    let data = cipher
+        .encrypt(GenericArray::from_slice(&iv), data)
+        .map_err(|_| anyhow!("Failed to encrypt the page"))?;
+
+    Ok(Encrypted {
+        salt: STANDARD.encode(salt),
+        iv: STANDARD.encode(iv),
+        data: STANDARD.encode(data),
+    })
+}
+
+fn derive_key(passphrase: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0; 32];
+    pbkdf2_hmac::<Sha256>(passphrase, salt, iterations, &mut key);
+
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_derive_the_key_with_pbkdf2() {
+        // From the test vectors of RFC 7914
+        let key = derive_key(b"passwd", b"salt", 1);
+
+        assert_eq!(
+            key.iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>(),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+    }
+
+    #[test]
+    fn it_should_encrypt_what_the_passphrase_decrypts() -> Result<()> {
+        let encrypted = encrypt(b"<p>Hello there</p>", "kenobi")?;
+        let salt = STANDARD.decode(&encrypted.salt)?;
+        let key = derive_key(b"kenobi", &salt, ITERATIONS);
+        let decrypted = Aes256Gcm::new(GenericArray::from_slice(&key))
+            .decrypt(
+                GenericArray::from_slice(&STANDARD.decode(&encrypted.iv)?),
+                STANDARD.decode(&encrypted.data)?.as_slice(),
+            )
+            .map_err(|_| anyhow!("Failed to decrypt"))?;
+
+        assert_eq!(decrypted, b"<p>Hello there</p>");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+//! Just enough of EPUB 3 for e-readers: one XHTML document per chapter, a navigation document
+//! with the summary, a stylesheet and the images the chapters show. The archive is a ZIP whose
+//! files are stored as they are, since images, the bulk of a book, are compressed already.
+
+use crate::{static_files, util};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+/// Where the files of the book live inside of the archive
+const PACKAGE_DIR: &str = "OEBPS";
+
+const NAV_FILE: &str = "nav.xhtml";
+
+/// Where the stylesheet is, inside of the package dir
+const STYLESHEET_FILE: &str = "style.css";
+
+/// The stylesheet books get by default
+pub const STYLESHEET: &str = include_str!("../../templates/epub.css");
+
+/// What the book is and has
+pub struct Book {
+    pub title: String,
+    pub authors: Vec<String>,
+    /// A language tag (e.g. `en`)
+    pub language: String,
+    /// Something only this book has, which readers tell books apart with
+    pub identifier: String,
+    pub modified: DateTime<Utc>,
+    pub stylesheet: String,
+    /// The table of contents, in the order of the summary
+    pub nav: Vec<NavPoint>,
+    /// Every document, in reading order
+    pub chapters: Vec<BookChapter>,
+    /// The images, by their file
+    pub resources: Vec<Resource>,
+}
+
+/// An entry of the table of contents. Entries without a document (e.g. sections) only group the
+/// ones under them.
+pub struct NavPoint {
+    pub title: String,
+    /// The document, relative to the package dir and maybe with an anchor
+    pub href: Option<String>,
+    pub children: Vec<NavPoint>,
+}
+
+pub struct BookChapter {
+    pub title: String,
+    /// Relative to the package dir (e.g. `guide/setup.xhtml`)
+    pub file: String,
+    /// The XHTML that goes in the body
+    pub body: String,
+}
+
+pub struct Resource {
+    /// Relative to the package dir (e.g. `images/diagram.png`)
+    pub file: String,
+    pub data: Vec<u8>,
+}
+
+/// Writes the book as an EPUB file
+pub fn write(book: &Book) -> Result<Vec<u8>> {
+    let mut archive = Zip::default();
+
+    // It has to be the first file, so the type of the archive can be told from its first bytes
+    archive.add("mimetype", b"application/epub+zip")?;
+    archive.add(
+        "META-INF/container.xml",
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n  \
+             <rootfiles>\n    \
+             <rootfile full-path=\"{PACKAGE_DIR}/content.opf\" media-type=\"application/oebps-package+xml\"/>\n  \
+             </rootfiles>\n\
+             </container>\n"
+        )
+        .as_bytes(),
+    )?;
+    archive.add(
+        &format!("{PACKAGE_DIR}/content.opf"),
+        package(book).as_bytes(),
+    )?;
+    archive.add(&format!("{PACKAGE_DIR}/{NAV_FILE}"), nav(book).as_bytes())?;
+    archive.add(
+        &format!("{PACKAGE_DIR}/{STYLESHEET_FILE}"),
+        book.stylesheet.as_bytes(),
+    )?;
+
+    for chapter in book.chapters.iter() {
+        archive.add(
+            &format!("{PACKAGE_DIR}/{}", chapter.file),
+            document(book, &chapter.title, &chapter.file, &chapter.body).as_bytes(),
+        )?;
+    }
+    for resource in book.resources.iter() {
+        archive.add(&format!("{PACKAGE_DIR}/{}", resource.file), &resource.data)?;
+    }
+
+    archive.finish()
+}
+
+// The metadata, every file of the book and the order they're read in
+fn package(book: &Book) -> String {
+    let mut metadata = format!(
+        "    <dc:identifier id=\"book-id\">{}</dc:identifier>\n    \
+         <dc:title>{}</dc:title>\n    \
+         <dc:language>{}</dc:language>\n    \
+         <meta property=\"dcterms:modified\">{}</meta>\n",
+        util::escape_html(&book.identifier),
+        util::escape_html(&book.title),
+        util::escape_html(&book.language),
+        book.modified.format("%Y-%m-%dT%H:%M:%SZ"),
+    );
+    for author in book.authors.iter() {
+        metadata.push_str(&format!(
+            "    <dc:creator>{}</dc:creator>\n",
+            util::escape_html(author)
+        ));
+    }
+
+    let mut manifest = format!(
+        "    <item id=\"nav\" href=\"{NAV_FILE}\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n    \
+         <item id=\"style\" href=\"{STYLESHEET_FILE}\" media-type=\"text/css\"/>\n"
+    );
+    let mut spine = String::new();
+    for (n, chapter) in book.chapters.iter().enumerate() {
+        manifest.push_str(&format!(
+            "    <item id=\"chapter-{n}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            util::escape_href(&chapter.file)
+        ));
+        spine.push_str(&format!("    <itemref idref=\"chapter-{n}\"/>\n"));
+    }
+    for (n, resource) in book.resources.iter().enumerate() {
+        let media_type = static_files::content_type(Path::new(&resource.file));
+
+        manifest.push_str(&format!(
+            "    <item id=\"resource-{n}\" href=\"{}\" media-type=\"{}\"/>\n",
+            util::escape_href(&resource.file),
+            media_type.split(';').next().unwrap_or(media_type)
+        ));
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"{}\">\n  \
+         <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n{metadata}  </metadata>\n  \
+         <manifest>\n{manifest}  </manifest>\n  \
+         <spine>\n{spine}  </spine>\n\
+         </package>\n",
+        util::escape_html(&book.language),
+    )
+}
+
+fn nav(book: &Book) -> String {
+    let mut list = String::new();
+    nav_list(&book.nav, &mut list);
+
+    document(
+        book,
+        &book.title,
+        NAV_FILE,
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n{list}</nav>\n",
+            util::escape_html(&book.title)
+        ),
+    )
+}
+
+// Entries without a document must have others under them, so empty ones are left out
+fn nav_list(points: &[NavPoint], list: &mut String) {
+    let points: Vec<&NavPoint> = points
+        .iter()
+        .filter(|point| point.href.is_some() || !point.children.is_empty())
+        .collect();
+    if points.is_empty() {
+        return;
+    }
+
+    list.push_str("<ol>\n");
+    for point in points {
+        match &point.href {
+            Some(target) => list.push_str(&format!(
+                "<li><a href=\"{}\">{}</a>",
+                util::escape_href(target),
+                util::escape_html(&point.title)
+            )),
+            None => list.push_str(&format!(
+                "<li><span>{}</span>",
+                util::escape_html(&point.title)
+            )),
+        }
+        nav_list(&point.children, list);
+        list.push_str("</li>\n");
+    }
+    list.push_str("</ol>\n");
+}
+
+// A whole XHTML document. The stylesheet is linked relative to it.
+fn document(book: &Book, title: &str, file: &str, body: &str) -> String {
+    let stylesheet = "../".repeat(file.matches('/').count()) + STYLESHEET_FILE;
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{language}\" xml:lang=\"{language}\">\n\
+         <head>\n\
+         <meta charset=\"UTF-8\"/>\n\
+         <title>{}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"{stylesheet}\"/>\n\
+         </head>\n\
+         <body>\n{body}</body>\n\
+         </html>\n",
+        util::escape_html(title),
+        language = util::escape_html(&book.language),
+    )
+}
+
+// A ZIP archive whose files aren't compressed. Dates are left at 1980, so the same book always
+// makes the same archive.
+#[derive(Default)]
+struct Zip {
+    data: Vec<u8>,
+    directory: Vec<u8>,
+    files: u16,
+}
+
+impl Zip {
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let offset = u32::try_from(self.data.len())?;
+        let size = u32::try_from(data.len())?;
+        let name_length = u16::try_from(name.len())?;
+        let crc = crc32(data);
+
+        // Version needed, flags, method (stored), time and date
+        let common = |header: &mut Vec<u8>| {
+            for field in [10u16, 0, 0, 0, 0x21] {
+                header.extend(field.to_le_bytes());
+            }
+            for field in [crc, size, size] {
+                header.extend(field.to_le_bytes());
+            }
+            header.extend(name_length.to_le_bytes());
+        };
+
+        self.data.extend(0x04034b50u32.to_le_bytes());
+        common(&mut self.data);
+        self.data.extend(0u16.to_le_bytes());
+        self.data.extend(name.as_bytes());
+        self.data.extend(data);
+
+        self.directory.extend(0x02014b50u32.to_le_bytes());
+        self.directory.extend(20u16.to_le_bytes());
+        common(&mut self.directory);
+        // Extra field, comment, disk, internal and external attributes
+        for field in [0u16, 0, 0, 0, 0, 0] {
+            self.directory.extend(field.to_le_bytes());
+        }
+        self.directory.extend(offset.to_le_bytes());
+        self.directory.extend(name.as_bytes());
+        self.files = self
+            .files
+            .checked_add(1)
+            .ok_or_else(|| anyhow::anyhow!("Too many files for a ZIP archive"))?;
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>> {
+        let offset = u32::try_from(self.data.len())?;
+        let size = u32::try_from(self.directory.len())?;
+
+        self.data.append(&mut self.directory);
+        self.data.extend(0x06054b50u32.to_le_bytes());
+        for field in [0u16, 0, self.files, self.files] {
+            self.data.extend(field.to_le_bytes());
+        }
+        self.data.extend(size.to_le_bytes());
+        self.data.extend(offset.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes());
+
+        Ok(self.data)
+    }
+}
+
+// The CRC-32 ZIP checks every file with
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb88320,
+                _ => crc >> 1,
+            };
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_check_files_with_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn it_should_write_the_mimetype_first_and_list_every_file() -> Result<()> {
+        let book = Book {
+            title: String::from("Star Wars & co"),
+            authors: vec![String::from("Obi-Wan Kenobi")],
+            language: String::from("en"),
+            identifier: String::from("urn:cahlter:star-wars"),
+            modified: DateTime::default(),
+            stylesheet: String::new(),
+            nav: vec![NavPoint {
+                title: String::from("Guide"),
+                href: None,
+                children: vec![NavPoint {
+                    title: String::from("Setup"),
+                    href: Some(String::from("guide/setup.xhtml")),
+                    children: Vec::new(),
+                }],
+            }],
+            chapters: vec![BookChapter {
+                title: String::from("Setup"),
+                file: String::from("guide/setup.xhtml"),
+                body: String::from("<p>Hello there</p>\n"),
+            }],
+            resources: vec![Resource {
+                file: String::from("images/kenobi.png"),
+                data: vec![0x89, b'P', b'N', b'G'],
+            }],
+        };
+        let epub = write(&book)?;
+        let text = String::from_utf8_lossy(&epub);
+
+        assert_eq!(&epub[30..38], b"mimetype");
+        assert_eq!(&epub[38..58], b"application/epub+zip");
+        assert!(text.contains("<dc:title>Star Wars &amp; co</dc:title>"));
+        assert!(text.contains("<itemref idref=\"chapter-0\"/>"));
+        assert!(text.contains("href=\"images/kenobi.png\" media-type=\"image/png\""));
+        assert!(text.contains(
+            "<li><span>Guide</span><ol>\n<li><a href=\"guide/setup.xhtml\">Setup</a></li>"
+        ));
+        assert!(text.contains("href=\"../style.css\""));
+        // The end of central directory record counts the files
+        assert_eq!(
+            &epub[epub.len() - 22..epub.len() - 18],
+            &0x06054b50u32.to_le_bytes()
+        );
+        assert_eq!(&epub[epub.len() - 12..epub.len() - 10], &7u16.to_le_bytes());
+
+        Ok(())
+    }
+}
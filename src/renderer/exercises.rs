@@ -0,0 +1,217 @@
+//! Exercises for courses, written as containers in the chapters:
+//!
+//! ```markdown
+//! ::: exercise
+//! Write a function that adds two numbers
+//! :::
+//!
+//! ::: solution
+//! `fn add(a: i32, b: i32) -> i32 { a + b }`
+//! :::
+//! ```
+//!
+//! Exercises stay where they are. Solutions belong to the exercise right before them and are
+//! taken out of the chapter, to be shown in an appendix or not at all.
+
+use super::includes::{self, LineKind};
+use crate::util;
+use anyhow::Result;
+use std::path::Path;
+
+/// The page with the solutions of every chapter, inside the build dir
+pub const SOLUTIONS_FILE: &str = "solutions.html";
+
+const OPENING: &str = ":::";
+
+/// Where the solutions of a chapter are shown
+#[derive(Debug, Clone, Copy)]
+pub struct Appendix<'a> {
+    /// The url of the page with the solutions (e.g. /docs/solutions.html)
+    pub url: &'a str,
+    /// The page of the chapter, relative to the build dir
+    pub page: &'a Path,
+}
+
+/// The solution to an exercise of a chapter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// The exercise it solves, counting from 1
+    pub exercise: usize,
+    pub markdown: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Block<'a> {
+    Text(&'a str),
+    Exercise(String),
+    Solution(String),
+}
+
+/// Numbers the exercises and takes the solutions out of the markdown. With an [`Appendix`], the
+/// exercises that have a solution link to it.
+pub fn exercises(markdown: &str, appendix: Option<&Appendix>) -> Result<String> {
+    let blocks = blocks(markdown)?;
+    let mut output = String::with_capacity(markdown.len());
+    let mut number = 0;
+
+    for (i, block) in blocks.iter().enumerate() {
+        match block {
+            Block::Text(text) => output.push_str(text),
+            Block::Exercise(body) => {
+                number += 1;
+                output.push_str(&format!(
+                    "<div class=\"exercise\" id=\"exercise-{number}\">\n\n\
+                     <p class=\"exercise__title\">Exercise {number}</p>\n\n{body}\n\n"
+                ));
+
+                let solved = blocks[i + 1..]
+                    .iter()
+                    .take_while(|block| !matches!(block, Block::Exercise(_)))
+                    .any(|block| matches!(block, Block::Solution(_)));
+                if let (true, Some(appendix)) = (solved, appendix) {
+                    let url = util::escape_href(appendix.url);
+
+                    output.push_str(&format!(
+                        "<p class=\"exercise__solution\"><a href=\"{url}#{}\">Solution</a></p>\n\n",
+                        solution_id(appendix.page, number)
+                    ));
+                }
+
+                output.push_str("</div>\n");
+            }
+            Block::Solution(_) => {}
+        }
+    }
+
+    Ok(output)
+}
+
+/// The solutions of the markdown, in the order of their exercises
+pub fn solutions(markdown: &str) -> Result<Vec<Solution>> {
+    let mut solutions = Vec::new();
+    let mut number = 0;
+
+    for block in blocks(markdown)? {
+        match block {
+            Block::Exercise(_) => number += 1,
+            Block::Solution(markdown) => solutions.push(Solution {
+                exercise: number,
+                markdown,
+            }),
+            Block::Text(_) => {}
+        }
+    }
+
+    Ok(solutions)
+}
+
+/// The id of the solution in the appendix. It's made from the page of the chapter, since every
+/// chapter numbers its exercises from 1 (e.g. `guide-install-exercise-2`).
+pub fn solution_id(page: &Path, exercise: usize) -> String {
+    let page: String = page
+        .with_extension("")
+        .to_string_lossy()
+        .chars()
+        .map(|c| match c.is_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '-',
+        })
+        .collect();
+
+    format!("{page}-exercise-{exercise}")
+}
+
+// Splits the markdown in text, exercises and solutions. Containers inside code blocks are left
+// alone, since they're most likely showing how to write one.
+fn blocks(markdown: &str) -> Result<Vec<Block<'_>>> {
+    let mut blocks = Vec::new();
+    // The kind of the container that's open and where it started
+    let mut open: Option<(&str, usize)> = None;
+    let mut body = String::new();
+    let mut offset = 0;
+    let mut text_start = 0;
+
+    for (line, kind) in includes::lines(markdown) {
+        let start = offset;
+        offset += line.len();
+
+        let marker = match kind {
+            LineKind::Code | LineKind::Closing => None,
+            _ => line.trim().strip_prefix(OPENING).map(str::trim),
+        };
+
+        match (marker, open) {
+            (Some(name @ ("exercise" | "solution")), None) => {
+                blocks.push(Block::Text(&markdown[text_start..start]));
+                open = Some((name, start));
+            }
+            (Some(""), Some((name, _))) => {
+                let body = std::mem::take(&mut body).trim_end().to_string();
+                blocks.push(match name {
+                    "exercise" => Block::Exercise(body),
+                    _ if !blocks
+                        .iter()
+                        .any(|block| matches!(block, Block::Exercise(_))) =>
+                    {
+                        anyhow::bail!("A solution has no exercise before it to solve")
+                    }
+                    _ => Block::Solution(body),
+                });
+                open = None;
+                text_start = offset;
+            }
+            (Some(name @ ("exercise" | "solution")), Some((open, _))) => {
+                anyhow::bail!("Exercises and solutions can't be nested. Close the {open} before the {name} with {OPENING}")
+            }
+            (_, Some(_)) => body.push_str(line),
+            (_, None) => {}
+        }
+    }
+
+    if let Some((name, start)) = open {
+        let line = markdown[..start].lines().count() + 1;
+        anyhow::bail!("The {name} at line {line} isn't closed with {OPENING}");
+    }
+
+    blocks.push(Block::Text(&markdown[text_start..]));
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CHAPTER: &str = "# Adding\n\n::: exercise\nAdd `1` and `2`\n:::\n\n::: solution\n```\n:::\n3\n```\n:::\n\n::: exercise\nAdd `2` and `2`\n:::\n";
+
+    #[test]
+    fn it_should_render_the_exercises_without_their_solutions() -> Result<()> {
+        let appendix = Appendix {
+            url: "/docs/solutions.html",
+            page: Path::new("guide/add.html"),
+        };
+        let html = exercises(CHAPTER, Some(&appendix))?;
+
+        assert!(html.starts_with("# Adding\n\n<div class=\"exercise\" id=\"exercise-1\">\n\n<p class=\"exercise__title\">Exercise 1</p>\n\nAdd `1` and `2`\n\n<p class=\"exercise__solution\"><a href=\"/docs/solutions.html#guide-add-exercise-1\">Solution</a></p>\n\n</div>\n"));
+        assert!(html.ends_with("Add `2` and `2`\n\n</div>\n"));
+        assert!(!html.contains("3\n"));
+        assert!(!exercises(CHAPTER, None)?.contains("exercise__solution"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_collect_the_solutions() -> Result<()> {
+        assert_eq!(
+            solutions(CHAPTER)?,
+            vec![Solution {
+                exercise: 1,
+                markdown: "```\n:::\n3\n```".to_string()
+            }]
+        );
+        assert!(solutions("::: exercise\nAdd them\n").is_err());
+        assert!(solutions("::: solution\n3\n:::").is_err());
+        assert!(solutions("::: exercise\n::: solution\n:::\n:::").is_err());
+
+        Ok(())
+    }
+}
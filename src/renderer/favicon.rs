@@ -0,0 +1,175 @@
+//! The icon browsers show in tabs and bookmarks. Besides the icon itself, the build may draw the
+//! sizes browsers and phones usually ask for.
+
+use anyhow::{anyhow, Context, Result};
+use resvg::tiny_skia::{FilterQuality, Pixmap, PixmapPaint, Transform};
+use resvg::usvg::{Options, Tree};
+use std::path::Path;
+
+/// The sizes drawn from the icon: the ones of browser tabs, Apple's home screen and Android's
+pub const VARIANT_SIZES: [u32; 4] = [16, 32, 180, 192];
+
+// Apple devices look for their icon with its own rel
+const APPLE_TOUCH_ICON_SIZE: u32 = 180;
+
+/// An icon of the page, written at the root of the build dir
+#[derive(Debug, Clone, PartialEq)]
+pub struct Favicon {
+    pub file: String,
+    pub rel: &'static str,
+    pub mime: Option<&'static str>,
+    /// How big it is (e.g. `32x32`), if it's one of the variants
+    pub sizes: Option<String>,
+    /// The size to draw it at, unless it's the icon as it is
+    pub size: Option<u32>,
+}
+
+/// The icons of the page for a given favicon, starting with the favicon itself. Variants are only
+/// drawn from PNGs and SVGs.
+pub fn favicons(favicon: &Path, variants: bool) -> Result<Vec<Favicon>> {
+    let extension = extension(favicon);
+    let mime = match extension.as_str() {
+        "png" => Some("image/png"),
+        "svg" => Some("image/svg+xml"),
+        "ico" => Some("image/x-icon"),
+        "gif" => Some("image/gif"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    };
+    let mut favicons = vec![Favicon {
+        file: match extension.is_empty() {
+            true => String::from("favicon"),
+            false => format!("favicon.{extension}"),
+        },
+        rel: "icon",
+        mime,
+        sizes: None,
+        size: None,
+    }];
+
+    if !variants {
+        return Ok(favicons);
+    }
+    if !matches!(extension.as_str(), "png" | "svg") {
+        anyhow::bail!(
+            "The sizes of the favicon can only be drawn from a PNG or an SVG, not {}",
+            favicon.display()
+        );
+    }
+
+    for size in VARIANT_SIZES {
+        favicons.push(match size {
+            APPLE_TOUCH_ICON_SIZE => Favicon {
+                file: String::from("apple-touch-icon.png"),
+                rel: "apple-touch-icon",
+                mime: None,
+                sizes: Some(format!("{size}x{size}")),
+                size: Some(size),
+            },
+            _ => Favicon {
+                file: format!("favicon-{size}x{size}.png"),
+                rel: "icon",
+                mime: Some("image/png"),
+                sizes: Some(format!("{size}x{size}")),
+                size: Some(size),
+            },
+        });
+    }
+
+    Ok(favicons)
+}
+
+/// Draws the icon as a square PNG of the given size. Icons that aren't square are centered in it.
+pub fn resize(icon: &[u8], favicon: &Path, size: u32) -> Result<Vec<u8>> {
+    let mut resized =
+        Pixmap::new(size, size).with_context(|| anyhow!("A favicon can't be {size}px wide"))?;
+
+    match extension(favicon).as_str() {
+        "svg" => {
+            let tree = Tree::from_data(icon, &Options::default())
+                .with_context(|| anyhow!("Invalid favicon {}", favicon.display()))?;
+            let original = tree.size();
+            let transform = fit(original.width(), original.height(), size);
+
+            resvg::render(&tree, transform, &mut resized.as_mut());
+        }
+        _ => {
+            let original = Pixmap::decode_png(icon)
+                .with_context(|| anyhow!("Invalid favicon {}", favicon.display()))?;
+            let transform = fit(original.width() as f32, original.height() as f32, size);
+            let paint = PixmapPaint {
+                quality: FilterQuality::Bicubic,
+                ..Default::default()
+            };
+
+            resized.draw_pixmap(0, 0, original.as_ref(), &paint, transform, None);
+        }
+    }
+
+    resized
+        .encode_png()
+        .with_context(|| anyhow!("Failed to encode the favicon"))
+}
+
+// Scales something of `width` by `height` to fit a square of `size`, centered
+fn fit(width: f32, height: f32, size: u32) -> Transform {
+    let size = size as f32;
+    let scale = size / width.max(height);
+
+    Transform::from_row(
+        scale,
+        0.0,
+        0.0,
+        scale,
+        (size - width * scale) / 2.0,
+        (size - height * scale) / 2.0,
+    )
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ICON: &str = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"64\" height=\"32\"><rect width=\"64\" height=\"32\" fill=\"#689d6a\"/></svg>";
+
+    #[test]
+    fn it_should_list_the_icons_of_the_favicon() -> Result<()> {
+        let icons = favicons(Path::new("assets/Icon.SVG"), true)?;
+
+        assert_eq!(icons.len(), VARIANT_SIZES.len() + 1);
+        assert_eq!(icons[0].file, "favicon.svg");
+        assert_eq!(icons[0].mime, Some("image/svg+xml"));
+        assert_eq!(icons[1].sizes.as_deref(), Some("16x16"));
+        assert!(icons
+            .iter()
+            .any(|favicon| favicon.rel == "apple-touch-icon"));
+        assert_eq!(favicons(Path::new("favicon.ico"), false)?.len(), 1);
+        assert!(favicons(Path::new("favicon.ico"), true).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_draw_the_icon_at_other_sizes() -> Result<()> {
+        let png = resize(ICON.as_bytes(), Path::new("icon.svg"), 32)?;
+        let pixmap = Pixmap::decode_png(&png)?;
+
+        assert_eq!((pixmap.width(), pixmap.height()), (32, 32));
+        // Wider than tall, so the top is left empty
+        assert_eq!(pixmap.pixel(16, 0).map(|pixel| pixel.alpha()), Some(0));
+        assert_eq!(pixmap.pixel(16, 16).map(|pixel| pixel.alpha()), Some(255));
+
+        let smaller = resize(&png, Path::new("icon.png"), 16)?;
+        assert_eq!(Pixmap::decode_png(&smaller)?.width(), 16);
+        assert!(resize(b"not an image", Path::new("icon.png"), 16).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use crate::util;
+use log::warn;
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+use serde::Deserialize;
+
+/// Turns fenced blocks of a given language into something other than code (e.g. diagrams). It's
+/// the extension point for tools that render on the client, like mermaid.
+pub trait FenceTransform {
+    /// The language in the info string of the blocks it takes care of (e.g. ```mermaid)
+    fn language(&self) -> &str;
+
+    /// The html that replaces the whole block
+    fn transform(&self, code: &str) -> String;
+
+    /// A script, relative to the site's root, that pages with these blocks need
+    fn script(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Every transform the renderer knows about
+pub const FENCE_TRANSFORMS: [&dyn FenceTransform; 2] = [&Mermaid, &Quiz];
+
+/// Leaves the diagram as text inside a `<pre class="mermaid">`. The mermaid script draws it when
+/// the page loads.
+///
+/// With the default files, the script loads mermaid from its CDN. A `mermaid.min.js` in the static
+/// dir takes its place.
+pub struct Mermaid;
+
+impl FenceTransform for Mermaid {
+    fn language(&self) -> &str {
+        "mermaid"
+    }
+
+    fn transform(&self, code: &str) -> String {
+        let mut html = String::from("<pre class=\"mermaid\">");
+        html.push_str(&util::escape_html(code));
+        html.push_str("</pre>\n");
+        html
+    }
+
+    fn script(&self) -> Option<&str> {
+        Some("/mermaid.min.js")
+    }
+}
+
+/// Turns a list of multiple choice questions, written in YAML, into a quiz the reader answers
+/// right in the page. The answers are checked by index.js.
+///
+/// # Example
+///
+/// ```quiz
+/// - question: What does cargo build do?
+///   choices: [Compiles the crate, Runs the tests]
+///   answer: 1
+///   explanation: Tests are run by cargo test
+/// ```
+///
+/// `answer` counts from 1. Questions with more than one right choice list all of them (e.g.
+/// `answer: [1, 3]`), and the reader picks with checkboxes instead.
+pub struct Quiz;
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Question {
+    question: String,
+    choices: Vec<String>,
+    answer: Answer,
+    #[serde(default)]
+    explanation: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum Answer {
+    One(usize),
+    Many(Vec<usize>),
+}
+
+impl Answer {
+    fn choices(&self) -> Vec<usize> {
+        match self {
+            Answer::One(choice) => vec![*choice],
+            Answer::Many(choices) => choices.clone(),
+        }
+    }
+}
+
+impl Quiz {
+    fn questions(code: &str) -> Result<Vec<Question>, String> {
+        let questions: Vec<Question> =
+            serde_yaml::from_str(code).map_err(|error| error.to_string())?;
+
+        for question in questions.iter() {
+            let answer = question.answer.choices();
+
+            if answer.is_empty()
+                || answer
+                    .iter()
+                    .any(|&choice| choice == 0 || choice > question.choices.len())
+            {
+                return Err(format!(
+                    "The answer to \"{}\" isn't one of its choices",
+                    question.question
+                ));
+            }
+        }
+
+        Ok(questions)
+    }
+}
+
+impl FenceTransform for Quiz {
+    fn language(&self) -> &str {
+        "quiz"
+    }
+
+    fn transform(&self, code: &str) -> String {
+        let questions = match Quiz::questions(code) {
+            Ok(questions) => questions,
+            Err(error) => {
+                warn!("Invalid quiz, so it's left as code: {error}");
+
+                let mut html = String::from("<pre><code>");
+                html.push_str(&util::escape_html(code));
+                html.push_str("</code></pre>\n");
+                return html;
+            }
+        };
+        // Inputs are grouped by name, which has to be unique in the page
+        let id = &util::content_hash(code)[..8];
+        let mut html = String::from("<form class=\"quiz\">\n");
+
+        for (i, question) in questions.iter().enumerate() {
+            let answer = question.answer.choices();
+            let kind = match answer.len() {
+                1 => "radio",
+                _ => "checkbox",
+            };
+            let answer: Vec<String> = answer.iter().map(|choice| choice.to_string()).collect();
+
+            html.push_str(&format!(
+                "<fieldset class=\"quiz__question\" data-answer=\"{}\">\n<legend>",
+                answer.join(",")
+            ));
+            html.push_str(&util::escape_html(&question.question));
+            html.push_str("</legend>\n");
+
+            for (choice, text) in question.choices.iter().enumerate() {
+                html.push_str(&format!(
+                    "<label class=\"quiz__choice\"><input type=\"{kind}\" name=\"quiz-{id}-{i}\" value=\"{}\" /> ",
+                    choice + 1
+                ));
+                html.push_str(&util::escape_html(text));
+                html.push_str("</label>\n");
+            }
+
+            if let Some(explanation) = &question.explanation {
+                html.push_str("<p class=\"quiz__explanation\" hidden>");
+                html.push_str(&util::escape_html(explanation));
+                html.push_str("</p>\n");
+            }
+
+            html.push_str("</fieldset>\n");
+        }
+
+        html.push_str(
+            "<button class=\"quiz__check\" type=\"submit\">Check</button>\n\
+             <p class=\"quiz__result\" aria-live=\"polite\"></p>\n</form>\n",
+        );
+        html
+    }
+}
+
+/// The language of a code block. Info strings may carry more than that (e.g. rust,ignore).
+pub fn language<'a>(kind: &'a CodeBlockKind) -> &'a str {
+    match kind {
+        CodeBlockKind::Fenced(info) => info
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .next()
+            .unwrap_or(""),
+        CodeBlockKind::Indented => "",
+    }
+}
+
+pub fn find<'a>(
+    transforms: &[&'a dyn FenceTransform],
+    language: &str,
+) -> Option<&'a dyn FenceTransform> {
+    transforms
+        .iter()
+        .find(|transform| transform.language() == language)
+        .copied()
+}
+
+/// The scripts needed by the transforms used in the events, without duplicates
+pub fn scripts<'a>(events: &[Event], transforms: &[&'a dyn FenceTransform]) -> Vec<&'a str> {
+    let mut scripts = Vec::new();
+
+    for event in events {
+        let Event::Start(Tag::CodeBlock(kind)) = event else {
+            continue;
+        };
+
+        if let Some(script) = find(transforms, language(kind)).and_then(|t| t.script()) {
+            if !scripts.contains(&script) {
+                scripts.push(script);
+            }
+        }
+    }
+
+    scripts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pulldown_cmark::Parser;
+
+    #[test]
+    fn it_should_find_the_scripts_of_the_transforms_in_use() {
+        let markdown = "```mermaid\ngraph TD;\n```\n\n```mermaid\nA-->B;\n```\n\n```rust\n```";
+        let events: Vec<Event> = Parser::new(markdown).collect();
+
+        assert_eq!(scripts(&events, &FENCE_TRANSFORMS), vec!["/mermaid.min.js"]);
+        assert!(scripts(&events[6..], &FENCE_TRANSFORMS).is_empty());
+    }
+
+    #[test]
+    fn it_should_turn_questions_into_a_quiz() {
+        let html = Quiz.transform(
+            "- question: Which are <b>odd</b>?\n  choices: [1, 2, 3]\n  answer: [1, 3]\n  explanation: Odd numbers\n- question: Is 2 even?\n  choices: [Yes, No]\n  answer: 1\n",
+        );
+
+        assert!(html.starts_with("<form class=\"quiz\">\n<fieldset class=\"quiz__question\" data-answer=\"1,3\">\n<legend>Which are &lt;b&gt;odd&lt;/b&gt;?</legend>\n"));
+        assert!(html.contains("type=\"checkbox\""));
+        assert!(html.contains("value=\"2\" /> No</label>"));
+        assert!(html.contains("<p class=\"quiz__explanation\" hidden>Odd numbers</p>"));
+        assert_eq!(html.matches("type=\"radio\"").count(), 2);
+    }
+
+    #[test]
+    fn it_should_leave_invalid_quizzes_as_code() {
+        assert_eq!(
+            Quiz.transform("- question: Is 2 even?\n  choices: [Yes, No]\n  answer: 3\n"),
+            "<pre><code>- question: Is 2 even?\n  choices: [Yes, No]\n  answer: 3\n</code></pre>\n"
+        );
+        assert!(Quiz
+            .transform("question: Is 2 even?")
+            .starts_with("<pre><code>"));
+    }
+}
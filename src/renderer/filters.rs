@@ -0,0 +1,188 @@
+use super::fences::FenceTransform;
+use super::highlight::HighlightCache;
+use super::markdown;
+use super::shortcodes::{self, Shortcode};
+use super::variables::{self, Variables};
+use crate::config::CodeBlocks;
+use anyhow::Result;
+use pulldown_cmark::Event;
+use std::path::PathBuf;
+
+/// A pass over the events of a chapter, between parsing its markdown and writing its html. The
+/// renderer runs a chain of them, each one over what the previous one left (see [`run`]), so
+/// every transformation can be written and tested on its own.
+pub trait MarkdownFilter {
+    /// What the filter is known by (e.g. `links`)
+    fn name(&self) -> &str;
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>>;
+}
+
+/// Runs the filters over the events, in order
+pub fn run<'a>(events: Vec<Event<'a>>, filters: &[&dyn MarkdownFilter]) -> Result<Vec<Event<'a>>> {
+    filters
+        .iter()
+        .try_fold(events, |events, filter| filter.filter(events))
+}
+
+/// Block quotes that are pull quotes or have an attribution become figures (see
+/// [`markdown::quotes`])
+pub struct Quotes;
+
+impl MarkdownFilter for Quotes {
+    fn name(&self) -> &str {
+        "quotes"
+    }
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        Ok(markdown::quotes(events))
+    }
+}
+
+/// `{{name}}` is replaced with the value of the variable (see [`variables::variables`])
+pub struct Interpolate<'r> {
+    pub variables: &'r Variables,
+    pub strict: bool,
+}
+
+impl MarkdownFilter for Interpolate<'_> {
+    fn name(&self) -> &str {
+        "variables"
+    }
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        variables::variables(events, self.variables, self.strict)
+    }
+}
+
+/// `{{#name arguments}}` is replaced with what the shortcode renders to
+pub struct Shortcodes<'r>(pub &'r [&'r dyn Shortcode]);
+
+impl MarkdownFilter for Shortcodes<'_> {
+    fn name(&self) -> &str {
+        "shortcodes"
+    }
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        shortcodes::shortcodes(events, self.0)
+    }
+}
+
+/// Code blocks are highlighted, or handed to the fence transform of their language
+pub struct Highlight<'r> {
+    pub theme: &'r str,
+    pub options: &'r CodeBlocks,
+    pub transforms: &'r [&'r dyn FenceTransform],
+    pub cache: Option<&'r HighlightCache>,
+}
+
+impl MarkdownFilter for Highlight<'_> {
+    fn name(&self) -> &str {
+        "highlight"
+    }
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        Ok(markdown::code_blocks(
+            events,
+            self.theme,
+            self.options,
+            self.transforms,
+            self.cache,
+        ))
+    }
+}
+
+/// Headings go down as many levels as the offset
+pub struct DemoteHeadings(pub u8);
+
+impl MarkdownFilter for DemoteHeadings {
+    fn name(&self) -> &str {
+        "demote_headings"
+    }
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        Ok(markdown::demote_headings(events, self.0))
+    }
+}
+
+/// The title of the chapter goes in front of content that doesn't start with a heading
+pub struct InjectTitle(pub String);
+
+impl MarkdownFilter for InjectTitle {
+    fn name(&self) -> &str {
+        "inject_title"
+    }
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        Ok(markdown::inject_title(events, &self.0))
+    }
+}
+
+/// Relative links and images point to where their targets end up in the site (see
+/// [`markdown::relative_urls`])
+pub struct Links<'r> {
+    /// Where the chapter is, relative to the source dir
+    pub base: PathBuf,
+    /// Where the site is served from
+    pub root: &'r str,
+}
+
+impl MarkdownFilter for Links<'_> {
+    fn name(&self) -> &str {
+        "links"
+    }
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        Ok(markdown::relative_urls(events, &self.base, self.root))
+    }
+}
+
+/// Images marked with `#light` or `#dark` only show with themes of that kind (see
+/// [`markdown::themed_images`]). It goes after [`Links`], which keeps the marks.
+pub struct ThemedImages;
+
+impl MarkdownFilter for ThemedImages {
+    fn name(&self) -> &str {
+        "themed_images"
+    }
+
+    fn filter<'a>(&self, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        Ok(markdown::themed_images(events))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pulldown_cmark::{html, Parser};
+
+    fn render(markdown: &str, filters: &[&dyn MarkdownFilter]) -> Result<String> {
+        let events = run(Parser::new(markdown).collect(), filters)?;
+        let mut output = String::new();
+        html::push_html(&mut output, events.into_iter());
+
+        Ok(output)
+    }
+
+    #[test]
+    fn it_should_run_the_filters_in_order() -> Result<()> {
+        let title = InjectTitle(String::from("Intro"));
+        let links = Links {
+            base: PathBuf::from("guide"),
+            root: "/docs",
+        };
+
+        assert_eq!(
+            render("[Setup](setup.md)", &[&title, &DemoteHeadings(1), &links])?,
+            "<h2>Intro</h2>\n<p><a href=\"/docs/guide/setup.html\">Setup</a></p>\n"
+        );
+        // The title comes after the headings were demoted, so it stays an H1
+        assert_eq!(
+            render("[Setup](setup.md)", &[&DemoteHeadings(1), &title])?,
+            "<h1>Intro</h1>\n<p><a href=\"setup.md\">Setup</a></p>\n"
+        );
+        assert_eq!(render("Plain", &[])?, "<p>Plain</p>\n");
+
+        Ok(())
+    }
+}
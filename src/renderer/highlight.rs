@@ -0,0 +1,215 @@
+use crate::util;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use syntect::easy::ScopeRangeIterator;
+use syntect::parsing::{BasicScopeStackOp, ParseState, Scope, ScopeStack, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// The themes the default css has colors for. Custom css can add more
+pub const THEMES: [&str; 2] = ["gruvbox", "catppuccin"];
+
+// Names the syntaxes don't go by
+const ALIASES: [(&str, &str); 5] = [
+    ("golang", "go"),
+    ("shell", "sh"),
+    ("console", "sh"),
+    ("kotlin", "kt"),
+    ("c++", "cpp"),
+];
+
+/// Turns a snippet into html where every token is wrapped in spans named after its scopes, as
+/// the syntax definitions of Sublime Text name them (e.g. `keyword.control.rust` becomes
+/// `<span class="hl-keyword hl-control hl-rust">`), so themes can color them by any part of it.
+/// Returns `None` for languages it doesn't know, so the caller can fall back to plain text.
+pub fn highlight(code: &str, language: &str) -> Option<String> {
+    let syntaxes = syntaxes();
+    let language = ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(language))
+        .map_or(language, |(_, name)| name);
+    let syntax = syntaxes
+        .find_syntax_by_token(language)
+        .filter(|syntax| syntax.name != "Plain Text")?;
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut html = String::with_capacity(code.len() * 2);
+
+    for line in LinesWithEndings::from(code) {
+        let ops = state.parse_line(line, syntaxes).ok()?;
+        let text = line.trim_end_matches(['\r', '\n']);
+        // Whether each scope of the stack got a span, up to the ones opened so far. Spans are
+        // only opened right before some text, so none of them are empty.
+        let mut open: Vec<bool> = Vec::new();
+
+        for (range, op) in ScopeRangeIterator::new(&ops, line) {
+            stack
+                .apply_with_hook(op, |op, scopes| {
+                    if op == BasicScopeStackOp::Pop
+                        && open.len() > scopes.len()
+                        && open.pop() == Some(true)
+                    {
+                        html.push_str("</span>");
+                    }
+                })
+                .ok()?;
+
+            let text = &text[range.start.min(text.len())..range.end.min(text.len())];
+            if text.is_empty() {
+                continue;
+            }
+
+            for (i, scope) in stack.as_slice().iter().enumerate().skip(open.len()) {
+                // The first scope is the language itself, which the code block already says
+                let span = (i > 0).then(|| span(*scope)).flatten();
+
+                if let Some(span) = &span {
+                    html.push_str(span);
+                }
+                open.push(span.is_some());
+            }
+            html.push_str(&util::escape_html(text));
+        }
+
+        // Spans never cross lines, so the html can be split into lines later (e.g. for line
+        // numbers). The ones still open are opened again on the next line.
+        html.push_str(&"</span>".repeat(open.iter().filter(|span| **span).count()));
+        html.push_str(&line[text.len()..]);
+    }
+
+    Some(html)
+}
+
+// The syntaxes of bat, which has more than the ones of syntect (e.g. TOML and TypeScript), loaded
+// once
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+
+    SYNTAXES.get_or_init(two_face::syntax::extra_newlines)
+}
+
+// Meta scopes only say what a part of the code is (e.g. the parameters of a function), which
+// nothing colors, so they get no span
+fn span(scope: Scope) -> Option<String> {
+    let name = scope.build_string();
+    if name.starts_with("meta.") {
+        return None;
+    }
+
+    let classes: Vec<String> = name.split('.').map(|atom| format!("hl-{atom}")).collect();
+
+    Some(format!("<span class=\"{}\">", classes.join(" ")))
+}
+
+/// Highlighted code kept on disk between builds, since highlighting is the slowest part of
+/// rendering code-heavy chapters. Each block is a file named after the hash of its language and
+/// code, so changing either of them misses the cache. The theme only changes the css, so it
+/// isn't part of it.
+#[derive(Debug, Clone)]
+pub struct HighlightCache {
+    dir: PathBuf,
+}
+
+impl HighlightCache {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Like [`highlight`], but looks in the cache first and fills it on a miss. The cache is only
+    /// a shortcut, so failing to write to it isn't an error.
+    pub fn highlight(&self, code: &str, language: &str) -> Option<String> {
+        // A new version may highlight differently
+        let key = util::content_hash(format!("{}\0{language}\0{code}", env!("CARGO_PKG_VERSION")));
+        // Split by the first characters, so no dir ends up with too many files
+        let dir = self.dir.join(&key[..2]);
+        let path = dir.join(&key);
+
+        if let Ok(html) = fs::read_to_string(&path) {
+            return Some(html);
+        }
+
+        let html = highlight(code, language)?;
+        let _ = fs::create_dir_all(dir).and_then(|_| fs::write(path, &html));
+
+        Some(html)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_keep_highlighted_code_in_the_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = HighlightCache::new(temp_dir.path());
+        let html = cache.highlight("let x = 1;", "rust");
+
+        assert_eq!(html, highlight("let x = 1;", "rust"));
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        // What's in the cache wins, so a tampered entry shows it came from there
+        let entry = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .flat_map(|dir| fs::read_dir(dir.unwrap().path()).unwrap())
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        fs::write(&entry, "cached").unwrap();
+        assert_eq!(
+            cache.highlight("let x = 1;", "rust"),
+            Some(String::from("cached"))
+        );
+        assert_eq!(
+            cache.highlight("let x = 2;", "rust"),
+            highlight("let x = 2;", "rust")
+        );
+        assert_eq!(cache.highlight("x", "unknown"), None);
+    }
+
+    #[test]
+    fn it_should_highlight_known_languages() {
+        let code = "// Hello there\nlet x = \"<General>\"; 42";
+        let html = highlight(code, "rust").unwrap();
+
+        assert!(html.starts_with("<span class=\"hl-comment hl-line hl-double-slash hl-rust\">"));
+        assert!(html.contains("<span class=\"hl-storage hl-type hl-rust\">let</span> x "));
+        assert!(html.contains("&quot;</span>&lt;General&gt;<span"));
+        assert!(html.ends_with(
+            "<span class=\"hl-constant hl-numeric hl-integer hl-decimal hl-rust\">42</span>"
+        ));
+        assert_eq!(highlight(code, "RS"), Some(html));
+    }
+
+    #[test]
+    fn it_should_know_languages_by_other_names() {
+        assert_eq!(highlight("x := 1", "golang"), highlight("x := 1", "go"));
+        assert!(highlight("[package]", "toml").is_some());
+        assert!(highlight("let x: number = 1;", "ts").is_some());
+    }
+
+    #[test]
+    fn it_should_not_let_spans_cross_lines() {
+        let html = highlight("/* a\nb */", "c").unwrap();
+
+        assert_eq!(
+            html,
+            concat!(
+                "<span class=\"hl-comment hl-block hl-c\"><span class=\"hl-punctuation hl-definition hl-comment hl-c\">/*</span> a</span>\n",
+                "<span class=\"hl-comment hl-block hl-c\">b <span class=\"hl-punctuation hl-definition hl-comment hl-c\">*/</span></span>"
+            )
+        );
+    }
+
+    #[test]
+    fn it_should_not_highlight_unknown_languages() {
+        assert_eq!(highlight("let x = 1;", "klingon"), None);
+        assert_eq!(highlight("let x = 1;", "txt"), None);
+    }
+}
@@ -0,0 +1,333 @@
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+/// Holds the pages with the whole files pulled into code blocks, inside the build dir
+pub const SOURCE_DIR: &str = "source";
+
+const INCLUDE: &str = "{{#include ";
+
+/// Lets code blocks link to a page with the whole file they were pulled from
+#[derive(Debug, Clone, Copy)]
+pub struct SourceView<'a> {
+    /// Source pages mirror where the files are relative to it, usually the vault
+    pub root: &'a Path,
+    /// Where the site is served from (e.g. /docs). Empty means the root.
+    pub base_path: &'a str,
+}
+
+/// A file pulled into a chapter with `{{#include path}}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Include {
+    pub path: PathBuf,
+    /// The first and last lines pulled, counting from 1 (e.g. `{{#include main.rs:10:20}}`).
+    /// Either end can be left out, like in `main.rs:10:` or `main.rs::20`.
+    pub lines: Option<(Option<usize>, Option<usize>)>,
+    /// Was it pulled into a fenced code block?
+    pub in_code_block: bool,
+}
+
+impl Include {
+    fn parse(argument: &str, dir: &Path, in_code_block: bool) -> Result<Include> {
+        let mut parts = argument.trim().splitn(3, ':');
+        let path = parts.next().unwrap_or_default();
+
+        if path.is_empty() {
+            anyhow::bail!("Missing the path of {INCLUDE}}}}}");
+        }
+
+        let lines = match (parts.next(), parts.next()) {
+            (None, _) => None,
+            (Some(first), last) => Some((line(first)?, line(last.unwrap_or(first))?)),
+        };
+
+        Ok(Include {
+            path: dir.join(path),
+            lines,
+            in_code_block,
+        })
+    }
+
+    /// The first line pulled, counting from 1
+    pub fn first_line(&self) -> usize {
+        self.lines.and_then(|(first, _)| first).unwrap_or(1)
+    }
+
+    fn contents(&self) -> Result<String> {
+        let contents = fs::read_to_string(&self.path)
+            .with_context(|| anyhow!("Failed to include {}", self.path.display()))?;
+
+        let Some((first, last)) = self.lines else {
+            return Ok(contents.trim_end().to_string());
+        };
+        let lines = first.unwrap_or(1)..=last.unwrap_or(usize::MAX);
+
+        Ok(contents
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| lines.contains(&(i + 1)))
+            .map(|(_, line)| line)
+            .collect::<Vec<&str>>()
+            .join("\n"))
+    }
+}
+
+fn line(number: &str) -> Result<Option<usize>> {
+    let number = number.trim();
+
+    match number.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(number.parse().with_context(|| {
+            anyhow!("Invalid line number {number} in {INCLUDE}}}}}")
+        })?)),
+    }
+}
+
+/// Replaces every `{{#include path}}` with the contents of the file, relative to `dir`. Unlike
+/// snippets, files are pulled into fenced code blocks too, which is where they're most useful.
+/// With a [`SourceView`], those code blocks are followed by a link to the whole file.
+pub fn includes(markdown: &str, dir: &Path, source_view: Option<&SourceView>) -> Result<String> {
+    let mut output = String::with_capacity(markdown.len());
+    // The first file pulled into the current code block
+    let mut source: Option<Include> = None;
+
+    for (line, kind) in lines(markdown) {
+        match kind {
+            LineKind::Text | LineKind::Code if line.contains(INCLUDE) => {
+                let mut end = 0;
+
+                for (range, include) in find_in_line(line, dir, kind == LineKind::Code)? {
+                    output.push_str(&line[end..range.start]);
+                    output.push_str(&include.contents()?);
+                    end = range.end;
+
+                    if include.in_code_block && source.is_none() {
+                        source = Some(include);
+                    }
+                }
+
+                output.push_str(&line[end..]);
+            }
+            LineKind::Closing => {
+                output.push_str(line);
+
+                if let (Some(include), Some(source_view)) = (source.take(), source_view) {
+                    if !line.ends_with('\n') {
+                        output.push('\n');
+                    }
+                    output.push_str(&source_link(&include, source_view));
+                }
+            }
+            _ => output.push_str(line),
+        }
+    }
+
+    Ok(output)
+}
+
+/// Every `{{#include path}}` of the markdown, with their paths relative to `dir`
+pub fn find_includes(markdown: &str, dir: &Path) -> Result<Vec<Include>> {
+    let mut includes = Vec::new();
+
+    for (line, kind) in lines(markdown) {
+        if matches!(kind, LineKind::Text | LineKind::Code) && line.contains(INCLUDE) {
+            includes.extend(
+                find_in_line(line, dir, kind == LineKind::Code)?
+                    .into_iter()
+                    .map(|(_, include)| include),
+            );
+        }
+    }
+
+    Ok(includes)
+}
+
+/// Where the page with the whole file goes, inside the build dir. It mirrors where the file is
+/// relative to `root`, and every `..` that leaves it becomes `_` (e.g. `source/_/src/main.rs.html`).
+pub fn source_page<P, Q>(root: P, file: Q) -> PathBuf
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let relative = util::relative_path(normalize(root.as_ref()), normalize(file.as_ref()));
+    let mut page = PathBuf::from(SOURCE_DIR);
+
+    for component in relative.components() {
+        match component {
+            Component::ParentDir => page.push("_"),
+            Component::Normal(name) => page.push(name),
+            _ => {}
+        }
+    }
+
+    let name = page.file_name().unwrap_or_default().to_string_lossy() + ".html";
+    page.with_file_name(name.as_ref())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum LineKind {
+    Text,
+    Opening,
+    Code,
+    Closing,
+}
+
+/// Every line of the markdown, along with whether it's inside a fenced code block
+pub(super) fn lines(markdown: &str) -> Vec<(&str, LineKind)> {
+    let mut lines = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+
+        let kind = match fence {
+            Some(marker) if trimmed.starts_with(marker) => {
+                fence = None;
+                LineKind::Closing
+            }
+            Some(_) => LineKind::Code,
+            None if trimmed.starts_with("```") => {
+                fence = Some("```");
+                LineKind::Opening
+            }
+            None if trimmed.starts_with("~~~") => {
+                fence = Some("~~~");
+                LineKind::Opening
+            }
+            None => LineKind::Text,
+        };
+
+        lines.push((line, kind));
+    }
+
+    lines
+}
+
+// The includes of a line and where they are in it
+fn find_in_line(
+    line: &str,
+    dir: &Path,
+    in_code_block: bool,
+) -> Result<Vec<(Range<usize>, Include)>> {
+    let mut includes = Vec::new();
+    let mut offset = 0;
+
+    while let Some(start) = line[offset..].find(INCLUDE).map(|start| offset + start) {
+        let Some(len) = line[start..].find("}}") else {
+            break;
+        };
+        let argument = &line[start + INCLUDE.len()..start + len];
+
+        includes.push((
+            start..start + len + 2,
+            Include::parse(argument, dir, in_code_block)?,
+        ));
+        offset = start + len + 2;
+    }
+
+    Ok(includes)
+}
+
+fn source_link(include: &Include, source_view: &SourceView) -> String {
+    let page = source_page(source_view.root, &include.path);
+    let mut url = format!("{}/", source_view.base_path);
+    url.push_str(&util::escape_href(&util::url_path(page)));
+
+    format!(
+        "<p class=\"code-block__source\"><a href=\"{url}#L{}\">View full source</a></p>\n\n",
+        include.first_line()
+    )
+}
+
+// Resolves the `.` and `..` in the path without going to the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_include_the_files() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("main.rs"), "fn main() {\n    run();\n}\n")?;
+        fs::write(dir.path().join("note.md"), "*Note:* it runs\n")?;
+
+        assert_eq!(
+            includes(
+                "{{#include note.md}}\n\n```rust\n{{#include main.rs:2}}\n```\n",
+                dir.path(),
+                None
+            )?,
+            "*Note:* it runs\n\n```rust\n    run();\n```\n"
+        );
+        assert_eq!(
+            includes("```rust\n{{#include ./main.rs::2}}\n```", dir.path(), None)?,
+            "```rust\nfn main() {\n    run();\n```"
+        );
+        assert!(includes("{{#include missing.rs}}", dir.path(), None).is_err());
+        assert!(includes("{{#include main.rs:two}}", dir.path(), None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_link_code_blocks_to_the_whole_file() -> Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(dir.path().join("main.rs"), "fn main() {\n    run();\n}\n")?;
+        let source_view = SourceView {
+            root: &dir.path().join("src"),
+            base_path: "/docs",
+        };
+
+        assert_eq!(
+            includes(
+                "```rust\n{{#include ../main.rs:2:3}}\n```",
+                &dir.path().join("src"),
+                Some(&source_view)
+            )?,
+            "```rust\n    run();\n}\n```\n<p class=\"code-block__source\"><a href=\"/docs/source/_/main.rs.html#L2\">View full source</a></p>\n\n"
+        );
+        assert_eq!(
+            find_includes(
+                "{{#include a.md}} ```\n```\n{{#include b.rs}}\n```",
+                Path::new("src")
+            )?
+            .iter()
+            .map(|include| (include.path.clone(), include.in_code_block))
+            .collect::<Vec<_>>(),
+            vec![
+                (PathBuf::from("src/a.md"), false),
+                (PathBuf::from("src/b.rs"), true)
+            ]
+        );
+        assert_eq!(
+            source_page("vault", "vault/src/../code/lib.rs"),
+            PathBuf::from("source/code/lib.rs.html")
+        );
+
+        Ok(())
+    }
+}
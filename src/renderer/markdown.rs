@@ -0,0 +1,940 @@
+use super::fences::{self, FenceTransform};
+use super::highlight::{self, HighlightCache};
+use crate::config::{CodeBlocks, Extensions, Slug};
+use crate::util;
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Options, Tag};
+use std::path::{Component, Path};
+use unicode_normalization::UnicodeNormalization;
+
+const PULL_QUOTE_MARKER: &str = "[!pull]";
+const ATTRIBUTION_PREFIXES: [&str; 2] = ["-- ", "— "];
+
+/// The parser options for the extensions that are turned on
+pub fn options(extensions: &Extensions) -> Options {
+    let mut options = Options::empty();
+
+    options.set(Options::ENABLE_TABLES, extensions.tables);
+    options.set(Options::ENABLE_FOOTNOTES, extensions.footnotes);
+    options.set(Options::ENABLE_STRIKETHROUGH, extensions.strikethrough);
+    options.set(Options::ENABLE_TASKLISTS, extensions.task_lists);
+    options.set(
+        Options::ENABLE_SMART_PUNCTUATION,
+        extensions.smart_punctuation,
+    );
+    options
+}
+
+/// pulldown-cmark splits text around brackets and other special characters. Merging it back
+/// makes matching custom syntax a lot easier.
+pub fn merge_text(events: Vec<Event>) -> Vec<Event> {
+    let mut merged: Vec<Event> = Vec::with_capacity(events.len());
+
+    for event in events {
+        if let (Some(Event::Text(previous)), Event::Text(text)) = (merged.last_mut(), &event) {
+            *previous = CowStr::from(format!("{previous}{text}"));
+            continue;
+        }
+
+        merged.push(event);
+    }
+
+    merged
+}
+
+/// Wraps block quotes in a `<figure>` when they end with an attribution line (`-- Author, Source`)
+/// or start with the pull quote marker (`[!pull]`). Anything else is left untouched.
+///
+/// # Example
+///
+/// > [!pull]
+/// > Here is where the fun begins
+/// > -- Anakin Skywalker, Revenge of the Sith
+pub fn quotes(events: Vec<Event>) -> Vec<Event> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut quote = Vec::new();
+    let mut depth = 0;
+
+    for event in events {
+        let closes_quote = depth == 1 && matches!(event, Event::End(Tag::BlockQuote));
+
+        match event {
+            Event::Start(Tag::BlockQuote) => depth += 1,
+            Event::End(Tag::BlockQuote) => depth -= 1,
+            _ => {}
+        }
+
+        if depth == 0 && !closes_quote {
+            output.push(event);
+            continue;
+        }
+
+        quote.push(event);
+
+        if closes_quote {
+            output.extend(figure(std::mem::take(&mut quote)));
+        }
+    }
+
+    output
+}
+
+// Expects a whole block quote, including its start and end tags.
+fn figure(quote: Vec<Event>) -> Vec<Event> {
+    let mut inner = quote[1..quote.len() - 1].to_vec();
+    let pull = take_pull_marker(&mut inner);
+    let attribution = take_attribution(&mut inner);
+
+    if !pull && attribution.is_none() {
+        return quote;
+    }
+
+    let class = match pull {
+        true => "quote quote--pull",
+        false => "quote",
+    };
+    let mut figure = vec![
+        Event::Html(CowStr::from(format!("<figure class=\"{class}\">"))),
+        Event::Start(Tag::BlockQuote),
+    ];
+
+    figure.extend(inner);
+    figure.push(Event::End(Tag::BlockQuote));
+
+    if let Some(attribution) = attribution {
+        figure.push(Event::Html(CowStr::from(
+            "<figcaption class=\"quote__attribution\">&mdash; <cite>",
+        )));
+        figure.extend(attribution);
+        figure.push(Event::Html(CowStr::from("</cite></figcaption>")));
+    }
+
+    figure.push(Event::Html(CowStr::from("</figure>")));
+    figure
+}
+
+fn take_pull_marker(inner: &mut Vec<Event>) -> bool {
+    let Some(Event::Text(text)) = inner.get(1) else {
+        return false;
+    };
+
+    if !matches!(inner.first(), Some(Event::Start(Tag::Paragraph)))
+        || !text.starts_with(PULL_QUOTE_MARKER)
+    {
+        return false;
+    }
+
+    let rest = text[PULL_QUOTE_MARKER.len()..].trim_start().to_string();
+
+    match rest.is_empty() {
+        true => {
+            inner.remove(1);
+
+            if matches!(inner.get(1), Some(Event::SoftBreak | Event::HardBreak)) {
+                inner.remove(1);
+            }
+        }
+        false => inner[1] = Event::Text(CowStr::from(rest)),
+    }
+
+    remove_empty_paragraph(inner, 0);
+    true
+}
+
+// The attribution is the last line of the last paragraph, as long as it starts with a dash.
+fn take_attribution<'a>(inner: &mut Vec<Event<'a>>) -> Option<Vec<Event<'a>>> {
+    if !matches!(inner.last(), Some(Event::End(Tag::Paragraph))) {
+        return None;
+    }
+
+    let paragraph_end = inner.len() - 1;
+    let paragraph_start = inner
+        .iter()
+        .rposition(|event| matches!(event, Event::Start(Tag::Paragraph)))?;
+    let line_break = inner[paragraph_start..paragraph_end]
+        .iter()
+        .rposition(|event| matches!(event, Event::SoftBreak | Event::HardBreak))
+        .map(|index| index + paragraph_start);
+    let line_start = line_break.unwrap_or(paragraph_start) + 1;
+
+    let Some(Event::Text(text)) = inner.get(line_start) else {
+        return None;
+    };
+    let prefix = ATTRIBUTION_PREFIXES
+        .iter()
+        .find(|prefix| text.starts_with(*prefix))?;
+    let author = text[prefix.len()..].to_string();
+
+    let mut attribution: Vec<Event> = inner.drain(line_start..paragraph_end).collect();
+    attribution[0] = Event::Text(CowStr::from(author));
+
+    if let Some(line_break) = line_break {
+        inner.remove(line_break);
+    }
+
+    remove_empty_paragraph(inner, paragraph_start);
+    Some(attribution)
+}
+
+fn remove_empty_paragraph(inner: &mut Vec<Event>, start: usize) {
+    if matches!(inner.get(start), Some(Event::Start(Tag::Paragraph)))
+        && matches!(inner.get(start + 1), Some(Event::End(Tag::Paragraph)))
+    {
+        inner.drain(start..start + 2);
+    }
+}
+
+/// Demotes every heading by `offset` levels. Headings can't go past H6, so they stop there.
+///
+/// # Example
+///
+/// With an offset of 1:
+///
+/// # Title -> ## Title
+/// ###### Detail -> ###### Detail
+pub fn demote_headings(events: Vec<Event>, offset: u8) -> Vec<Event> {
+    if offset == 0 {
+        return events;
+    }
+
+    let demote = |level: HeadingLevel| {
+        HeadingLevel::try_from(level as usize + offset as usize).unwrap_or(HeadingLevel::H6)
+    };
+
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::Heading(level, id, classes)) => {
+                Event::Start(Tag::Heading(demote(level), id, classes))
+            }
+            Event::End(Tag::Heading(level, id, classes)) => {
+                Event::End(Tag::Heading(demote(level), id, classes))
+            }
+            event => event,
+        })
+        .collect()
+}
+
+/// The anchors of the headings in the markdown, in order. Headings with the same slug get a
+/// number (e.g. `usage`, `usage-1`) so each anchor is unique within the page.
+pub fn anchors(markdown: &str, options: Options, style: Slug) -> Vec<String> {
+    let mut anchors: Vec<String> = Vec::new();
+    let mut heading: Option<String> = None;
+
+    for event in pulldown_cmark::Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::Heading(_, Some(id), _)) => anchors.push(id.to_string()),
+            Event::Start(Tag::Heading(..)) => heading = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = heading.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            Event::End(Tag::Heading(..)) => {
+                let Some(text) = heading.take() else {
+                    continue;
+                };
+
+                anchors.push(unique_anchor(&text, &anchors, style));
+            }
+            _ => {}
+        }
+    }
+
+    anchors
+}
+
+/// A heading of the page, for its table of contents
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    /// 1 for H1, 2 for H2 and so on
+    pub level: usize,
+    pub text: String,
+    pub anchor: String,
+}
+
+/// Gives each heading an `id`, the same anchor [`anchors`] finds for it, and collects them. Links
+/// to `page.html#anchor` land on the heading this way, and each heading ends with a permalink to
+/// itself that shows on hover.
+pub fn headings(events: Vec<Event>, style: Slug) -> (Vec<Event>, Vec<Heading>) {
+    let mut output = Vec::with_capacity(events.len());
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut anchors: Vec<String> = Vec::new();
+    // Where the heading that's open starts in the output and its text so far
+    let mut open: Option<(usize, String)> = None;
+
+    for event in events {
+        match &event {
+            Event::Start(Tag::Heading(..)) => open = Some((output.len(), String::new())),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, heading)) = open.as_mut() {
+                    heading.push_str(text);
+                }
+            }
+            Event::End(Tag::Heading(level, id, classes)) => {
+                let Some((start, text)) = open.take() else {
+                    output.push(event);
+                    continue;
+                };
+                let anchor = match id {
+                    Some(id) => id.to_string(),
+                    None => unique_anchor(&text, &anchors, style),
+                };
+                let mut tag = format!("<{level} id=\"");
+                tag.push_str(&util::escape_html(&anchor));
+                tag.push('"');
+
+                if !classes.is_empty() {
+                    tag.push_str(" class=\"");
+                    tag.push_str(&util::escape_html(&classes.join(" ")));
+                    tag.push('"');
+                }
+                tag.push('>');
+
+                let mut href = String::from("#");
+                href.push_str(&util::escape_href(&anchor));
+
+                output[start] = Event::Html(CowStr::from(tag));
+                output.push(Event::Html(CowStr::from(format!(
+                    "<a class=\"heading__permalink\" href=\"{href}\" aria-label=\"Link to this section\"><i class=\"ti ti-link\"></i></a></{level}>\n"
+                ))));
+                anchors.push(anchor.clone());
+                headings.push(Heading {
+                    level: *level as usize,
+                    text,
+                    anchor,
+                });
+                continue;
+            }
+            _ => {}
+        }
+
+        output.push(event);
+    }
+
+    (output, headings)
+}
+
+// The slug of the heading, with a number if another heading of the page already has it (e.g.
+// `usage-1`). Headings without anything to slug become `section`.
+fn unique_anchor(text: &str, anchors: &[String], style: Slug) -> String {
+    let slug = match slug(text, style) {
+        slug if slug.is_empty() => String::from("section"),
+        slug => slug,
+    };
+    let mut anchor = slug.clone();
+    let mut n = 0;
+
+    while anchors.contains(&anchor) {
+        n += 1;
+        anchor = format!("{slug}-{n}");
+    }
+
+    anchor
+}
+
+/// Turns the text of a heading into something that can go in a url
+///
+/// # Example
+///
+/// Hello there, General Kenobi! -> hello-there-general-kenobi
+pub fn slug(text: &str, style: Slug) -> String {
+    let text = text.trim().to_lowercase();
+    let text = match style {
+        Slug::Unicode => text,
+        // Decomposed, accented letters are the letter and a mark that isn't ASCII
+        Slug::Ascii => text.nfkd().filter(char::is_ascii).collect(),
+    };
+
+    text.chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '_' || c == '-' => Some(c),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Adds `title` as an H1 in front of the content, unless it already starts with a heading
+pub fn inject_title<'a>(mut events: Vec<Event<'a>>, title: &str) -> Vec<Event<'a>> {
+    if matches!(events.first(), Some(Event::Start(Tag::Heading(..)))) {
+        return events;
+    }
+
+    let heading = Tag::Heading(HeadingLevel::H1, None, vec![]);
+    events.splice(
+        0..0,
+        [
+            Event::Start(heading.clone()),
+            Event::Text(CowStr::from(title.to_string())),
+            Event::End(heading),
+        ],
+    );
+
+    events
+}
+
+/// Renders code blocks, highlighting the ones whose info string names a known language (e.g.
+/// ```rust). Depending on `options`, they also get a copy button and line numbers. Blocks in a
+/// language handled by one of the `transforms` are left to it. With a `cache`, blocks highlighted
+/// by a previous build aren't highlighted again.
+pub fn code_blocks<'a>(
+    events: Vec<Event<'a>>,
+    theme: &str,
+    options: &CodeBlocks,
+    transforms: &[&dyn FenceTransform],
+    cache: Option<&HighlightCache>,
+) -> Vec<Event<'a>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut block: Option<(String, String)> = None;
+
+    for event in events {
+        match (&mut block, event) {
+            (None, Event::Start(Tag::CodeBlock(kind))) => {
+                block = Some((fences::language(&kind).to_string(), String::new()));
+            }
+            (Some((_, code)), Event::Text(text)) => code.push_str(&text),
+            (Some((language, code)), Event::End(Tag::CodeBlock(_))) => {
+                let html = match fences::find(transforms, language) {
+                    Some(transform) => transform.transform(code),
+                    None => code_block(code, language, theme, options, cache),
+                };
+
+                output.push(Event::Html(CowStr::from(html)));
+                block = None;
+            }
+            (_, event) => output.push(event),
+        }
+    }
+
+    output
+}
+
+fn code_block(
+    code: &str,
+    language: &str,
+    theme: &str,
+    options: &CodeBlocks,
+    cache: Option<&HighlightCache>,
+) -> String {
+    let (pre_class, code_class, mut html) = highlighted(code, language, theme, cache);
+
+    if options.line_numbers {
+        html = html
+            .strip_suffix('\n')
+            .unwrap_or(&html)
+            .split('\n')
+            .map(|line| format!("<span class=\"code-block__line\">{line}</span>\n"))
+            .collect();
+    }
+
+    let pre = format!("<pre{pre_class}><code{code_class}>{html}</code></pre>\n");
+
+    if !options.copy_button && !options.line_numbers {
+        return pre;
+    }
+
+    let mut wrapper = match options.line_numbers {
+        true => String::from("<div class=\"code-block code-block--numbered\">"),
+        false => String::from("<div class=\"code-block\">"),
+    };
+
+    if options.copy_button {
+        wrapper.push_str(
+            "<button class=\"code-block__copy\" title=\"Copy to clipboard\"><i class=\"ti ti-copy\"></i></button>",
+        );
+    }
+
+    wrapper + &pre + "</div>\n"
+}
+
+/// A whole file, highlighted, with numbered lines that can be linked to (e.g. `#L12`)
+pub fn source_listing(code: &str, language: &str, theme: &str) -> String {
+    let (pre_class, code_class, html) = highlighted(code, language, theme, None);
+    let lines: String = html
+        .strip_suffix('\n')
+        .unwrap_or(&html)
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            format!(
+                "<span class=\"code-block__line\" id=\"L{}\">{line}</span>\n",
+                i + 1
+            )
+        })
+        .collect();
+
+    format!(
+        "<div class=\"code-block code-block--numbered\"><pre{pre_class}><code{code_class}>{lines}</code></pre>\n</div>\n"
+    )
+}
+
+// The classes of the pre and code tags, and the code as html
+fn highlighted(
+    code: &str,
+    language: &str,
+    theme: &str,
+    cache: Option<&HighlightCache>,
+) -> (String, String, String) {
+    let html = match cache {
+        Some(cache) => cache.highlight(code, language),
+        None => highlight::highlight(code, language),
+    };
+    let (pre_class, html) = match html {
+        Some(html) => (
+            format!(
+                " class=\"highlight highlight--{}\"",
+                util::escape_html(theme)
+            ),
+            html,
+        ),
+        None => {
+            let mut html = String::new();
+            html.push_str(&util::escape_html(code));
+            (String::new(), html)
+        }
+    };
+    let code_class = match language.is_empty() {
+        true => String::new(),
+        false => format!(" class=\"language-{}\"", util::escape_html(language)),
+    };
+
+    (pre_class, code_class, html)
+}
+
+/// Rewrites relative link and image urls so they start at the site's root instead of the chapter's
+/// source directory. This way they resolve no matter where the page ends up in the build dir.
+/// Links to other markdown files point to their rendered html.
+///
+/// # Example
+///
+/// With `base` being `chapter1` and `root` being empty:
+///
+/// images/diagram.png -> /chapter1/images/diagram.png
+/// ../chapter2.md#setup -> /chapter2.html#setup
+///
+/// A `root` like `/docs` is put in front of the result, for sites that aren't served from `/`.
+pub fn relative_urls<'a>(events: Vec<Event<'a>>, base: &Path, root: &str) -> Vec<Event<'a>> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::Link(kind, url, title)) => {
+                Event::Start(Tag::Link(kind, resolve_url(url, base, root), title))
+            }
+            Event::Start(Tag::Image(kind, url, title)) => {
+                Event::Start(Tag::Image(kind, resolve_url(url, base, root), title))
+            }
+            event => event,
+        })
+        .collect()
+}
+
+/// Images whose url ends with `#light` or `#dark` only show with themes of that kind, so a
+/// diagram can have a version for each instead of glowing white in the dark. Themes say which
+/// kind they are with `--image-light` and `--image-dark` (see main.css).
+///
+/// # Example
+///
+/// ![Architecture](architecture.png#light)
+/// ![Architecture](architecture-dark.png#dark)
+pub fn themed_images(events: Vec<Event>) -> Vec<Event> {
+    let mut output = Vec::with_capacity(events.len());
+    // The url, title, kind and alt text of the image being read
+    let mut image: Option<(String, String, &'static str, String)> = None;
+
+    for event in events {
+        match (&mut image, event) {
+            (None, Event::Start(Tag::Image(kind, url, title))) => {
+                let scheme = match url.rsplit_once('#') {
+                    Some((_, "light")) => "light",
+                    Some((_, "dark")) => "dark",
+                    _ => {
+                        output.push(Event::Start(Tag::Image(kind, url, title)));
+                        continue;
+                    }
+                };
+                let src = &url[..url.len() - scheme.len() - 1];
+
+                image = Some((src.to_string(), title.to_string(), scheme, String::new()));
+            }
+            (Some((src, title, scheme, alt)), Event::End(Tag::Image(..))) => {
+                let mut html = String::from("<img src=\"");
+                html.push_str(&util::escape_href(src));
+                html.push_str("\" alt=\"");
+                html.push_str(&util::escape_html(alt));
+                if !title.is_empty() {
+                    html.push_str("\" title=\"");
+                    html.push_str(&util::escape_html(title));
+                }
+                html.push_str(&format!(
+                    "\" class=\"themed-image themed-image--{scheme}\" />"
+                ));
+
+                output.push(Event::Html(CowStr::from(html)));
+                image = None;
+            }
+            (Some((.., alt)), Event::Text(text) | Event::Code(text)) => alt.push_str(&text),
+            (Some(_), _) => {}
+            (None, event) => output.push(event),
+        }
+    }
+
+    output
+}
+
+fn resolve_url<'a>(url: CowStr<'a>, base: &Path, root: &str) -> CowStr<'a> {
+    if !is_relative_url(&url) {
+        return url;
+    }
+
+    let (path, suffix) = url.split_at(url.find(['#', '?']).unwrap_or(url.len()));
+    let mut components: Vec<String> = Vec::new();
+
+    for component in base.join(path).components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_string_lossy().to_string()),
+            // It points outside of the vault. There is nothing we can do about it.
+            Component::ParentDir if components.pop().is_none() => return url,
+            _ => {}
+        }
+    }
+
+    let mut resolved = components.join("/");
+    // Chapters lose their number prefixes when rendered. Assets are copied as they are.
+    if resolved.ends_with(".md") {
+        resolved = util::strip_number_prefixes(&resolved)
+            .with_extension("html")
+            .to_string_lossy()
+            .replace('\\', "/");
+    }
+
+    CowStr::from(format!("{root}/{resolved}{suffix}"))
+}
+
+pub fn is_relative_url(url: &str) -> bool {
+    let has_scheme = url.split('/').next().unwrap_or("").contains(':');
+
+    !(url.is_empty() || url.starts_with('/') || url.starts_with('#') || has_scheme)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pulldown_cmark::{html, Parser};
+
+    fn render(markdown: &str) -> String {
+        let events = quotes(merge_text(Parser::new(markdown).collect()));
+        let mut html = String::new();
+
+        html::push_html(&mut html, events.into_iter());
+        html
+    }
+
+    #[test]
+    fn it_should_render_quote_attributions() {
+        let tests = [
+            (
+                "> Here is where the fun begins\n> -- Han Solo, *Star Wars*",
+                "<figure class=\"quote\">\n<blockquote>\n<p>Here is where the fun begins</p>\n</blockquote>\n<figcaption class=\"quote__attribution\">&mdash; <cite>Han Solo, <em>Star Wars</em></cite></figcaption></figure>",
+            ),
+            (
+                "> Hello there\n>\n> — Obi-Wan",
+                "<figure class=\"quote\">\n<blockquote>\n<p>Hello there</p>\n</blockquote>\n<figcaption class=\"quote__attribution\">&mdash; <cite>Obi-Wan</cite></figcaption></figure>",
+            ),
+        ];
+
+        for test in tests.iter() {
+            assert_eq!(render(test.0), test.1);
+        }
+    }
+
+    #[test]
+    fn it_should_render_pull_quotes() {
+        assert_eq!(
+            render("> [!pull]\n> Hello there"),
+            "<figure class=\"quote quote--pull\">\n<blockquote>\n<p>Hello there</p>\n</blockquote>\n</figure>"
+        );
+    }
+
+    #[test]
+    fn it_should_demote_headings() {
+        let tests = [
+            (0, "# Title", "<h1>Title</h1>\n"),
+            (1, "# Title", "<h2>Title</h2>\n"),
+            (2, "### Title", "<h5>Title</h5>\n"),
+            (3, "##### Title", "<h6>Title</h6>\n"),
+        ];
+
+        for test in tests.iter() {
+            let events = demote_headings(Parser::new(test.1).collect(), test.0);
+            let mut html = String::new();
+            html::push_html(&mut html, events.into_iter());
+
+            assert_eq!(html, test.2);
+        }
+    }
+
+    #[test]
+    fn it_should_inject_the_title_only_when_there_is_no_heading() {
+        let tests = [
+            ("Hello there", "<h1>1 Intro</h1>\n<p>Hello there</p>\n"),
+            ("", "<h1>1 Intro</h1>\n"),
+            (
+                "## Setup\nHello there",
+                "<h2>Setup</h2>\n<p>Hello there</p>\n",
+            ),
+        ];
+
+        for test in tests.iter() {
+            let events = inject_title(Parser::new(test.0).collect(), "1 Intro");
+            let mut html = String::new();
+            html::push_html(&mut html, events.into_iter());
+
+            assert_eq!(html, test.1);
+        }
+    }
+
+    #[test]
+    fn it_should_highlight_fenced_code_blocks() {
+        let tests = [
+            (
+                "```rust,ignore\n42\n```",
+                "<pre class=\"highlight highlight--gruvbox\"><code class=\"language-rust\"><span class=\"hl-constant hl-numeric hl-integer hl-decimal hl-rust\">42</span>\n</code></pre>\n",
+            ),
+            (
+                "```klingon\nQapla'\n```",
+                "<pre><code class=\"language-klingon\">Qapla'\n</code></pre>\n",
+            ),
+            ("```\n<plain>\n```", "<pre><code>&lt;plain&gt;\n</code></pre>\n"),
+            (
+                "```x\"onmouseover=\"alert(1)\nhi\n```",
+                "<pre><code class=\"language-x&quot;onmouseover=&quot;alert(1)\">hi\n</code></pre>\n",
+            ),
+            ("    indented\n", "<pre><code>indented\n</code></pre>\n"),
+        ];
+        let options = CodeBlocks {
+            copy_button: false,
+            line_numbers: false,
+            ..Default::default()
+        };
+
+        for test in tests.iter() {
+            let events = code_blocks(
+                Parser::new(test.0).collect(),
+                "gruvbox",
+                &options,
+                &[],
+                None,
+            );
+            let mut html = String::new();
+            html::push_html(&mut html, events.into_iter());
+
+            assert_eq!(html, test.1);
+        }
+    }
+
+    #[test]
+    fn it_should_add_a_copy_button_and_line_numbers_to_code_blocks() {
+        let options = CodeBlocks {
+            copy_button: true,
+            line_numbers: true,
+            ..Default::default()
+        };
+        let events = code_blocks(
+            Parser::new("```\none\ntwo\n```").collect(),
+            "gruvbox",
+            &options,
+            &[],
+            None,
+        );
+        let mut html = String::new();
+        html::push_html(&mut html, events.into_iter());
+
+        assert_eq!(
+            html,
+            concat!(
+                "<div class=\"code-block code-block--numbered\">",
+                "<button class=\"code-block__copy\" title=\"Copy to clipboard\"><i class=\"ti ti-copy\"></i></button>",
+                "<pre><code><span class=\"code-block__line\">one</span>\n",
+                "<span class=\"code-block__line\">two</span>\n</code></pre>\n</div>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn it_should_leave_diagrams_to_their_transforms() {
+        let options = CodeBlocks::default();
+        let events = code_blocks(
+            Parser::new("```mermaid\nA-->B;\n```").collect(),
+            "gruvbox",
+            &options,
+            &fences::FENCE_TRANSFORMS,
+            None,
+        );
+        let mut html = String::new();
+        html::push_html(&mut html, events.into_iter());
+
+        assert_eq!(html, "<pre class=\"mermaid\">A--&gt;B;\n</pre>\n");
+    }
+
+    #[test]
+    fn it_should_only_parse_the_extensions_that_are_on() {
+        let markdown = "~~gone~~\n\n- [x] done";
+        let render = |extensions: &Extensions| {
+            let mut html = String::new();
+            html::push_html(&mut html, Parser::new_ext(markdown, options(extensions)));
+            html
+        };
+
+        let html = render(&Extensions::default());
+        assert!(html.contains("<del>gone</del>"));
+        assert!(html.contains("checkbox"));
+
+        let html = render(&Extensions {
+            strikethrough: false,
+            task_lists: false,
+            ..Extensions::default()
+        });
+        assert!(html.contains("~~gone~~"));
+        assert!(!html.contains("checkbox"));
+    }
+
+    #[test]
+    fn it_should_resolve_relative_urls_from_the_chapter_dir() {
+        let tests = [
+            ("images/diagram.png", "/chapter1/images/diagram.png"),
+            ("./images/diagram.png", "/chapter1/images/diagram.png"),
+            ("../chapter2.md#setup", "/chapter2.html#setup"),
+            ("chapter1.1.md?query", "/chapter1/chapter1.1.html?query"),
+            ("../../outside.png", "../../outside.png"),
+            ("/absolute.png", "/absolute.png"),
+            ("#anchor", "#anchor"),
+            (
+                "https://example.com/image.png",
+                "https://example.com/image.png",
+            ),
+            ("mailto:someone@example.com", "mailto:someone@example.com"),
+        ];
+
+        for test in tests.iter() {
+            assert_eq!(
+                resolve_url(CowStr::from(test.0), Path::new("chapter1"), "").as_ref(),
+                test.1
+            );
+        }
+
+        assert_eq!(
+            resolve_url(
+                CowStr::from("../chapter2.md"),
+                Path::new("chapter1"),
+                "/docs"
+            )
+            .as_ref(),
+            "/docs/chapter2.html"
+        );
+
+        // Number prefixes are gone from the chapters, but not from the assets
+        let tests = [
+            ("01-install.md", "/setup/install.html"),
+            ("../01-intro.md#hello", "/intro.html#hello"),
+            ("images/01-diagram.png", "/02_setup/images/01-diagram.png"),
+        ];
+
+        for test in tests.iter() {
+            assert_eq!(
+                resolve_url(CowStr::from(test.0), Path::new("02_setup"), "").as_ref(),
+                test.1
+            );
+        }
+    }
+
+    #[test]
+    fn it_should_show_themed_images_only_with_their_kind_of_theme() {
+        let tests = [
+            (
+                "![Diagram](diagram.png#dark \"Dark\")",
+                "<p><img src=\"diagram.png\" alt=\"Diagram\" title=\"Dark\" class=\"themed-image themed-image--dark\" /></p>\n",
+            ),
+            (
+                "![The `<main>` diagram](diagram.png#light)",
+                "<p><img src=\"diagram.png\" alt=\"The &lt;main&gt; diagram\" class=\"themed-image themed-image--light\" /></p>\n",
+            ),
+            (
+                "![Diagram](diagram.png#top)",
+                "<p><img src=\"diagram.png#top\" alt=\"Diagram\" /></p>\n",
+            ),
+        ];
+
+        for test in tests.iter() {
+            let events = themed_images(Parser::new(test.0).collect());
+            let mut html = String::new();
+            html::push_html(&mut html, events.into_iter());
+
+            assert_eq!(html, test.1);
+        }
+    }
+
+    #[test]
+    fn it_should_find_the_anchors_of_the_headings() {
+        assert_eq!(
+            anchors(
+                "# Hello there!\n\n## Usage\n\n## `cahlter build`\n\n## Usage\n\n## ???",
+                Options::empty(),
+                Slug::Unicode
+            ),
+            vec![
+                "hello-there",
+                "usage",
+                "cahlter-build",
+                "usage-1",
+                "section"
+            ]
+        );
+        assert_eq!(
+            slug("Hello there, General Kenobi!", Slug::Unicode),
+            "hello-there-general-kenobi"
+        );
+        assert_eq!(slug("Café 日本", Slug::Unicode), "café-日本");
+        assert_eq!(slug("Café 日本", Slug::Ascii), "cafe-");
+        assert_eq!(slug("Ünïcödé", Slug::Ascii), "unicode");
+    }
+
+    #[test]
+    fn it_should_give_the_headings_an_id() {
+        let (events, headings) = headings(
+            Parser::new("# Hello *there*\n\ntext\n\n## Usage\n\n### Usage").collect(),
+            Slug::Unicode,
+        );
+        let mut html = String::new();
+        html::push_html(&mut html, events.into_iter());
+
+        assert!(html.starts_with(
+            "<h1 id=\"hello-there\">Hello <em>there</em><a class=\"heading__permalink\" href=\"#hello-there\" aria-label=\"Link to this section\"><i class=\"ti ti-link\"></i></a></h1>\n<p>text</p>\n"
+        ));
+        assert!(html
+            .contains("<h3 id=\"usage-1\">Usage<a class=\"heading__permalink\" href=\"#usage-1\""));
+        assert_eq!(
+            headings[2],
+            Heading {
+                level: 3,
+                text: "Usage".to_string(),
+                anchor: "usage-1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn it_should_leave_other_quotes_untouched() {
+        let tests = [
+            "> Hello there\n",
+            "> Hello there\n> -- not an attribution\n>\n> > General Kenobi\n",
+            "> [link](https://example.com) -- text\n",
+        ];
+
+        for test in tests.iter() {
+            let mut expected = String::new();
+            html::push_html(&mut expected, Parser::new(test));
+
+            assert_eq!(render(test), expected);
+        }
+    }
+}
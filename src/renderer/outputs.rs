@@ -0,0 +1,158 @@
+use super::pdf::{self, Block};
+use crate::Chapter;
+use anyhow::Result;
+use log::warn;
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag};
+
+/// The page every chapter gets. Listing it in `outputs` changes nothing.
+pub const PAGE_OUTPUT: &str = "html";
+
+/// Another format a chapter can be exported to, on top of its page. Chapters ask for them in their
+/// front matter (e.g. `outputs: [html, pdf]`) and the file is written next to the page.
+pub trait OutputFormat {
+    /// The name used in `outputs`
+    fn name(&self) -> &str;
+
+    fn extension(&self) -> &str;
+
+    /// Exports the markdown of a chapter, without its front matter
+    fn export(&self, chapter: &Chapter, markdown: &str, options: Options) -> Result<Vec<u8>>;
+}
+
+/// Every format chapters can be exported to
+pub const OUTPUT_FORMATS: [&dyn OutputFormat; 2] = [&Markdown, &Pdf];
+
+pub fn find(name: &str) -> Option<&'static dyn OutputFormat> {
+    OUTPUT_FORMATS
+        .iter()
+        .find(|format| format.name() == name)
+        .copied()
+}
+
+/// The source itself, so it can be downloaded
+pub struct Markdown;
+
+impl OutputFormat for Markdown {
+    fn name(&self) -> &str {
+        "md"
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn export(&self, _: &Chapter, markdown: &str, _: Options) -> Result<Vec<u8>> {
+        Ok(markdown.as_bytes().to_vec())
+    }
+}
+
+/// The text of the chapter, laid out on A4 pages with the base fonts every PDF reader has. It's
+/// meant for text-only documents (e.g. cheatsheets), since only the text makes it:
+///
+/// - Headings come in three sizes. Levels past the third look like the third.
+/// - Emphasis, strong text, strikethrough and inline code are plain text.
+/// - Each list item is a paragraph starting with `•`, numbered or not, and nesting is lost.
+/// - Block quotes and footnote definitions are plain paragraphs.
+/// - Each table row is a line, with its cells separated by spaces.
+/// - Code blocks are monospaced, but not highlighted.
+/// - Links are only their text, and images only their alt text.
+/// - Html, rules, task list checkboxes and footnote references are left out.
+/// - Characters outside of WinAnsiEncoding (mostly Latin-1) are question marks, with a warning
+///   naming the chapter.
+pub struct Pdf;
+
+impl OutputFormat for Pdf {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn extension(&self) -> &str {
+        "pdf"
+    }
+
+    fn export(&self, chapter: &Chapter, markdown: &str, options: Options) -> Result<Vec<u8>> {
+        let blocks = blocks(Parser::new_ext(markdown, options));
+        let unsupported = pdf::unsupported(&blocks);
+
+        if !unsupported.is_empty() {
+            warn!(
+                "The PDF of {} ({}) can't show {}, so they are question marks",
+                chapter.title,
+                chapter.content.display(),
+                unsupported.iter().collect::<String>()
+            );
+        }
+
+        Ok(pdf::write(&chapter.title, &blocks))
+    }
+}
+
+// Flattens the markdown into blocks of text, dropping what [`Pdf`] says it does
+fn blocks<'a, I>(events: I) -> Vec<Block>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    let mut blocks = Vec::new();
+    let mut text = String::new();
+
+    for event in events {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak => text.push('\n'),
+            Event::Start(Tag::Item) => text.push_str("• "),
+            Event::End(Tag::TableCell) => text.push_str("   "),
+            Event::End(Tag::Heading(level, _, _)) => {
+                let level = match level {
+                    HeadingLevel::H1 => 1,
+                    HeadingLevel::H2 => 2,
+                    _ => 3,
+                };
+
+                blocks.push(Block::Heading(level, std::mem::take(&mut text)));
+            }
+            Event::End(Tag::CodeBlock(_)) => blocks.push(Block::Code(
+                std::mem::take(&mut text).trim_end().to_string(),
+            )),
+            Event::End(Tag::Paragraph | Tag::Item | Tag::TableHead | Tag::TableRow) => {
+                let paragraph = std::mem::take(&mut text);
+
+                // Items holding a paragraph were already pushed
+                if !paragraph.trim().is_empty() {
+                    blocks.push(Block::Paragraph(paragraph.trim_end().to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_flatten_the_markdown_into_blocks() {
+        let markdown = "# Hello there\n\nGeneral *Kenobi*\n\n- You are\n- a bold one\n\n```rust\nlet x = 1;\n```\n";
+
+        assert_eq!(
+            blocks(Parser::new(markdown)),
+            vec![
+                Block::Heading(1, "Hello there".to_string()),
+                Block::Paragraph("General Kenobi".to_string()),
+                Block::Paragraph("• You are".to_string()),
+                Block::Paragraph("• a bold one".to_string()),
+                Block::Code("let x = 1;".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_find_the_formats_by_name() {
+        assert_eq!(find("pdf").map(|format| format.extension()), Some("pdf"));
+        assert!(find(PAGE_OUTPUT).is_none());
+        assert!(find("docx").is_none());
+    }
+}
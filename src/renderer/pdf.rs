@@ -0,0 +1,295 @@
+//! Just enough of PDF to lay out text. There are no images, links or custom fonts, only the base
+//! fonts every reader has, which is fine for things like cheatsheets.
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const LINE_HEIGHT: f32 = 1.4;
+
+/// A piece of the document. Each one starts on a new line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading(u8, String),
+    Paragraph(String),
+    Code(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Monospace,
+}
+
+impl Font {
+    fn resource(&self) -> &str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Monospace => "F3",
+        }
+    }
+
+    // Helvetica isn't monospaced, so this is an average. Good enough to break lines.
+    fn char_width(&self, size: f32) -> f32 {
+        match self {
+            Font::Regular | Font::Bold => size * 0.5,
+            Font::Monospace => size * 0.6,
+        }
+    }
+}
+
+/// Lays out the blocks on A4 pages and returns the whole file
+pub fn write(title: &str, blocks: &[Block]) -> Vec<u8> {
+    let mut pages: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    for block in blocks {
+        let (font, size, lines) = layout(block);
+        let height = size * LINE_HEIGHT;
+
+        for line in lines {
+            if y - height < MARGIN {
+                pages.push(Vec::new());
+                y = PAGE_HEIGHT - MARGIN;
+            }
+
+            y -= height;
+            let page = pages.last_mut().expect("There is always a page");
+            page.extend_from_slice(
+                format!("BT /{} {size} Tf {MARGIN} {y:.2} Td (", font.resource()).as_bytes(),
+            );
+            page.extend(encode(&line));
+            page.extend_from_slice(b") Tj ET\n");
+        }
+
+        // Some room between blocks
+        y -= size * 0.6;
+    }
+
+    document(title, &pages)
+}
+
+fn layout(block: &Block) -> (Font, f32, Vec<String>) {
+    let (font, size, text) = match block {
+        Block::Heading(1, text) => (Font::Bold, 20.0, text),
+        Block::Heading(2, text) => (Font::Bold, 16.0, text),
+        Block::Heading(_, text) => (Font::Bold, 13.0, text),
+        Block::Paragraph(text) => (Font::Regular, 11.0, text),
+        Block::Code(text) => (Font::Monospace, 9.5, text),
+    };
+    let width = ((PAGE_WIDTH - 2.0 * MARGIN) / font.char_width(size)) as usize;
+    let lines = match font {
+        Font::Monospace => text
+            .lines()
+            .flat_map(|line| split_at_width(line, width))
+            .collect(),
+        _ => text.lines().flat_map(|line| wrap(line, width)).collect(),
+    };
+
+    (font, size, lines)
+}
+
+// Breaks between words. Words longer than a line are split.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        for part in split_at_width(word, width) {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+
+            line.push_str(&part);
+        }
+    }
+
+    lines.push(line);
+    lines
+}
+
+fn split_at_width(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+
+    match chars.is_empty() {
+        true => vec![String::new()],
+        false => chars
+            .chunks(width.max(1))
+            .map(|chunk| chunk.iter().collect())
+            .collect(),
+    }
+}
+
+/// The characters of the blocks the base fonts can't show, without duplicates. They come out as
+/// question marks.
+pub fn unsupported(blocks: &[Block]) -> Vec<char> {
+    let mut unsupported = Vec::new();
+
+    for block in blocks {
+        let (Block::Heading(_, text) | Block::Paragraph(text) | Block::Code(text)) = block;
+
+        for c in text.chars() {
+            if c != '\n' && win_ansi(c).is_none() && !unsupported.contains(&c) {
+                unsupported.push(c);
+            }
+        }
+    }
+
+    unsupported
+}
+
+// Text goes in WinAnsiEncoding, which is mostly Latin-1. Anything else becomes a question mark.
+fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(c, '(' | ')' | '\\') {
+            bytes.push(b'\\');
+        }
+
+        bytes.push(win_ansi(c).unwrap_or(b'?'));
+    }
+
+    bytes
+}
+
+fn win_ansi(c: char) -> Option<u8> {
+    Some(match c {
+        '\t' => b' ',
+        ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+        '…' => 0x85,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        _ => return None,
+    })
+}
+
+fn document(title: &str, pages: &[Vec<u8>]) -> Vec<u8> {
+    // Objects 1 to 6 are fixed. Each page then takes two: the page and its content.
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Count {} /Kids [{}] >>",
+            pages.len(),
+            (0..pages.len())
+                .map(|i| format!("{} 0 R", 7 + i * 2))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+        .into_bytes(),
+        font_object("Helvetica"),
+        font_object("Helvetica-Bold"),
+        font_object("Courier"),
+        [b"<< /Title (".as_slice(), &encode(title), b") >>"].concat(),
+    ];
+
+    for (i, content) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+                8 + i * 2
+            )
+            .into_bytes(),
+        );
+        objects.push(
+            [
+                format!("<< /Length {} >>\nstream\n", content.len()).as_bytes(),
+                content,
+                b"endstream",
+            ]
+            .concat(),
+        );
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 6 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
+
+fn font_object(name: &str) -> Vec<u8> {
+    format!("<< /Type /Font /Subtype /Type1 /BaseFont /{name} /Encoding /WinAnsiEncoding >>")
+        .into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_write_a_pdf() {
+        let pdf = write(
+            "Hello there",
+            &[
+                Block::Heading(1, "Hello there".to_string()),
+                Block::Paragraph("General (Kenobi)".to_string()),
+            ],
+        );
+        let pdf = String::from_utf8_lossy(&pdf);
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/F2 20 Tf 56 758.00 Td (Hello there) Tj"));
+        assert!(pdf.contains("(General \\(Kenobi\\)) Tj"));
+        assert!(pdf.contains("/Count 1"));
+    }
+
+    #[test]
+    fn it_should_break_long_text_into_lines_and_pages() {
+        let paragraph = Block::Paragraph("bold ".repeat(100));
+        let pdf = write("", &vec![paragraph; 30]);
+        let pdf = String::from_utf8_lossy(&pdf);
+
+        // 30 paragraphs of 6 lines
+        assert!(pdf.contains("/Count 5"));
+        assert_eq!(
+            wrap("Hello there General Kenobi", 12),
+            vec!["Hello there", "General", "Kenobi"]
+        );
+        assert_eq!(split_at_width("abcde", 2), vec!["ab", "cd", "e"]);
+    }
+
+    #[test]
+    fn it_should_find_the_characters_the_fonts_cant_show() {
+        let blocks = [
+            Block::Heading(1, "Café — “Kenobi”".to_string()),
+            Block::Paragraph("Olá\n日本 🚀 日本".to_string()),
+        ];
+
+        assert_eq!(unsupported(&blocks), vec!['日', '本', '🚀']);
+        assert_eq!(encode("日 é"), b"? \xe9");
+    }
+}
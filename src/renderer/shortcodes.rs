@@ -0,0 +1,165 @@
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use pulldown_cmark::{CowStr, Event, Tag};
+use qrcodegen::{QrCode, QrCodeEcc};
+
+/// Something written as `{{#name arguments}}` in the text of a chapter and replaced by html when
+/// the chapter is rendered. Shortcodes inside code are left alone.
+pub trait Shortcode {
+    fn name(&self) -> &str;
+
+    /// The html that replaces the shortcode. `arguments` is everything after the name, trimmed.
+    fn render(&self, arguments: &str) -> Result<String>;
+}
+
+/// Every shortcode the renderer knows about
+pub const SHORTCODES: [&dyn Shortcode; 1] = [&Qr];
+
+/// `{{#qr url}}` draws a QR code pointing to the url as an inline svg, so printed pages can carry
+/// links too.
+pub struct Qr;
+
+// The quiet zone around the code, in modules, as recommended by the spec
+const QR_BORDER: i32 = 4;
+
+impl Shortcode for Qr {
+    fn name(&self) -> &str {
+        "qr"
+    }
+
+    fn render(&self, url: &str) -> Result<String> {
+        if url.is_empty() {
+            anyhow::bail!("A QR code needs a url (e.g. {{{{#qr https://example.com}}}})");
+        }
+
+        let qr = QrCode::encode_text(url, QrCodeEcc::Medium)
+            .map_err(|_| anyhow!("{url} is too long for a QR code"))?;
+        let size = qr.size() + QR_BORDER * 2;
+        let mut path = String::new();
+
+        for y in 0..qr.size() {
+            for x in 0..qr.size() {
+                if qr.get_module(x, y) {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + QR_BORDER, y + QR_BORDER));
+                }
+            }
+        }
+
+        let href = util::escape_href(url);
+        let label = util::escape_html(url);
+
+        Ok(format!(
+            "<a class=\"qr\" href=\"{href}\"><svg xmlns=\"http://www.w3.org/2000/svg\" \
+             viewBox=\"0 0 {size} {size}\" role=\"img\" aria-label=\"QR code for {label}\">\
+             <rect width=\"{size}\" height=\"{size}\" fill=\"#fff\"/>\
+             <path d=\"{path}\" fill=\"#000\"/></svg></a>"
+        ))
+    }
+}
+
+/// Replaces the shortcodes in the text with what they render to. Unknown ones are left as they
+/// are, since `{{#` may as well be part of the text.
+pub fn shortcodes<'a>(
+    events: Vec<Event<'a>>,
+    shortcodes: &[&dyn Shortcode],
+) -> Result<Vec<Event<'a>>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut in_code = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code = true,
+            Event::End(Tag::CodeBlock(_)) => in_code = false,
+            Event::Text(ref text) if !in_code && text.contains("{{#") => {
+                output.extend(expand(text, shortcodes)?);
+                continue;
+            }
+            _ => {}
+        }
+
+        output.push(event);
+    }
+
+    Ok(output)
+}
+
+fn expand<'a>(text: &str, shortcodes: &[&dyn Shortcode]) -> Result<Vec<Event<'a>>> {
+    let mut events = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{#") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let inner = &rest[start + 3..start + len];
+        let (name, arguments) = inner.split_once(' ').unwrap_or((inner, ""));
+        let Some(shortcode) = shortcodes.iter().find(|shortcode| shortcode.name() == name) else {
+            events.push(Event::Text(CowStr::from(
+                rest[..start + len + 2].to_string(),
+            )));
+            rest = &rest[start + len + 2..];
+            continue;
+        };
+
+        if start > 0 {
+            events.push(Event::Text(CowStr::from(rest[..start].to_string())));
+        }
+
+        let html = shortcode
+            .render(arguments.trim())
+            .with_context(|| anyhow!("Failed to render {{{{#{inner}}}}}"))?;
+        events.push(Event::Html(CowStr::from(html)));
+        rest = &rest[start + len + 2..];
+    }
+
+    if !rest.is_empty() {
+        events.push(Event::Text(CowStr::from(rest.to_string())));
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::markdown::merge_text;
+    use pulldown_cmark::{html, Parser};
+
+    fn render(markdown: &str) -> Result<String> {
+        let events = shortcodes(merge_text(Parser::new(markdown).collect()), &SHORTCODES)?;
+        let mut html = String::new();
+
+        html::push_html(&mut html, events.into_iter());
+        Ok(html)
+    }
+
+    #[test]
+    fn it_should_render_qr_codes() -> Result<()> {
+        let html = render("Scan {{#qr https://example.com/?a=1&b=2}} to read more")?;
+
+        assert!(
+            html.starts_with("<p>Scan <a class=\"qr\" href=\"https://example.com/?a=1&amp;b=2\">")
+        );
+        assert!(html.contains("aria-label=\"QR code for https://example.com/?a=1&amp;b=2\""));
+        // 29 bytes need a version 3 code, which is 29 modules wide, plus the border
+        assert!(html.contains("viewBox=\"0 0 37 37\""));
+        assert!(html.ends_with("</svg></a> to read more</p>\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_leave_code_and_unknown_shortcodes_alone() -> Result<()> {
+        assert_eq!(
+            render("```\n{{#qr https://example.com}}\n```")?,
+            "<pre><code>{{#qr https://example.com}}\n</code></pre>\n"
+        );
+        assert_eq!(
+            render("{{#dance now}} and {{#")?,
+            "<p>{{#dance now}} and {{#</p>\n"
+        );
+        assert!(render("{{#qr}}").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Snippets may include other snippets, but not forever
+const MAX_DEPTH: usize = 8;
+
+/// Replaces every `{{#snippet name}}` with the contents of `name.md` inside `dir`, before the
+/// markdown is parsed. The snippet then goes through the rest of the pipeline as if it were
+/// written in the chapter, so its relative links resolve from the chapter too. Fenced code blocks
+/// are left alone.
+pub fn snippets(markdown: &str, dir: &Path) -> Result<String> {
+    expand(markdown, dir, 0)
+}
+
+fn expand(markdown: &str, dir: &Path, depth: usize) -> Result<String> {
+    let mut output = String::with_capacity(markdown.len());
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+
+        match fence {
+            Some(marker) if trimmed.starts_with(marker) => fence = None,
+            Some(_) => {}
+            None if trimmed.starts_with("```") => fence = Some("```"),
+            None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+            None if line.contains("{{#snippet ") => {
+                output.push_str(&expand_line(line, dir, depth)?);
+                continue;
+            }
+            None => {}
+        }
+
+        output.push_str(line);
+    }
+
+    Ok(output)
+}
+
+fn expand_line(line: &str, dir: &Path, depth: usize) -> Result<String> {
+    let mut output = String::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find("{{#snippet ") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + "{{#snippet ".len()..start + len].trim();
+
+        if depth >= MAX_DEPTH {
+            anyhow::bail!("Too many nested snippets. Does {name} include itself?");
+        }
+
+        let path = path(dir, name)?;
+        let snippet = fs::read_to_string(&path)
+            .with_context(|| anyhow!("Failed to read the snippet {}", path.display()))?;
+
+        output.push_str(&rest[..start]);
+        output.push_str(
+            expand(snippet.trim_end(), dir, depth + 1)
+                .with_context(|| anyhow!("Failed to expand the snippet {name}"))?
+                .as_str(),
+        );
+        rest = &rest[start + len + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+// Snippets are named without their extension, but can live in subdirectories
+fn path(dir: &Path, name: &str) -> Result<PathBuf> {
+    let name = Path::new(name);
+
+    if name.as_os_str().is_empty()
+        || !name
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        anyhow::bail!("Invalid snippet name {}", name.display());
+    }
+
+    Ok(match name.extension() {
+        Some(_) => dir.join(name),
+        None => dir.join(name).with_extension("md"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_insert_the_snippets() -> Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir(dir.path().join("legal"))?;
+        fs::write(
+            dir.path().join("warning.md"),
+            "> **Warning:** {{#snippet legal/license}}\n",
+        )?;
+        fs::write(dir.path().join("legal/license.md"), "MIT licensed")?;
+
+        assert_eq!(
+            snippets(
+                "# Hello there\n\n{{#snippet warning}}\n\nGeneral Kenobi",
+                dir.path()
+            )?,
+            "# Hello there\n\n> **Warning:** MIT licensed\n\nGeneral Kenobi"
+        );
+        assert_eq!(
+            snippets("```\n{{#snippet warning}}\n```\n", dir.path())?,
+            "```\n{{#snippet warning}}\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_with_missing_or_recursive_snippets() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("echo.md"), "{{#snippet echo}}")?;
+
+        assert!(snippets("{{#snippet missing}}", dir.path()).is_err());
+        assert!(snippets("{{#snippet ../secret}}", dir.path()).is_err());
+        assert!(snippets("{{#snippet echo}}", dir.path()).is_err());
+
+        Ok(())
+    }
+}
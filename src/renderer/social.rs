@@ -0,0 +1,192 @@
+//! Social cards, the images sites like Mastodon or Slack show next to a link to a page. They're
+//! drawn from an SVG template, where `{{title}}` becomes the title of the chapter and `{{site}}`
+//! the title of the vault. `{{font}}`, `{{background}}`, `{{foreground}}` and `{{accent}}` come
+//! from a [`CardStyle`].
+
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{fontdb, Options, Tree};
+use std::sync::{Arc, OnceLock};
+
+/// Used when the vault doesn't have a template of its own
+pub const DEFAULT_TEMPLATE: &str = include_str!("../../templates/social_card.svg");
+
+// Good enough to break lines of the default template, whose title is 72px on 1200px
+const MAX_LINE_LENGTH: usize = 26;
+const MAX_LINES: usize = 4;
+
+// Finding the fonts of the system is slow, so it only happens once
+static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+
+/// The font and colors of a card
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CardStyle<'a> {
+    /// A font family, tried before the sans serif font of the system
+    pub font: Option<&'a str>,
+    pub background: &'a str,
+    pub foreground: &'a str,
+    pub accent: &'a str,
+}
+
+impl CardStyle<'_> {
+    /// The colors of one of the themes, or gruvbox for the ones it doesn't know
+    pub fn of_theme(theme: &str) -> CardStyle<'static> {
+        let (background, foreground, accent) = match theme {
+            "catppuccin" => ("#1e1e2e", "#cdd6f4", "#b4befe"),
+            _ => ("#282828", "#ddc7a1", "#689d6a"),
+        };
+
+        CardStyle {
+            font: None,
+            background,
+            foreground,
+            accent,
+        }
+    }
+}
+
+/// Draws the card as a PNG. Titles are broken in lines, each one a `<tspan>` that starts at
+/// `x="0"`, so templates should move the `<text>` around with a `transform`.
+pub fn render_card(template: &str, title: &str, site: &str, style: &CardStyle) -> Result<Vec<u8>> {
+    let font = match style.font {
+        Some(font) => format!("{}, sans-serif", util::escape_html(font.trim())),
+        None => String::from("sans-serif"),
+    };
+    let svg = template
+        .replace("{{title}}", &title_lines(title))
+        .replace("{{site}}", &util::escape_html(site))
+        .replace("{{font}}", &font)
+        .replace("{{background}}", color(style.background)?)
+        .replace("{{foreground}}", color(style.foreground)?)
+        .replace("{{accent}}", color(style.accent)?);
+    let options = Options {
+        fontdb: FONTS.get_or_init(|| Arc::new(system_fonts())).clone(),
+        ..Default::default()
+    };
+
+    let tree =
+        Tree::from_str(&svg, &options).with_context(|| anyhow!("Invalid social card template"))?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = Pixmap::new(size.width(), size.height())
+        .with_context(|| anyhow!("The social card template has no size"))?;
+    resvg::render(&tree, Transform::default(), &mut pixmap.as_mut());
+
+    pixmap
+        .encode_png()
+        .with_context(|| anyhow!("Failed to encode the social card"))
+}
+
+// `sans-serif` means Arial to fontdb. Systems without it use another sans serif font, or any font
+// they have.
+fn system_fonts() -> fontdb::Database {
+    let mut fonts = fontdb::Database::new();
+    fonts.load_system_fonts();
+
+    let query = fontdb::Query {
+        families: &[fontdb::Family::SansSerif],
+        ..Default::default()
+    };
+    if fonts.query(&query).is_none() {
+        let families: Vec<&String> = fonts
+            .faces()
+            .filter_map(|face| face.families.first())
+            .map(|(family, _)| family)
+            .collect();
+        let fallback = families
+            .iter()
+            .find(|family| family.contains("Sans") && !family.contains("Mono"))
+            .or(families.first())
+            .map(|family| family.to_string());
+
+        if let Some(family) = fallback {
+            fonts.set_sans_serif_family(family);
+        }
+    }
+
+    fonts
+}
+
+fn title_lines(title: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    for word in title.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + word.chars().count() < MAX_LINE_LENGTH => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES);
+        lines[MAX_LINES - 1].push('…');
+    }
+
+    lines
+        .iter()
+        .map(|line| {
+            format!(
+                "<tspan x=\"0\" dy=\"1.2em\">{}</tspan>",
+                util::escape_html(line)
+            )
+        })
+        .collect()
+}
+
+// Colors end up inside attributes, so anything that isn't a plain color is refused
+fn color(color: &str) -> Result<&str> {
+    let color = color.trim();
+
+    match !color.is_empty()
+        && color
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "#(),.% -".contains(c))
+    {
+        true => Ok(color),
+        false => Err(anyhow!("Invalid social card color {color}")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_break_long_titles_in_lines() {
+        assert_eq!(
+            title_lines("Fish & chips"),
+            "<tspan x=\"0\" dy=\"1.2em\">Fish &amp; chips</tspan>"
+        );
+        assert_eq!(
+            title_lines("A title that is much too long for a single line of the card")
+                .matches("<tspan")
+                .count(),
+            3
+        );
+        assert!(title_lines(&"word ".repeat(40)).ends_with("word…</tspan>"));
+    }
+
+    #[test]
+    fn it_should_render_the_card_as_a_png() -> Result<()> {
+        let style = CardStyle::of_theme("catppuccin");
+        let png = render_card(DEFAULT_TEMPLATE, "Getting started", "Cahlter", &style)?;
+
+        assert!(png.starts_with(b"\x89PNG"));
+        assert!(render_card("<svg", "Getting started", "Cahlter", &style).is_err());
+        assert!(render_card(
+            DEFAULT_TEMPLATE,
+            "Getting started",
+            "Cahlter",
+            &CardStyle {
+                background: "red\" onload=\"",
+                ..style
+            }
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{anyhow, Context, Result};
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Values chapters can refer to with `{{name}}`, like product names or versions
+pub type Variables = BTreeMap<String, String>;
+
+/// Reads a YAML file of names and values (e.g. `version: 2.1`). Only strings, numbers and booleans
+/// make sense as values.
+pub fn from_disk<P>(path: P) -> Result<Variables>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let yaml = fs::read_to_string(path)
+        .with_context(|| anyhow!("Failed to read contents of {}", path.display()))?;
+    let values: BTreeMap<String, serde_yaml::Value> = match yaml.trim().is_empty() {
+        true => BTreeMap::new(),
+        false => serde_yaml::from_str(&yaml)
+            .with_context(|| anyhow!("Failed to parse {}", path.display()))?,
+    };
+
+    values
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_yaml::Value::String(value) => value,
+                serde_yaml::Value::Number(value) => value.to_string(),
+                serde_yaml::Value::Bool(value) => value.to_string(),
+                _ => anyhow::bail!(
+                    "The variable {name} in {} must be a string, number or boolean",
+                    path.display()
+                ),
+            };
+
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Replaces `{{name}}` with the value of the variable in the text, links and images. Code is left
+/// alone. Unknown variables are left as they are or, if `strict`, are an error.
+pub fn variables<'a>(
+    events: Vec<Event<'a>>,
+    variables: &Variables,
+    strict: bool,
+) -> Result<Vec<Event<'a>>> {
+    let mut in_code = false;
+    let replace = |text: CowStr<'a>| -> Result<CowStr<'a>> {
+        Ok(match interpolate(&text, variables, strict)? {
+            Some(text) => CowStr::from(text),
+            None => text,
+        })
+    };
+
+    events
+        .into_iter()
+        .map(|event| {
+            Ok(match event {
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    in_code = true;
+                    Event::Start(Tag::CodeBlock(kind))
+                }
+                Event::End(Tag::CodeBlock(kind)) => {
+                    in_code = false;
+                    Event::End(Tag::CodeBlock(kind))
+                }
+                Event::Text(text) if !in_code => Event::Text(replace(text)?),
+                Event::Start(Tag::Link(kind, url, title)) => {
+                    Event::Start(Tag::Link(kind, replace(url)?, replace(title)?))
+                }
+                Event::End(Tag::Link(kind, url, title)) => {
+                    Event::End(Tag::Link(kind, replace(url)?, replace(title)?))
+                }
+                Event::Start(Tag::Image(kind, url, title)) => {
+                    Event::Start(Tag::Image(kind, replace(url)?, replace(title)?))
+                }
+                Event::End(Tag::Image(kind, url, title)) => {
+                    Event::End(Tag::Image(kind, replace(url)?, replace(title)?))
+                }
+                event => event,
+            })
+        })
+        .collect()
+}
+
+// Nothing is allocated for text without variables
+fn interpolate(text: &str, variables: &Variables, strict: bool) -> Result<Option<String>> {
+    if !text.contains("{{") {
+        return Ok(None);
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + len].trim();
+        let end = start + len + 2;
+
+        // Shortcodes start with # and aren't variables
+        if !is_name(name) {
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+
+        match variables.get(name) {
+            Some(value) => {
+                output.push_str(&rest[..start]);
+                output.push_str(value);
+            }
+            None if strict => anyhow::bail!("Undefined variable {name}"),
+            None => output.push_str(&rest[..end]),
+        }
+
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    Ok(Some(output))
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::markdown::merge_text;
+    use pulldown_cmark::{html, Parser};
+    use tempfile::tempdir;
+
+    fn render(markdown: &str, strict: bool) -> Result<String> {
+        let values = Variables::from([
+            ("product".to_string(), "Cahlter".to_string()),
+            ("version".to_string(), "2.1".to_string()),
+        ]);
+        let events = variables(merge_text(Parser::new(markdown).collect()), &values, strict)?;
+        let mut html = String::new();
+
+        html::push_html(&mut html, events.into_iter());
+        Ok(html)
+    }
+
+    #[test]
+    fn it_should_replace_the_variables() -> Result<()> {
+        assert_eq!(
+            render(
+                "Welcome to {{product}} {{ version }}! [Download]({{version}}.zip)",
+                false
+            )?,
+            "<p>Welcome to Cahlter 2.1! <a href=\"2.1.zip\">Download</a></p>\n"
+        );
+        assert_eq!(
+            render("`{{product}}`\n\n```\n{{version}}\n```", false)?,
+            "<p><code>{{product}}</code></p>\n<pre><code>{{version}}\n</code></pre>\n"
+        );
+        assert_eq!(
+            render("{{#qr https://example.com}} {{unknown}}", false)?,
+            "<p>{{#qr https://example.com}} {{unknown}}</p>\n"
+        );
+        assert!(render("{{unknown}}", true).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_read_the_variables_file() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("variables.yml");
+
+        fs::write(&path, "product: Cahlter\nversion: 2.1\nstable: true\n")?;
+        assert_eq!(
+            from_disk(&path)?,
+            Variables::from([
+                ("product".to_string(), "Cahlter".to_string()),
+                ("stable".to_string(), "true".to_string()),
+                ("version".to_string(), "2.1".to_string()),
+            ])
+        );
+
+        fs::write(&path, "versions: [1, 2]\n")?;
+        assert!(from_disk(&path).is_err());
+
+        Ok(())
+    }
+}
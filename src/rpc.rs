@@ -0,0 +1,205 @@
+//! A small JSON-RPC 2.0 API so editors can preview a vault without running a build on every
+//! keystroke. It's served by `cahlter serve --rpc` with the following methods:
+//!
+//! - `render`: renders a file to html. Takes `path`, relative to the vault, and optionally the
+//!   unsaved `content` of the file.
+//! - `summary`: lists the chapters and sections of the vault.
+//! - `diagnostics`: the problems found by [`Vault::check`].
+
+use crate::vault::Vault;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Path, relative to where a vault is served from, of the endpoint that takes the requests
+pub const RPC_ENDPOINT: &str = "/__rpc";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// Anything that went wrong while working with the vault (e.g. a file that doesn't exist)
+pub const SERVER_ERROR: i64 = -32000;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Response {
+    pub jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Error {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct RenderParams {
+    path: String,
+    content: Option<String>,
+}
+
+impl Response {
+    pub fn result(id: Value, result: Value) -> Response {
+        Response {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error<S>(id: Value, code: i64, message: S) -> Response
+    where
+        S: Into<String>,
+    {
+        Response {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(Error {
+                code,
+                message: message.into(),
+            }),
+        }
+    }
+}
+
+/// Parses a request and runs it against the vault. Failures are reported in the response, as
+/// JSON-RPC expects.
+pub fn handle(vault: &Vault, body: &str) -> Response {
+    let request: Request = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => return Response::error(Value::Null, PARSE_ERROR, e.to_string()),
+    };
+
+    if request.jsonrpc != "2.0" {
+        return Response::error(
+            request.id,
+            INVALID_REQUEST,
+            "Only JSON-RPC 2.0 is supported",
+        );
+    }
+
+    let id = request.id.clone();
+    match dispatch(vault, request) {
+        Ok(result) => Response::result(id, result),
+        Err((code, message)) => Response::error(id, code, message),
+    }
+}
+
+fn dispatch(vault: &Vault, request: Request) -> Result<Value, (i64, String)> {
+    let server_error = |e: anyhow::Error| (SERVER_ERROR, format!("{e}: {}", e.root_cause()));
+
+    match request.method.as_str() {
+        "render" => {
+            let params: RenderParams = serde_json::from_value(request.params)
+                .map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+            let html = vault
+                .render_file(&params.path, params.content.as_deref())
+                .map_err(server_error)?;
+
+            Ok(json!({ "html": html }))
+        }
+        "summary" => {
+            let content = vault.content().map_err(server_error)?;
+
+            Ok(json!({ "items": content.summary.items }))
+        }
+        "diagnostics" => {
+            let problems = vault.check().map_err(server_error)?;
+
+            Ok(json!({ "problems": problems }))
+        }
+        method => Err((METHOD_NOT_FOUND, format!("Unknown method: {method}"))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_answer_requests() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("summary.md"), "- [Intro](./intro.md)")?;
+        fs::write(vault.src_dir().join("intro.md"), "[Broken](./nope.md)")?;
+
+        let response = handle(
+            &vault,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "render", "params": {"path": "src/intro.md", "content": "Hello there"}}"#,
+        );
+        let html = response.result.unwrap()["html"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(html.contains("Hello there"));
+
+        for path in ["/etc/passwd", "src/../cahlter.yml"] {
+            let response = handle(
+                &vault,
+                &json!({"jsonrpc": "2.0", "id": 1, "method": "render", "params": {"path": path}})
+                    .to_string(),
+            );
+            assert_eq!(response.error.unwrap().code, SERVER_ERROR);
+        }
+
+        let response = handle(
+            &vault,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "summary"}"#,
+        );
+        assert_eq!(
+            response.result.unwrap()["items"][0]["title"],
+            json!("Intro")
+        );
+
+        let response = handle(
+            &vault,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "diagnostics"}"#,
+        );
+        assert_eq!(
+            response.result.unwrap()["problems"][0]["message"],
+            json!("Broken link to ./nope.md")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_report_bad_requests() {
+        let vault = Vault::new("/does/not/exist");
+        let tests = [
+            ("{", PARSE_ERROR),
+            (
+                r#"{"jsonrpc": "1.0", "method": "summary"}"#,
+                INVALID_REQUEST,
+            ),
+            (r#"{"jsonrpc": "2.0", "method": "dance"}"#, METHOD_NOT_FOUND),
+            (r#"{"jsonrpc": "2.0", "method": "render"}"#, INVALID_PARAMS),
+            (r#"{"jsonrpc": "2.0", "method": "summary"}"#, SERVER_ERROR),
+        ];
+
+        for test in tests.iter() {
+            assert_eq!(handle(&vault, test.0).error.unwrap().code, test.1);
+        }
+    }
+}
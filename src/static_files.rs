@@ -0,0 +1,295 @@
+//! Serves the build dir of a vault with `cahlter serve`. Unlike tide's `serve_dir`, the content
+//! type of every file comes from its extension (see [`content_type`]), HEAD requests are answered
+//! with the headers a GET would get, and requests with a `Range` header get only the bytes they
+//! ask for, so browsers can seek through large videos and PDFs.
+
+use async_std::fs::File;
+use async_std::io::{prelude::SeekExt, BufReader, ReadExt, SeekFrom};
+use percent_encoding::percent_decode_str;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use tide::http::headers::{ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE};
+use tide::{Body, Request, Response, StatusCode};
+
+// http-types has no constant for it
+const RANGE: &str = "range";
+
+/// The part of a file a request asks for, going by its `Range` header
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    /// No range, or one that can't be understood (e.g. several ranges at once)
+    Whole,
+    /// From `start` to `end`, both included
+    Partial { start: u64, end: u64 },
+    /// A range that starts past the end of the file
+    Unsatisfiable,
+}
+
+/// Reads the `Range` header of a request for a file of `len` bytes. Only single ranges are
+/// understood, which is what browsers send for media.
+pub fn byte_range(header: Option<&str>, len: u64) -> ByteRange {
+    let Some((start, end)) = header
+        .and_then(|header| header.trim().strip_prefix("bytes="))
+        .filter(|range| !range.contains(','))
+        .and_then(|range| range.split_once('-'))
+    else {
+        return ByteRange::Whole;
+    };
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        // The last bytes of the file (e.g. `bytes=-500`)
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Whole,
+        },
+        (start, "") => match start.parse() {
+            Ok(start) => (start, len.saturating_sub(1)),
+            Err(_) => return ByteRange::Whole,
+        },
+        (start, end) => match (start.parse(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+            _ => return ByteRange::Whole,
+        },
+    };
+
+    match start < len {
+        true => ByteRange::Partial { start, end },
+        false => ByteRange::Unsatisfiable,
+    }
+}
+
+/// The content type of a file the build may write or copy, by its extension
+pub fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html;charset=utf-8",
+        "css" => "text/css;charset=utf-8",
+        "js" | "mjs" => "text/javascript;charset=utf-8",
+        "json" | "map" => "application/json",
+        "xml" => "application/xml;charset=utf-8",
+        "txt" => "text/plain;charset=utf-8",
+        "md" => "text/markdown;charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "ogv" => "video/ogg",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "pdf" => "application/pdf",
+        "epub" => "application/epub+zip",
+        "zip" => "application/zip",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The endpoint that serves the files of a dir, for GET and HEAD requests
+#[derive(Clone)]
+pub struct StaticFiles {
+    /// Where the dir is served from (e.g. /docs)
+    prefix: String,
+    dir: PathBuf,
+}
+
+impl StaticFiles {
+    pub fn new<P>(prefix: &str, dir: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            dir: dir.as_ref().canonicalize()?,
+        })
+    }
+
+    // The file of the dir at the path of the url, which is percent encoded (e.g. `my%20notes.html`).
+    // Nothing outside of the dir can be reached.
+    fn file(&self, path: &str) -> Option<PathBuf> {
+        let relative = path.strip_prefix(&self.prefix).unwrap_or(path);
+        let mut file = self.dir.clone();
+
+        for segment in relative.split('/') {
+            let segment = percent_decode_str(segment).decode_utf8().ok()?;
+
+            match segment.as_ref() {
+                "" | "." => {}
+                ".." => return None,
+                // An encoded separator would make a segment of several components
+                name if name.contains(['/', '\\']) => return None,
+                name => match Path::new(name).components().next() {
+                    Some(Component::Normal(_)) => file.push(name),
+                    _ => return None,
+                },
+            }
+        }
+
+        Some(file)
+    }
+}
+
+#[tide::utils::async_trait]
+impl<State> tide::Endpoint<State> for StaticFiles
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn call(&self, request: Request<State>) -> tide::Result {
+        let Some(path) = self.file(request.url().path()) else {
+            return Ok(Response::new(StatusCode::Forbidden));
+        };
+        let mut file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Response::new(StatusCode::NotFound))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let metadata = file.metadata().await?;
+        if !metadata.is_file() {
+            return Ok(Response::new(StatusCode::NotFound));
+        }
+
+        let len = metadata.len();
+        let range = request.header(RANGE).map(|header| header.last().as_str());
+        let mut response = match byte_range(range, len) {
+            ByteRange::Whole => Response::builder(StatusCode::Ok)
+                .body(Body::from_reader(BufReader::new(file), Some(len as usize)))
+                .build(),
+            ByteRange::Partial { start, end } => {
+                file.seek(SeekFrom::Start(start)).await?;
+                let length = end - start + 1;
+
+                Response::builder(StatusCode::PartialContent)
+                    .header(CONTENT_RANGE, format!("bytes {start}-{end}/{len}"))
+                    .body(Body::from_reader(
+                        BufReader::new(file.take(length)),
+                        Some(length as usize),
+                    ))
+                    .build()
+            }
+            ByteRange::Unsatisfiable => Response::builder(StatusCode::RequestedRangeNotSatisfiable)
+                .header(CONTENT_RANGE, format!("bytes */{len}"))
+                .build(),
+        };
+        response.insert_header(ACCEPT_RANGES, "bytes");
+        response.insert_header(CONTENT_TYPE, content_type(&path));
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use std::fs;
+    use tempfile::tempdir;
+    use tide::http::{Method, Url};
+
+    #[test]
+    fn it_should_read_the_range_header() {
+        assert_eq!(byte_range(None, 100), ByteRange::Whole);
+        assert_eq!(
+            byte_range(Some("bytes=10-19"), 100),
+            ByteRange::Partial { start: 10, end: 19 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=90-"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=-10"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=50-500"), 100),
+            ByteRange::Partial { start: 50, end: 99 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(byte_range(Some("bytes=0-1,5-6"), 100), ByteRange::Whole);
+        assert_eq!(byte_range(Some("lines=1-2"), 100), ByteRange::Whole);
+    }
+
+    #[test]
+    fn it_should_know_the_content_type_of_what_the_build_writes() {
+        assert_eq!(
+            content_type(Path::new("search-index.json")),
+            "application/json"
+        );
+        assert_eq!(content_type(Path::new("fonts/inter.woff2")), "font/woff2");
+        assert_eq!(
+            content_type(Path::new("feed.xml")),
+            "application/xml;charset=utf-8"
+        );
+        assert_eq!(content_type(Path::new("talk.MP4")), "video/mp4");
+        assert_eq!(content_type(Path::new("CNAME")), "application/octet-stream");
+    }
+
+    #[test]
+    fn it_should_serve_heads_and_ranges() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        fs::write(temp_dir.path().join("video.mp4"), "Hello there")?;
+        fs::create_dir(temp_dir.path().join("my notes"))?;
+        fs::write(temp_dir.path().join("my notes/diagramme-été.png"), "png")?;
+        let mut app = tide::new();
+        app.at("/docs/*")
+            .get(StaticFiles::new("/docs", temp_dir.path())?);
+
+        let files = StaticFiles::new("/docs", temp_dir.path())?;
+        assert_eq!(
+            files.file("/docs/my%20notes/diagramme-%C3%A9t%C3%A9.png"),
+            Some(files.dir.join("my notes/diagramme-été.png"))
+        );
+        assert_eq!(files.file("/docs/my%20notes%2F..%2F..%2Fsecrets.md"), None);
+        assert_eq!(files.file("/docs/%2E%2E/secrets.md"), None);
+
+        async_std::task::block_on(async {
+            let url = Url::parse("http://localhost/docs/video.mp4")?;
+            let mut request = tide::http::Request::new(Method::Get, url.clone());
+            request.insert_header(RANGE, "bytes=6-");
+            let mut response: tide::http::Response = app.respond(request).await?;
+
+            assert_eq!(response.status(), StatusCode::PartialContent);
+            assert_eq!(response[CONTENT_RANGE], "bytes 6-10/11");
+            assert_eq!(response[CONTENT_TYPE], "video/mp4");
+            assert_eq!(response.body_string().await?, "there");
+
+            let request = tide::http::Request::new(Method::Head, url);
+            let response: tide::http::Response = app.respond(request).await?;
+            assert_eq!(response.status(), StatusCode::Ok);
+            assert_eq!(response[ACCEPT_RANGES], "bytes");
+            assert_eq!(response.len(), Some(11));
+
+            let url = Url::parse("http://localhost/docs/../secrets.md")?;
+            let request = tide::http::Request::new(Method::Get, url);
+            let response: tide::http::Response = app.respond(request).await?;
+            assert_ne!(response.status(), StatusCode::Ok);
+
+            let url = Url::parse("http://localhost/docs/my%20notes/diagramme-%C3%A9t%C3%A9.png")?;
+            let request = tide::http::Request::new(Method::Get, url);
+            let mut response: tide::http::Response = app.respond(request).await?;
+            assert_eq!(response.status(), StatusCode::Ok);
+            assert_eq!(response.body_string().await?, "png");
+
+            Ok(())
+        })
+    }
+}
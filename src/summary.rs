@@ -1,16 +1,37 @@
 mod file_tree_summarizer;
+mod merged_summarizer;
+mod single_file_summarizer;
 mod summary_file;
 
-use crate::Item;
+use crate::{Chapter, Item};
 use anyhow::Result;
 pub use file_tree_summarizer::FileTreeSummarizer;
+pub use merged_summarizer::{MergedSummarizer, UNSORTED_SECTION};
+use serde::Serialize;
+pub use single_file_summarizer::SingleFileSummarizer;
+use std::path::Path;
 pub use summary_file::SummaryFileSummarizer;
 
+/// Names a summary file can have inside the source dir
+pub const SUMMARY_FILE_NAMES: [&str; 4] = ["summary.md", "SUMMARY.md", "SUMMARY.MD", "Summary.md"];
+
+/// Names of the file that sets the order of the chapters in a directory when there is no summary.
+/// See [`FileTreeSummarizer`].
+pub const ORDER_FILE_NAMES: [&str; 2] = [".order", "order.yml"];
+
+/// Name of the file that turns a directory into a section when there is no summary. See
+/// [`FileTreeSummarizer`].
+pub const SECTION_FILE_NAME: &str = ".section";
+
+/// Name of the file, at the top of the source dir, that becomes the page for missing paths. It's
+/// never a chapter.
+pub const NOT_FOUND_FILE_NAME: &str = "404.md";
+
 pub trait Summarizer {
     fn summarize(&self) -> Result<Summary>;
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Serialize, Clone, PartialEq, Debug)]
 pub struct Summary {
     pub items: Vec<Item>,
 }
@@ -19,4 +40,148 @@ impl Summary {
     pub fn new(items: Vec<Item>) -> Summary {
         Summary { items }
     }
+
+    /// Writes the summary in the same format [`SummaryFileSummarizer`] reads. Paths are made
+    /// relative to `base`, which should be the directory the summary file will be placed in.
+    pub fn to_markdown<P>(&self, base: P) -> String
+    where
+        P: AsRef<Path>,
+    {
+        let mut markdown = String::new();
+
+        for item in self.items.iter() {
+            match item {
+                Item::Section(section) => {
+                    markdown.push_str(&format!("\n# {}\n\n", section.title));
+
+                    for chapter in section.chapters.iter() {
+                        Summary::push_chapter(&mut markdown, chapter, base.as_ref());
+                    }
+                }
+                Item::Chapter(chapter) => {
+                    Summary::push_chapter(&mut markdown, chapter, base.as_ref())
+                }
+            }
+        }
+
+        markdown.trim_start().to_string()
+    }
+
+    // Chapters without a number (e.g. prefaces) are plain links
+    fn push_chapter(markdown: &mut String, chapter: &Chapter, base: &Path) {
+        match chapter.number.is_empty() {
+            true => markdown.push_str(&format!("{}\n", Summary::link(chapter, base))),
+            false => Summary::push_list(markdown, chapter, base, 0),
+        }
+    }
+
+    fn push_list(markdown: &mut String, chapter: &Chapter, base: &Path, depth: usize) {
+        markdown.push_str(&format!(
+            "{}- {}\n",
+            "    ".repeat(depth),
+            Summary::link(chapter, base)
+        ));
+
+        for item in chapter.subchapters.iter() {
+            match item {
+                Item::Chapter(subchapter) => {
+                    Summary::push_list(markdown, subchapter, base, depth + 1)
+                }
+                Item::Section(section) => {
+                    markdown.push_str(&format!(
+                        "{}# {}\n",
+                        "    ".repeat(depth + 1),
+                        section.title
+                    ));
+
+                    for subchapter in section.chapters.iter() {
+                        Summary::push_list(markdown, subchapter, base, depth + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    fn link(chapter: &Chapter, base: &Path) -> String {
+        if chapter.draft {
+            return format!("[{}]()", chapter.title);
+        }
+
+        if let Some(url) = &chapter.url {
+            return format!("[{}]({url})", chapter.title);
+        }
+
+        let path = chapter
+            .content
+            .strip_prefix(base)
+            .unwrap_or(&chapter.content);
+        let anchor = chapter
+            .anchor
+            .as_ref()
+            .map(|anchor| format!("#{anchor}"))
+            .unwrap_or_default();
+
+        format!("[{}](./{}{anchor})", chapter.title, path.display())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Section;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_write_a_summary_that_can_be_read_back() -> Result<()> {
+        let dir = tempdir()?;
+        let summary = Summary::new(vec![
+            Item::from(Chapter::new(
+                "Intro",
+                "",
+                dir.path().join("intro.md"),
+                vec![],
+            )),
+            Item::from(Section::new(
+                "Part 1",
+                vec![
+                    Chapter::new(
+                        "Chapter 1",
+                        "1",
+                        dir.path().join("chapter1/index.md"),
+                        vec![Item::from(Chapter::new(
+                            "Chapter 1.1",
+                            "1.1",
+                            dir.path().join("chapter1/setup.md"),
+                            vec![],
+                        ))],
+                    ),
+                    Chapter::new("Chapter 2", "2", dir.path().join("chapter2.md"), vec![]),
+                ],
+            )),
+        ]);
+        let markdown = summary.to_markdown(dir.path());
+
+        assert_eq!(
+            markdown,
+            r#"[Intro](./intro.md)
+
+# Part 1
+
+- [Chapter 1](./chapter1/index.md)
+    - [Chapter 1.1](./chapter1/setup.md)
+- [Chapter 2](./chapter2.md)
+"#
+        );
+
+        fs::write(dir.path().join("summary.md"), markdown)?;
+
+        assert_eq!(
+            SummaryFileSummarizer::new(dir.path().join("summary.md")).summarize()?,
+            summary
+        );
+
+        Ok(())
+    }
 }
@@ -1,16 +1,24 @@
-use super::{Summarizer, Summary};
+use super::{
+    Summarizer, Summary, NOT_FOUND_FILE_NAME, ORDER_FILE_NAMES, SECTION_FILE_NAME,
+    SUMMARY_FILE_NAMES,
+};
 use crate::util;
-use crate::{Chapter, Item};
+use crate::{Chapter, FrontMatter, Item, Section};
 use anyhow::{anyhow, Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 const SUPPORTED_CHAPTER_FILE_NAMES: [&str; 4] = ["index", "readme", "INDEX", "README"];
 
-/// It creates a summary using the file tree. The ordering is random. It depends in what it finds first.
-/// It supports chapters and subchapters, but not sections. Each directory is a chapter and it must
-/// contain a file named "index.md", "readme.md", "INDEX.md", "README.md" or a file with the same name as the directory.
+/// It creates a summary using the file tree. A directory may have an order file (`.order` or
+/// `order.yml`) listing its entries in the order they should appear. Anything not listed comes
+/// after, sorted by the `weight` in their front matter, then by number prefixes such as
+/// `01-intro.md` or `02_setup/` (which are left out of titles and urls), and then by name.
+/// Each directory is a chapter and it must contain a file named "index.md", "readme.md", "INDEX.md", "README.md" or a file with the same name as the directory.
 /// Any other files are considered as subchapters. Standalone files are also considered main chapters.
+/// The exception are directories with a `.section` file: they become a section, titled after the
+/// first line of the file (or the directory), followed by their chapters. Inside a chapter, they
+/// group some of its subchapters.
 ///
 /// # Example
 ///
@@ -21,7 +29,10 @@ const SUPPORTED_CHAPTER_FILE_NAMES: [&str; 4] = ["index", "readme", "INDEX", "RE
 /// │   ├── subchapter1
 /// │   └── subchapter2
 /// ├── chapter2.md
-/// └── chapter3.md
+/// ├── chapter3.md
+/// └── reference/
+///     ├── .section
+///     └── chapter4.md
 ///
 /// Will be summarized as:
 ///
@@ -30,6 +41,8 @@ const SUPPORTED_CHAPTER_FILE_NAMES: [&str; 4] = ["index", "readme", "INDEX", "RE
 /// └── Subchapter2 (chapter1/subchapter2.md) (1.2)
 /// Chapter2 (chapter2.md) (2)
 /// Chapter3 (chapter3.md) (3)
+/// Reference
+/// Chapter4 (reference/chapter4.md) (4)
 pub struct FileTreeSummarizer {
     path: PathBuf,
 }
@@ -47,45 +60,173 @@ impl FileTreeSummarizer {
     /// It finds all the chapters in [`self.path`] recursively. It takes an initial chapter number
     /// where the enumeration will start.
     fn find_chapters<S>(&self, initial_chapter_number: S) -> Result<Vec<Chapter>>
+    where
+        S: ToString,
+    {
+        Ok(self
+            .find_items(initial_chapter_number, false)?
+            .into_iter()
+            .flat_map(|item| match item {
+                Item::Chapter(chapter) => vec![chapter],
+                Item::Section(section) => section.chapters,
+            })
+            .collect())
+    }
+
+    /// Like [`FileTreeSummarizer::find_chapters`], but section directories become a section
+    /// holding their chapters. Only the top has the page for missing paths.
+    fn find_items<S>(&self, initial_chapter_number: S, top: bool) -> Result<Vec<Item>>
     where
         S: ToString,
     {
         let dir_entries = fs::read_dir(&self.path)
             .with_context(|| anyhow!("Failed to read contentes of {}", self.path.display()))?;
         let mut chapter_number: String = initial_chapter_number.to_string();
-
-        Ok(dir_entries
+        let mut entries: Vec<(PathBuf, Option<PathBuf>, FrontMatter)> = dir_entries
             .filter_map(|entry| {
                 let entry = entry.ok()?;
+                let content = match entry.file_type().ok()?.is_dir() {
+                    true if entry.path().join(SECTION_FILE_NAME).exists() => None,
+                    true => Some(self.find_main_chapter_content(entry.path()).ok()?),
+                    false
+                        if self.is_parent_content(&entry.path())
+                            || SUMMARY_FILE_NAMES.contains(&entry.file_name().to_str()?)
+                            || ORDER_FILE_NAMES.contains(&entry.file_name().to_str()?)
+                            || entry.file_name() == SECTION_FILE_NAME
+                            || (top && entry.file_name() == NOT_FOUND_FILE_NAME) =>
+                    {
+                        return None
+                    }
+                    false => Some(entry.path()),
+                };
+                let front_matter = content
+                    .as_ref()
+                    .and_then(|content| FrontMatter::from_disk(content).ok())
+                    .unwrap_or_default();
+
+                Some((entry.path(), content, front_matter))
+            })
+            .collect();
 
-                if entry.file_type().ok()?.is_dir() {
-                    let chapter = Chapter::new(
-                        self.format_chapter_title(entry.path()),
-                        chapter_number.clone(),
-                        self.find_main_chapter_content(entry.path()).ok()?,
-                        FileTreeSummarizer::new(entry.path())
-                            .find_chapters(chapter_number.clone() + ".1")
-                            .ok()?,
-                    );
-
-                    return Some(chapter);
-                }
+        let listed = self.read_order_file()?;
+        entries
+            .sort_by_cached_key(|(path, _, front_matter)| self.order(path, front_matter, &listed));
 
-                if self.is_parent_content(&entry.path()) {
-                    return None;
+        let mut items = Vec::new();
+        for (path, content, front_matter) in entries {
+            let Some(content) = content else {
+                let chapters = FileTreeSummarizer::new(&path).find_chapters(&chapter_number)?;
+
+                if let Some(last) = chapters.last() {
+                    chapter_number = util::next_chapter_number(&last.number);
                 }
+                items.push(Item::from(Section::new(
+                    self.section_title(&path)?,
+                    chapters,
+                )));
+                continue;
+            };
+
+            let subchapters = match path.is_dir() {
+                true => match FileTreeSummarizer::new(&path)
+                    .find_items(chapter_number.clone() + ".1", false)
+                {
+                    Ok(subchapters) => subchapters,
+                    Err(_) => continue,
+                },
+                false => Vec::new(),
+            };
+            let title = front_matter
+                .title
+                .unwrap_or_else(|| self.format_chapter_title(path));
+
+            items.push(Item::from(Chapter::new(
+                title,
+                chapter_number.clone(),
+                content,
+                subchapters,
+            )));
+            chapter_number = util::next_chapter_number(&chapter_number);
+        }
 
-                let chapter = Chapter::new(
-                    self.format_chapter_title(entry.path()),
-                    chapter_number.clone(),
-                    entry.path(),
-                    Vec::new(),
-                );
-                chapter_number = util::next_chapter_number(&chapter_number);
+        Ok(items)
+    }
 
-                Some(chapter)
-            })
-            .collect::<Vec<Chapter>>())
+    // The first line of the section file or, if it's empty, the name of the directory
+    fn section_title(&self, dir: &Path) -> Result<String> {
+        let path = dir.join(SECTION_FILE_NAME);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| anyhow!("Failed to read contents of {}", path.display()))?;
+
+        Ok(match contents.lines().next().map(str::trim) {
+            Some(title) if !title.is_empty() => title.to_string(),
+            _ => self.format_chapter_title(dir.to_path_buf()),
+        })
+    }
+
+    /// The chapters listed in the order file come first, as they are listed. Then the ones with a
+    /// weight in their front matter, lighter ones before heavier ones. Then the ones with a number
+    /// prefix (e.g. `01-intro.md`) in that order, and then everything else by name.
+    fn order(
+        &self,
+        path: &Path,
+        front_matter: &FrontMatter,
+        listed: &[String],
+    ) -> (bool, usize, bool, i32, bool, u32, String) {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let file_stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (number, _) = util::split_number_prefix(&file_name);
+        let position = listed
+            .iter()
+            .position(|name| *name == file_name || *name == file_stem);
+
+        (
+            position.is_none(),
+            position.unwrap_or_default(),
+            front_matter.weight.is_none(),
+            front_matter.weight.unwrap_or_default(),
+            number.is_none(),
+            number.unwrap_or_default(),
+            file_name,
+        )
+    }
+
+    /// The names in the order file of the directory, if there is one. `.order` has a name per line
+    /// (lines starting with `#` are comments) and `order.yml` is a list. Names may leave out the
+    /// extension and directories may end with a `/`.
+    fn read_order_file(&self) -> Result<Vec<String>> {
+        for name in ORDER_FILE_NAMES {
+            let path = self.path.join(name);
+
+            if !path.exists() {
+                continue;
+            }
+
+            let contents = fs::read_to_string(&path)
+                .with_context(|| anyhow!("Failed to read contents of {}", path.display()))?;
+            let names: Vec<String> = match name.ends_with(".yml") {
+                true => serde_yaml::from_str(&contents)
+                    .with_context(|| anyhow!("Invalid order file {}", path.display()))?,
+                false => contents
+                    .lines()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .collect(),
+            };
+
+            return Ok(names
+                .into_iter()
+                .map(|name| name.trim_end_matches('/').to_string())
+                .collect());
+        }
+
+        Ok(Vec::new())
     }
 
     /// It returns a formatted chapter title for the given file name. It capitalizes the first letter and removes the extension.
@@ -94,8 +235,10 @@ impl FileTreeSummarizer {
     ///
     /// chapter.md -> Chapter
     /// chapter2.md -> Chapter2
+    /// 01-intro.md -> Intro
     fn format_chapter_title(&self, file_name: PathBuf) -> String {
         let file_name = file_name.file_stem().unwrap().to_string_lossy();
+        let (_, file_name) = util::split_number_prefix(&file_name);
         let mut file_name_iter = file_name.chars();
 
         match file_name_iter.next() {
@@ -145,13 +288,7 @@ impl FileTreeSummarizer {
 
 impl Summarizer for FileTreeSummarizer {
     fn summarize(&self) -> Result<Summary> {
-        let items = self
-            .find_chapters("1")?
-            .iter()
-            .map(|chapter| return Item::from(chapter.clone()))
-            .collect();
-
-        Ok(Summary::new(items))
+        Ok(Summary::new(self.find_items("1", true)?))
     }
 }
 
@@ -171,30 +308,30 @@ mod test {
             "1",
             chapter_path.join("chapter1.md"),
             vec![
-                Chapter::new(
+                Item::from(Chapter::new(
                     "Chapter1.1",
                     "1.1",
                     chapter_path.join("chapter1.1.md"),
                     Vec::new(),
-                ),
-                Chapter::new(
+                )),
+                Item::from(Chapter::new(
                     "Chapter1.2",
                     "1.2",
                     chapter_path.join("chapter1.2.md"),
                     Vec::new(),
-                ),
-                Chapter::new(
+                )),
+                Item::from(Chapter::new(
                     "Chapter1.3",
                     "1.3",
                     chapter_path.join("chapter1.3.md"),
                     Vec::new(),
-                ),
+                )),
             ],
         );
 
         fs::create_dir(&chapter_path)?;
-        fs::write(&chapter_path.join("chapter1.md"), "")?;
-        for subchapter in expected.subchapters.iter() {
+        fs::write(chapter_path.join("chapter1.md"), "")?;
+        for subchapter in expected.children() {
             fs::write(temp_dir.path().join(&subchapter.content), "")?;
         }
 
@@ -238,11 +375,8 @@ mod test {
         ];
 
         for chapter in expected.iter() {
-            match chapter {
-                Item::Chapter(chapter) => {
-                    fs::write(temp_dir.path().join(&chapter.content), "")?;
-                }
-                _ => {}
+            if let Item::Chapter(chapter) = chapter {
+                fs::write(temp_dir.path().join(&chapter.content), "")?;
             }
         }
 
@@ -253,13 +387,193 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn it_should_order_by_weight_then_number_prefix_then_name() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+
+        fs::write(temp_dir.path().join("zebra.md"), "")?;
+        fs::write(temp_dir.path().join("apple.md"), "")?;
+        fs::write(temp_dir.path().join("10-later.md"), "")?;
+        fs::write(temp_dir.path().join("2-sooner.md"), "")?;
+        fs::write(temp_dir.path().join("heavy.md"), "---\nweight: 2\n---\n")?;
+        fs::write(temp_dir.path().join("light.md"), "---\nweight: -1\n---\n")?;
+        fs::create_dir(temp_dir.path().join("03_setup"))?;
+        fs::write(temp_dir.path().join("03_setup/index.md"), "")?;
+        fs::write(temp_dir.path().join("03_setup/02-run.md"), "")?;
+        fs::write(temp_dir.path().join("03_setup/01-install.md"), "")?;
+
+        let chapters = FileTreeSummarizer::new(temp_dir.path()).find_chapters("1")?;
+        let titles: Vec<(&str, &str)> = chapters
+            .iter()
+            .map(|chapter| (chapter.number.as_str(), chapter.title.as_str()))
+            .collect();
+
+        assert_eq!(
+            titles,
+            vec![
+                ("1", "Light"),
+                ("2", "Heavy"),
+                ("3", "Sooner"),
+                ("4", "Setup"),
+                ("5", "Later"),
+                ("6", "Apple"),
+                ("7", "Zebra"),
+            ]
+        );
+        assert_eq!(chapters[3].children()[0].title, "Install");
+        assert_eq!(chapters[3].children()[1].number, "4.2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_follow_the_order_file() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+
+        for name in ["apple.md", "banana.md", "cherry.md", "01-date.md"] {
+            fs::write(temp_dir.path().join(name), "")?;
+        }
+        fs::create_dir(temp_dir.path().join("setup"))?;
+        fs::write(temp_dir.path().join("setup/index.md"), "")?;
+        fs::write(temp_dir.path().join("setup/b.md"), "")?;
+        fs::write(temp_dir.path().join("setup/a.md"), "")?;
+        fs::write(
+            temp_dir.path().join(".order"),
+            "# Fruits first\ncherry.md\n\nsetup/\nbanana\nnope.md\n",
+        )?;
+        fs::write(temp_dir.path().join("setup/order.yml"), "- b.md\n")?;
+
+        let chapters = FileTreeSummarizer::new(temp_dir.path()).find_chapters("1")?;
+        let titles: Vec<&str> = chapters
+            .iter()
+            .map(|chapter| chapter.title.as_str())
+            .collect();
+
+        assert_eq!(titles, vec!["Cherry", "Setup", "Banana", "Date", "Apple"]);
+        assert_eq!(chapters[1].children()[0].title, "B");
+        assert_eq!(chapters[1].children()[1].title, "A");
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_turn_section_dirs_into_sections() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let reference = temp_dir.path().join("02-reference");
+
+        fs::write(temp_dir.path().join("01-intro.md"), "")?;
+        fs::create_dir(&reference)?;
+        fs::write(reference.join(".section"), "")?;
+        fs::write(reference.join("api.md"), "")?;
+        fs::write(reference.join("cli.md"), "")?;
+        fs::create_dir(temp_dir.path().join("03-appendix"))?;
+        fs::write(temp_dir.path().join("03-appendix/.section"), "Appendices\n")?;
+        fs::write(temp_dir.path().join("03-appendix/glossary.md"), "")?;
+
+        let summary = FileTreeSummarizer::new(temp_dir.path()).summarize()?;
+        let expected = vec![
+            Item::from(Chapter::new(
+                "Intro",
+                "1",
+                temp_dir.path().join("01-intro.md"),
+                Vec::new(),
+            )),
+            Item::from(Section::new(
+                "Reference",
+                vec![
+                    Chapter::new("Api", "2", reference.join("api.md"), Vec::new()),
+                    Chapter::new("Cli", "3", reference.join("cli.md"), Vec::new()),
+                ],
+            )),
+            Item::from(Section::new(
+                "Appendices",
+                vec![Chapter::new(
+                    "Glossary",
+                    "4",
+                    temp_dir.path().join("03-appendix/glossary.md"),
+                    Vec::new(),
+                )],
+            )),
+        ];
+
+        assert_eq!(summary.items, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_group_subchapters_in_section_dirs() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let guide = temp_dir.path().join("guide");
+        let advanced = guide.join("02-advanced");
+
+        fs::create_dir_all(&advanced)?;
+        fs::write(guide.join("index.md"), "")?;
+        fs::write(guide.join("01-setup.md"), "")?;
+        fs::write(advanced.join(".section"), "Going further\n")?;
+        fs::write(advanced.join("plugins.md"), "")?;
+
+        let summary = FileTreeSummarizer::new(temp_dir.path()).summarize()?;
+        let expected = vec![Item::from(Chapter::new(
+            "Guide",
+            "1",
+            guide.join("index.md"),
+            vec![
+                Item::from(Chapter::new(
+                    "Setup",
+                    "1.1",
+                    guide.join("01-setup.md"),
+                    Vec::new(),
+                )),
+                Item::from(Section::new(
+                    "Going further",
+                    vec![Chapter::new(
+                        "Plugins",
+                        "1.2",
+                        advanced.join("plugins.md"),
+                        Vec::new(),
+                    )],
+                )),
+            ],
+        ))];
+
+        assert_eq!(summary.items, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_prefer_the_title_in_the_front_matter() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let chapter_path = temp_dir.path().join("chapter1");
+
+        fs::create_dir(&chapter_path)?;
+        fs::write(
+            chapter_path.join("index.md"),
+            "---\ntitle: Hello there\n---\n",
+        )?;
+        fs::write(
+            chapter_path.join("chapter1.1.md"),
+            "---\ntitle: General Kenobi\n---\n",
+        )?;
+
+        let chapters = FileTreeSummarizer::new(temp_dir.path()).find_chapters("1")?;
+
+        assert_eq!(chapters[0].title, "Hello there");
+        assert_eq!(chapters[0].children()[0].title, "General Kenobi");
+
+        Ok(())
+    }
+
     #[test]
     fn it_should_format_the_file_name() -> Result<(), Box<dyn Error>> {
         let summarizer = FileTreeSummarizer::new("");
-        let tests = vec![
+        let tests = [
             ("chapter.md", "Chapter"),
             ("intro", "Intro"),
             ("file.txt", "File"),
+            ("01-intro.md", "Intro"),
+            ("02_setup", "Setup"),
         ];
 
         for test in tests.iter() {
@@ -281,7 +595,7 @@ mod test {
         let main_chapter_path = temp_dir.path().join("chapter1");
 
         fs::create_dir(&main_chapter_path)?;
-        fs::write(&main_chapter_path.join("index.md"), "")?;
+        fs::write(main_chapter_path.join("index.md"), "")?;
 
         let content = summarizer.find_main_chapter_content(&main_chapter_path)?;
 
@@ -298,7 +612,7 @@ mod test {
         let main_chapter_path = temp_dir.path().join("chapter1");
 
         fs::create_dir(&main_chapter_path)?;
-        fs::write(&main_chapter_path.join("chapter1.md"), "")?;
+        fs::write(main_chapter_path.join("chapter1.md"), "")?;
 
         let content = summarizer.find_main_chapter_content(&main_chapter_path)?;
 
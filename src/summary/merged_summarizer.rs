@@ -0,0 +1,137 @@
+use super::{Summarizer, Summary};
+use crate::util;
+use crate::{Chapter, Item, Section};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Title of the section with the chapters only the discovered summary has
+pub const UNSORTED_SECTION: &str = "Unsorted";
+
+/// It puts a curated summary together with one that finds every chapter (e.g. a summary file and
+/// the file tree). Chapters that only the second one has go after the curated ones, in a section
+/// of their own, so nothing that was written is left out of the book.
+pub struct MergedSummarizer<C, D> {
+    curated: C,
+    discovered: D,
+}
+
+impl<C, D> MergedSummarizer<C, D>
+where
+    C: Summarizer,
+    D: Summarizer,
+{
+    pub fn new(curated: C, discovered: D) -> Self {
+        Self {
+            curated,
+            discovered,
+        }
+    }
+}
+
+impl<C, D> Summarizer for MergedSummarizer<C, D>
+where
+    C: Summarizer,
+    D: Summarizer,
+{
+    fn summarize(&self) -> Result<Summary> {
+        let mut summary = self.curated.summarize()?;
+        let chapters: Vec<Chapter> = summary.items.iter().flat_map(item_chapters).collect();
+        // Summary files have paths like `./intro.md`, which are the same file as `intro.md`
+        let curated: HashSet<PathBuf> = chapters
+            .iter()
+            .flat_map(Chapter::flatten)
+            .map(|chapter| chapter.content.components().collect())
+            .collect();
+        let mut number = match chapters
+            .iter()
+            .rev()
+            .find(|chapter| !chapter.number.is_empty())
+        {
+            Some(last) => util::next_chapter_number(&last.number),
+            None => String::from("1"),
+        };
+
+        let mut unsorted = Vec::new();
+        for chapter in self
+            .discovered
+            .summarize()?
+            .items
+            .iter()
+            .flat_map(item_chapters)
+            .flat_map(|chapter| chapter.flatten())
+        {
+            let content: PathBuf = chapter.content.components().collect();
+            if !chapter.has_content() || curated.contains(&content) {
+                continue;
+            }
+
+            unsorted.push(Chapter {
+                number: number.clone(),
+                subchapters: Vec::new(),
+                ..chapter
+            });
+            number = util::next_chapter_number(&number);
+        }
+
+        if !unsorted.is_empty() {
+            summary
+                .items
+                .push(Item::from(Section::new(UNSORTED_SECTION, unsorted)));
+        }
+
+        Ok(summary)
+    }
+}
+
+fn item_chapters(item: &Item) -> Vec<Chapter> {
+    match item {
+        Item::Chapter(chapter) => vec![chapter.clone()],
+        Item::Section(section) => section.chapters.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::summary::{FileTreeSummarizer, SummaryFileSummarizer};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_merge_the_chapters_missing_from_the_summary() -> Result<()> {
+        let dir = tempdir()?;
+        let expected = vec![
+            Item::from(Chapter::new(
+                "Intro",
+                "1",
+                dir.path().join("./intro.md"),
+                vec![],
+            )),
+            Item::from(Section::new(
+                UNSORTED_SECTION,
+                vec![
+                    Chapter::new("Guide", "2", dir.path().join("guide/index.md"), vec![]),
+                    Chapter::new("Setup", "3", dir.path().join("guide/setup.md"), vec![]),
+                    Chapter::new("Usage", "4", dir.path().join("usage.md"), vec![]),
+                ],
+            )),
+        ];
+
+        fs::write(dir.path().join("summary.md"), "- [Intro](./intro.md)")?;
+        fs::write(dir.path().join("intro.md"), "# Intro")?;
+        fs::write(dir.path().join("usage.md"), "# Usage")?;
+        fs::create_dir(dir.path().join("guide"))?;
+        fs::write(dir.path().join("guide/index.md"), "# Guide")?;
+        fs::write(dir.path().join("guide/setup.md"), "# Setup")?;
+
+        let summarizer = MergedSummarizer::new(
+            SummaryFileSummarizer::new(dir.path().join("summary.md")),
+            FileTreeSummarizer::new(dir.path()),
+        );
+
+        assert_eq!(expected, summarizer.summarize()?.items);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use super::{Summarizer, Summary};
+use crate::config::{Extensions, Slug};
+use crate::renderer::markdown;
+use crate::{Chapter, Item};
+use anyhow::{anyhow, Context, Result};
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// It creates a summary from the headings of a single file, for notes that grew into a book
+/// before they were split. Each H1 is a chapter and each H2 a subchapter of the H1 before it.
+/// The file stays one page, so the chapters point to their part of it.
+///
+/// # Example
+///
+/// A file such as this:
+///
+/// # Intro
+/// ## Install
+/// ## Usage
+/// # Reference
+///
+/// Will be summarized as:
+///
+/// Intro (notes.md#intro) (1)
+/// ├── Install (notes.md#install) (1.1)
+/// └── Usage (notes.md#usage) (1.2)
+/// Reference (notes.md#reference) (2)
+pub struct SingleFileSummarizer {
+    path: PathBuf,
+    // How the anchors are made, which has to match how the headings get their ids
+    slug: Slug,
+}
+
+impl SingleFileSummarizer {
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            slug: Slug::default(),
+        }
+    }
+
+    pub fn with_slug(mut self, slug: Slug) -> Self {
+        self.slug = slug;
+        self
+    }
+
+    fn find_chapters(&self) -> Result<Vec<Chapter>> {
+        let md = fs::read_to_string(&self.path)
+            .with_context(|| anyhow!("Failed to read contents of {}", self.path.display()))?;
+        let options = markdown::options(&Extensions::default());
+        // Every heading has an anchor, in the same order they're found below
+        let mut anchors = markdown::anchors(&md, options, self.slug).into_iter();
+        let mut chapters: Vec<Chapter> = Vec::new();
+        let mut heading: Option<(HeadingLevel, String)> = None;
+
+        for event in Parser::new_ext(&md, options) {
+            match event {
+                Event::Start(Tag::Heading(level, ..)) => heading = Some((level, String::new())),
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, title)) = heading.as_mut() {
+                        title.push_str(&text);
+                    }
+                }
+                Event::End(Tag::Heading(..)) => {
+                    let Some((level, title)) = heading.take() else {
+                        continue;
+                    };
+                    let anchor = anchors.next();
+                    let chapter = Chapter {
+                        anchor,
+                        ..Chapter::new(title.trim(), "", &self.path, vec![])
+                    };
+
+                    match (level, chapters.last_mut()) {
+                        (HeadingLevel::H1, _) | (HeadingLevel::H2, None) => chapters.push(chapter),
+                        (HeadingLevel::H2, Some(parent)) => {
+                            parent.subchapters.push(Item::from(chapter))
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (i, chapter) in chapters.iter_mut().enumerate() {
+            chapter.number = (i + 1).to_string();
+
+            for (j, item) in chapter.subchapters.iter_mut().enumerate() {
+                let Item::Chapter(subchapter) = item else {
+                    continue;
+                };
+                subchapter.number = format!("{}.{}", i + 1, j + 1);
+            }
+        }
+
+        Ok(chapters)
+    }
+}
+
+impl Summarizer for SingleFileSummarizer {
+    fn summarize(&self) -> Result<Summary> {
+        Ok(Summary::new(
+            self.find_chapters()?.into_iter().map(Item::from).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_chapters_from_headings() -> Result<()> {
+        let dir = tempdir()?;
+        let notes = dir.path().join("notes.md");
+        let part = |title: &str, number: &str, anchor: &str, subchapters| Chapter {
+            anchor: Some(anchor.to_string()),
+            ..Chapter::new(title, number, &notes, subchapters)
+        };
+        let expected = vec![
+            part("Preface", "1", "preface", vec![]),
+            part(
+                "Intro",
+                "2",
+                "intro",
+                vec![
+                    Item::from(part("Install cahlter", "2.1", "install-cahlter", vec![])),
+                    Item::from(part("Usage", "2.2", "usage", vec![])),
+                ],
+            ),
+            part("Usage", "3", "usage-1", vec![]),
+        ];
+
+        fs::write(
+            &notes,
+            "Some notes\n\n## Preface\n\n# Intro\n\n## Install `cahlter`\n\n### Details\n\n## Usage\n\n# Usage",
+        )?;
+
+        let summarizer = SingleFileSummarizer::new(&notes);
+
+        assert_eq!(expected, summarizer.find_chapters()?);
+
+        Ok(())
+    }
+}
@@ -30,24 +30,33 @@ impl SummaryFileSummarizer {
         let md = fs::read_to_string(&self.path)?;
         let summary = SummaryParser::parse(Rule::summary, &md)?;
         let mut chapter_number = "1".to_string();
+        let mut items = Vec::new();
 
-        Ok(summary
-            .filter_map(|line| match line.as_rule() {
+        for line in summary {
+            let chapter = match line.as_rule() {
                 Rule::heading => {
                     let mut rules = line.into_inner();
 
-                    Some(Item::from(Section::new(rules.next().unwrap().as_str())))
+                    items.push(Item::from(Section::new(
+                        rules.next().unwrap().as_str(),
+                        vec![],
+                    )));
+                    continue;
                 }
-                Rule::link => Some(Item::from(self.parse_link(line))),
+                Rule::link => self.parse_link(line),
                 Rule::list => {
-                    let item = Item::from(self.parse_list(line, chapter_number.clone()));
+                    let chapter = self.parse_list(line, chapter_number.clone());
                     chapter_number = util::next_chapter_number(&chapter_number);
 
-                    Some(item)
+                    chapter
                 }
-                _ => None,
-            })
-            .collect())
+                _ => continue,
+            };
+
+            push_chapter(&mut items, chapter);
+        }
+
+        Ok(items)
     }
 
     fn parse_link(&self, rules: Pair<Rule>) -> Chapter {
@@ -55,32 +64,77 @@ impl SummaryFileSummarizer {
         let title = rules.next().unwrap().as_str();
         let content = rules.next().unwrap().as_str();
 
-        Chapter::new(
-            title,
-            "",
-            self.path.parent().unwrap_or(Path::new("")).join(content),
-            vec![],
-        )
+        if content.trim().is_empty() {
+            return Chapter::draft(title, "", vec![]);
+        }
+
+        if is_external(content) {
+            return Chapter::external(title, "", content.trim(), vec![]);
+        }
+
+        // The anchor points to a part of the page, not to a file
+        let (path, anchor) = match content.split_once('#') {
+            Some((path, anchor)) if !path.trim().is_empty() => (path, Some(anchor.to_string())),
+            _ => (content, None),
+        };
+
+        Chapter {
+            anchor,
+            ..Chapter::new(
+                title,
+                "",
+                self.path.parent().unwrap_or(Path::new("")).join(path),
+                vec![],
+            )
+        }
     }
 
     fn parse_list(&self, rules: Pair<Rule>, chapter_number: String) -> Chapter {
         let mut rules = rules.into_inner();
         let mut chapter = self.parse_link(rules.next().unwrap());
 
+        // Headings under a chapter group the subchapters after them, like at the top
         let mut number = chapter_number.clone() + ".0";
-        chapter.subchapters = rules
-            .map(|rule| {
-                number = util::next_chapter_number(&number);
+        for rule in rules {
+            if rule.as_rule() == Rule::heading {
+                let title = rule.into_inner().next().unwrap().as_str();
+                chapter
+                    .subchapters
+                    .push(Item::from(Section::new(title, vec![])));
+                continue;
+            }
 
-                self.parse_list(rule, number.clone())
-            })
-            .collect();
+            number = util::next_chapter_number(&number);
+            let subchapter = self.parse_list(rule, number.clone());
+            push_chapter(&mut chapter.subchapters, subchapter);
+        }
         chapter.number = chapter_number.clone();
 
         chapter
     }
 }
 
+// Every chapter after a heading belongs to its section, up to the next heading
+fn push_chapter(items: &mut Vec<Item>, chapter: Chapter) {
+    match items.last_mut() {
+        Some(Item::Section(section)) => section.chapters.push(chapter),
+        _ => items.push(Item::from(chapter)),
+    }
+}
+
+// Anything with a scheme (e.g. https: or mailto:) can't be a file in the vault
+fn is_external(url: &str) -> bool {
+    match url.trim().split_once(':') {
+        Some((scheme, _)) => {
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        None => false,
+    }
+}
+
 impl Summarizer for SummaryFileSummarizer {
     fn summarize(&self) -> Result<Summary> {
         Ok(Summary::new(self.find_items()?))
@@ -115,12 +169,14 @@ mod test {
                 dir.path().join("./chapter1.md"),
                 vec![],
             )),
-            Item::from(Section::new("Section")),
-            Item::from(Chapter::new(
-                "Chapter 2",
-                "2",
-                dir.path().join("./chapter2.md"),
-                vec![],
+            Item::from(Section::new(
+                "Section",
+                vec![Chapter::new(
+                    "Chapter 2",
+                    "2",
+                    dir.path().join("./chapter2.md"),
+                    vec![],
+                )],
             )),
         ];
 
@@ -154,23 +210,32 @@ mod test {
                 "1",
                 dir.path().join("./chapter1.md"),
                 vec![
-                    Chapter::new(
+                    Item::from(Chapter::new(
                         "Chapter 1.1",
                         "1.1",
                         dir.path().join("./chapter1/chapter1.1.md"),
-                        vec![Chapter::new(
+                        vec![Item::from(Chapter::new(
                             "Chapter 1.1.1",
                             "1.1.1",
                             dir.path().join("./chapter1/chapter1.1/chapter1.1.1.md"),
                             vec![],
-                        )],
-                    ),
-                    Chapter::new(
+                        ))],
+                    )),
+                    Item::from(Chapter::new(
                         "Chapter 1.2",
                         "1.2",
                         dir.path().join("./chapter1/chapter1.2.md"),
                         vec![],
-                    ),
+                    )),
+                    Item::from(Section::new(
+                        "Appendix",
+                        vec![Chapter::new(
+                            "Chapter 1.3",
+                            "1.3",
+                            dir.path().join("./chapter1/chapter1.3.md"),
+                            vec![],
+                        )],
+                    )),
                 ],
             )),
             Item::from(Chapter::new(
@@ -179,7 +244,7 @@ mod test {
                 dir.path().join("chapter2.md"),
                 vec![],
             )),
-            Item::from(Section::new("Section")),
+            Item::from(Section::new("Section", vec![])),
         ];
 
         fs::write(
@@ -189,6 +254,8 @@ mod test {
     - [Chapter 1.1](./chapter1/chapter1.1.md)
         - [Chapter 1.1.1](./chapter1/chapter1.1/chapter1.1.1.md)
     - [Chapter 1.2](./chapter1/chapter1.2.md)
+    # Appendix
+    - [Chapter 1.3](./chapter1/chapter1.3.md)
 
 - [Chapter 2](./chapter2.md)
 
@@ -202,4 +269,106 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_find_external_chapters() -> Result<()> {
+        let dir = tempdir()?;
+        let expected = vec![
+            Item::from(Chapter::external(
+                "Rust Book",
+                "1",
+                "https://doc.rust-lang.org/book",
+                vec![Item::from(Chapter::new(
+                    "Notes",
+                    "1.1",
+                    dir.path().join("./notes:rust.md"),
+                    vec![],
+                ))],
+            )),
+            Item::from(Chapter::external(
+                "Contact",
+                "",
+                "mailto:obi-wan@jedi.org",
+                vec![],
+            )),
+        ];
+
+        fs::write(
+            dir.path().join("summary.md"),
+            r#"
+- [Rust Book](https://doc.rust-lang.org/book)
+    - [Notes](./notes:rust.md)
+
+[Contact](mailto:obi-wan@jedi.org)
+"#,
+        )?;
+
+        let summarizer = SummaryFileSummarizer::new(dir.path().join("summary.md"));
+
+        assert_eq!(expected, summarizer.find_items()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_chapters_with_anchors() -> Result<()> {
+        let dir = tempdir()?;
+        let expected = vec![Item::from(Chapter::new(
+            "Setup",
+            "1",
+            dir.path().join("./setup.md"),
+            vec![Item::from(Chapter {
+                anchor: Some("install".to_string()),
+                ..Chapter::new("Install", "1.1", dir.path().join("./setup.md"), vec![])
+            })],
+        ))];
+
+        fs::write(
+            dir.path().join("summary.md"),
+            r#"
+- [Setup](./setup.md)
+    - [Install](./setup.md#install)
+"#,
+        )?;
+
+        let summarizer = SummaryFileSummarizer::new(dir.path().join("summary.md"));
+
+        assert_eq!(expected, summarizer.find_items()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_chapters_with_drafts() -> Result<()> {
+        let dir = tempdir()?;
+        let expected = vec![
+            Item::from(Chapter::draft("Foreword", "", vec![])),
+            Item::from(Chapter::draft(
+                "Chapter 1",
+                "1",
+                vec![Item::from(Chapter::new(
+                    "Chapter 1.1",
+                    "1.1",
+                    dir.path().join("./chapter1.1.md"),
+                    vec![],
+                ))],
+            )),
+        ];
+
+        fs::write(
+            dir.path().join("summary.md"),
+            r#"
+[Foreword]()
+
+- [Chapter 1]()
+    - [Chapter 1.1](./chapter1.1.md)
+"#,
+        )?;
+
+        let summarizer = SummaryFileSummarizer::new(dir.path().join("summary.md"));
+
+        assert_eq!(expected, summarizer.find_items()?);
+
+        Ok(())
+    }
 }
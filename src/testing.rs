@@ -0,0 +1,103 @@
+//! Helpers to check how chapters render, for themes and templates. They are behind the `testing`
+//! feature.
+//!
+//! A golden file holds the html a chapter is expected to render to. Changing the output on
+//! purpose means updating them, which is done by running the tests with `CAHLTER_UPDATE_GOLDEN=1`
+//! and reviewing the result like any other change.
+
+use crate::config::Config;
+use crate::renderer::{AskamaRenderer, RendererContext};
+use crate::summary::Summary;
+use crate::{util, Chapter, Content, Item};
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Set it to anything to rewrite the golden files instead of comparing against them
+pub const UPDATE_GOLDEN_VAR: &str = "CAHLTER_UPDATE_GOLDEN";
+
+/// Renders `markdown` as the only chapter of a vault using `config`. Nothing is read from the disk,
+/// so the output only depends on the arguments.
+pub fn render_chapter(config: &Config, markdown: &str) -> Result<String> {
+    let src_dir = PathBuf::from("src");
+    let chapter = Chapter::new("Chapter1", "1", src_dir.join("chapter1.md"), Vec::new());
+    let content = Content {
+        summary: Summary::new(vec![Item::from(chapter.clone())]),
+    };
+    let renderer = AskamaRenderer::new(RendererContext::new(content, config.clone(), src_dir));
+
+    renderer.render_markdown(&chapter, markdown)
+}
+
+/// Compares `html` to the golden file, failing with a diff when they don't match. Missing golden
+/// files are written instead.
+pub fn assert_golden<P>(golden: P, html: &str) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let golden = golden.as_ref();
+
+    if env::var_os(UPDATE_GOLDEN_VAR).is_some() || !golden.exists() {
+        if let Some(parent) = golden.parent() {
+            util::create_dir_if_not_exists(parent)?;
+        }
+
+        return fs::write(golden, html)
+            .with_context(|| anyhow!("Failed to write {}", golden.display()));
+    }
+
+    let expected = fs::read_to_string(golden)
+        .with_context(|| anyhow!("Failed to read {}", golden.display()))?;
+    let diff = util::diff_lines(&expected, html);
+
+    if !diff.is_empty() {
+        anyhow::bail!(
+            "The output doesn't match {}. Run with {UPDATE_GOLDEN_VAR}=1 if that's on purpose.\n{diff}",
+            golden.display()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    #[test]
+    fn it_should_match_the_golden_files() -> Result<()> {
+        let markdown = "# Hello there\n\nGeneral *Kenobi*\n\n```rust\nlet x = 1;\n```\n";
+        let mut config = Config::default();
+
+        assert_golden(
+            Path::new(GOLDEN_DIR).join("default.html"),
+            &render_chapter(&config, markdown)?,
+        )?;
+
+        config.general.title = "Star Wars".to_string();
+        config.code_blocks.line_numbers = true;
+        assert_golden(
+            Path::new(GOLDEN_DIR).join("line_numbers.html"),
+            &render_chapter(&config, markdown)?,
+        )
+    }
+
+    #[test]
+    fn it_should_fail_when_the_output_changes() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let golden = temp_dir.path().join("chapter.html");
+
+        fs::write(&golden, "<p>Hello there</p>\n")?;
+        let error = assert_golden(&golden, "<p>General Kenobi</p>\n").unwrap_err();
+
+        assert!(error
+            .to_string()
+            .ends_with("-<p>Hello there</p>\n+<p>General Kenobi</p>\n"));
+
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
-use anyhow::Result;
-use std::fs;
-use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
 
 pub fn create_dir_if_not_exists<P>(path: P) -> Result<()>
 where
@@ -15,27 +20,396 @@ where
     Ok(())
 }
 
-pub fn copy_dir<P>(source: P, destination: P) -> Result<()>
+/// Removes the file, if there is one. Writing a new file instead of writing into the old one
+/// leaves whatever is hard linked to it (e.g. an asset of the src dir) alone.
+pub fn remove_file_if_exists<P>(path: P) -> Result<()>
 where
     P: AsRef<Path>,
 {
-    let source = source.as_ref();
-    let destination = destination.as_ref();
+    let path = path.as_ref();
+
+    if path.symlink_metadata().is_ok() {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
 
-    for entry in source.read_dir()? {
+/// How far a [`copy_dir`] got. It's reported after every file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CopyProgress {
+    pub files: usize,
+    pub bytes: u64,
+    /// The last file copied
+    pub current: PathBuf,
+}
+
+/// Copies a directory recursively. Files are streamed through a buffer, so even huge ones never
+/// have to fit in memory. `exclude` gets paths relative to `source` and skips whatever it returns
+/// true for (see [`is_ignored`]), and `progress` is called after each file.
+pub fn copy_dir<P, Q, E, R>(
+    source: P,
+    destination: Q,
+    exclude: E,
+    mut progress: R,
+) -> Result<CopyProgress>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    E: Fn(&Path) -> bool,
+    R: FnMut(&CopyProgress),
+{
+    let mut copied = CopyProgress::default();
+
+    copy_dir_into(
+        source.as_ref(),
+        source.as_ref(),
+        destination.as_ref(),
+        &exclude,
+        &mut progress,
+        &mut copied,
+    )?;
+
+    Ok(copied)
+}
+
+fn copy_dir_into<E, R>(
+    root: &Path,
+    source: &Path,
+    destination: &Path,
+    exclude: &E,
+    progress: &mut R,
+    copied: &mut CopyProgress,
+) -> Result<()>
+where
+    E: Fn(&Path) -> bool,
+    R: FnMut(&CopyProgress),
+{
+    fs::create_dir_all(destination)
+        .with_context(|| anyhow!("Failed to create {}", destination.display()))?;
+
+    for entry in source
+        .read_dir()
+        .with_context(|| anyhow!("Failed to read {}", source.display()))?
+    {
         let entry = entry?;
+        let from = entry.path();
+        let to = destination.join(entry.file_name());
+
+        if exclude(from.strip_prefix(root)?) {
+            continue;
+        }
 
         if entry.file_type()?.is_dir() {
-            fs::create_dir(destination.join(entry.file_name()))?;
-            copy_dir(entry.path(), destination.join(entry.file_name()))?
-        } else {
-            fs::copy(entry.path(), destination.join(entry.file_name()))?;
+            copy_dir_into(root, &from, &to, exclude, progress, copied)?;
+            continue;
         }
+
+        copied.bytes += copy_file(&from, &to)
+            .with_context(|| anyhow!("Failed to copy {} to {}", from.display(), to.display()))?;
+        copied.files += 1;
+        copied.current = from;
+        progress(copied);
     }
 
     Ok(())
 }
 
+fn copy_file(from: &Path, to: &Path) -> Result<u64> {
+    let mut reader = BufReader::with_capacity(COPY_BUFFER_SIZE, File::open(from)?);
+    remove_file_if_exists(to)?;
+    let mut writer = BufWriter::with_capacity(COPY_BUFFER_SIZE, File::create(to)?);
+    let bytes = io::copy(&mut reader, &mut writer)?;
+
+    writer.flush()?;
+    Ok(bytes)
+}
+
+/// Is a path, relative to the directory the patterns are about, matched by any of them? Patterns
+/// follow [`matches_wildcard`] and always use `/`, even on Windows.
+pub fn is_ignored<P>(patterns: &[String], relative: P) -> bool
+where
+    P: AsRef<Path>,
+{
+    let relative = relative.as_ref().to_string_lossy().replace('\\', "/");
+
+    patterns
+        .iter()
+        .any(|pattern| matches_wildcard(pattern, &relative))
+}
+
+/// Returns the most recent modification time of a file or, for a directory, of anything inside it
+pub fn last_modified<P>(path: P) -> Result<SystemTime>
+where
+    P: AsRef<Path>,
+{
+    let metadata = fs::metadata(&path)?;
+    let mut modified = metadata.modified()?;
+
+    if metadata.is_dir() {
+        for entry in path.as_ref().read_dir()? {
+            modified = modified.max(last_modified(entry?.path())?);
+        }
+    }
+
+    Ok(modified)
+}
+
+/// Matches a text against a pattern where `*` stands for any sequence of characters
+///
+/// # Example
+///
+/// images/*.png matches images/diagram.png
+/// *.pdf matches slides/intro.pdf
+pub fn matches_wildcard<S, T>(pattern: S, text: T) -> bool
+where
+    S: AsRef<str>,
+    T: AsRef<str>,
+{
+    let mut parts = pattern.as_ref().split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.as_ref().strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+/// A SHA-256 of the bytes, hex encoded. Unlike modification times, it survives fresh checkouts.
+pub fn content_hash<B>(bytes: B) -> String
+where
+    B: AsRef<[u8]>,
+{
+    Sha256::digest(bytes.as_ref())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Encodes the text to be a value in the query of a url. Only letters, digits and `-._~` are left
+/// as they are.
+///
+/// # Example
+///
+/// obi+wan@example.com -> obi%2Bwan%40example.com
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// A [`content_hash`] of every file inside a directory, along with their paths, so renaming a file
+/// changes it too
+pub fn dir_hash<P>(path: P) -> Result<String>
+where
+    P: AsRef<Path>,
+{
+    fn collect(path: &Path, bytes: &mut Vec<u8>) -> Result<()> {
+        let mut entries = path
+            .read_dir()?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()?;
+        entries.sort();
+
+        for entry in entries {
+            bytes.extend(entry.to_string_lossy().as_bytes());
+
+            match entry.is_dir() {
+                true => collect(&entry, bytes)?,
+                false => bytes.extend(fs::read(&entry)?),
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut bytes = Vec::new();
+    collect(path.as_ref(), &mut bytes)?;
+
+    Ok(content_hash(bytes))
+}
+
+/// Splits the number off names like `01-intro.md` or `02_setup`, which is how chapters are ordered
+/// without a summary. The number has to be followed by a `-`, `_` or space. Names without one are
+/// returned untouched.
+///
+/// # Example
+///
+/// 01-intro.md -> (Some(1), intro.md)
+/// 02_setup -> (Some(2), setup)
+/// 2024.md -> (None, 2024.md)
+pub fn split_number_prefix(name: &str) -> (Option<u32>, &str) {
+    let digits = name
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(name.len());
+    let (number, rest) = name.split_at(digits);
+
+    match (number.parse(), rest.strip_prefix(['-', '_', ' '])) {
+        (Ok(number), Some(rest)) if !rest.is_empty() && !rest.starts_with('.') => {
+            (Some(number), rest)
+        }
+        _ => (None, name),
+    }
+}
+
+/// Removes the number prefix (see [`split_number_prefix`]) of every component of a path
+pub fn strip_number_prefixes<P>(path: P) -> PathBuf
+where
+    P: AsRef<Path>,
+{
+    path.as_ref()
+        .components()
+        .map(|component| match component {
+            Component::Normal(name) => {
+                PathBuf::from(split_number_prefix(&name.to_string_lossy()).1)
+            }
+            component => PathBuf::from(component.as_os_str()),
+        })
+        .collect()
+}
+
+/// The path that leads from the `from` dir to `to`, when both are relative to the same dir (e.g.
+/// `guide` and `setup/install.md` give `../setup/install.md`)
+pub fn relative_path<P, Q>(from: P, to: Q) -> PathBuf
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let from: Vec<Component> = from.as_ref().components().collect();
+    let to: Vec<Component> = to.as_ref().components().collect();
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    from[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(to[common..].iter().copied())
+        .collect()
+}
+
+/// Resolves the `..` in a relative path. It returns `None` if the path leaves the dir it's
+/// relative to (e.g. `../setup.md`).
+pub fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir if !normalized.pop() => return None,
+            _ => {}
+        }
+    }
+
+    Some(normalized)
+}
+
+/// The path as it goes in a url, with `/` even on Windows
+pub fn url_path<P>(path: P) -> String
+where
+    P: AsRef<Path>,
+{
+    path.as_ref().to_string_lossy().replace('\\', "/")
+}
+
+/// The url of the page, relative to the site, where the site is published. It's percent encoded
+/// and with `&` escaped, which is what XML wants too.
+pub fn absolute_url<P>(site_url: &str, page: P) -> String
+where
+    P: AsRef<Path>,
+{
+    format!("{site_url}/{}", escape_href(&url_path(page)))
+}
+
+/// The text with the characters that mean something in html (e.g. `<` and `&`) escaped
+pub fn escape_html(text: &str) -> String {
+    escaped(|escaped| pulldown_cmark::escape::escape_html(escaped, text))
+}
+
+/// The url with the characters that can't go in an `href` percent encoded (e.g. spaces)
+pub fn escape_href(url: &str) -> String {
+    escaped(|escaped| pulldown_cmark::escape::escape_href(escaped, url))
+}
+
+fn escaped<F>(escape: F) -> String
+where
+    F: FnOnce(&mut String) -> io::Result<()>,
+{
+    let mut escaped = String::new();
+    // Can't fail since writing to a String never fails
+    let _ = escape(&mut escaped);
+
+    escaped
+}
+
+/// The lines that have to be removed (`-`) from `old` and added (`+`) to it to get `new`, in
+/// order. Unchanged lines are left out, so an empty string means they are the same.
+pub fn diff_lines(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // Most changes are small, so the common start and end are skipped before the expensive part
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+
+    // common[i][j] is how many lines old[i..] and new[j..] have in common
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = match old[i] == new[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        }
+    }
+
+    diff
+}
+
 pub fn remove_whitespace<S>(s: S) -> String
 where
     S: AsRef<str>,
@@ -43,6 +417,25 @@ where
     s.as_ref().chars().filter(|c| !c.is_whitespace()).collect()
 }
 
+/// Turns the path a site is served from into the form urls are built with: a leading slash and
+/// no trailing one. The root is empty.
+///
+/// # Example
+///
+/// docs/ -> /docs
+/// / -> (empty)
+pub fn base_path<S>(base_path: S) -> String
+where
+    S: AsRef<str>,
+{
+    let base_path = base_path.as_ref().trim().trim_matches('/');
+
+    match base_path.is_empty() {
+        true => String::new(),
+        false => format!("/{base_path}"),
+    }
+}
+
 /// Takes a chapter number and increases it's last component
 ///
 /// # Example
@@ -57,9 +450,135 @@ where
         Some((rest, number_to_increase)) => {
             format!(
                 "{rest}.{}",
-                (number_to_increase.parse::<u32>().unwrap() + 1).to_string()
+                (number_to_increase.parse::<u32>().unwrap() + 1)
             )
         }
         None => (number.to_string().parse::<u32>().unwrap() + 1).to_string(),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_percent_encode() {
+        assert_eq!(
+            percent_encode("obi+wan@example.com"),
+            "obi%2Bwan%40example.com"
+        );
+        assert_eq!(percent_encode("café"), "caf%C3%A9");
+    }
+
+    #[test]
+    fn it_should_normalize_the_base_path() {
+        assert_eq!(base_path("docs/"), "/docs");
+        assert_eq!(base_path("/docs/api"), "/docs/api");
+        assert_eq!(base_path("/"), "");
+        assert_eq!(base_path(""), "");
+    }
+
+    #[test]
+    fn it_should_copy_a_dir() -> Result<()> {
+        let source = tempdir()?;
+        let destination = tempdir()?;
+
+        fs::create_dir_all(source.path().join("images/drafts"))?;
+        fs::write(source.path().join("intro.md"), "Hello there")?;
+        fs::write(source.path().join("images/diagram.png"), "png")?;
+        fs::write(source.path().join("images/drafts/secret.png"), "png")?;
+
+        let patterns = vec!["images/drafts".to_string()];
+        let mut reported = Vec::new();
+        let copied = copy_dir(
+            source.path(),
+            destination.path().join("copy"),
+            |path| is_ignored(&patterns, path),
+            |progress| reported.push(progress.files),
+        )?;
+
+        assert_eq!(copied.files, 2);
+        assert_eq!(copied.bytes, 14);
+        assert_eq!(reported, vec![1, 2]);
+        assert_eq!(
+            fs::read_to_string(destination.path().join("copy/images/diagram.png"))?,
+            "png"
+        );
+        assert!(!destination.path().join("copy/images/drafts").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_split_number_prefixes() {
+        let tests = [
+            ("01-intro.md", (Some(1), "intro.md")),
+            ("02_setup", (Some(2), "setup")),
+            ("3 faq.md", (Some(3), "faq.md")),
+            ("2024.md", (None, "2024.md")),
+            ("1-.md", (None, "1-.md")),
+            ("chapter1.md", (None, "chapter1.md")),
+        ];
+
+        for test in tests.iter() {
+            assert_eq!(split_number_prefix(test.0), test.1, "{test:?}");
+        }
+
+        assert_eq!(
+            strip_number_prefixes("02_setup/01-install.md"),
+            PathBuf::from("setup/install.md")
+        );
+    }
+
+    #[test]
+    fn it_should_find_relative_paths() {
+        assert_eq!(
+            relative_path("guide", "setup/install.md"),
+            PathBuf::from("../setup/install.md")
+        );
+        assert_eq!(
+            relative_path("guide", "guide/basics/intro.md"),
+            PathBuf::from("basics/intro.md")
+        );
+        assert_eq!(relative_path("", "intro.md"), PathBuf::from("intro.md"));
+    }
+
+    #[test]
+    fn it_should_normalize_relative_paths() {
+        assert_eq!(
+            normalize(Path::new("guide/../setup/./install.md")),
+            Some(PathBuf::from("setup/install.md"))
+        );
+        assert_eq!(normalize(Path::new("guide/../../setup.md")), None);
+    }
+
+    #[test]
+    fn it_should_diff_lines() {
+        let old = "<h1>Hello there</h1>\n<p>General Kenobi</p>\n<p>You are a bold one</p>\n";
+        let new = "<h1>Hello there</h1>\n<p>General Grievous</p>\n<p>You are a bold one</p>\n<p>Kill him</p>\n";
+
+        assert_eq!(
+            diff_lines(old, new),
+            "-<p>General Kenobi</p>\n+<p>General Grievous</p>\n+<p>Kill him</p>\n"
+        );
+        assert_eq!(diff_lines(old, old), "");
+    }
+
+    #[test]
+    fn it_should_match_wildcards() {
+        let tests = [
+            ("images/*.png", "images/diagram.png", true),
+            ("*.pdf", "slides/intro.pdf", true),
+            ("drafts/*", "drafts/a/b.md", true),
+            ("drafts/*", "published/a.md", false),
+            ("intro.md", "intro.md", true),
+            ("intro.md", "intro.mdx", false),
+            ("*a*b*", "xaxbx", true),
+        ];
+
+        for test in tests.iter() {
+            assert_eq!(matches_wildcard(test.0, test.1), test.2, "{test:?}");
+        }
+    }
+}
@@ -1,21 +1,124 @@
+mod assets;
+mod backends;
+mod check;
 pub mod content;
+mod contributors;
+mod custom;
+mod dates;
+mod diff;
+mod doctor;
+mod epub;
+mod exercises;
+mod favicon;
+mod feed;
+mod format;
+mod languages;
+pub mod manifest;
+mod moves;
+mod preview;
+mod print;
+mod private;
+mod redirects;
+mod replace;
+mod report;
+mod search;
+mod sitemap;
+mod social;
+mod sources;
+mod standalone;
+mod stats;
+mod themes;
+mod toc;
+mod watch;
 
-use crate::config::Config;
-use crate::renderer::{self, AskamaRenderer, Renderer};
-use crate::Chapter;
+use crate::config::{Config, PrivateMode, Profile};
+use crate::renderer::variables::{self, Variables};
+use crate::renderer::{self, markdown, outputs, AskamaRenderer, Renderer, SiteLanguage};
+use crate::summary::{
+    FileTreeSummarizer, Summarizer, NOT_FOUND_FILE_NAME, ORDER_FILE_NAMES, SECTION_FILE_NAME,
+    SUMMARY_FILE_NAMES,
+};
+use crate::{git, util, Chapter, FrontMatter};
 use anyhow::{anyhow, Context, Result};
+pub use check::Problem;
 use content::Content;
+pub use contributors::Contributors;
+pub use dates::LastUpdated;
+pub use diff::{diff_pages, BuiltPages, PageDiff};
+pub use doctor::{doctor, Diagnosis, Severity};
+pub use epub::EPUB_FILE;
+pub use format::format;
 use log::warn;
+use manifest::{Manifest, ManifestChapter};
+pub use moves::{Move, MovedLink};
+pub use replace::Replacement;
+pub use report::OverdueReview;
+pub use search::{query_terms, SearchDocument, SearchIndex, SEARCH_INDEX_FILE};
+pub use sitemap::{ROBOTS_FILE, SITEMAP_XML_FILE};
+pub use standalone::STANDALONE_FILE;
+pub use stats::{ChapterStats, Counts, Growth, SectionStats, Stats};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+pub use themes::{Theme, THEMES_DIR, THEME_CSS, THEME_JS};
+pub use toc::{ReadingOrderPage, TocChapter, TocItem, READING_ORDER_FILE, TOC_FILE};
+pub use watch::WatchEvent;
 
 static CSS: &[u8] = include_bytes!("../templates/main.css");
 static JS: &[u8] = include_bytes!("../templates/index.js");
+static MERMAID: &[u8] = include_bytes!("../templates/mermaid.js");
 pub const CONFIG_FILE: &str = "cahlter.yml";
+pub const SUMMARY_FILE: &str = "SUMMARY.md";
+pub const SITEMAP_FILE: &str = "sitemap.html";
+/// Holds the files chapters can insert with `{{#snippet name}}`, next to the source dir
+pub const SNIPPETS_DIR: &str = "snippets";
+/// Holds the values chapters can refer to with `{{name}}`, next to the config
+pub const VARIABLES_FILE: &str = "variables.yml";
+/// Holds what a build keeps for the next ones (e.g. highlighted code), next to the config. It's
+/// safe to delete at any time.
+pub const CACHE_DIR: &str = ".cahlter-cache";
+/// Copied as it is to the root of the build dir, next to the source dir. The place for files that
+/// aren't chapters or their assets (e.g. robots.txt, CNAME or downloads).
+pub const STATIC_DIR: &str = "static";
+
+/// What a build did
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BuildReport {
+    /// How many chapters the vault has, subchapters included
+    pub chapters: usize,
+    /// How many of them weren't rendered again, since they didn't change since the last build (or
+    /// the git ref of [`Vault::build_since`])
+    pub cached: usize,
+    /// How many assets were copied (or symlinked), along with the files of the static dir
+    pub assets: usize,
+    pub duration: Duration,
+}
 
 pub struct Vault {
     pub config: Config,
     pub path: PathBuf,
+    /// Should the built pages reload themselves when the vault is rebuilt? Used by `serve`.
+    pub live_reload: bool,
+    /// Where the built site is served from (e.g. /docs). Empty means the root. It comes from
+    /// `general.base_path`, unless `serve` is told to mount the vault somewhere else.
+    pub base_path: String,
+    /// Decides whether search engines may index the build (see [`crate::config::Robots`])
+    pub profile: Profile,
+    /// Should the solutions to the exercises be shown, even if the config hides them?
+    pub with_solutions: bool,
+    /// Should the build leave the cache dir alone? Everything is rendered from scratch then.
+    pub no_cache: bool,
+    /// Which readers the build is for. Blocks of the chapters for other audiences are left out
+    /// (see [`renderer::audiences`])
+    pub audience: Option<String>,
+    // The content of the last build, kept until the summary changes
+    content_cache: Option<(Vec<(PathBuf, SystemTime)>, Content)>,
+    // The dir of the translation being built and the source dir of the default language, when
+    // the vault is one of the languages of another (see languages.rs)
+    translation: Option<(PathBuf, PathBuf)>,
+    // The languages of the build, for the switcher of the pages
+    languages: Vec<SiteLanguage>,
 }
 
 impl Vault {
@@ -28,6 +131,15 @@ impl Vault {
         Vault {
             config,
             path: path.as_ref().to_path_buf(),
+            live_reload: false,
+            base_path: String::new(),
+            profile: Profile::default(),
+            with_solutions: false,
+            no_cache: false,
+            audience: None,
+            content_cache: None,
+            translation: None,
+            languages: Vec::new(),
         }
     }
 
@@ -37,8 +149,17 @@ impl Vault {
     {
         let config = Config::from_disk(path.as_ref().join(CONFIG_FILE))?;
         let vault = Vault {
+            base_path: util::base_path(&config.general.base_path),
             config,
             path: path.as_ref().to_path_buf(),
+            live_reload: false,
+            profile: Profile::default(),
+            with_solutions: false,
+            no_cache: false,
+            audience: None,
+            content_cache: None,
+            translation: None,
+            languages: Vec::new(),
         };
 
         if !vault.src_dir().exists() || !vault.build_dir().exists() {
@@ -79,80 +200,552 @@ impl Vault {
         Ok(())
     }
 
-    pub fn build(&mut self) -> Result<()> {
-        let content = Content::new(self.src_dir())?;
-        let context =
-            renderer::RendererContext::new(content.clone(), self.config.clone(), self.src_dir());
-        let renderer = AskamaRenderer::new(context);
-        let chapters = content.chapters();
+    /// Builds every chapter into the build dir and, with `multiple_language`, every language into
+    /// a dir of its own inside it
+    pub fn build(&mut self) -> Result<BuildReport> {
+        self.build_languages()
+    }
 
-        for chapter in chapters.iter() {
+    /// Like [`Vault::build`], but only renders the chapters that changed since `git_ref`, along
+    /// with the sitemap and the error pages. It's meant for quick previews (e.g. of a pull
+    /// request), so the other pages keep the navigation they had. If something every page depends
+    /// on changed (the config, the summary, snippets or variables), everything is rendered.
+    pub fn build_since(&mut self, git_ref: &str) -> Result<BuildReport> {
+        let changed = git::changed_files(&self.path, git_ref)?;
+
+        if let Some(path) = changed.iter().find(|path| self.is_shared_input(path)) {
+            warn!(
+                "{} changed since {git_ref}, so every chapter is rendered",
+                path.display()
+            );
+            return self.build();
+        }
+
+        self.build_chapters(Some(&changed.into_iter().collect()))
+    }
+
+    // `only` has the chapters to render, relative to the vault. The others count as cached.
+    fn build_chapters(&mut self, only: Option<&HashSet<PathBuf>>) -> Result<BuildReport> {
+        let start = Instant::now();
+        let content = self.cached_content()?;
+        // Looked up once, since the pages and the feed both need them
+        let last_updated = self.last_updated(&content)?;
+        let contributors = self.contributors(&content)?;
+        let renderer = self.renderer(&content, &last_updated, &contributors)?;
+        let previous = Manifest::from_disk(self.build_dir())?.unwrap_or_default();
+        // Translations are staged in a dir of their own, so only the default language can tell
+        // which chapters moved and save them to the config
+        if self.translation.is_none() {
+            let moves = self.find_moves(&content, &previous)?;
+            self.record_moves(&moves)?;
+        }
+        let inputs = self.inputs_hash(&content, &last_updated, &contributors)?;
+        // Encrypted pages are always written again, in case the passphrase changed
+        let encrypted = self.encrypted_chapters(&content)?;
+        let mut cached = 0;
+        self.unlink_assets()?;
+
+        for chapter in content.all_chapters().iter() {
             if !chapter.content.exists() {
                 warn!("Missing file: {}", chapter.content.display())
+            } else if only.is_some_and(|only| !only.contains(&self.relative(&chapter.content)))
+                || (previous.inputs == inputs
+                    && !encrypted.contains(&chapter.content)
+                    && self.is_up_to_date(chapter, &previous)?)
+            {
+                cached += 1;
+                continue;
+            }
+
+            self.write_chapter(chapter, &renderer)?;
+        }
+
+        let sitemap = self.build_dir().join(SITEMAP_FILE);
+        let html = renderer
+            .render_sitemap()
+            .with_context(|| anyhow!("Failed to render the sitemap"))?;
+        fs::write(&sitemap, html)
+            .with_context(|| anyhow!("Failed to write {}", sitemap.display()))?;
+
+        let not_found = self.src_dir().join(NOT_FOUND_FILE_NAME);
+        for (status, message) in renderer::ERROR_PAGES {
+            let page = self.build_dir().join(renderer::error_page_path(status));
+            let html = match status == 404 && not_found.exists() {
+                true => renderer.render_not_found(&not_found),
+                false => renderer.render_error(status, message),
             }
+            .with_context(|| anyhow!("Failed to render the {status} page"))?;
+            fs::write(&page, html)
+                .with_context(|| anyhow!("Failed to write {}", page.display()))?;
+        }
+
+        self.write_solutions(&content, &renderer)?;
+        self.write_print(&content, &renderer)?;
+        self.write_theme_preview(&content, &renderer)?;
 
-            self.write_chapter(&chapter, renderer.clone(), self.build_dir())?;
+        // Pages that weren't rendered may be older than their chapters, so the next full build
+        // can't trust the manifest of a partial one
+        match only {
+            Some(_) => self.write_manifest(&content, String::new())?,
+            None => self.write_manifest(&content, inputs)?,
         }
 
+        if self.config.code_blocks.source_view {
+            self.write_source_pages(&content, &renderer)?;
+        }
+        self.write_redirects(&content, &renderer)?;
+        self.write_toc(&content)?;
+        self.write_reading_order(&content)?;
+
+        if self.config.search.enabled {
+            self.write_search_index(&content)?;
+        }
+        if let Some(site_url) = &self.config.general.site_url {
+            self.write_sitemap_xml(&content, site_url)?;
+            self.write_robots(site_url)?;
+        }
+        if self.config.feed.enabled {
+            self.write_feed(&content, &renderer, &last_updated)?;
+        }
+        let assets = self.copy_assets()?;
+
         if self.config.general.use_default {
-            for static_file in vec![("main.css", CSS), ("index.js", JS)] {
-                fs::write(self.build_dir().join(static_file.0), static_file.1)
+            for static_file in [
+                ("main.css", CSS),
+                ("index.js", JS),
+                ("mermaid.min.js", MERMAID),
+            ] {
+                let path = self.build_dir().join(static_file.0);
+                util::remove_file_if_exists(&path)
+                    .and_then(|_| Ok(fs::write(&path, static_file.1)?))
                     .with_context(|| anyhow!("Failed to write default files"))?;
             }
         }
 
-        for css_file in self.config.appearance.custom.iter() {
-            let file_name = Path::new(css_file)
+        self.write_themes(&self.themes()?)?;
+
+        for file in self.custom_files()? {
+            let file_name = file
                 .file_name()
-                .with_context(|| anyhow!("Failed to extract file name from {css_file}"))?;
+                .with_context(|| anyhow!("Failed to extract file name from {}", file.display()))?;
 
-            fs::copy(self.path.join(css_file), self.build_dir().join(file_name))
-                .with_context(|| anyhow!("Failed to copy custom css",))?;
+            let destination = self.build_dir().join(file_name);
+            util::remove_file_if_exists(&destination)
+                .and_then(|_| Ok(fs::copy(self.path.join(&file), &destination)?))
+                .with_context(|| anyhow!("Failed to copy {}", file.display()))?;
         }
 
-        Ok(())
+        if let Some(favicon) = &self.config.appearance.favicon {
+            self.write_favicons(favicon)?;
+        }
+
+        // Last, so its files win over the generated ones
+        let static_files = self.copy_static()?;
+
+        Ok(BuildReport {
+            chapters: content.all_chapters().len(),
+            cached,
+            assets: assets + static_files,
+            duration: start.elapsed(),
+        })
     }
 
-    fn write_chapter<R, P>(&self, chapter: &Chapter, renderer: R, destination: P) -> Result<()>
+    // Reading the summary means walking the whole source dir when there is no summary file, so
+    // it's only done again when the summary changes. Chapters can become private at any time, so
+    // they're always left out again.
+    fn cached_content(&mut self) -> Result<Content> {
+        let fingerprint = Content::fingerprint(self.src_dir(), &self.config)?;
+
+        if let Some((cached, content)) = &self.content_cache {
+            if *cached == fingerprint {
+                return self.without_private(content.clone());
+            }
+        }
+
+        let content = self.content()?;
+        self.content_cache = Some((fingerprint, content.clone()));
+
+        self.without_private(content)
+    }
+
+    /// Renders a single file of the src dir as a page of the vault, without touching the build
+    /// dir. `markdown` replaces the contents of the file, so editors can preview unsaved changes.
+    pub fn render_file<P>(&self, path: P, markdown: Option<&str>) -> Result<String>
     where
-        R: Renderer + Clone,
         P: AsRef<Path>,
     {
-        let file_name = chapter
-            .content
-            .file_name()
-            .and_then(|name| Some(PathBuf::from(name).with_extension("html")))
-            .with_context(|| {
-                anyhow!(
-                    "Failed to extract the file name from {}",
+        let path = self.path.join(path);
+        // Anything else readable (e.g. /etc/passwd) would be rendered as well
+        if !self.is_in_src_dir(&path) {
+            anyhow::bail!("{} isn't inside the source dir", path.display());
+        }
+
+        let content = self.content()?;
+        // Files outside of the summary are still rendered, they just aren't numbered
+        let chapter = content
+            .all_chapters()
+            .into_iter()
+            .find(|chapter| self.relative(&chapter.content) == self.relative(&path))
+            .unwrap_or_else(|| {
+                let title = path.file_stem().unwrap_or_default().to_string_lossy();
+                Chapter::new(title.to_string(), String::new(), &path, vec![])
+            });
+        let renderer = self.renderer(
+            &content,
+            &self.last_updated(&content)?,
+            &self.contributors(&content)?,
+        )?;
+
+        match markdown {
+            Some(markdown) => renderer.render_markdown(&chapter, markdown),
+            None => renderer.render(&chapter),
+        }
+    }
+
+    // With `..` resolved and, for files that exist, symlinks followed
+    fn is_in_src_dir(&self, path: &Path) -> bool {
+        let Some(relative) = path.strip_prefix(&self.path).ok().and_then(util::normalize) else {
+            return false;
+        };
+        let src_dir = util::normalize(&self.config.general.src_dir).unwrap_or_default();
+
+        match (fs::canonicalize(path), fs::canonicalize(self.src_dir())) {
+            (Ok(path), Ok(src_dir)) => path.starts_with(src_dir),
+            _ => relative.starts_with(src_dir),
+        }
+    }
+
+    fn renderer(
+        &self,
+        content: &Content,
+        last_updated: &LastUpdated,
+        contributors: &Contributors,
+    ) -> Result<AskamaRenderer> {
+        let context =
+            renderer::RendererContext::new(content.clone(), self.config.clone(), self.src_dir())
+                .with_live_reload(self.live_reload)
+                .with_base_path(&self.base_path)
+                .with_snippets_dir(self.snippets_dir())
+                .with_variables(self.variables()?)
+                .with_solutions(self.shows_solutions())
+                .with_contributors(contributors.clone())
+                .with_languages(self.languages.clone())
+                .with_themes(self.themes()?)
+                .with_head_include(self.head_include()?)
+                .with_footer_html(self.footer_html()?)
+                .with_custom_files(self.custom_files()?)
+                .with_audience(self.audience.clone())
+                .with_private(self.private_mode(), self.passphrase());
+        let context = match self.no_cache {
+            true => context,
+            false => context.with_highlight_cache(self.cache_dir()?.join("highlight")),
+        };
+        let context = match self.config.appearance.last_updated {
+            true => context.with_last_updated(last_updated.clone()),
+            false => context,
+        };
+        let context = match self.config.code_blocks.source_view {
+            true => context.with_source_root(&self.path),
+            false => context,
+        };
+
+        Ok(AskamaRenderer::new(context))
+    }
+
+    /// The chapters of the vault, found the way the config says
+    pub fn content(&self) -> Result<Content> {
+        Content::from_config(self.src_dir(), &self.config)
+    }
+
+    /// The html that goes in the head of every page, if the config has a file for it
+    pub fn head_include(&self) -> Result<String> {
+        self.read_include(self.config.appearance.head_include.as_ref())
+    }
+
+    /// The html that goes at the end of the footer of every page, if the config has a file for it
+    pub fn footer_html(&self) -> Result<String> {
+        self.read_include(self.config.footer.html.as_ref())
+    }
+
+    fn read_include(&self, path: Option<&PathBuf>) -> Result<String> {
+        let Some(path) = path else {
+            return Ok(String::new());
+        };
+        let path = self.path.join(path);
+
+        fs::read_to_string(&path)
+            .with_context(|| anyhow!("Failed to read contents of {}", path.display()))
+    }
+
+    // The files of the config whose html goes in every page, relative to the vault
+    fn includes(&self) -> impl Iterator<Item = &PathBuf> {
+        self.config
+            .appearance
+            .head_include
+            .iter()
+            .chain(self.config.footer.html.iter())
+    }
+
+    /// The values in the variables file, if there is one
+    pub fn variables(&self) -> Result<Variables> {
+        let path = self.path.join(VARIABLES_FILE);
+
+        match path.exists() {
+            true => variables::from_disk(path),
+            false => Ok(Variables::new()),
+        }
+    }
+
+    /// Writes a chapter to the build dir. The output mirrors the source tree, so the urls in the
+    /// sidebar and the relative urls in the content always resolve.
+    fn write_chapter<R>(&self, chapter: &Chapter, renderer: &R) -> Result<()>
+    where
+        R: Renderer,
+    {
+        let destination = self
+            .build_dir()
+            .join(renderer::output_path(&chapter.content, self.src_dir())?);
+
+        if let Some(parent) = destination.parent() {
+            util::create_dir_if_not_exists(parent)?;
+        }
+
+        fs::write(&destination, renderer.render(chapter)?)
+            .with_context(|| anyhow!("Failed to write {}", destination.display()))?;
+        if self.config.social_cards.enabled {
+            self.write_social_card(chapter)?;
+        }
+
+        self.export_chapter(chapter, &destination)
+    }
+
+    // The other formats asked for in the front matter are written next to the page
+    fn export_chapter(&self, chapter: &Chapter, page: &Path) -> Result<()> {
+        let markdown = fs::read_to_string(&chapter.content)
+            .with_context(|| anyhow!("Failed to read contents of {}", chapter.content.display()))?;
+        let (front_matter, markdown) = FrontMatter::extract(&markdown)?;
+        let markdown = renderer::audiences::audiences(markdown, self.audience.as_deref())?;
+
+        if front_matter.private && self.private_mode() == PrivateMode::Encrypt {
+            if !front_matter.outputs.is_empty() {
+                warn!(
+                    "{} is private, so it's only built as an encrypted page",
                     chapter.content.display()
-                )
-            })?;
-
-        match chapter.subchapters.is_empty() {
-            true => {
-                fs::write(
-                    destination.as_ref().join(&file_name),
-                    renderer.render(chapter)?,
-                )?;
+                );
             }
-            false => {
-                let destination = self
-                    .build_dir()
-                    .join(chapter.content.parent().unwrap().file_stem().unwrap());
-                fs::create_dir(&destination)?;
+            return Ok(());
+        }
 
-                fs::write(destination.join(&file_name), renderer.render(chapter)?)?;
+        for name in front_matter.outputs.iter() {
+            if name == outputs::PAGE_OUTPUT {
+                continue;
+            }
 
-                for subchapter in chapter.subchapters.iter() {
-                    self.write_chapter(&subchapter, renderer.clone(), &destination)?;
-                }
+            let Some(format) = outputs::find(name) else {
+                warn!(
+                    "Unknown output format {name} in {}",
+                    chapter.content.display()
+                );
+                continue;
+            };
+            if front_matter.excludes(name) {
+                continue;
             }
+
+            let destination = page.with_extension(format.extension());
+            let options = renderer::markdown::options(&self.config.markdown.extensions);
+            let exported = format
+                .export(chapter, &markdown, options)
+                .with_context(|| anyhow!("Failed to export {} to {name}", chapter.title))?;
+
+            fs::write(&destination, exported)
+                .with_context(|| anyhow!("Failed to write {}", destination.display()))?;
         }
 
         Ok(())
     }
 
+    // Files, relative to the vault, that every page depends on
+    fn is_shared_input(&self, path: &Path) -> bool {
+        let src_dir = &self.config.general.src_dir;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        path == Path::new(CONFIG_FILE)
+            || path == Path::new(VARIABLES_FILE)
+            || path.starts_with(SNIPPETS_DIR)
+            || self
+                .config
+                .social_cards
+                .template
+                .as_ref()
+                .is_some_and(|template| self.relative(&self.path.join(template)) == path)
+            || self.is_custom(path)
+            || self
+                .includes()
+                .any(|include| self.relative(&self.path.join(include)) == path)
+            || (path.starts_with(src_dir)
+                && (SUMMARY_FILE_NAMES.contains(&name.as_ref())
+                    || ORDER_FILE_NAMES.contains(&name.as_ref())
+                    || name == SECTION_FILE_NAME))
+    }
+
+    // Hashes are used instead of modification times, so fresh checkouts (e.g. in CI) still get to
+    // reuse what was built before
+    fn is_up_to_date(&self, chapter: &Chapter, previous: &Manifest) -> Result<bool> {
+        let source: PathBuf = chapter
+            .content
+            .strip_prefix(self.src_dir())?
+            .components()
+            .collect();
+        let Some(built) = previous.chapter(&source) else {
+            return Ok(false);
+        };
+
+        let markdown = fs::read(&chapter.content)?;
+        // The files it pulls in could have changed too
+        if String::from_utf8_lossy(&markdown).contains("{{#include ") {
+            return Ok(false);
+        }
+
+        Ok(built.hash == util::content_hash(markdown)
+            && self.build_dir().join(&built.output).exists()
+            && (!self.config.social_cards.enabled
+                || self
+                    .build_dir()
+                    .join(renderer::social_card_path(
+                        &self.config.social_cards.dir,
+                        &built.output,
+                    ))
+                    .exists()))
+    }
+
+    // Every page depends on these, besides its own file. If any of them change, everything is
+    // rendered again.
+    fn inputs_hash(
+        &self,
+        content: &Content,
+        last_updated: &LastUpdated,
+        contributors: &Contributors,
+    ) -> Result<String> {
+        let inputs = serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "config": self.config,
+            "summary": content.summary,
+            "live_reload": self.live_reload,
+            "with_solutions": self.with_solutions,
+            // Chapters become outdated as days go by, even if nothing changes
+            "today": self
+                .config
+                .review
+                .outdated_badge
+                .then(|| chrono::Local::now().date_naive()),
+            "base_path": self.base_path,
+            "private": self.private_mode(),
+            "audience": self.audience,
+            // A commit can change the date in the footer of every page it touches
+            "last_updated": self.config.appearance.last_updated.then_some(last_updated),
+            "contributors": contributors,
+            "snippets": self.snippets_hash()?,
+            "variables": self.variables()?,
+            // Pages link to every theme
+            "themes": self.themes()?,
+            "head_include": util::content_hash(self.head_include()?),
+            "footer_html": util::content_hash(self.footer_html()?),
+            // Globs can match other files than before, and pages link to every one
+            "custom_files": self.custom_files()?,
+            "social_card_template": match self.config.social_cards.enabled {
+                true => Some(util::content_hash(self.social_card_template()?)),
+                false => None,
+            },
+        });
+
+        Ok(util::content_hash(serde_json::to_vec(&inputs)?))
+    }
+
+    // Snippets can end up in any page, so they count as an input of all of them
+    fn snippets_hash(&self) -> Result<Option<String>> {
+        match self.snippets_dir().exists() {
+            true => Ok(Some(util::dir_hash(self.snippets_dir())?)),
+            false => Ok(None),
+        }
+    }
+
+    /// Writes a summary file generated from the file tree, so it can be reordered by hand. An
+    /// existing summary is only replaced if `force` is true.
+    pub fn generate_summary(&self, force: bool) -> Result<PathBuf> {
+        for name in SUMMARY_FILE_NAMES {
+            let path = self.src_dir().join(name);
+
+            if path.exists() && !force {
+                anyhow::bail!(
+                    "{} already exists. Use --force to overwrite it",
+                    path.display()
+                );
+            }
+        }
+
+        let summary = FileTreeSummarizer::new(self.src_dir()).summarize()?;
+        let path = self.src_dir().join(SUMMARY_FILE);
+
+        fs::write(&path, summary.to_markdown(self.src_dir()))
+            .with_context(|| anyhow!("Failed to write {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    fn write_manifest(&self, content: &Content, inputs: String) -> Result<()> {
+        let mut chapters = Vec::new();
+        // The manifest is published along with the site, so it can't tell anything about the text
+        // of encrypted chapters. Their pages are always written again, so they need no hash.
+        let encrypted = self.encrypted_chapters(content)?;
+
+        for chapter in content.all_chapters().iter() {
+            if !chapter.content.exists() {
+                continue;
+            }
+
+            let (hash, anchors) = match encrypted.contains(&chapter.content) {
+                true => (String::new(), Vec::new()),
+                false => (
+                    util::content_hash(fs::read(&chapter.content)?),
+                    self.anchors(&chapter.content)?,
+                ),
+            };
+            let counts = Counts::from_disk(&chapter.content)?;
+            chapters.push(ManifestChapter {
+                source: chapter
+                    .content
+                    .strip_prefix(self.src_dir())?
+                    .components()
+                    .collect(),
+                output: renderer::output_path(&chapter.content, self.src_dir())?,
+                words: counts.words,
+                reading_time: counts.reading_time(self.config.reading_time.words_per_minute),
+                hash,
+                anchors,
+            });
+        }
+
+        Manifest::new(chapters, inputs).save(self.build_dir())
+    }
+
+    // The front matter is skipped, but a broken one isn't the manifest's problem. Like the pages,
+    // it only has the headings for the audience of the build.
+    fn anchors(&self, path: &Path) -> Result<Vec<String>> {
+        let markdown = fs::read_to_string(path)
+            .with_context(|| anyhow!("Failed to read contents of {}", path.display()))?;
+        let body = FrontMatter::extract(&markdown)
+            .map(|(_, body)| body)
+            .unwrap_or(&markdown);
+        let body = renderer::audiences::audiences(body, self.audience.as_deref())?;
+
+        Ok(markdown::anchors(
+            &body,
+            markdown::options(&self.config.markdown.extensions),
+            self.config.markdown.slug,
+        ))
+    }
+
     fn was_initialized<P>(path: P) -> bool
     where
         P: AsRef<Path>,
@@ -160,6 +753,43 @@ impl Vault {
         path.as_ref().to_path_buf().join(CONFIG_FILE).exists()
     }
 
+    /// The most recent modification time among the config file and everything inside the source
+    /// dir. It's cheap enough to be polled to find out if the vault needs to be rebuilt.
+    pub fn last_modified(&self) -> Result<SystemTime> {
+        let config_modified = fs::metadata(self.path.join(CONFIG_FILE))?.modified()?;
+
+        let mut modified = util::last_modified(self.src_dir())?.max(config_modified);
+
+        let translations = self
+            .translations()
+            .filter(|_| self.config.general.multiple_language)
+            .map(|language| self.path.join(&language.path));
+        for path in [
+            self.snippets_dir(),
+            self.path.join(VARIABLES_FILE),
+            self.static_dir(),
+            self.themes_dir(),
+        ]
+        .into_iter()
+        .chain(self.includes().map(|path| self.path.join(path)))
+        .chain(translations)
+        {
+            if path.exists() {
+                modified = modified.max(util::last_modified(path)?);
+            }
+        }
+
+        Ok(modified)
+    }
+
+    // Also gets rid of the `./` that summary files usually have
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.path)
+            .unwrap_or(path)
+            .components()
+            .collect()
+    }
+
     pub fn src_dir(&self) -> PathBuf {
         self.path.join(&self.config.general.src_dir)
     }
@@ -167,12 +797,42 @@ impl Vault {
     pub fn build_dir(&self) -> PathBuf {
         self.path.join(&self.config.general.build_dir)
     }
+
+    pub fn snippets_dir(&self) -> PathBuf {
+        self.path.join(SNIPPETS_DIR)
+    }
+
+    pub fn static_dir(&self) -> PathBuf {
+        self.path.join(STATIC_DIR)
+    }
+
+    pub fn themes_dir(&self) -> PathBuf {
+        self.path.join(THEMES_DIR)
+    }
+
+    // Created on demand, with a .gitignore so it never ends up in the repository
+    fn cache_dir(&self) -> Result<PathBuf> {
+        let dir = self.path.join(CACHE_DIR);
+        let gitignore = dir.join(".gitignore");
+
+        if !gitignore.exists() {
+            fs::create_dir_all(&dir)
+                .with_context(|| anyhow!("Failed to create {}", dir.display()))?;
+            fs::write(&gitignore, "*\n")
+                .with_context(|| anyhow!("Failed to write {}", gitignore.display()))?;
+        }
+
+        Ok(dir)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::config::{Footer, Link, Strategy};
+    use crate::summary::UNSORTED_SECTION;
     use std::error::Error;
+    use std::thread;
     use tempfile::tempdir;
 
     #[test]
@@ -190,7 +850,7 @@ mod test {
 
     #[test]
     #[should_panic(expected = "calhter.yml already exists at")]
-    fn it_should_not_initialize_the_vault_if_it_was_already_initialized() -> () {
+    fn it_should_not_initialize_the_vault_if_it_was_already_initialized() {
         let temp_dir = tempdir().unwrap();
         let mut vault = Vault::new(temp_dir.path().join("test_vault"));
 
@@ -253,6 +913,517 @@ mod test {
         assert!(vault.build_dir().join("chapter1.html").exists());
         assert!(vault.build_dir().join("chapter2.html").exists());
         assert!(vault.build_dir().join("main.css").exists());
+        assert!(vault.build_dir().join("mermaid.min.js").exists());
+        assert!(vault.build_dir().join(SITEMAP_FILE).exists());
+        assert!(vault.build_dir().join("500.html").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_export_the_outputs_in_the_front_matter() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("cheatsheet.md"),
+            "---\noutputs: [html, pdf, md, docx]\n---\n# Hello there",
+        )?;
+        vault.build()?;
+
+        assert!(vault.build_dir().join("cheatsheet.html").exists());
+        assert!(fs::read(vault.build_dir().join("cheatsheet.pdf"))?.starts_with(b"%PDF"));
+        assert_eq!(
+            fs::read_to_string(vault.build_dir().join("cheatsheet.md"))?,
+            "# Hello there"
+        );
+        assert!(!vault.build_dir().join("cheatsheet.docx").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_build_drafts_or_external_chapters() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Chapter 1](./chapter1.md)\n- [Future Chapter]()\n- [Rust Book](https://doc.rust-lang.org/book)",
+        )?;
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+
+        let report = vault.build()?;
+        let page = fs::read_to_string(vault.build_dir().join("chapter1.html"))?;
+
+        assert_eq!(report.chapters, 1);
+        assert!(page.contains("table-of-contents__chapter--draft\">Future Chapter"));
+        assert!(vault.check()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_build_the_not_found_page() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("intro.md"), "# Intro")?;
+        vault.build()?;
+        assert!(fs::read_to_string(vault.build_dir().join("404.html"))?
+            .contains("There is nothing here"));
+
+        fs::write(
+            vault.src_dir().join(NOT_FOUND_FILE_NAME),
+            "---\ntitle: Lost?\n---\nTry the [intro](intro.md).",
+        )?;
+        let report = vault.build()?;
+        let page = fs::read_to_string(vault.build_dir().join("404.html"))?;
+
+        assert_eq!(report.chapters, 1);
+        assert!(page.contains("<title>Lost?"));
+        assert!(page.contains("href=\"/intro.html\""));
+        assert!(page.contains("class=\"sidebar"));
+        assert!(!fs::read_to_string(vault.build_dir().join("intro.html"))?.contains("Lost?"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_link_to_anchors_in_the_summary() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Setup](./setup.md)\n    - [Install](./setup.md#install)\n- [Usage](./usage.md#cli)",
+        )?;
+        fs::write(vault.src_dir().join("setup.md"), "# Install")?;
+        fs::write(vault.src_dir().join("usage.md"), "# CLI")?;
+
+        let report = vault.build()?;
+        let page = fs::read_to_string(vault.build_dir().join("setup.html"))?;
+
+        assert_eq!(report.chapters, 2);
+        assert!(page.contains("href=\"/setup.html#install\""));
+        assert!(page.contains("href=\"/usage.html#cli\""));
+        assert!(vault.build_dir().join("usage.html").exists());
+        assert!(vault.check()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_render_a_single_file() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.general.enumerate = true;
+        vault.config.markdown.inject_title = true;
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("summary.md"), "- [Intro](./intro.md)")?;
+        fs::write(vault.src_dir().join("intro.md"), "Hello there")?;
+
+        let page = vault.render_file("src/intro.md", None)?;
+        assert!(page.contains("1 Intro"));
+        assert!(page.contains("Hello there"));
+
+        let page = vault.render_file("src/intro.md", Some("General Kenobi"))?;
+        assert!(page.contains("General Kenobi"));
+        assert!(!page.contains("Hello there"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_render_the_chapters_that_changed() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Chapter 1](./chapter1.md)\n- [Chapter 2](./chapter2.md)",
+        )?;
+        fs::write(vault.src_dir().join("chapter1.md"), "Hello there")?;
+        fs::write(vault.src_dir().join("chapter2.md"), "General Kenobi")?;
+
+        assert_eq!(vault.build()?.cached, 0);
+        assert_eq!(vault.build()?.cached, 2);
+
+        fs::write(vault.src_dir().join("chapter1.md"), "Hello")?;
+        assert_eq!(vault.build()?.cached, 1);
+        assert!(fs::read_to_string(vault.build_dir().join("chapter1.html"))?.contains("Hello"));
+
+        fs::remove_file(vault.build_dir().join("chapter2.html"))?;
+        assert_eq!(vault.build()?.cached, 1);
+
+        vault.config.general.enumerate = true;
+        assert_eq!(vault.build()?.cached, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_render_the_chapters_changed_since_a_git_ref() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+        fs::write(vault.src_dir().join("chapter2.md"), "# General Kenobi")?;
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(["-c", "user.name=Obi-Wan", "-c", "user.email=obi@wan.com"])
+                .args(args)
+                .current_dir(temp_dir.path())
+                .output()
+        };
+        git(&["init"])?;
+        git(&["add", "."])?;
+        git(&["commit", "-m", "Hello there"])?;
+
+        fs::write(vault.src_dir().join("chapter2.md"), "# You are a bold one")?;
+        let report = vault.build_since("HEAD")?;
+
+        assert_eq!((report.chapters, report.cached), (2, 1));
+        assert!(!vault.build_dir().join("chapter1.html").exists());
+        assert!(fs::read_to_string(vault.build_dir().join("chapter2.html"))?
+            .contains("You are a bold one"));
+
+        fs::write(vault.path.join(VARIABLES_FILE), "name: Kenobi")?;
+        assert_eq!(vault.build_since("HEAD")?.cached, 0);
+        assert!(vault.build_since("nope").is_err());
+        let output = temp_dir.path().join("output.txt");
+        assert!(vault
+            .build_since(&format!("--output={}", output.display()))
+            .is_err());
+        assert!(!output.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_insert_snippets() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::create_dir(vault.snippets_dir())?;
+        fs::write(vault.snippets_dir().join("warning.md"), "**Hello there**")?;
+        fs::write(vault.src_dir().join("chapter1.md"), "{{#snippet warning}}")?;
+        vault.build()?;
+
+        let page = vault.build_dir().join("chapter1.html");
+        assert!(fs::read_to_string(&page)?.contains("<strong>Hello there</strong>"));
+
+        // Every chapter may use it, so changing it renders them all again
+        fs::write(vault.snippets_dir().join("warning.md"), "General Kenobi")?;
+        assert_eq!(vault.build()?.cached, 0);
+        assert!(fs::read_to_string(&page)?.contains("General Kenobi"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reuse_the_summary_until_it_changes() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "Hello there")?;
+        vault.build()?;
+        let fingerprint = Content::fingerprint(vault.src_dir(), &vault.config)?;
+
+        vault.build()?;
+        assert_eq!(
+            Content::fingerprint(vault.src_dir(), &vault.config)?,
+            fingerprint
+        );
+
+        // Directory times have a coarse resolution on some filesystems
+        thread::sleep(Duration::from_millis(10));
+        fs::write(vault.src_dir().join("chapter2.md"), "Hi")?;
+        assert_ne!(
+            Content::fingerprint(vault.src_dir(), &vault.config)?,
+            fingerprint
+        );
+        assert_eq!(vault.build()?.chapters, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_rebuild_the_summary_when_a_title_changes() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "Hello there")?;
+        vault.build()?;
+
+        thread::sleep(Duration::from_millis(10));
+        fs::write(
+            vault.src_dir().join("chapter1.md"),
+            "---\ntitle: General Kenobi\n---\nHello there",
+        )?;
+        vault.build()?;
+
+        let html = fs::read_to_string(vault.build_dir().join("chapter1.html"))?;
+        assert!(html.contains("General Kenobi"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_rebuild_the_vault_with_subchapters() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::create_dir(vault.src_dir().join("chapter1"))?;
+        fs::write(vault.src_dir().join("chapter1/index.md"), "# Hello there")?;
+        fs::write(
+            vault.src_dir().join("chapter1/chapter1.1.md"),
+            "# General Kenobi",
+        )?;
+        vault.build()?;
+        vault.build()?;
+
+        assert!(vault.build_dir().join("chapter1/index.html").exists());
+        assert!(vault.build_dir().join("chapter1/chapter1.1.html").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_mirror_the_source_tree_in_the_build_dir() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::create_dir_all(vault.src_dir().join("part1/chapter1"))?;
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Chapter 1](./part1/chapter1/index.md)\n    - [Chapter 1.1](./part1/chapter1/setup.md)",
+        )?;
+        fs::write(
+            vault.src_dir().join("part1/chapter1/index.md"),
+            "![diagram](images/diagram.png)",
+        )?;
+        fs::write(vault.src_dir().join("part1/chapter1/setup.md"), "")?;
+        vault.build()?;
+
+        let index = fs::read_to_string(vault.build_dir().join("part1/chapter1/index.html"))?;
+
+        assert!(index.contains("src=\"/part1/chapter1/images/diagram.png\""));
+        assert!(vault.build_dir().join("part1/chapter1/setup.html").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_generate_a_summary_from_the_file_tree() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+        let path = vault.generate_summary(false)?;
+
+        assert_eq!(fs::read_to_string(&path)?, "- [Chapter1](./chapter1.md)\n");
+        assert!(vault.generate_summary(false).is_err());
+
+        fs::write(vault.src_dir().join("chapter2.md"), "# General Kenobi")?;
+        vault.generate_summary(true)?;
+
+        assert_eq!(Content::new(vault.src_dir())?.chapters().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_find_the_chapters_with_the_summary_strategy() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        vault.config.summary.strategy = Strategy::File;
+        assert!(vault.content().is_err());
+
+        fs::write(vault.src_dir().join("summary.md"), "- [Intro](./intro.md)")?;
+        fs::write(vault.src_dir().join("intro.md"), "# Intro\n\n## Setup")?;
+        fs::write(vault.src_dir().join("usage.md"), "# Usage")?;
+        assert_eq!(vault.content()?.all_chapters().len(), 1);
+
+        vault.config.summary.strategy = Strategy::Tree;
+        assert_eq!(vault.content()?.all_chapters().len(), 2);
+
+        vault.config.summary.strategy = Strategy::Merged;
+        let content = vault.content()?;
+        assert_eq!(content.all_chapters().len(), 2);
+        assert_eq!(content.sections()[0].title, UNSORTED_SECTION);
+
+        vault.config.summary.strategy = Strategy::SingleFile;
+        assert!(vault.content().is_err());
+        vault.config.summary.single_file = Some(PathBuf::from("intro.md"));
+        assert_eq!(vault.content()?.chapters()[0].subchapters.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_link_to_the_required_chapters() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::create_dir_all(vault.src_dir().join("guide"))?;
+        fs::create_dir_all(vault.src_dir().join("reference"))?;
+        fs::write(vault.src_dir().join("guide/index.md"), "# Guide")?;
+        fs::write(
+            vault.src_dir().join("guide/01-installing.md"),
+            "# Installing",
+        )?;
+        fs::write(vault.src_dir().join("guide/02-setup.md"), "# Setup")?;
+        fs::write(vault.src_dir().join("reference/index.md"), "# Reference")?;
+        fs::write(vault.src_dir().join("reference/setup.md"), "# Setup")?;
+        fs::write(
+            vault.src_dir().join("guide/03-usage.md"),
+            "---\nrequires: [installing, guide/setup, setup]\n---\n# Usage",
+        )?;
+        vault.build()?;
+
+        let usage = fs::read_to_string(vault.build_dir().join("guide/usage.html"))?;
+        let usage = usage
+            .split_once("<aside class=\"prerequisites\" aria-label=\"Before you read this\">")
+            .and_then(|(_, aside)| aside.split_once("</aside>"))
+            .map(|(aside, _)| aside)
+            .unwrap_or_default();
+        assert!(usage.contains("Before you read this"));
+        assert!(usage.contains("<li><a href=\"/guide/installing.html\">Installing</a></li>"));
+        assert!(usage.contains("<li><a href=\"/guide/setup.html\">Setup</a></li>"));
+        assert!(!usage.contains("/reference/setup.html"));
+
+        vault.config.markdown.strict_requires = true;
+        assert!(vault.build().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_prefix_the_urls_with_the_base_path_of_the_config() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.general.base_path = "docs/".to_string();
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+        let mut vault = Vault::from_disk(temp_dir.path())?;
+        vault.build()?;
+
+        let page = fs::read_to_string(vault.build_dir().join("chapter1.html"))?;
+        assert_eq!(vault.base_path, "/docs");
+        assert!(page.contains("href=\"/docs/main.css\""));
+        assert!(page.contains("<script src=\"/docs/index.js\"></script>"));
+        assert!(page.contains("href=\"/docs/chapter1.html\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_put_the_head_include_in_every_page() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.appearance.head_include = Some(PathBuf::from("head.html"));
+        vault.init()?;
+
+        let tag = "<meta name=\"google-site-verification\" content=\"kenobi\" />";
+        fs::write(vault.path.join("head.html"), tag)?;
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+        vault.build()?;
+
+        for page in ["chapter1.html", "sitemap.html", "404.html"] {
+            let html = fs::read_to_string(vault.build_dir().join(page))?;
+            let (head, _) = html.split_once("</head>").unwrap_or_default();
+            assert!(head.contains(tag));
+        }
+
+        // A new tag means new pages, even if no chapter changed
+        fs::write(
+            vault.path.join("head.html"),
+            "<meta name=\"robots\" content=\"none\" />",
+        )?;
+        vault.build()?;
+        assert!(fs::read_to_string(vault.build_dir().join("chapter1.html"))?
+            .contains("content=\"none\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_build_the_blocks_of_the_audience() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.search.enabled = true;
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("deploy.md"),
+            "# Deploy\n\n::: only internal\n## Keys\n\nAsk Obi for the keys\n:::\n\n::: only public\nOpen a ticket\n:::\n",
+        )?;
+        vault.audience = Some(String::from("public"));
+        vault.build()?;
+
+        let page = fs::read_to_string(vault.build_dir().join("deploy.html"))?;
+        let search_index = fs::read_to_string(vault.build_dir().join(SEARCH_INDEX_FILE))?;
+        let manifest = Manifest::from_disk(vault.build_dir())?.unwrap_or_default();
+        assert!(page.contains("Open a ticket") && !page.contains("Ask Obi"));
+        assert!(!search_index.contains("Ask Obi"));
+        assert_eq!(manifest.chapters[0].anchors, vec!["deploy"]);
+
+        vault.audience = Some(String::from("internal"));
+        vault.build()?;
+
+        let page = fs::read_to_string(vault.build_dir().join("deploy.html"))?;
+        assert!(page.contains("Ask Obi") && !page.contains("Open a ticket"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_put_the_footer_of_the_config_in_every_page() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.footer = Footer {
+            copyright: Some(String::from("© Obi & Anakin")),
+            links: vec![Link {
+                name: String::from("Source"),
+                url: String::from("https://github.com/marcos-brito/cahlter"),
+                icon: Some(String::from("brand-github")),
+            }],
+            html: Some(PathBuf::from("footer.html")),
+        };
+        vault.init()?;
+
+        fs::write(vault.path.join("footer.html"), "<p>Hello there</p>")?;
+        fs::write(vault.src_dir().join("chapter1.md"), "# General Kenobi")?;
+        vault.build()?;
+
+        for page in ["chapter1.html", "sitemap.html", "404.html"] {
+            let html = fs::read_to_string(vault.build_dir().join(page))?;
+            let (_, footer) = html.split_once("<footer").unwrap_or_default();
+            assert!(footer.contains("© Obi &amp; Anakin"));
+            assert!(footer.contains(
+                "<a class=\"footer__link\" href=\"https://github.com/marcos-brito/cahlter\">\
+                <i class=\"ti ti-brand-github\"></i> Source"
+            ));
+            assert!(footer.contains("<p>Hello there</p>"));
+        }
 
         Ok(())
     }
@@ -280,6 +1451,7 @@ mod test {
 
         assert!(vault.build_dir().join("chapter1.html").exists());
         assert!(!vault.build_dir().join("main.css").exists());
+        assert!(!vault.build_dir().join("mermaid.min.js").exists());
         assert!(vault.build_dir().join("custom1.css").exists());
         assert!(vault.build_dir().join("custom2.css").exists());
         assert!(vault.build_dir().join("custom3.css").exists());
@@ -0,0 +1,348 @@
+use super::Vault;
+use crate::summary::{ORDER_FILE_NAMES, SECTION_FILE_NAME, SUMMARY_FILE_NAMES};
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+impl Vault {
+    /// Copies (or links) every asset in the src dir to the same place in the build dir, so
+    /// things like `![diagram](images/diagram.png)` keep working after the build. Assets are
+    /// copied in parallel. Returns how many assets were copied.
+    pub fn copy_assets(&self) -> Result<usize> {
+        let mut assets = Vec::new();
+        self.find_assets(&self.src_dir(), &mut assets)?;
+
+        let workers = thread::available_parallelism()
+            .map(|workers| workers.get())
+            .unwrap_or(1)
+            .min(assets.len());
+        let next = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| -> Result<()> {
+                        // Each worker takes the next asset nobody took yet
+                        while let Some(path) = assets.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            self.place_asset(path)?;
+                        }
+
+                        Ok(())
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().expect("An asset worker panicked"))
+        })?;
+
+        Ok(assets.len())
+    }
+
+    /// Copies everything in the static dir to the root of the build dir, as it is. Returns how
+    /// many files were copied.
+    pub fn copy_static(&self) -> Result<usize> {
+        if !self.static_dir().exists() {
+            return Ok(0);
+        }
+
+        let copied = util::copy_dir(self.static_dir(), self.build_dir(), |_| false, |_| {})
+            .with_context(|| anyhow!("Failed to copy {}", self.static_dir().display()))?;
+
+        Ok(copied.files)
+    }
+
+    /// Removes the hard links the last build placed in the build dir, before anything is written
+    /// there, so the pages and files generated at the same paths don't go through them into the
+    /// src dir. The assets are linked again by [`Vault::copy_assets`].
+    pub(super) fn unlink_assets(&self) -> Result<()> {
+        if !self.config.assets.hard_links || self.config.assets.symlink {
+            return Ok(());
+        }
+
+        let mut assets = Vec::new();
+        self.find_assets(&self.src_dir(), &mut assets)?;
+
+        for path in assets {
+            let destination = self.build_dir().join(path.strip_prefix(self.src_dir())?);
+            util::remove_file_if_exists(&destination)
+                .with_context(|| anyhow!("Failed to remove {}", destination.display()))?;
+        }
+
+        Ok(())
+    }
+
+    fn find_assets(&self, dir: &Path, assets: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in dir
+            .read_dir()
+            .with_context(|| anyhow!("Failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+
+            // The build dir may live inside the src dir
+            if path == self.build_dir() || self.is_ignored_asset(&path) {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.find_assets(&path, assets)?;
+            } else if self.is_asset(&path) {
+                assets.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn place_asset(&self, path: &Path) -> Result<()> {
+        let relative = path.strip_prefix(self.src_dir())?;
+        let destination = self.build_dir().join(relative);
+
+        if let Some(parent) = destination.parent() {
+            util::create_dir_if_not_exists(parent)?;
+        }
+
+        self.copy_asset(path, &destination)
+            .with_context(|| anyhow!("Failed to copy {}", path.display()))
+    }
+
+    fn copy_asset(&self, source: &Path, destination: &Path) -> Result<()> {
+        // Symlinks can't be overwritten, and copying through one (or a hard link) would change
+        // the source
+        util::remove_file_if_exists(destination)?;
+
+        if !self.config.assets.symlink {
+            // Hard links only work within the same filesystem. Anywhere else it's a plain copy.
+            if self.config.assets.hard_links && fs::hard_link(source, destination).is_ok() {
+                return Ok(());
+            }
+
+            fs::copy(source, destination)?;
+            return Ok(());
+        }
+
+        let source = fs::canonicalize(source)?;
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(source, destination)?;
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_file(source, destination)?;
+
+        Ok(())
+    }
+
+    // Chapters and summaries are rendered, not copied. Order and section files are only read.
+    fn is_asset(&self, path: &Path) -> bool {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        if extension == "md"
+            || SUMMARY_FILE_NAMES.contains(&file_name.as_ref())
+            || ORDER_FILE_NAMES.contains(&file_name.as_ref())
+            || file_name == SECTION_FILE_NAME
+        {
+            return false;
+        }
+
+        let extensions = &self.config.assets.extensions;
+
+        extensions.is_empty()
+            || extensions
+                .iter()
+                .any(|wanted| wanted.trim_start_matches('.').to_lowercase() == extension)
+    }
+
+    fn is_ignored_asset(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(self.src_dir()) else {
+            return false;
+        };
+
+        util::is_ignored(&self.config.assets.ignore, relative)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_copy_the_static_dir_to_the_build_root() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.config.general.site_url = Some("https://example.com".to_string());
+
+        fs::create_dir_all(vault.static_dir().join("downloads"))?;
+        fs::write(vault.static_dir().join("CNAME"), "example.com")?;
+        fs::write(vault.static_dir().join("robots.txt"), "User-agent: *")?;
+        fs::write(
+            vault.static_dir().join("downloads/notes.md"),
+            "# Not a chapter",
+        )?;
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+
+        assert_eq!(vault.build()?.assets, 3);
+        assert_eq!(
+            fs::read_to_string(vault.build_dir().join("CNAME"))?,
+            "example.com"
+        );
+        assert_eq!(
+            fs::read_to_string(vault.build_dir().join("robots.txt"))?,
+            "User-agent: *"
+        );
+        assert!(vault.build_dir().join("downloads/notes.md").exists());
+        assert!(!vault.build_dir().join("downloads/notes.html").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_copy_the_assets_to_the_build_dir() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.assets.ignore = vec!["drafts/*".to_string()];
+        vault.init()?;
+
+        fs::create_dir_all(vault.src_dir().join("images/nested"))?;
+        fs::create_dir(vault.src_dir().join("drafts"))?;
+        fs::write(vault.src_dir().join("images/diagram.png"), "png")?;
+        fs::write(vault.src_dir().join("images/nested/slides.pdf"), "pdf")?;
+        fs::write(vault.src_dir().join("drafts/secret.png"), "png")?;
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Chapter 1](./chapter1.md)",
+        )?;
+
+        assert_eq!(vault.copy_assets()?, 2);
+        assert_eq!(
+            fs::read_to_string(vault.build_dir().join("images/diagram.png"))?,
+            "png"
+        );
+        assert!(vault.build_dir().join("images/nested/slides.pdf").exists());
+        assert!(!vault.build_dir().join("drafts/secret.png").exists());
+        assert!(!vault.build_dir().join("chapter1.md").exists());
+        assert!(!vault.build_dir().join("summary.md").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_copy_the_configured_extensions() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.assets.extensions = vec!["png".to_string()];
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("diagram.PNG"), "png")?;
+        fs::write(vault.src_dir().join("notes.txt"), "txt")?;
+
+        assert_eq!(vault.copy_assets()?, 1);
+        assert!(vault.build_dir().join("diagram.PNG").exists());
+        assert!(!vault.build_dir().join("notes.txt").exists());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_should_hard_link_the_assets() -> Result<(), Box<dyn Error>> {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.assets.hard_links = true;
+        vault.init()?;
+
+        for i in 0..32 {
+            fs::write(vault.src_dir().join(format!("image{i}.png")), "png")?;
+        }
+
+        assert_eq!(vault.copy_assets()?, 32);
+        // Rebuilds replace the links
+        assert_eq!(vault.copy_assets()?, 32);
+
+        let source = fs::metadata(vault.src_dir().join("image7.png"))?;
+        let destination = fs::metadata(vault.build_dir().join("image7.png"))?;
+        assert_eq!(source.ino(), destination.ino());
+
+        vault.config.assets.hard_links = false;
+        vault.copy_assets()?;
+
+        let destination = fs::metadata(vault.build_dir().join("image7.png"))?;
+        assert_ne!(source.ino(), destination.ino());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_leave_hard_linked_assets_alone_when_building() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.assets.hard_links = true;
+        vault.config.general.site_url = Some("https://example.com".to_string());
+        vault.config.appearance.custom = vec!["src/custom.css".to_string()];
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("main.css"), "body { color: red; }")?;
+        fs::write(vault.src_dir().join("custom.css"), "h1 { color: blue; }")?;
+        fs::write(vault.src_dir().join("robots.txt"), "User-agent: *")?;
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+
+        // Twice, since the second build finds the links of the first one in the build dir
+        vault.build()?;
+        vault.build()?;
+
+        assert_eq!(
+            fs::read_to_string(vault.src_dir().join("main.css"))?,
+            "body { color: red; }"
+        );
+        assert_eq!(
+            fs::read_to_string(vault.src_dir().join("custom.css"))?,
+            "h1 { color: blue; }"
+        );
+        assert_eq!(
+            fs::read_to_string(vault.src_dir().join("robots.txt"))?,
+            "User-agent: *"
+        );
+        assert_ne!(
+            fs::read_to_string(vault.build_dir().join("main.css"))?,
+            "body { color: red; }"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_should_symlink_the_assets() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.assets.symlink = true;
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("diagram.png"), "png")?;
+
+        // Twice, since rebuilds shouldn't fail because the link is already there
+        vault.copy_assets()?;
+        vault.copy_assets()?;
+
+        let destination = vault.build_dir().join("diagram.png");
+        assert!(destination.symlink_metadata()?.file_type().is_symlink());
+        assert_eq!(fs::read_to_string(destination)?, "png");
+
+        Ok(())
+    }
+}
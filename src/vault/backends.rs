@@ -0,0 +1,81 @@
+use super::{Content, Vault};
+use crate::renderer::outputs;
+use crate::FrontMatter;
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// The page with every chapter for printing
+pub const PRINT_BACKEND: &str = "print";
+
+/// The book of `export epub`
+pub const EPUB_BACKEND: &str = "epub";
+
+/// The single page of `export html`
+pub const HTML_BACKEND: &str = "html";
+
+/// Is it something chapters can leave themselves out of with `exclude_from`? Besides the print
+/// page, the book and the single page, that's every output format (e.g. `pdf`). The site itself
+/// isn't one.
+pub fn is_backend(name: &str) -> bool {
+    [PRINT_BACKEND, EPUB_BACKEND, HTML_BACKEND].contains(&name) || outputs::find(name).is_some()
+}
+
+impl Vault {
+    /// The chapters that go into the backend, leaving out the ones whose front matter excludes
+    /// it, along with their subchapters
+    pub(super) fn content_for(&self, mut content: Content, backend: &str) -> Result<Content> {
+        let mut excluded = HashSet::new();
+
+        for chapter in content.pages() {
+            if chapter.content.exists()
+                && FrontMatter::from_disk(&chapter.content)?.excludes(backend)
+            {
+                excluded.insert(chapter.content.clone());
+            }
+        }
+        content.retain(|chapter| !excluded.contains(&chapter.content));
+
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::PRINT_FILE;
+    use crate::vault::EPUB_FILE;
+    use std::error::Error;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_leave_the_chapters_out_of_the_backends_they_exclude() -> Result<(), Box<dyn Error>>
+    {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("01_intro.md"),
+            "# Intro\n\nHello there",
+        )?;
+        fs::write(
+            vault.src_dir().join("02_playground.md"),
+            "---\nexclude_from: [epub, print, pdf]\noutputs: [pdf, md]\n---\n# Playground\n\nGeneral Kenobi",
+        )?;
+        vault.build()?;
+        vault.export_epub(None)?;
+
+        assert!(vault.build_dir().join("playground.html").exists());
+        assert!(vault.build_dir().join("playground.md").exists());
+        assert!(!vault.build_dir().join("playground.pdf").exists());
+        let print = fs::read_to_string(vault.build_dir().join(PRINT_FILE))?;
+        assert!(print.contains("Hello there"));
+        assert!(!print.contains("General Kenobi"));
+        let epub = fs::read(vault.build_dir().join(EPUB_FILE))?;
+        assert!(!String::from_utf8_lossy(&epub).contains("Playground"));
+        assert!(is_backend("pdf") && is_backend("html") && !is_backend("web"));
+
+        Ok(())
+    }
+}
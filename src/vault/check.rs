@@ -0,0 +1,411 @@
+use super::manifest::Manifest;
+use super::{backends, Vault};
+use crate::renderer::{self, markdown, outputs};
+use crate::{Chapter, FrontMatter};
+use anyhow::Result;
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event, Parser, Tag};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Something wrong found while checking the vault
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// The file where the problem was found
+    pub path: PathBuf,
+    /// The line where the problem was found, if it's about a specific spot in the file
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new<P, S>(path: P, line: Option<usize>, message: S) -> Self
+    where
+        P: AsRef<Path>,
+        S: Into<String>,
+    {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl Problem {
+    /// The problem as a GitHub Actions workflow command, so it shows up next to the line in pull
+    /// requests. `prefix` is where the vault is inside the repository, since GitHub wants paths
+    /// relative to it.
+    pub fn github_annotation(&self, prefix: &Path) -> String {
+        let path = prefix.join(&self.path).to_string_lossy().replace('\\', "/");
+        let mut properties = format!("file={}", escape_property(&path));
+
+        if let Some(line) = self.line {
+            properties.push_str(&format!(",line={line}"));
+        }
+
+        format!("::warning {properties}::{}", escape_data(&self.message))
+    }
+}
+
+// Workflow commands end at new lines, so they have to be escaped, along with `%` itself
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+// Properties are also separated by commas and end at `::`
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl Vault {
+    /// Looks for anything that would make the build fail or produce a broken site: missing
+    /// chapters, chapters with the same title or output and broken relative links.
+    pub fn check(&self) -> Result<Vec<Problem>> {
+        let chapters = self.content()?.all_chapters();
+        let mut problems = Vec::new();
+
+        let mut titles: HashMap<&String, &Chapter> = HashMap::new();
+        let mut outputs: HashMap<PathBuf, &Chapter> = HashMap::new();
+
+        for chapter in chapters.iter() {
+            if !chapter.content.exists() {
+                problems.push(Problem::new(
+                    self.relative(&chapter.content),
+                    None,
+                    format!("\"{}\" points to a file that doesn't exist", chapter.title),
+                ));
+                continue;
+            }
+
+            if let Some(other) = titles.insert(&chapter.title, chapter) {
+                problems.push(Problem::new(
+                    self.relative(&chapter.content),
+                    None,
+                    format!(
+                        "\"{}\" has the same title as {}",
+                        chapter.title,
+                        self.relative(&other.content).display()
+                    ),
+                ));
+            }
+
+            let output = renderer::output_path(&chapter.content, self.src_dir())?;
+            if let Some(other) = outputs.insert(output.clone(), chapter) {
+                problems.push(Problem::new(
+                    self.relative(&chapter.content),
+                    None,
+                    format!(
+                        "{} would overwrite the output of {}",
+                        output.display(),
+                        self.relative(&other.content).display()
+                    ),
+                ));
+                continue;
+            }
+
+            problems.extend(self.check_links(chapter, &chapters)?);
+        }
+
+        Ok(problems)
+    }
+
+    /// Compares the anchors of the headings against the ones of the last build, finding the ones
+    /// that disappeared. Other pages may still link to them. Nothing is found if the vault was
+    /// never built.
+    pub fn check_anchors(&self) -> Result<Vec<Problem>> {
+        let Some(previous) = Manifest::from_disk(self.build_dir())? else {
+            return Ok(Vec::new());
+        };
+        let mut problems = Vec::new();
+
+        for built in previous.chapters.iter() {
+            let source = self.src_dir().join(&built.source);
+
+            // Pages that are gone are a different problem
+            if !source.exists() {
+                continue;
+            }
+
+            let anchors = self.anchors(&source)?;
+            let removed: Vec<&String> = built
+                .anchors
+                .iter()
+                .filter(|anchor| !anchors.contains(anchor))
+                .collect();
+            let added: Vec<&String> = anchors
+                .iter()
+                .filter(|anchor| !built.anchors.contains(anchor))
+                .collect();
+
+            for (i, anchor) in removed.iter().enumerate() {
+                let page = built.output.display();
+                // The same number of anchors came and went, so they were most likely renamed
+                let message = match added.get(i).filter(|_| added.len() == removed.len()) {
+                    Some(new) => format!(
+                        "#{anchor} is gone since the last build. Was it renamed? Add a redirect from {page}#{anchor} to {page}#{new}"
+                    ),
+                    None => format!(
+                        "#{anchor} is gone since the last build. Links to {page}#{anchor} won't find it"
+                    ),
+                };
+
+                problems.push(Problem::new(self.relative(&source), None, message));
+            }
+        }
+
+        Ok(problems)
+    }
+
+    fn check_links(&self, chapter: &Chapter, chapters: &[Chapter]) -> Result<Vec<Problem>> {
+        let markdown = fs::read_to_string(&chapter.content)?;
+        let dir = chapter.content.parent().unwrap_or(Path::new(""));
+        let mut problems = Vec::new();
+
+        let body = match FrontMatter::extract(&markdown) {
+            Ok((front_matter, body)) => {
+                for name in front_matter.outputs.iter() {
+                    if name != outputs::PAGE_OUTPUT && outputs::find(name).is_none() {
+                        problems.push(Problem::new(
+                            self.relative(&chapter.content),
+                            None,
+                            format!("Unknown output format {name}"),
+                        ));
+                    }
+                }
+
+                for name in front_matter.exclude_from.iter() {
+                    if !backends::is_backend(name) {
+                        problems.push(Problem::new(
+                            self.relative(&chapter.content),
+                            None,
+                            format!("Unknown backend {name} in exclude_from"),
+                        ));
+                    }
+                }
+
+                for requirement in front_matter.requires.iter() {
+                    let message = match renderer::required_chapters(
+                        chapters,
+                        self.src_dir(),
+                        requirement,
+                    )?
+                    .len()
+                    {
+                        1 => continue,
+                        0 => format!("The required chapter {requirement} isn't in the summary"),
+                        _ => format!(
+                            "The required chapter {requirement} could be more than one. Use its whole path"
+                        ),
+                    };
+
+                    problems.push(Problem::new(self.relative(&chapter.content), None, message));
+                }
+
+                body
+            }
+            Err(e) => {
+                problems.push(Problem::new(
+                    self.relative(&chapter.content),
+                    Some(1),
+                    format!("{e}: {}", e.root_cause()),
+                ));
+                return Ok(problems);
+            }
+        };
+        // The offsets are relative to the body, but the lines should match the whole file
+        let front_matter_len = markdown.len() - body.len();
+
+        let options = markdown::options(&self.config.markdown.extensions);
+
+        for (event, range) in Parser::new_ext(body, options).into_offset_iter() {
+            let url = match event {
+                Event::Start(Tag::Link(_, url, _)) | Event::Start(Tag::Image(_, url, _)) => url,
+                _ => continue,
+            };
+
+            if !markdown::is_relative_url(&url) {
+                continue;
+            }
+
+            // The file of `my%20notes.md` is `my notes.md`
+            let path = url.split(['#', '?']).next().unwrap_or("");
+            let path = percent_decode_str(path).decode_utf8_lossy();
+            if !path.is_empty() && !dir.join(path.as_ref()).exists() {
+                problems.push(Problem::new(
+                    self.relative(&chapter.content),
+                    Some(
+                        markdown[..front_matter_len + range.start]
+                            .matches('\n')
+                            .count()
+                            + 1,
+                    ),
+                    format!("Broken link to {url}"),
+                ));
+            }
+        }
+
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_format_problems_as_github_annotations() {
+        let problem = Problem::new("src/a,b.md", Some(3), "Broken link to 100%\nfake.md");
+
+        assert_eq!(
+            problem.github_annotation(Path::new("docs")),
+            "::warning file=docs/src/a%2Cb.md,line=3::Broken link to 100%25%0Afake.md"
+        );
+        assert_eq!(
+            Problem::new("src/intro.md", None, "Oops").github_annotation(Path::new("")),
+            "::warning file=src/intro.md::Oops"
+        );
+    }
+
+    #[test]
+    fn it_should_not_find_problems_in_a_healthy_vault() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::create_dir(vault.src_dir().join("images"))?;
+        fs::write(vault.src_dir().join("images/diagram.png"), "")?;
+        fs::write(vault.src_dir().join("images/old diagram.png"), "")?;
+        fs::write(
+            vault.src_dir().join("chapter1.md"),
+            "![diagram](images/diagram.png) [next](chapter2.md#intro) [top](#top) ![old](images/old%20diagram.png)",
+        )?;
+        fs::write(
+            vault.src_dir().join("chapter2.md"),
+            "[web](https://example.com)",
+        )?;
+
+        assert_eq!(vault.check()?, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_find_problems() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            r#"
+- [Chapter 1](./chapter1.md)
+- [Chapter 1](./chapter2.md)
+- [Chapter 3](./chapter3.md)
+- [Chapter 4](./chapter1.md)
+"#,
+        )?;
+        fs::write(
+            vault.src_dir().join("chapter1.md"),
+            "---\nowner: Obi-Wan\n---\n# Chapter 1\n\n![diagram](images/diagram.png)",
+        )?;
+        fs::write(
+            vault.src_dir().join("chapter2.md"),
+            "---\noutputs: [pdf, docx]\nexclude_from: [web]\nrequires: [chapter1, chapter9]\n---\n",
+        )?;
+
+        let expected = vec![
+            Problem::new(
+                "src/chapter1.md",
+                Some(6),
+                "Broken link to images/diagram.png",
+            ),
+            Problem::new(
+                "src/chapter2.md",
+                None,
+                "\"Chapter 1\" has the same title as src/chapter1.md",
+            ),
+            Problem::new("src/chapter2.md", None, "Unknown output format docx"),
+            Problem::new(
+                "src/chapter2.md",
+                None,
+                "Unknown backend web in exclude_from",
+            ),
+            Problem::new(
+                "src/chapter2.md",
+                None,
+                "The required chapter chapter9 isn't in the summary",
+            ),
+            Problem::new(
+                "src/chapter3.md",
+                None,
+                "\"Chapter 3\" points to a file that doesn't exist",
+            ),
+            Problem::new(
+                "src/chapter1.md",
+                None,
+                "chapter1.html would overwrite the output of src/chapter1.md",
+            ),
+        ];
+
+        assert_eq!(vault.check()?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_find_the_anchors_that_disappeared() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("chapter1.md"),
+            "# Hello there\n\n## Usage",
+        )?;
+        fs::write(vault.src_dir().join("chapter2.md"), "# Install\n\n## Cli")?;
+        assert!(vault.check_anchors()?.is_empty());
+
+        vault.build()?;
+        fs::write(
+            vault.src_dir().join("chapter1.md"),
+            "# Hello there\n\n## How to use it",
+        )?;
+        fs::write(vault.src_dir().join("chapter2.md"), "# Install")?;
+
+        let expected = vec![
+            Problem::new(
+                "src/chapter1.md",
+                None,
+                "#usage is gone since the last build. Was it renamed? Add a redirect from chapter1.html#usage to chapter1.html#how-to-use-it",
+            ),
+            Problem::new(
+                "src/chapter2.md",
+                None,
+                "#cli is gone since the last build. Links to chapter2.html#cli won't find it",
+            ),
+        ];
+
+        assert_eq!(vault.check_anchors()?, expected);
+
+        Ok(())
+    }
+}
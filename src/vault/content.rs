@@ -1,21 +1,41 @@
 mod chapter;
+mod front_matter;
 mod section;
 
-use crate::summary::{FileTreeSummarizer, Summarizer, Summary, SummaryFileSummarizer};
-use anyhow::Result;
+use crate::config::{Config, Strategy};
+use crate::summary::{
+    FileTreeSummarizer, MergedSummarizer, SingleFileSummarizer, Summarizer, Summary,
+    SummaryFileSummarizer, SUMMARY_FILE_NAMES,
+};
+use crate::util;
+use anyhow::{anyhow, Result};
 pub use chapter::Chapter;
+pub use front_matter::FrontMatter;
 pub use section::Section;
+use serde::Serialize;
+use std::collections::HashSet;
 use std::convert::From;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-const SUMMARY_FILE_NAMES: [&str; 3] = ["summary.md", "SUMMARY.MD", "Summary.md"];
-
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum Item {
     Chapter(Chapter),
     Section(Section),
 }
 
+impl Item {
+    /// The chapter itself, or the chapters of the section
+    pub fn chapters(&self) -> &[Chapter] {
+        match self {
+            Item::Chapter(chapter) => std::slice::from_ref(chapter),
+            Item::Section(section) => &section.chapters,
+        }
+    }
+}
+
 impl From<Chapter> for Item {
     fn from(chapter: Chapter) -> Self {
         Item::Chapter(chapter)
@@ -38,23 +58,162 @@ impl Content {
     where
         P: AsRef<Path>,
     {
-        let summary = Content::create_summary(path)?;
+        Content::from_config(path, &Config::default())
+    }
+
+    /// Like [`Content::new`], but the summary comes from where the config says (see
+    /// [`Strategy`])
+    pub fn from_config<P>(path: P, config: &Config) -> Result<Content>
+    where
+        P: AsRef<Path>,
+    {
+        let summary = Content::create_summary(path.as_ref(), config)?;
 
         Ok(Content { summary })
     }
 
-    // Just iterate over the summary and filter
+    /// The top level chapters, including the ones inside sections
     pub fn chapters(&self) -> Vec<Chapter> {
         self.summary
             .items
             .iter()
-            .filter_map(|item| match item {
-                Item::Chapter(chapter) => Some(chapter.clone()),
-                _ => None,
+            .flat_map(|item| item.chapters().to_vec())
+            .collect()
+    }
+
+    /// All the chapters and their subchapters in reading order. Drafts and external chapters are
+    /// left out, since there is nothing to build for them, but not their subchapters. Chapters
+    /// pointing to a part of a page (e.g. `./setup.md#install`) only show up if nothing else
+    /// points to the whole page, so it's built once.
+    pub fn all_chapters(&self) -> Vec<Chapter> {
+        self.pages().into_iter().cloned().collect()
+    }
+
+    /// Like [`Content::all_chapters`], but borrowed from the summary
+    pub fn pages(&self) -> Vec<&Chapter> {
+        let chapters: Vec<&Chapter> = self
+            .iter()
+            .filter(|chapter| chapter.has_content())
+            .collect();
+        let whole: HashSet<&PathBuf> = chapters
+            .iter()
+            .filter(|chapter| chapter.anchor.is_none())
+            .map(|chapter| &chapter.content)
+            .collect();
+        let mut seen = HashSet::new();
+
+        chapters
+            .into_iter()
+            .filter(|chapter| {
+                chapter.anchor.is_none()
+                    || (!whole.contains(&chapter.content) && seen.insert(&chapter.content))
             })
             .collect()
     }
 
+    /// Every chapter of the summary, drafts and external ones included, with the subchapters
+    /// right after their parent
+    pub fn iter(&self) -> impl Iterator<Item = &Chapter> {
+        let mut stack: Vec<&Chapter> = self.summary.items.iter().flat_map(Item::chapters).collect();
+        stack.reverse();
+
+        std::iter::from_fn(move || {
+            let chapter = stack.pop()?;
+            stack.extend(chapter.children().into_iter().rev());
+
+            Some(chapter)
+        })
+    }
+
+    /// The chapter whose content is the file. One pointing to the whole page wins over the ones
+    /// pointing to parts of it.
+    pub fn find_by_path<P>(&self, path: P) -> Option<&Chapter>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut chapters = self
+            .iter()
+            .filter(|chapter| chapter.has_content() && chapter.content == path);
+        let first = chapters.next()?;
+
+        match first.anchor {
+            Some(_) => chapters
+                .find(|chapter| chapter.anchor.is_none())
+                .or(Some(first)),
+            None => Some(first),
+        }
+    }
+
+    /// The chapter with the number (e.g. `2.1`). Only enumerated vaults number their chapters.
+    pub fn find_by_number(&self, number: &str) -> Option<&Chapter> {
+        self.iter()
+            .find(|chapter| !chapter.number.is_empty() && chapter.number == number)
+    }
+
+    /// The chapter whose page, without the extension, is the slug (e.g. `guide/setup` for
+    /// `src/02_guide/setup.md`)
+    pub fn find_by_slug<P>(&self, src_dir: P, slug: &str) -> Option<&Chapter>
+    where
+        P: AsRef<Path>,
+    {
+        let slug = Path::new(slug.trim_matches('/')).with_extension("");
+
+        self.pages().into_iter().find(|chapter| {
+            chapter
+                .content
+                .strip_prefix(&src_dir)
+                .is_ok_and(|path| util::strip_number_prefixes(path).with_extension("") == slug)
+        })
+    }
+
+    /// The chapter the chapter is a subchapter of
+    pub fn parent(&self, chapter: &Chapter) -> Option<&Chapter> {
+        self.iter().find(|parent| {
+            parent
+                .children()
+                .into_iter()
+                .any(|subchapter| subchapter.is(chapter))
+        })
+    }
+
+    /// The chapters the chapter is under, from the top level one down to its parent (e.g. for
+    /// breadcrumbs)
+    pub fn ancestors(&self, chapter: &Chapter) -> Vec<&Chapter> {
+        let mut ancestors = Vec::new();
+        let mut current = self.parent(chapter);
+
+        while let Some(parent) = current {
+            ancestors.push(parent);
+            current = self.parent(parent);
+        }
+        ancestors.reverse();
+
+        ancestors
+    }
+
+    /// The section the chapter is in, at any depth. With sections inside of chapters, it's the
+    /// innermost one.
+    pub fn section_of(&self, chapter: &Chapter) -> Option<&Section> {
+        section_in(&self.summary.items, chapter)
+    }
+
+    /// The page read before the chapter, following [`Content::pages`]
+    pub fn previous(&self, chapter: &Chapter) -> Option<&Chapter> {
+        let pages = self.pages();
+        let position = pages.iter().position(|page| page.is(chapter))?;
+
+        position.checked_sub(1).map(|previous| pages[previous])
+    }
+
+    /// The page read after the chapter, following [`Content::pages`]
+    pub fn next(&self, chapter: &Chapter) -> Option<&Chapter> {
+        let pages = self.pages();
+        let position = pages.iter().position(|page| page.is(chapter))?;
+
+        pages.get(position + 1).copied()
+    }
+
     // Just iterate over the summary and filter
     pub fn sections(&self) -> Vec<Section> {
         self.summary
@@ -67,18 +226,287 @@ impl Content {
             .collect()
     }
 
-    fn create_summary<P>(path: P) -> Result<Summary>
+    /// Leaves out the chapters `keep` says no to, along with their subchapters, and numbers the
+    /// others again so there are no gaps where they were. Sections left empty go too.
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: Fn(&Chapter) -> bool,
+    {
+        let mut next = Some(String::from("1"));
+
+        retain_items(&mut self.summary.items, &mut next, &keep);
+    }
+
+    /// Changes whenever the summary would. That's the modification time of the summary file or,
+    /// without one, of every directory and file, since adding, removing or renaming a file
+    /// changes the modification time of its directory and the front matter of a chapter (e.g. its
+    /// title or weight) changes its place in the summary.
+    pub fn fingerprint<P>(path: P, config: &Config) -> Result<Vec<(PathBuf, SystemTime)>>
     where
         P: AsRef<Path>,
     {
-        let path = path.as_ref().to_path_buf();
+        let path = path.as_ref();
+        let strategy = config.summary.strategy;
+        let mut fingerprint = Vec::new();
+
+        if strategy == Strategy::SingleFile {
+            let file = Content::single_file(path, config)?;
+            if file.exists() {
+                fingerprint.push((file.clone(), fs::metadata(&file)?.modified()?));
+            }
+
+            return Ok(fingerprint);
+        }
+
+        if let Some(file) = Content::summary_file(path).filter(|_| strategy != Strategy::Tree) {
+            fingerprint.push((file.clone(), fs::metadata(&file)?.modified()?));
+
+            if strategy != Strategy::Merged {
+                return Ok(fingerprint);
+            }
+        }
+
+        Content::dir_fingerprint(path, &mut fingerprint)?;
+
+        Ok(fingerprint)
+    }
+
+    fn dir_fingerprint(path: &Path, fingerprint: &mut Vec<(PathBuf, SystemTime)>) -> Result<()> {
+        fingerprint.push((path.to_path_buf(), fs::metadata(path)?.modified()?));
+
+        // Editing a file doesn't change the modification time of its directory
+        for entry in path.read_dir()? {
+            let entry = entry?;
+
+            if entry.file_type()?.is_dir() {
+                Content::dir_fingerprint(&entry.path(), fingerprint)?;
+            } else {
+                fingerprint.push((entry.path(), entry.metadata()?.modified()?));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_summary(path: &Path, config: &Config) -> Result<Summary> {
+        match (config.summary.strategy, Content::summary_file(path)) {
+            (Strategy::Auto | Strategy::File, Some(file)) => {
+                SummaryFileSummarizer::new(file).summarize()
+            }
+            (Strategy::File, None) => Err(anyhow!(
+                "summary.strategy is file, but there is no summary file in {}",
+                path.display()
+            )),
+            (Strategy::SingleFile, _) => {
+                SingleFileSummarizer::new(Content::single_file(path, config)?)
+                    .with_slug(config.markdown.slug)
+                    .summarize()
+            }
+            (Strategy::Merged, Some(file)) => MergedSummarizer::new(
+                SummaryFileSummarizer::new(file),
+                FileTreeSummarizer::new(path),
+            )
+            .summarize(),
+            // Without a summary file, there's nothing else to merge
+            (Strategy::Auto | Strategy::Tree | Strategy::Merged, _) => {
+                FileTreeSummarizer::new(path).summarize()
+            }
+        }
+    }
+
+    fn summary_file(path: &Path) -> Option<PathBuf> {
+        SUMMARY_FILE_NAMES
+            .iter()
+            .map(|name| path.join(name))
+            .find(|file| file.exists())
+    }
+
+    fn single_file(path: &Path, config: &Config) -> Result<PathBuf> {
+        match &config.summary.single_file {
+            Some(file) => Ok(path.join(file)),
+            None => Err(anyhow!(
+                "summary.strategy is single-file, but summary.single_file isn't set"
+            )),
+        }
+    }
+}
+
+// The innermost section among the items holding the chapter
+fn section_in<'a>(items: &'a [Item], chapter: &Chapter) -> Option<&'a Section> {
+    items.iter().find_map(|item| {
+        let nested = item
+            .chapters()
+            .iter()
+            .find_map(|parent| section_in(&parent.subchapters, chapter));
+
+        match item {
+            Item::Section(section) if nested.is_none() => section
+                .chapters
+                .iter()
+                .any(|top| top.flatten().iter().any(|other| other.is(chapter)))
+                .then_some(section),
+            _ => nested,
+        }
+    })
+}
 
-        for name in SUMMARY_FILE_NAMES {
-            if path.join(name).exists() {
-                return Ok(SummaryFileSummarizer::new(path.join(name)).summarize()?);
+// Chapters with a number are numbered from `next` on, and their subchapters from the first one
+// under them. Sections left empty go too.
+fn retain_items<F>(items: &mut Vec<Item>, next: &mut Option<String>, keep: &F)
+where
+    F: Fn(&Chapter) -> bool,
+{
+    items.retain_mut(|item| match item {
+        Item::Chapter(chapter) if keep(chapter) => {
+            renumber(chapter, next, keep);
+            true
+        }
+        Item::Chapter(_) => false,
+        Item::Section(section) => {
+            section.chapters.retain(|chapter| keep(chapter));
+            for chapter in section.chapters.iter_mut() {
+                renumber(chapter, next, keep);
             }
+
+            !section.chapters.is_empty()
+        }
+    });
+}
+
+fn renumber<F>(chapter: &mut Chapter, next: &mut Option<String>, keep: &F)
+where
+    F: Fn(&Chapter) -> bool,
+{
+    let mut first = None;
+
+    if !chapter.number.is_empty() {
+        if let Some(number) = next.take() {
+            *next = Some(util::next_chapter_number(&number));
+            first = Some(format!("{number}.1"));
+            chapter.number = number;
+        }
+    }
+
+    retain_items(&mut chapter.subchapters, &mut first, keep);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // - [Intro](intro.md)
+    // # Guide
+    // - [Setup](02_guide/setup.md)
+    //   - [Install](02_guide/setup.md#install)
+    //   - [Linux](02_guide/linux.md)
+    // - [Later]()
+    //   # Links
+    //   - [Docs](https://example.com)
+    fn content() -> Content {
+        let install = Chapter {
+            anchor: Some(String::from("install")),
+            ..Chapter::new("Install", "2.1", "src/02_guide/setup.md", Vec::new())
+        };
+        let linux = Chapter::new("Linux", "2.2", "src/02_guide/linux.md", Vec::new());
+        let docs = Chapter::external("Docs", "3.1", "https://example.com", Vec::new());
+
+        Content {
+            summary: Summary::new(vec![
+                Item::from(Chapter::new("Intro", "1", "src/intro.md", Vec::new())),
+                Item::from(Section::new(
+                    "Guide",
+                    vec![
+                        Chapter::new(
+                            "Setup",
+                            "2",
+                            "src/02_guide/setup.md",
+                            vec![Item::from(install), Item::from(linux)],
+                        ),
+                        Chapter::draft(
+                            "Later",
+                            "3",
+                            vec![Item::from(Section::new("Links", vec![docs]))],
+                        ),
+                    ],
+                )),
+            ]),
         }
+    }
 
-        Ok(FileTreeSummarizer::new(&path).summarize()?)
+    #[test]
+    fn it_should_walk_the_whole_tree() {
+        let content = content();
+        let titles: Vec<&str> = content
+            .iter()
+            .map(|chapter| chapter.title.as_str())
+            .collect();
+        let pages: Vec<&str> = content
+            .pages()
+            .iter()
+            .map(|chapter| chapter.title.as_str())
+            .collect();
+
+        assert_eq!(
+            titles,
+            vec!["Intro", "Setup", "Install", "Linux", "Later", "Docs"]
+        );
+        assert_eq!(pages, vec!["Intro", "Setup", "Linux"]);
+    }
+
+    #[test]
+    fn it_should_find_chapters_and_where_they_are() {
+        let content = content();
+        let title = |chapter: Option<&Chapter>| chapter.map(|chapter| chapter.title.clone());
+
+        assert_eq!(
+            title(content.find_by_path("src/02_guide/setup.md")),
+            Some(String::from("Setup"))
+        );
+        assert_eq!(
+            title(content.find_by_number("2.2")),
+            Some(String::from("Linux"))
+        );
+        assert_eq!(
+            title(content.find_by_slug("src", "guide/linux")),
+            Some(String::from("Linux"))
+        );
+        assert_eq!(content.find_by_slug("src", "linux"), None);
+
+        let docs = content
+            .iter()
+            .find(|chapter| chapter.url.is_some())
+            .unwrap();
+        let linux = content.find_by_number("2.2").unwrap();
+        assert_eq!(title(content.parent(docs)), Some(String::from("Later")));
+        assert_eq!(
+            content
+                .ancestors(linux)
+                .iter()
+                .map(|chapter| chapter.title.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Setup"]
+        );
+        assert_eq!(
+            content
+                .section_of(docs)
+                .map(|section| section.title.as_str()),
+            Some("Links")
+        );
+        assert_eq!(
+            content
+                .section_of(linux)
+                .map(|section| section.title.as_str()),
+            Some("Guide")
+        );
+        assert_eq!(title(content.previous(linux)), Some(String::from("Setup")));
+        assert_eq!(title(content.next(linux)), None);
+        assert_eq!(
+            title(
+                content
+                    .find_by_path("src/intro.md")
+                    .and_then(|intro| content.next(intro))
+            ),
+            Some(String::from("Setup"))
+        );
     }
 }
@@ -1,15 +1,27 @@
+use super::Item;
+use serde::Serialize;
 use std::path::{Path, PathBuf};
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Serialize, Clone, PartialEq, Debug)]
 pub struct Chapter {
     pub title: String,
     pub number: String,
     pub content: PathBuf,
-    pub subchapters: Vec<Chapter>,
+    /// Chapters and sections under it, so a chapter can group its subchapters in sections too
+    pub subchapters: Vec<Item>,
+    /// A placeholder for a chapter that wasn't written yet (e.g. `- [Future Chapter]()` in the
+    /// summary). It has no content, so nothing is built for it.
+    pub draft: bool,
+    /// Where the chapter points to when it lives outside of the vault (e.g.
+    /// `- [Rust Book](https://doc.rust-lang.org/book)` in the summary). Like drafts, it has no
+    /// content.
+    pub url: Option<String>,
+    /// The part of the page the chapter points to (e.g. `install` for `./setup.md#install`)
+    pub anchor: Option<String>,
 }
 
 impl Chapter {
-    pub fn new<P, S>(title: S, number: S, content: P, subchapters: Vec<Chapter>) -> Self
+    pub fn new<P, S>(title: S, number: S, content: P, subchapters: Vec<Item>) -> Self
     where
         P: AsRef<Path>,
         S: Into<String>,
@@ -23,6 +35,60 @@ impl Chapter {
             number,
             content,
             subchapters,
+            draft: false,
+            url: None,
+            anchor: None,
         }
     }
+
+    pub fn draft<S>(title: S, number: S, subchapters: Vec<Item>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            draft: true,
+            ..Chapter::new(title, number, PathBuf::new(), subchapters)
+        }
+    }
+
+    pub fn external<S>(title: S, number: S, url: S, subchapters: Vec<Item>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            url: Some(url.into()),
+            ..Chapter::new(title, number, PathBuf::new(), subchapters)
+        }
+    }
+
+    /// Is there a page to build for it? Drafts and external chapters have no content.
+    pub fn has_content(&self) -> bool {
+        !self.draft && self.url.is_none()
+    }
+
+    /// Is it the same chapter as `other`? Chapters with a page are told apart by what they point
+    /// to, since the summary hands out copies of them. The others have to be equal.
+    pub fn is(&self, other: &Chapter) -> bool {
+        match (self.has_content(), other.has_content()) {
+            (true, true) => self.content == other.content && self.anchor == other.anchor,
+            (false, false) => self == other,
+            _ => false,
+        }
+    }
+
+    /// The subchapters right under it, including the ones inside its sections
+    pub fn children(&self) -> Vec<&Chapter> {
+        self.subchapters.iter().flat_map(Item::chapters).collect()
+    }
+
+    /// The chapter itself followed by all of its subchapters, in reading order
+    pub fn flatten(&self) -> Vec<Chapter> {
+        let mut chapters = vec![self.clone()];
+
+        for subchapter in self.children() {
+            chapters.extend(subchapter.flatten());
+        }
+
+        chapters
+    }
 }
@@ -0,0 +1,204 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const DELIMITER: &str = "---";
+
+/// Metadata placed in a YAML block at the very beginning of a chapter
+///
+/// # Example
+///
+/// ---
+/// title: Hello there
+/// icon: 🚀
+/// weight: 2
+/// status: reviewed
+/// owner: Obi-Wan
+/// reviewed: 2024-05-04
+/// date: 2024-04-01
+/// requires: [installing]
+/// exclude_from: [epub]
+/// heading_offset: 1
+/// mood: bold
+/// ---
+///
+/// # Hello there
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct FrontMatter {
+    /// Replaces the title taken from the file name when there is no summary file
+    pub title: Option<String>,
+    /// Shown before the title in the sidebar and the sitemap. Either an emoji or the name of a
+    /// [tabler icon](https://tabler.io/icons) (e.g. `rocket`)
+    pub icon: Option<String>,
+    /// Where the chapter goes among its siblings. Lighter ones come first
+    pub weight: Option<i32>,
+    /// Is the chapter still being written?
+    pub draft: bool,
+    /// Is the chapter only for whoever has the passphrase? It's built the way `private` in the
+    /// config says
+    pub private: bool,
+    /// A short summary of the chapter
+    pub description: Option<String>,
+    /// Where the chapter is in its review cycle (e.g. draft, reviewed)
+    pub status: Option<String>,
+    /// Who is responsible for keeping the chapter up to date
+    pub owner: Option<String>,
+    /// When the chapter was last reviewed
+    #[serde(alias = "last_reviewed", alias = "last-reviewed")]
+    pub reviewed: Option<NaiveDate>,
+    /// When the chapter was published. The feed is ordered by it
+    pub date: Option<NaiveDate>,
+    /// Formats the chapter is also exported to, besides its page (e.g. `[html, pdf]`). A `pdf`
+    /// only has the text of the chapter (see [`Pdf`](crate::renderer::outputs::Pdf))
+    pub outputs: Vec<String>,
+    /// Backends the chapter is left out of (e.g. `[epub, pdf]`), for pages that only work on the
+    /// web. Its page is always built.
+    pub exclude_from: Vec<String>,
+    /// Replaces `heading_offset` of the config for this chapter (e.g. `0` for a chapter whose
+    /// headings are already where they should be)
+    pub heading_offset: Option<u8>,
+    /// Chapters to read before this one, by their page without the extension (e.g. `guide/setup`)
+    /// or only its name (e.g. `setup`) if no other chapter has it
+    pub requires: Vec<String>,
+    /// Any other keys, so custom templates can make use of them
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl FrontMatter {
+    /// Splits the front matter from the rest of the markdown. Files without one get the default
+    /// front matter and are returned untouched. A block that isn't a YAML mapping isn't a front
+    /// matter either, but markdown that starts with a thematic break.
+    pub fn extract(markdown: &str) -> Result<(FrontMatter, &str)> {
+        let Some((yaml, body)) = FrontMatter::split(markdown) else {
+            return Ok((FrontMatter::default(), markdown));
+        };
+
+        if yaml.trim().is_empty() {
+            return Ok((FrontMatter::default(), body));
+        }
+
+        let mapping = match serde_yaml::from_str(yaml) {
+            Ok(serde_yaml::Value::Mapping(mapping)) => mapping,
+            _ => return Ok((FrontMatter::default(), markdown)),
+        };
+        let front_matter = serde_yaml::from_value(serde_yaml::Value::Mapping(mapping))
+            .with_context(|| anyhow!("Failed to parse the front matter"))?;
+
+        Ok((front_matter, body))
+    }
+
+    /// Reads only the front matter of a chapter
+    pub fn from_disk<P>(path: P) -> Result<FrontMatter>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let markdown = fs::read_to_string(path)
+            .with_context(|| anyhow!("Failed to read contents of {}", path.display()))?;
+
+        FrontMatter::extract(&markdown)
+            .map(|(front_matter, _)| front_matter)
+            .with_context(|| anyhow!("Invalid front matter in {}", path.display()))
+    }
+
+    /// Does the chapter leave itself out of the backend (see `exclude_from`)?
+    pub fn excludes(&self, backend: &str) -> bool {
+        self.exclude_from.iter().any(|name| name == backend)
+    }
+
+    /// Is there anything worth showing to the reader?
+    pub fn has_metadata(&self) -> bool {
+        self.status.is_some() || self.owner.is_some() || self.reviewed.is_some()
+    }
+
+    // The block must start at the first line and be closed by another delimiter line
+    fn split(markdown: &str) -> Option<(&str, &str)> {
+        let rest = markdown
+            .strip_prefix(DELIMITER)?
+            .strip_prefix('\n')
+            .or_else(|| markdown.strip_prefix("---\r\n"))?;
+        let mut offset = 0;
+
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == DELIMITER {
+                return Some((&rest[..offset], &rest[offset + line.len()..]));
+            }
+
+            offset += line.len();
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_extract_the_front_matter() -> Result<()> {
+        let markdown =
+            "---\nstatus: draft\nowner: Obi-Wan\nreviewed: 2024-05-04\n---\n# Hello there\n";
+        let expected = FrontMatter {
+            status: Some("draft".to_string()),
+            owner: Some("Obi-Wan".to_string()),
+            reviewed: NaiveDate::from_ymd_opt(2024, 5, 4),
+            ..FrontMatter::default()
+        };
+
+        assert_eq!(
+            FrontMatter::extract(markdown)?,
+            (expected, "# Hello there\n")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_keep_the_unknown_keys() -> Result<()> {
+        let markdown = "---\ntitle: Hello there\nicon: rocket\nweight: -1\ndraft: true\nlast-reviewed: 2024-05-04\nmood: bold\n---\n";
+        let (front_matter, _) = FrontMatter::extract(markdown)?;
+
+        assert_eq!(front_matter.title, Some("Hello there".to_string()));
+        assert_eq!(front_matter.icon, Some("rocket".to_string()));
+        assert_eq!(front_matter.weight, Some(-1));
+        assert!(front_matter.draft);
+        assert_eq!(front_matter.reviewed, NaiveDate::from_ymd_opt(2024, 5, 4));
+        assert_eq!(
+            front_matter.extra,
+            BTreeMap::from([("mood".to_string(), serde_yaml::Value::from("bold"))])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_leave_markdown_without_front_matter_untouched() -> Result<()> {
+        let tests = [
+            "# Hello there\n",
+            "---\n\nNot closed",
+            "Some text\n---\nstatus: draft\n---\n",
+            "----\nstatus: draft\n---\n",
+            // Thematic breaks around text that isn't a YAML mapping
+            "---\nHello there\n\n---\nGeneral Kenobi\n",
+            "---\n- one\n- two\n---\n",
+            "---\n*Hello: [there*\n---\n",
+        ];
+
+        for test in tests.iter() {
+            assert_eq!(FrontMatter::extract(test)?, (FrontMatter::default(), *test));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_with_invalid_front_matter() {
+        assert!(FrontMatter::extract("---\nreviewed: yesterday\n---\n").is_err());
+    }
+}
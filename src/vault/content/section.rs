@@ -1,15 +1,20 @@
-#[derive(Clone, PartialEq, Debug)]
+use super::Chapter;
+use serde::Serialize;
+
+/// A part of the book. It owns every chapter that comes after it, up to the next section.
+#[derive(Serialize, Clone, PartialEq, Debug)]
 pub struct Section {
     pub title: String,
+    pub chapters: Vec<Chapter>,
 }
 
 impl Section {
-    pub fn new<S>(title: S) -> Self
+    pub fn new<S>(title: S, chapters: Vec<Chapter>) -> Self
     where
         S: Into<String>,
     {
         let title = title.into();
 
-        Self { title }
+        Self { title, chapters }
     }
 }
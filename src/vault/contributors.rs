@@ -0,0 +1,86 @@
+use super::{Content, Vault};
+use crate::git::{self, Author};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Who committed to each chapter, by the path to its file
+pub type Contributors = BTreeMap<PathBuf, Vec<Author>>;
+
+impl Vault {
+    /// Who committed to each chapter, the ones with the most commits first. It's only looked up
+    /// if the pages show it, and chapters outside of a repository have no one.
+    pub(super) fn contributors(&self, content: &Content) -> Result<Contributors> {
+        if !self.config.contributors.enabled {
+            return Ok(Contributors::new());
+        }
+
+        let authors = git::authors(&self.path).unwrap_or_default();
+
+        Ok(content
+            .all_chapters()
+            .into_iter()
+            .filter_map(|chapter| {
+                let authors = authors
+                    .get(&self.relative(&self.origin(&chapter.content)))?
+                    .clone();
+                Some((chapter.content, authors))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use std::fs;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_find_who_committed_to_each_chapter() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.contributors.enabled = true;
+        vault.init()?;
+
+        let git = |author: &str, args: &[&str]| {
+            Command::new("git")
+                .args(["-c", &format!("user.name={author}")])
+                .args([
+                    "-c",
+                    &format!("user.email={}@wan.com", author.to_lowercase()),
+                ])
+                .args(args)
+                .current_dir(temp_dir.path())
+                .output()
+        };
+        let intro = vault.src_dir().join("intro.md");
+        git("Obi", &["init"])?;
+        for (author, text) in [("Obi", "# Hello"), ("Anakin", "# There"), ("Obi", "# Hi")] {
+            fs::write(&intro, text)?;
+            git(author, &["add", "."])?;
+            git(author, &["commit", "-m", text])?;
+        }
+        fs::write(vault.src_dir().join("draft.md"), "# Draft")?;
+
+        let contributors = vault.contributors(&Content::new(vault.src_dir())?)?;
+        assert_eq!(
+            contributors[&intro],
+            vec![
+                Author {
+                    name: String::from("Obi"),
+                    email: String::from("obi@wan.com")
+                },
+                Author {
+                    name: String::from("Anakin"),
+                    email: String::from("anakin@wan.com")
+                },
+            ]
+        );
+        assert!(!contributors.contains_key(&vault.src_dir().join("draft.md")));
+
+        Ok(())
+    }
+}
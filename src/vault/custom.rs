@@ -0,0 +1,173 @@
+use super::Vault;
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const WILDCARD: char = '*';
+
+impl Vault {
+    /// The files of `appearance.custom`, relative to the vault, with the globs expanded. Every
+    /// entry has to point to some file, and no two files can have the same name, since they're
+    /// all copied to the root of the build dir.
+    pub(super) fn custom_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut names: HashMap<String, PathBuf> = HashMap::new();
+
+        for entry in self.config.appearance.custom.iter() {
+            let matches = self.custom_entry(entry)?;
+
+            if matches.is_empty() {
+                match entry.contains(WILDCARD) {
+                    true => {
+                        anyhow::bail!("appearance.custom has {entry}, which doesn't match any file")
+                    }
+                    false => anyhow::bail!("appearance.custom has {entry}, which doesn't exist"),
+                }
+            }
+
+            for file in matches {
+                if files.contains(&file) {
+                    continue;
+                }
+
+                let name = file
+                    .file_name()
+                    .with_context(|| anyhow!("Failed to extract the file name from {entry}"))?
+                    .to_string_lossy()
+                    .to_string();
+                if let Some(other) = names.insert(name.clone(), file.clone()) {
+                    anyhow::bail!(
+                        "appearance.custom has {} and {}, which would both be copied to {name}",
+                        other.display(),
+                        file.display()
+                    );
+                }
+
+                files.push(file);
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// The files an entry of `appearance.custom` points to, relative to the vault. A glob (e.g.
+    /// `css/*.css`) gives the files it matches in alphabetical order, leaving out the build dir
+    /// and hidden ones.
+    pub(super) fn custom_entry(&self, entry: &str) -> Result<Vec<PathBuf>> {
+        let pattern = normalize(entry);
+
+        if !entry.contains(WILDCARD) {
+            return Ok(match self.path.join(&pattern).is_file() {
+                true => vec![pattern],
+                false => Vec::new(),
+            });
+        }
+
+        let pattern = pattern.to_string_lossy().replace('\\', "/");
+        // Only the dir before the first wildcard can have matches
+        let dir = match pattern[..pattern.find(WILDCARD).unwrap_or(0)].rfind('/') {
+            Some(index) => self.path.join(&pattern[..index]),
+            None => self.path.clone(),
+        };
+        let mut files = Vec::new();
+        self.collect_custom(&dir, &pattern, &mut files)?;
+        files.sort();
+
+        Ok(files)
+    }
+
+    /// Is the file, relative to the vault, one `appearance.custom` points to? It doesn't need to
+    /// exist, so removed files count too.
+    pub(super) fn is_custom(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy().replace('\\', "/");
+
+        self.config.appearance.custom.iter().any(|entry| {
+            util::matches_wildcard(normalize(entry).to_string_lossy().replace('\\', "/"), &path)
+        })
+    }
+
+    fn collect_custom(&self, dir: &Path, pattern: &str, files: &mut Vec<PathBuf>) -> Result<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+
+        let entries = fs::read_dir(dir)
+            .with_context(|| anyhow!("Failed to read contents of {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+            if hidden || path == self.build_dir() {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.collect_custom(&path, pattern, files)?;
+                continue;
+            }
+
+            let relative = self.relative(&path);
+            if util::matches_wildcard(pattern, relative.to_string_lossy().replace('\\', "/")) {
+                files.push(relative);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// `./css/main.css` and `css/main.css` are the same file
+fn normalize(entry: &str) -> PathBuf {
+    Path::new(entry)
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_expand_the_globs_of_the_custom_files() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        fs::create_dir_all(vault.path.join("css/print"))?;
+        fs::write(vault.path.join("css/main.css"), "")?;
+        fs::write(vault.path.join("css/code.css"), "")?;
+        fs::write(vault.path.join("css/print/print.css"), "")?;
+        fs::write(vault.path.join("css/notes.txt"), "")?;
+        fs::write(vault.path.join("menu.js"), "")?;
+
+        vault.config.appearance.custom = vec![
+            "./css/main.css".to_string(),
+            "css/*.css".to_string(),
+            "*.js".to_string(),
+        ];
+        assert_eq!(
+            vault.custom_files()?,
+            vec![
+                PathBuf::from("css/main.css"),
+                PathBuf::from("css/code.css"),
+                PathBuf::from("css/print/print.css"),
+                PathBuf::from("menu.js"),
+            ]
+        );
+
+        vault.config.appearance.custom = vec!["fonts/*.css".to_string()];
+        assert!(vault.custom_files().is_err());
+
+        fs::write(vault.path.join("main.css"), "")?;
+        vault.config.appearance.custom = vec!["main.css".to_string(), "css/main.css".to_string()];
+        assert!(vault.custom_files().is_err());
+
+        Ok(())
+    }
+}
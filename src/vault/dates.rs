@@ -0,0 +1,91 @@
+use super::{Content, Vault};
+use crate::git;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// When each chapter was last updated, by the path to its file
+pub type LastUpdated = BTreeMap<PathBuf, DateTime<Utc>>;
+
+impl Vault {
+    /// When each chapter was last committed or, outside of a repository and for files that were
+    /// never committed, last modified. It's only looked up if the footer or the feed need it.
+    pub(super) fn last_updated(&self, content: &Content) -> Result<LastUpdated> {
+        if !self.config.appearance.last_updated && !self.config.feed.enabled {
+            return Ok(LastUpdated::new());
+        }
+
+        let commits = git::last_commit_dates(&self.path).unwrap_or_default();
+        let mut dates = LastUpdated::new();
+
+        for chapter in content.all_chapters() {
+            if !chapter.content.exists() {
+                continue;
+            }
+
+            let origin = self.origin(&chapter.content);
+            let date = match commits.get(&self.relative(&origin)) {
+                Some(date) => (*date).into(),
+                None => fs::metadata(&origin)?.modified()?.into(),
+            };
+            dates.insert(chapter.content, date);
+        }
+
+        Ok(dates)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_date_the_chapters_by_their_last_commit() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.appearance.last_updated = true;
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("intro.md"), "# Intro")?;
+        fs::write(vault.src_dir().join("draft.md"), "# Draft")?;
+        let content = Content::new(vault.src_dir())?;
+        let intro = vault.src_dir().join("intro.md");
+        let draft = vault.src_dir().join("draft.md");
+
+        // Not a repository yet, so it's the file system
+        let dates = vault.last_updated(&content)?;
+        assert_eq!(
+            dates[&intro],
+            DateTime::<Utc>::from(fs::metadata(&intro)?.modified()?)
+        );
+
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=Obi-Wan", "-c", "user.email=obi@wan.com"])
+                .args(args)
+                .env("GIT_COMMITTER_DATE", "2024-03-01T10:00:00Z")
+                .current_dir(temp_dir.path())
+                .output()
+        };
+        git(&["init"])?;
+        git(&["add", "src/intro.md"])?;
+        git(&["commit", "-m", "Hello there"])?;
+
+        let dates = vault.last_updated(&content)?;
+        assert_eq!(dates[&intro].to_rfc3339(), "2024-03-01T10:00:00+00:00");
+        assert_eq!(
+            dates[&draft],
+            DateTime::<Utc>::from(fs::metadata(&draft)?.modified()?)
+        );
+
+        vault.config.appearance.last_updated = false;
+        assert!(vault.last_updated(&content)?.is_empty());
+
+        Ok(())
+    }
+}
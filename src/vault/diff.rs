@@ -0,0 +1,114 @@
+use super::Vault;
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The pages in the build dir, by their path relative to it
+pub type BuiltPages = BTreeMap<PathBuf, String>;
+
+/// How a page changed between two builds
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageDiff {
+    Added(PathBuf),
+    Removed(PathBuf),
+    /// The page and its changed lines, as given by [`util::diff_lines`]
+    Changed(PathBuf, String),
+}
+
+impl Vault {
+    /// Reads every html page in the build dir. Take them before and after a build and compare
+    /// them with [`diff_pages`] to see what the build changed.
+    pub fn built_pages(&self) -> Result<BuiltPages> {
+        let mut pages = BuiltPages::new();
+
+        if self.build_dir().exists() {
+            self.read_pages(&self.build_dir(), &mut pages)?;
+        }
+
+        Ok(pages)
+    }
+
+    fn read_pages(&self, dir: &Path, pages: &mut BuiltPages) -> Result<()> {
+        for entry in dir
+            .read_dir()
+            .with_context(|| anyhow!("Failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                self.read_pages(&path, pages)?;
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension == "html")
+            {
+                let html = fs::read_to_string(&path)
+                    .with_context(|| anyhow!("Failed to read {}", path.display()))?;
+
+                pages.insert(path.strip_prefix(self.build_dir())?.to_path_buf(), html);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What changed from one set of pages to the other. Pages that didn't change are left out.
+pub fn diff_pages(before: &BuiltPages, after: &BuiltPages) -> Vec<PageDiff> {
+    let mut diffs = Vec::new();
+
+    for (path, html) in after.iter() {
+        match before.get(path) {
+            None => diffs.push(PageDiff::Added(path.clone())),
+            Some(previous) => {
+                let diff = util::diff_lines(previous, html);
+
+                if !diff.is_empty() {
+                    diffs.push(PageDiff::Changed(path.clone(), diff));
+                }
+            }
+        }
+    }
+
+    for path in before.keys().filter(|path| !after.contains_key(*path)) {
+        diffs.push(PageDiff::Removed(path.clone()));
+    }
+
+    diffs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_diff_the_pages_of_two_builds() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "Hello there")?;
+        vault.build()?;
+        let before = vault.built_pages()?;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "General Kenobi")?;
+        fs::write(vault.src_dir().join("chapter2.md"), "You are a bold one")?;
+        vault.build()?;
+        let diffs = diff_pages(&before, &vault.built_pages()?);
+
+        assert!(diffs.contains(&PageDiff::Added(PathBuf::from("chapter2.html"))));
+        assert!(diffs.iter().any(|diff| matches!(
+            diff,
+            PageDiff::Changed(path, lines)
+                if path == Path::new("chapter1.html")
+                    && lines.contains("Hello there")
+                    && lines.contains("General Kenobi")
+        )));
+        assert!(diff_pages(&before, &before).is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+use super::{Content, Vault, CONFIG_FILE};
+use crate::config::{self, Config, SCHEMA_VERSION};
+use crate::renderer::favicon;
+use crate::summary::{FileTreeSummarizer, Summarizer, NOT_FOUND_FILE_NAME, SUMMARY_FILE_NAMES};
+use crate::{git, util};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Something wrong with the vault, or with what it needs to be built, and what to do about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    pub severity: Severity,
+    pub message: String,
+    pub fix: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// The build fails or the site ends up broken
+    Error,
+    /// Something works worse than it could, or not at all when asked for
+    Warning,
+}
+
+impl Diagnosis {
+    fn new<S, T>(severity: Severity, message: S, fix: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            severity,
+            message: message.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n  fix: {}", self.message, self.fix)
+    }
+}
+
+/// Looks at everything that usually goes wrong with a vault in one pass: the config, the source
+/// and build dirs, the summary, the files the config points to and git. Unlike
+/// [`Vault::check`], it works on vaults that can't even be read.
+pub fn doctor<P>(path: P) -> Vec<Diagnosis>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let config_path = path.join(CONFIG_FILE);
+
+    if !config_path.exists() {
+        return vec![Diagnosis::new(
+            Severity::Error,
+            format!("There's no {CONFIG_FILE} in {}", path.display()),
+            format!(
+                "Run cahlter init {} to create a vault there",
+                path.display()
+            ),
+        )];
+    }
+
+    let (config, version) = match config::read_migrated(&config_path)
+        .and_then(|(config, version)| Ok((serde_yaml::from_value::<Config>(config)?, version)))
+    {
+        Ok(config) => config,
+        Err(err) => {
+            return vec![Diagnosis::new(
+                Severity::Error,
+                format!("{CONFIG_FILE} is invalid: {err:#}"),
+                format!("Fix {CONFIG_FILE}, or run cahlter migrate if it's from an older cahlter"),
+            )]
+        }
+    };
+
+    let mut diagnoses = Vec::new();
+    if version < SCHEMA_VERSION {
+        diagnoses.push(Diagnosis::new(
+            Severity::Warning,
+            format!("{CONFIG_FILE} is at version {version} of the config, not {SCHEMA_VERSION}"),
+            "Run cahlter migrate to upgrade it",
+        ));
+    }
+
+    let mut vault = Vault::new(path);
+    vault.config = config;
+
+    let dirs = vault.diagnose_dirs();
+    let broken_dirs = dirs
+        .iter()
+        .any(|diagnosis| diagnosis.severity == Severity::Error);
+    diagnoses.extend(dirs);
+
+    // The rest needs the chapters
+    if !broken_dirs {
+        diagnoses.extend(vault.diagnose_summary());
+    }
+    diagnoses.extend(vault.diagnose_files());
+    diagnoses.extend(vault.diagnose_git());
+
+    diagnoses
+}
+
+impl Vault {
+    fn diagnose_dirs(&self) -> Vec<Diagnosis> {
+        let (src_dir, build_dir) = (self.src_dir(), self.build_dir());
+        let mut diagnoses = Vec::new();
+
+        if src_dir.starts_with(&build_dir) || build_dir.starts_with(&src_dir) {
+            diagnoses.push(Diagnosis::new(
+                Severity::Error,
+                format!(
+                    "The source dir {} and the build dir {} overlap, so builds would mix pages and chapters",
+                    src_dir.display(),
+                    build_dir.display()
+                ),
+                "Point general.src_dir and general.build_dir to dirs that aren't inside each other",
+            ));
+        }
+
+        for (dir, key) in [(&src_dir, "src_dir"), (&build_dir, "build_dir")] {
+            if !dir.is_dir() {
+                diagnoses.push(Diagnosis::new(
+                    Severity::Error,
+                    format!("{} doesn't exist", dir.display()),
+                    format!("Create it, or point general.{key} to the right dir"),
+                ));
+            }
+        }
+
+        diagnoses
+    }
+
+    fn diagnose_summary(&self) -> Vec<Diagnosis> {
+        let content = match self.content() {
+            Ok(content) => content,
+            Err(err) => {
+                return vec![Diagnosis::new(
+                    Severity::Error,
+                    format!("The summary can't be read: {err:#}"),
+                    "Fix the summary file, or remove it to build from the file tree",
+                )]
+            }
+        };
+        let chapters = content.all_chapters();
+        let mut diagnoses = Vec::new();
+
+        for chapter in chapters.iter() {
+            if !chapter.content.exists() {
+                diagnoses.push(Diagnosis::new(
+                    Severity::Error,
+                    format!(
+                        "\"{}\" points to {}, which doesn't exist",
+                        chapter.title,
+                        self.relative(&chapter.content).display()
+                    ),
+                    "Create the file, or remove the chapter from the summary",
+                ));
+            }
+        }
+
+        let summary = SUMMARY_FILE_NAMES
+            .iter()
+            .map(|name| self.src_dir().join(name))
+            .find(|path| path.exists());
+        if let Some(summary) = summary {
+            let listed: HashSet<&PathBuf> =
+                chapters.iter().map(|chapter| &chapter.content).collect();
+
+            for file in self.chapter_files() {
+                if !listed.contains(&file) {
+                    diagnoses.push(Diagnosis::new(
+                        Severity::Warning,
+                        format!(
+                            "{} isn't in the summary, so it's never built",
+                            self.relative(&file).display()
+                        ),
+                        format!(
+                            "Add it to {}, or to general.ignore",
+                            self.relative(&summary).display()
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Missing files were already diagnosed, with a better fix
+        let problems = match self.check() {
+            Ok(problems) => problems
+                .into_iter()
+                .filter(|problem| self.path.join(&problem.path).exists())
+                .count(),
+            Err(_) => 0,
+        };
+        if problems > 0 {
+            diagnoses.push(Diagnosis::new(
+                Severity::Warning,
+                format!("cahlter check found {problems} problem(s), like broken links"),
+                "Run cahlter check to see where they are",
+            ));
+        }
+
+        diagnoses
+    }
+
+    // Every file the file tree would make a chapter of, if there was no summary
+    fn chapter_files(&self) -> Vec<PathBuf> {
+        let Ok(summary) = FileTreeSummarizer::new(self.src_dir()).summarize() else {
+            return Vec::new();
+        };
+
+        Content { summary }
+            .iter()
+            .map(|chapter| chapter.content.clone())
+            .filter(|file| {
+                let relative = file.strip_prefix(self.src_dir()).unwrap_or(file);
+
+                file.is_file()
+                    && file
+                        .file_name()
+                        .is_some_and(|name| name != NOT_FOUND_FILE_NAME)
+                    && !util::is_ignored(&self.config.general.ignore, relative)
+            })
+            .collect()
+    }
+
+    // The files the config points to
+    fn diagnose_files(&self) -> Vec<Diagnosis> {
+        let appearance = &self.config.appearance;
+        let mut files: Vec<(&Path, &str)> = appearance
+            .custom
+            .iter()
+            .filter(|css| !css.contains('*'))
+            .map(|css| (Path::new(css), "appearance.custom"))
+            .collect();
+        files.extend(
+            appearance
+                .favicon
+                .as_deref()
+                .map(|favicon| (favicon, "appearance.favicon")),
+        );
+        files.extend(
+            self.config
+                .social_cards
+                .template
+                .as_deref()
+                .map(|template| (template, "social_cards.template")),
+        );
+
+        let mut diagnoses: Vec<Diagnosis> = files
+            .into_iter()
+            .filter(|(file, _)| !self.path.join(file).is_file())
+            .map(|(file, key)| {
+                Diagnosis::new(
+                    Severity::Error,
+                    format!("{} doesn't exist", file.display()),
+                    format!("Create it, or remove it from {key}"),
+                )
+            })
+            .collect();
+
+        for glob in appearance.custom.iter().filter(|css| css.contains('*')) {
+            if self.custom_entry(glob).is_ok_and(|files| files.is_empty()) {
+                diagnoses.push(Diagnosis::new(
+                    Severity::Error,
+                    format!("{glob} doesn't match any file"),
+                    "Fix the pattern, or remove it from appearance.custom",
+                ));
+            }
+        }
+        // Files with the same name, once every entry points to some
+        if diagnoses.is_empty() {
+            if let Err(err) = self.custom_files() {
+                diagnoses.push(Diagnosis::new(
+                    Severity::Error,
+                    format!("{err:#}"),
+                    "Rename one of them, or remove it from appearance.custom",
+                ));
+            }
+        }
+
+        if let Some(favicon) = &appearance.favicon {
+            if let Err(err) = favicon::favicons(favicon, appearance.favicon_variants) {
+                diagnoses.push(Diagnosis::new(
+                    Severity::Error,
+                    format!("{err:#}"),
+                    "Use a PNG or SVG favicon, or turn off appearance.favicon_variants",
+                ));
+            }
+        }
+
+        diagnoses
+    }
+
+    fn diagnose_git(&self) -> Option<Diagnosis> {
+        let features =
+            "build --since, the webhook of serve, the dates of the feed and the contributors";
+
+        if git::version().is_err() {
+            return Some(Diagnosis::new(
+                Severity::Warning,
+                format!("git isn't installed, and {features} need it"),
+                "Install git",
+            ));
+        }
+
+        git::prefix(&self.path).is_err().then(|| {
+            Diagnosis::new(
+                Severity::Warning,
+                format!("The vault isn't in a git repository, and {features} need one"),
+                format!("Run git init in {}", self.path.display()),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_find_what_is_wrong_with_the_vault() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        assert_eq!(doctor(temp_dir.path())[0].severity, Severity::Error);
+
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        fs::write(vault.src_dir().join("intro.md"), "# Intro")?;
+        assert!(doctor(temp_dir.path())
+            .iter()
+            .all(|diagnosis| diagnosis.severity == Severity::Warning));
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Intro](./intro.md)\n- [Setup](./setup.md)",
+        )?;
+        fs::write(vault.src_dir().join("orphan.md"), "# Orphan")?;
+        vault.config.appearance.custom = vec!["missing.css".to_string()];
+        vault.config.save(temp_dir.path().join(CONFIG_FILE))?;
+
+        let messages: Vec<String> = doctor(temp_dir.path())
+            .into_iter()
+            .map(|diagnosis| diagnosis.message)
+            .collect();
+        assert!(
+            messages.contains(&"\"Setup\" points to src/setup.md, which doesn't exist".to_string())
+        );
+        assert!(messages
+            .contains(&"src/orphan.md isn't in the summary, so it's never built".to_string()));
+        assert!(messages.contains(&"missing.css doesn't exist".to_string()));
+
+        vault.config.general.build_dir = PathBuf::from("src/build");
+        vault.config.save(temp_dir.path().join(CONFIG_FILE))?;
+        assert!(doctor(temp_dir.path())
+            .iter()
+            .any(|diagnosis| diagnosis.message.contains("overlap")));
+
+        fs::write(temp_dir.path().join(CONFIG_FILE), "general: [")?;
+        assert!(doctor(temp_dir.path())[0].message.contains("is invalid"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+use super::backends::EPUB_BACKEND;
+use super::Vault;
+use crate::renderer::epub::{self, Book, BookChapter, NavPoint, Resource};
+use crate::renderer::{self, audiences, markdown};
+use crate::{util, Chapter, FrontMatter, Item};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use log::warn;
+use pulldown_cmark::{CowStr, Event, Parser, Tag};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where `export epub` writes the book, inside the build dir
+pub const EPUB_FILE: &str = "book.epub";
+
+/// Where the images of the chapters go inside the book, keeping where they are in the vault
+const IMAGES_DIR: &str = "images";
+
+impl Vault {
+    /// Exports the chapters as an EPUB book, with a table of contents from the summary, and
+    /// writes it to `destination` or, without one, to the build dir. Private chapters are left out
+    /// unless the profile builds them as they are, since a book can't ask for a passphrase.
+    pub fn export_epub(&self, destination: Option<&Path>) -> Result<PathBuf> {
+        let content = self.content_for(self.without_private(self.content()?)?, EPUB_BACKEND)?;
+        let encrypted = self.encrypted_chapters(&content)?;
+        // The document of each chapter, by its source relative to the vault
+        let mut files: HashMap<PathBuf, String> = HashMap::new();
+
+        for chapter in content.pages() {
+            if chapter.content.is_file() && !encrypted.contains(&chapter.content) {
+                let file = renderer::output_path(&chapter.content, self.src_dir())?
+                    .with_extension("xhtml");
+                files.insert(self.relative(&chapter.content), util::url_path(file));
+            }
+        }
+
+        let mut chapters = Vec::new();
+        let mut images = BTreeMap::new();
+        for chapter in content.pages() {
+            let Some(file) = files.get(&self.relative(&chapter.content)) else {
+                if !chapter.content.is_file() {
+                    warn!("Missing file: {}", chapter.content.display());
+                }
+                continue;
+            };
+            if chapters
+                .iter()
+                .any(|other: &BookChapter| other.file == *file)
+            {
+                continue;
+            }
+
+            let body = self
+                .epub_body(chapter, file, &files, &mut images)
+                .with_context(|| anyhow!("Failed to export {} to epub", chapter.title))?;
+            chapters.push(BookChapter {
+                title: chapter.title.clone(),
+                file: file.clone(),
+                body,
+            });
+        }
+
+        let mut resources = Vec::new();
+        for (file, source) in images {
+            let data = fs::read(&source)
+                .with_context(|| anyhow!("Failed to read contents of {}", source.display()))?;
+            resources.push(Resource { file, data });
+        }
+
+        let general = &self.config.general;
+        let book = Book {
+            title: match general.title.is_empty() {
+                true => String::from("Untitled"),
+                false => general.title.clone(),
+            },
+            authors: general
+                .authors
+                .iter()
+                .filter(|author| !author.is_empty())
+                .cloned()
+                .collect(),
+            language: general.language.clone(),
+            identifier: match &general.site_url {
+                Some(site_url) => site_url.clone(),
+                None => format!("urn:cahlter:{}", util::content_hash(&general.title)),
+            },
+            modified: Utc::now(),
+            stylesheet: String::from(epub::STYLESHEET),
+            nav: nav(&content.summary.items, &files, self),
+            chapters,
+            resources,
+        };
+
+        let destination = match destination {
+            Some(destination) => destination.to_path_buf(),
+            None => {
+                fs::create_dir_all(self.build_dir())
+                    .with_context(|| anyhow!("Failed to create {}", self.build_dir().display()))?;
+                self.build_dir().join(EPUB_FILE)
+            }
+        };
+        fs::write(&destination, epub::write(&book)?)
+            .with_context(|| anyhow!("Failed to write {}", destination.display()))?;
+
+        Ok(destination)
+    }
+
+    // The XHTML of a chapter. Links to other chapters point to their documents, and the images
+    // found in the vault are collected, by their file in the book, to be copied into it.
+    fn epub_body(
+        &self,
+        chapter: &Chapter,
+        file: &str,
+        files: &HashMap<PathBuf, String>,
+        images: &mut BTreeMap<String, PathBuf>,
+    ) -> Result<String> {
+        let markdown = fs::read_to_string(&chapter.content)
+            .with_context(|| anyhow!("Failed to read contents of {}", chapter.content.display()))?;
+        let (_, markdown) = FrontMatter::extract(&markdown)
+            .with_context(|| anyhow!("Invalid front matter in {}", chapter.content.display()))?;
+        let markdown = audiences::audiences(markdown, self.audience.as_deref())?;
+
+        let config = &self.config.markdown;
+        let options = markdown::options(&config.extensions);
+        let anchors = markdown::anchors(&markdown, options, config.slug);
+        let mut anchors = anchors.iter();
+        // Both are relative to the vault, like the urls of the chapter are resolved
+        let source_dir = self.relative(chapter.content.parent().unwrap_or(Path::new("")));
+        let document_dir = Path::new(file).parent().unwrap_or(Path::new(""));
+
+        let mut resolve = |url: &str, image: bool| -> Option<String> {
+            if !markdown::is_relative_url(url) {
+                return None;
+            }
+
+            let (path, suffix) = url.split_at(url.find(['#', '?']).unwrap_or(url.len()));
+            let source = util::normalize(&source_dir.join(path))?;
+            let target = match (image, files.get(&source)) {
+                (false, Some(target)) => target.clone(),
+                (true, _) if self.path.join(&source).is_file() => {
+                    let target = format!("{IMAGES_DIR}/{}", util::url_path(&source));
+                    images.insert(target.clone(), self.path.join(&source));
+                    target
+                }
+                _ => return None,
+            };
+
+            Some(format!(
+                "{}{suffix}",
+                util::url_path(util::relative_path(document_dir, target))
+            ))
+        };
+
+        let mut events: Vec<Event> = Vec::new();
+        for event in Parser::new_ext(&markdown, options) {
+            events.push(match event {
+                Event::Start(Tag::Heading(level, id, classes)) => Event::Start(Tag::Heading(
+                    level,
+                    id.or(anchors.next().map(String::as_str)),
+                    classes,
+                )),
+                Event::Start(Tag::Link(kind, url, title)) => {
+                    let url = resolve(&url, false).map(CowStr::from).unwrap_or(url);
+                    Event::Start(Tag::Link(kind, url, title))
+                }
+                Event::Start(Tag::Image(kind, url, title)) => {
+                    let url = resolve(&url, true).map(CowStr::from).unwrap_or(url);
+                    Event::Start(Tag::Image(kind, url, title))
+                }
+                event => event,
+            });
+        }
+        if config.inject_title {
+            events = markdown::inject_title(events, &chapter.title);
+        }
+
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, events.into_iter());
+
+        Ok(html)
+    }
+}
+
+// The table of contents follows the summary. Drafts and external chapters only show up to hold
+// their subchapters.
+fn nav(items: &[Item], files: &HashMap<PathBuf, String>, vault: &Vault) -> Vec<NavPoint> {
+    items
+        .iter()
+        .map(|item| match item {
+            Item::Chapter(chapter) => nav_point(chapter, files, vault),
+            Item::Section(section) => NavPoint {
+                title: section.title.clone(),
+                href: None,
+                children: section
+                    .chapters
+                    .iter()
+                    .map(|chapter| nav_point(chapter, files, vault))
+                    .collect(),
+            },
+        })
+        .collect()
+}
+
+fn nav_point(chapter: &Chapter, files: &HashMap<PathBuf, String>, vault: &Vault) -> NavPoint {
+    let href = match chapter.has_content() {
+        true => files
+            .get(&vault.relative(&chapter.content))
+            .map(|file| match &chapter.anchor {
+                Some(anchor) => format!("{file}#{anchor}"),
+                None => file.clone(),
+            }),
+        false => None,
+    };
+
+    NavPoint {
+        title: chapter.title.clone(),
+        href,
+        children: nav(&chapter.subchapters, files, vault),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_export_the_vault_as_an_epub() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        fs::create_dir_all(vault.src_dir().join("02_guide"))?;
+        fs::create_dir_all(vault.path.join("images"))?;
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Intro](./intro.md)\n\n# Guide\n\n- [Setup](./02_guide/setup.md)\n  - [Install](./02_guide/setup.md#install)\n- [Draft]()\n",
+        )?;
+        fs::write(
+            vault.src_dir().join("intro.md"),
+            "# Intro\n\nRead the [setup](02_guide/setup.md#install) first\n",
+        )?;
+        fs::write(
+            vault.src_dir().join("02_guide/setup.md"),
+            "# Setup\n\n![Kenobi](../../images/kenobi.png)\n\n## Install\n\nHello there\n",
+        )?;
+        fs::write(
+            vault.path.join("images/kenobi.png"),
+            [0x89, b'P', b'N', b'G'],
+        )?;
+
+        let path = vault.export_epub(None)?;
+        let epub = fs::read(&path)?;
+        let text = String::from_utf8_lossy(&epub);
+
+        assert_eq!(path, vault.build_dir().join(EPUB_FILE));
+        assert!(text.contains("Read the <a href=\"guide/setup.xhtml#install\">setup</a>"));
+        assert!(text.contains("<img src=\"../images/images/kenobi.png\" alt=\"Kenobi\" />"));
+        assert!(text.contains("<h2 id=\"install\">Install</h2>"));
+        assert!(text.contains("<item id=\"chapter-0\" href=\"intro.xhtml\""));
+        assert!(text.contains("<item id=\"chapter-1\" href=\"guide/setup.xhtml\""));
+        assert!(!text.contains("chapter-2"));
+        assert!(text.contains("<li><a href=\"guide/setup.xhtml#install\">Install</a></li>"));
+        assert!(text.contains("media-type=\"image/png\""));
+        assert!(!text.contains("Draft"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use super::{Content, Vault};
+use crate::config::Solutions;
+use crate::renderer::{self, exercises::SOLUTIONS_FILE, AskamaRenderer};
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use std::fs;
+use std::path::Path;
+
+impl Vault {
+    /// Are the solutions to the exercises in the build? The config may hide them, unless the
+    /// vault is built with `--with-solutions`.
+    pub fn shows_solutions(&self) -> bool {
+        self.with_solutions || self.config.exercises.solutions == Solutions::Appendix
+    }
+
+    /// Writes the page with the solutions to the exercises of every chapter. If there are none,
+    /// or they're hidden, the page of a previous build is removed so it doesn't give them away.
+    pub(super) fn write_solutions(
+        &self,
+        content: &Content,
+        renderer: &AskamaRenderer,
+    ) -> Result<()> {
+        for chapter in content.all_chapters().iter() {
+            if renderer::output_path(&chapter.content, self.src_dir())? == Path::new(SOLUTIONS_FILE)
+            {
+                if self.shows_solutions() {
+                    warn!("{SOLUTIONS_FILE} is a chapter, so the solutions to the exercises aren't written");
+                }
+                return Ok(());
+            }
+        }
+
+        let path = self.build_dir().join(SOLUTIONS_FILE);
+        let html = match self.shows_solutions() {
+            true => renderer
+                .render_solutions()
+                .with_context(|| anyhow!("Failed to render the solutions to the exercises"))?,
+            false => None,
+        };
+
+        match html {
+            Some(html) => fs::write(&path, html)
+                .with_context(|| anyhow!("Failed to write {}", path.display())),
+            None if path.exists() => fs::remove_file(&path)
+                .with_context(|| anyhow!("Failed to remove {}", path.display())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_collect_the_solutions_in_an_appendix() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::create_dir(vault.src_dir().join("guide"))?;
+        fs::write(vault.src_dir().join("guide/index.md"), "# Guide")?;
+        fs::write(
+            vault.src_dir().join("guide/01-add.md"),
+            "# Adding\n\n::: exercise\nAdd `1` and `2`\n:::\n\n::: solution\nIt's [three](../numbers.md)\n:::\n",
+        )?;
+        vault.build()?;
+
+        let chapter = fs::read_to_string(vault.build_dir().join("guide/add.html"))?;
+        let solutions = fs::read_to_string(vault.build_dir().join(SOLUTIONS_FILE))?;
+        assert!(chapter.contains("<p class=\"exercise__title\">Exercise 1</p>"));
+        assert!(chapter.contains("<a href=\"/solutions.html#guide-add-exercise-1\">Solution</a>"));
+        assert!(!chapter.contains("three"));
+        assert!(solutions.contains("<h3 class=\"solution__title\" id=\"guide-add-exercise-1\"><a href=\"/guide/add.html#exercise-1\">Exercise 1</a></h3>"));
+        assert!(solutions.contains("<a href=\"/numbers.html\">three</a>"));
+
+        vault.config.exercises.solutions = Solutions::Hidden;
+        vault.build()?;
+        assert!(!vault.build_dir().join(SOLUTIONS_FILE).exists());
+        assert!(
+            !fs::read_to_string(vault.build_dir().join("guide/add.html"))?
+                .contains("exercise__solution")
+        );
+
+        vault.with_solutions = true;
+        vault.build()?;
+        assert!(vault.build_dir().join(SOLUTIONS_FILE).exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use super::Vault;
+use crate::renderer::favicon;
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+impl Vault {
+    /// Copies the favicon to the root of the build dir, along with the sizes drawn from it
+    pub(super) fn write_favicons(&self, favicon: &Path) -> Result<()> {
+        let path = self.path.join(favicon);
+        let icon = fs::read(&path)
+            .with_context(|| anyhow!("Failed to read the favicon {}", path.display()))?;
+
+        for variant in favicon::favicons(favicon, self.config.appearance.favicon_variants)? {
+            let destination = self.build_dir().join(&variant.file);
+            let contents = match variant.size {
+                Some(size) => favicon::resize(&icon, favicon, size)?,
+                None => icon.clone(),
+            };
+
+            util::remove_file_if_exists(&destination)
+                .and_then(|_| Ok(fs::write(&destination, contents)?))
+                .with_context(|| anyhow!("Failed to write {}", destination.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_write_the_favicon_and_its_sizes() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.base_path = "docs".to_string();
+        vault.config.appearance.favicon = Some("assets/icon.svg".into());
+        vault.config.appearance.favicon_variants = true;
+
+        fs::create_dir(temp_dir.path().join("assets"))?;
+        fs::write(
+            temp_dir.path().join("assets/icon.svg"),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"16\" height=\"16\"><circle cx=\"8\" cy=\"8\" r=\"8\"/></svg>",
+        )?;
+        fs::write(vault.src_dir().join("intro.md"), "# Intro")?;
+        vault.build()?;
+
+        assert!(vault.build_dir().join("favicon.svg").exists());
+        assert!(fs::read(vault.build_dir().join("apple-touch-icon.png"))?.starts_with(b"\x89PNG"));
+
+        let intro = fs::read_to_string(vault.build_dir().join("intro.html"))?;
+        assert!(intro
+            .contains("<link rel=\"icon\" href=\"/docs/favicon.svg\" type=\"image/svg+xml\" />"));
+        assert!(intro.contains(
+            "<link rel=\"icon\" href=\"/docs/favicon-32x32.png\" type=\"image/png\" sizes=\"32x32\" />"
+        ));
+        assert!(intro.contains(
+            "<link rel=\"apple-touch-icon\" href=\"/docs/apple-touch-icon.png\" sizes=\"180x180\" />"
+        ));
+
+        Ok(())
+    }
+}
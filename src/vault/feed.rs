@@ -0,0 +1,227 @@
+use super::search::{excerpt, plain_text};
+use super::{Content, LastUpdated, Vault};
+use crate::renderer::{self, audiences, markdown, AskamaRenderer, FEED_FILE};
+use crate::{util, FrontMatter};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use std::cmp::Reverse;
+use std::fs;
+
+impl Vault {
+    /// Writes an Atom feed with the newest chapters to the build dir. Chapters are dated by their
+    /// front matter or, if it has no date, by their last commit or the last time their file was
+    /// modified.
+    pub(super) fn write_feed(
+        &self,
+        content: &Content,
+        renderer: &AskamaRenderer,
+        last_updated: &LastUpdated,
+    ) -> Result<()> {
+        let options = &self.config.feed;
+        let mut entries = Vec::new();
+        let encrypted = self.encrypted_chapters(content)?;
+
+        for chapter in content.all_chapters() {
+            if !chapter.content.exists() || encrypted.contains(&chapter.content) {
+                continue;
+            }
+
+            let markdown = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+            let (front_matter, _) = FrontMatter::extract(&markdown).with_context(|| {
+                anyhow!("Invalid front matter in {}", chapter.content.display())
+            })?;
+            let date = match front_matter.date {
+                Some(date) => date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+                None => last_updated
+                    .get(&chapter.content)
+                    .copied()
+                    .unwrap_or_else(Utc::now),
+            };
+
+            entries.push((date, chapter, front_matter, markdown));
+        }
+
+        // Stable, so chapters of the same day keep the order of the summary
+        entries.sort_by_key(|(date, ..)| Reverse(*date));
+        entries.truncate(options.limit);
+
+        let site_url = match &self.config.general.site_url {
+            Some(site_url) => site_url.trim_end_matches('/'),
+            None => self.base_path.as_str(),
+        };
+        let title = options.title.as_ref().unwrap_or(&self.config.general.title);
+        let updated = entries
+            .first()
+            .map(|(date, ..)| *date)
+            .unwrap_or_else(Utc::now);
+        let mut authors: Vec<&String> = self
+            .config
+            .general
+            .authors
+            .iter()
+            .filter(|author| !author.is_empty())
+            .collect();
+        // Atom wants someone to blame for every entry
+        if authors.is_empty() {
+            authors.push(title);
+        }
+
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\">\n  \
+             <title>{}</title>\n  \
+             <id>{}</id>\n  \
+             <updated>{}</updated>\n  \
+             <link href=\"{}\" rel=\"self\"/>\n  \
+             <link href=\"{site_url}/\"/>\n",
+            util::escape_html(title),
+            id(site_url, FEED_FILE),
+            updated.to_rfc3339(),
+            util::absolute_url(site_url, FEED_FILE),
+        );
+        for author in authors {
+            xml.push_str(&format!(
+                "  <author>\n    <name>{}</name>\n  </author>\n",
+                util::escape_html(author)
+            ));
+        }
+
+        for (date, chapter, front_matter, markdown) in entries.iter() {
+            let page = renderer::output_path(&chapter.content, self.src_dir())?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let body = match options.full_content {
+                true => format!(
+                    "<content type=\"html\" xml:base=\"{site_url}/\">{}</content>",
+                    util::escape_html(&renderer.render_content(chapter, markdown)?)
+                ),
+                false => format!(
+                    "<summary>{}</summary>",
+                    util::escape_html(&summary(self, front_matter, markdown)?)
+                ),
+            };
+
+            xml.push_str(&format!(
+                "  <entry>\n    \
+                 <title>{}</title>\n    \
+                 <link href=\"{}\"/>\n    \
+                 <id>{}</id>\n    \
+                 <updated>{}</updated>\n    \
+                 {body}\n  \
+                 </entry>\n",
+                util::escape_html(&chapter.title),
+                util::absolute_url(site_url, &page),
+                id(site_url, &page),
+                date.to_rfc3339(),
+            ));
+        }
+
+        xml.push_str("</feed>\n");
+
+        let path = self.build_dir().join(FEED_FILE);
+        fs::write(&path, xml).with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+}
+
+// The description of the chapter or, if it has none, the beginning of its text
+fn summary(vault: &Vault, front_matter: &FrontMatter, markdown: &str) -> Result<String> {
+    if let Some(description) = &front_matter.description {
+        return Ok(description.clone());
+    }
+
+    let body = FrontMatter::extract(markdown)
+        .map(|(_, body)| body)
+        .unwrap_or(markdown);
+    let body = audiences::audiences(body, vault.audience.as_deref())?;
+    let options = markdown::options(&vault.config.markdown.extensions);
+
+    Ok(excerpt(
+        &plain_text(&body, options),
+        vault.config.search.max_excerpt_length,
+    ))
+}
+
+// Without a site url, there is nothing unique to tell the entries of different vaults apart
+fn id(site_url: &str, page: &str) -> String {
+    match site_url.contains("://") {
+        true => util::absolute_url(site_url, page),
+        false => format!(
+            "urn:cahlter:{}",
+            util::absolute_url("", page).trim_start_matches('/')
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn vault_with_posts(path: &Path) -> Result<Vault, Box<dyn Error>> {
+        let mut vault = Vault::new(path);
+        vault.init()?;
+        vault.config.general.title = "Changelog".to_string();
+        vault.config.general.site_url = Some("https://example.com".to_string());
+        vault.config.feed.enabled = true;
+        vault.config.feed.limit = 2;
+
+        fs::write(
+            vault.src_dir().join("01-one.md"),
+            "---\ndate: 2024-01-01\n---\n# One\n\nThe first one",
+        )?;
+        fs::write(
+            vault.src_dir().join("02-two.md"),
+            "---\ndate: 2024-03-01\ndescription: Tom & Jerry\n---\n# Two",
+        )?;
+        fs::write(
+            vault.src_dir().join("03-three.md"),
+            "---\ndate: 2024-02-01\n---\n# Three\n\nThe *third* one",
+        )?;
+
+        Ok(vault)
+    }
+
+    #[test]
+    fn it_should_write_a_feed_of_the_newest_chapters() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = vault_with_posts(temp_dir.path())?;
+        vault.build()?;
+
+        let xml = fs::read_to_string(vault.build_dir().join(FEED_FILE))?;
+
+        assert!(xml.contains("<title>Changelog</title>"));
+        assert!(xml.contains("<updated>2024-03-01T00:00:00+00:00</updated>\n  <link"));
+        assert!(xml.contains("<link href=\"https://example.com/feed.xml\" rel=\"self\"/>"));
+        assert!(
+            xml.contains("<title>Two</title>\n    <link href=\"https://example.com/two.html\"/>")
+        );
+        assert!(xml.contains("<summary>Tom &amp; Jerry</summary>"));
+        assert!(xml.contains("<summary>Three The third one</summary>"));
+        assert!(xml.find("<title>Two</title>") < xml.find("<title>Three</title>"));
+        assert!(!xml.contains("<title>One</title>"));
+        assert!(fs::read_to_string(vault.build_dir().join("two.html"))?
+            .contains("href=\"/feed.xml\" rel=\"alternate\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_put_whole_chapters_in_the_feed() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = vault_with_posts(temp_dir.path())?;
+        vault.config.feed.full_content = true;
+        vault.build()?;
+
+        let xml = fs::read_to_string(vault.build_dir().join(FEED_FILE))?;
+
+        assert!(xml.contains(
+            "<content type=\"html\" xml:base=\"https://example.com/\">&lt;h1 id=&quot;three&quot;&gt;Three&lt;a class=&quot;heading__permalink&quot; href=&quot;#three&quot; aria-label=&quot;Link to this section&quot;&gt;&lt;i class=&quot;ti ti-link&quot;&gt;&lt;/i&gt;&lt;/a&gt;&lt;/h1&gt;\n&lt;p&gt;The &lt;em&gt;third&lt;/em&gt; one&lt;/p&gt;\n</content>"
+        ));
+
+        Ok(())
+    }
+}
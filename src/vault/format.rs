@@ -0,0 +1,343 @@
+use super::Vault;
+use crate::renderer::markdown;
+use crate::FrontMatter;
+use anyhow::{anyhow, Context, Result};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::collections::HashSet;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+impl Vault {
+    /// The chapters whose markdown isn't formatted like [`format`] would, relative to the vault
+    pub fn unformatted(&self) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .find_unformatted()?
+            .into_iter()
+            .map(|(path, _)| self.relative(&path))
+            .collect())
+    }
+
+    /// Formats the chapters found by [`Vault::unformatted`] and returns them
+    pub fn format(&self) -> Result<Vec<PathBuf>> {
+        let mut formatted = Vec::new();
+
+        for (path, markdown) in self.find_unformatted()? {
+            fs::write(&path, markdown)
+                .with_context(|| anyhow!("Failed to write {}", path.display()))?;
+            formatted.push(self.relative(&path));
+        }
+
+        Ok(formatted)
+    }
+
+    // Each chapter that changes, as its formatted markdown
+    fn find_unformatted(&self) -> Result<Vec<(PathBuf, String)>> {
+        let options = markdown::options(&self.config.markdown.extensions);
+        let mut seen = HashSet::new();
+        let mut unformatted = Vec::new();
+
+        for chapter in self.chapters_with_content()?.iter() {
+            // The same file can be in the summary more than once
+            if !seen.insert(chapter.content.clone()) {
+                continue;
+            }
+
+            let markdown = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+            let formatted = format(&markdown, options);
+
+            if formatted != markdown {
+                unformatted.push((chapter.content.clone(), formatted));
+            }
+        }
+
+        Ok(unformatted)
+    }
+}
+
+/// Normalizes the markdown without changing what it renders to:
+///
+/// - Headings use `#`, with one space after them and no closing `#`.
+/// - Bullets are `-`, unless the list is right next to another one. Only a different bullet keeps
+///   them apart.
+/// - Hard breaks are a `\` instead of trailing spaces, which are removed everywhere else.
+/// - There's never more than one blank line in a row, and the file ends with a single newline.
+/// - Reference definitions go at the end, in the order they were written.
+///
+/// Code blocks, html blocks and the front matter are left as they are.
+pub fn format(markdown: &str, options: Options) -> String {
+    let body = FrontMatter::extract(markdown)
+        .map(|(_, body)| body)
+        .unwrap_or(markdown);
+    let front_matter = markdown[..markdown.len() - body.len()].trim_end();
+
+    let (body, definitions) = rewrite(body, options);
+    let body = whitespace(&body, options);
+    let mut formatted = match front_matter.is_empty() {
+        true => body.trim_end().to_string(),
+        false => format!("{front_matter}\n\n{}", body.trim_end()),
+    };
+
+    if !definitions.is_empty() {
+        formatted.push_str("\n\n");
+        formatted.push_str(&definitions.join("\n"));
+    }
+    if !formatted.is_empty() {
+        formatted.push('\n');
+    }
+
+    formatted
+}
+
+// Rewrites headings, bullets and hard breaks, and takes the reference definitions out
+fn rewrite(body: &str, options: Options) -> (String, Vec<String>) {
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    // Setext headings inside block quotes or lists have markers on each line, so they stay
+    let mut containers = 0;
+    // The bullets of each list, and whether it's right next to another one
+    let mut lists: Vec<(Vec<usize>, bool)> = Vec::new();
+    // The lists being read, with `None` for numbered ones
+    let mut open: Vec<Option<usize>> = Vec::new();
+    let mut just_closed: Option<usize> = None;
+    let mut parser = Parser::new_ext(body, options).into_offset_iter();
+
+    for (event, range) in parser.by_ref() {
+        let source = &body[range.clone()];
+        let closed = just_closed.take();
+
+        match event {
+            Event::Start(Tag::List(None)) => {
+                let adjacent = closed.is_some();
+                if let Some(previous) = closed {
+                    lists[previous].1 = true;
+                }
+
+                open.push(Some(lists.len()));
+                lists.push((Vec::new(), adjacent));
+            }
+            Event::Start(Tag::List(Some(_))) => open.push(None),
+            Event::End(Tag::List(_)) => just_closed = open.pop().flatten(),
+            Event::Start(Tag::BlockQuote | Tag::Item) => {
+                containers += 1;
+
+                if let (Event::Start(Tag::Item), Some(Some(list))) = (&event, open.last()) {
+                    if source.starts_with(['*', '+']) {
+                        lists[*list].0.push(range.start);
+                    }
+                }
+            }
+            Event::End(Tag::BlockQuote | Tag::Item) => containers -= 1,
+            Event::Start(Tag::Heading(level, _, _)) => {
+                let newline = if source.ends_with('\n') { "\n" } else { "" };
+                let text = match source.starts_with('#') {
+                    true => atx_text(source.trim_end()).to_string(),
+                    false if containers == 0 => setext_text(source),
+                    false => continue,
+                };
+                let heading = format!("{} {text}{newline}", "#".repeat(level as usize));
+
+                if heading != source {
+                    edits.push((range, heading));
+                }
+            }
+            Event::HardBreak if source.starts_with([' ', '\t']) => {
+                edits.push((range, String::from("\\\n")));
+            }
+            _ => {}
+        }
+    }
+
+    for (bullets, adjacent) in lists {
+        if !adjacent {
+            edits.extend(
+                bullets
+                    .into_iter()
+                    .map(|start| (start..start + 1, String::from("-"))),
+            );
+        }
+    }
+
+    let mut definitions: Vec<(Range<usize>, String)> = parser
+        .reference_definitions()
+        .iter()
+        .map(|(_, definition)| definition.span.clone())
+        .filter(|span| span.start == 0 || body[..span.start].ends_with('\n'))
+        .filter_map(|span| definition(&body[span.clone()]).map(|line| (span, line)))
+        .collect();
+    definitions.sort_by_key(|(span, _)| span.start);
+
+    for (span, _) in definitions.iter() {
+        let end = match body[span.end..].starts_with('\n') {
+            true => span.end + 1,
+            false => span.end,
+        };
+        edits.push((span.start..end, String::new()));
+    }
+
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut rewritten = String::with_capacity(body.len());
+    let mut end = 0;
+    for (range, replacement) in edits {
+        // Nested edits are already covered by the ones around them
+        if range.start < end {
+            continue;
+        }
+
+        rewritten.push_str(&body[end..range.start]);
+        rewritten.push_str(&replacement);
+        end = range.end;
+    }
+    rewritten.push_str(&body[end..]);
+
+    (
+        rewritten,
+        definitions.into_iter().map(|(_, line)| line).collect(),
+    )
+}
+
+// The text of `## Title ##`, without the opening and closing sequences
+fn atx_text(source: &str) -> &str {
+    let text = source.trim_start_matches('#').trim();
+    let without_closing = text.trim_end_matches('#');
+
+    match without_closing.is_empty() || without_closing.ends_with([' ', '\t']) {
+        true => without_closing.trim_end(),
+        false => text,
+    }
+}
+
+// The text of a heading underlined with `=` or `-`, in a single line
+fn setext_text(source: &str) -> String {
+    let lines: Vec<&str> = source.trim_end().lines().collect();
+
+    lines[..lines.len().saturating_sub(1)]
+        .iter()
+        .map(|line| line.trim())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// The definition as `[label]: destination "title"`, keeping what was written
+fn definition(source: &str) -> Option<String> {
+    let end = source.find("]:")?;
+    let rest: Vec<&str> = source[end + 2..]
+        .trim()
+        .lines()
+        .map(|line| line.trim_end())
+        .collect();
+
+    match rest.is_empty() {
+        true => None,
+        false => Some(format!("{}: {}", &source[..end + 1], rest.join("\n"))),
+    }
+}
+
+// Removes trailing whitespace and extra blank lines outside of code and html blocks
+fn whitespace(body: &str, options: Options) -> String {
+    let mut code: Vec<Range<usize>> = Vec::new();
+    // Each line of an html block is an event of its own
+    let mut html: Vec<Range<usize>> = Vec::new();
+    let mut inline = 0;
+
+    for (event, range) in Parser::new_ext(body, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => code.push(range),
+            Event::Start(Tag::Paragraph | Tag::Heading(..) | Tag::TableCell) => inline += 1,
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::TableCell) => inline -= 1,
+            Event::Html(_) if inline == 0 => html.push(range),
+            _ => {}
+        }
+    }
+    let mut output = String::with_capacity(body.len());
+    let mut start = 0;
+    let mut blank = false;
+
+    for line in body.split_inclusive('\n') {
+        let end = start + line.len();
+        let verbatim = code.iter().any(|range| range.contains(&start))
+            || html
+                .iter()
+                .any(|range| range.start < end && start < range.end);
+        start = end;
+
+        if verbatim {
+            output.push_str(line);
+            blank = false;
+            continue;
+        }
+
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() && (blank || output.is_empty()) {
+            continue;
+        }
+
+        blank = trimmed.is_empty();
+        output.push_str(trimmed);
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_normalize_the_markdown() {
+        let options = Options::all();
+
+        assert_eq!(
+            format(
+                "Title\n=====\n\n##   Install ##  \n\n* one\n+ two  \nstill two\n\n\n\nSee [the book][book].\n\n[book]:   https://example.com \"Book\"\n\n## C#\n",
+                options
+            ),
+            "# Title\n\n## Install\n\n* one\n+ two\\\nstill two\n\nSee [the book][book].\n\n## C#\n\n[book]: https://example.com \"Book\"\n"
+        );
+        // Two lists stay two lists, but a list on its own gets dashes
+        assert_eq!(
+            format("* a\n* b\n- c\n- d\n\ntext\n\n+ e\n  * f\n", options),
+            "* a\n* b\n- c\n- d\n\ntext\n\n- e\n  - f\n"
+        );
+        assert_eq!(
+            format(
+                "<pre>\nfn main() {  \n\n\n}\n</pre>\n\n\n\nafter  \n",
+                options
+            ),
+            "<pre>\nfn main() {  \n\n\n}\n</pre>\n\nafter\n"
+        );
+        assert_eq!(
+            format(
+                "---\ntitle: Intro\n---\n\n> * quoted\n\n```md\n* code  \n\n\n# stays\n```\n",
+                options
+            ),
+            "---\ntitle: Intro\n---\n\n> - quoted\n\n```md\n* code  \n\n\n# stays\n```\n"
+        );
+
+        let formatted = "# Title\n\n- one\\\ntwo\n\n[a]: https://example.com\n";
+        assert_eq!(format(formatted, options), formatted);
+    }
+
+    #[test]
+    fn it_should_only_format_the_chapters_that_need_it() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("intro.md"), "Intro\n=====\n")?;
+        fs::write(vault.src_dir().join("done.md"), "# Done\n")?;
+
+        assert_eq!(vault.unformatted()?, vec![PathBuf::from("src/intro.md")]);
+        assert_eq!(vault.format()?, vec![PathBuf::from("src/intro.md")]);
+        assert_eq!(
+            fs::read_to_string(vault.src_dir().join("intro.md"))?,
+            "# Intro\n"
+        );
+        assert!(vault.unformatted()?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+use super::{BuildReport, Vault};
+use crate::config::Language;
+use crate::renderer::{self, SiteLanguage};
+use crate::summary::SUMMARY_FILE_NAMES;
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+impl Vault {
+    /// Builds the default language at the root of the build dir and, with `multiple_language`,
+    /// each of the `languages` into a dir of the build dir named like its own (see
+    /// [`Language::code`]). Pages link to themselves in the other languages.
+    pub(super) fn build_languages(&mut self) -> Result<BuildReport> {
+        let mut translations = match self.config.general.multiple_language {
+            true => self.stage_translations()?,
+            false => Vec::new(),
+        };
+        let report = self.build_staged(&mut translations);
+
+        for (_, translated) in translations.iter() {
+            let staged = translated.src_dir();
+            fs::remove_dir_all(&staged)
+                .with_context(|| anyhow!("Failed to remove {}", staged.display()))?;
+        }
+
+        report
+    }
+
+    fn build_staged(&mut self, translations: &mut [(Language, Vault)]) -> Result<BuildReport> {
+        let languages = self.site_languages(translations)?;
+        self.languages = languages.clone();
+        let mut report = self.build_chapters(None)?;
+
+        for (language, translated) in translations.iter_mut() {
+            translated.languages = languages.clone();
+            // Along with the moves the default language just recorded
+            translated.config.redirects = self.config.redirects.clone();
+            let built = translated
+                .build_chapters(None)
+                .with_context(|| anyhow!("Failed to build {}", language.name))?;
+
+            report.chapters += built.chapters;
+            report.cached += built.cached;
+            report.assets += built.assets;
+            report.duration += built.duration;
+        }
+
+        Ok(report)
+    }
+
+    // The languages that aren't the default one, which lives in the source dir
+    pub(super) fn translations(&self) -> impl Iterator<Item = &Language> {
+        self.config
+            .languages
+            .iter()
+            .filter(|language| !self.is_default(language))
+    }
+
+    fn is_default(&self, language: &Language) -> bool {
+        self.relative(&self.path.join(&language.path)) == self.relative(&self.src_dir())
+    }
+
+    // Each translation is laid over a copy of the source dir, so chapters that weren't translated
+    // yet fall back to the default language. The copy sits next to the source dir, so the paths
+    // chapters include files from resolve just like they would from it.
+    fn stage_translations(&self) -> Result<Vec<(Language, Vault)>> {
+        let dirs: Vec<PathBuf> = self
+            .translations()
+            .map(|language| self.path.join(&language.path))
+            .collect();
+        let mut translations = Vec::new();
+
+        for language in self.translations() {
+            let dir = self.path.join(&language.path);
+            let code = language.code();
+
+            if !dir.is_dir() {
+                warn!("Missing the dir of {}: {}", language.name, dir.display());
+                continue;
+            }
+
+            let staged = self.staged_dir(&code);
+            if staged.exists() {
+                fs::remove_dir_all(&staged)
+                    .with_context(|| anyhow!("Failed to remove {}", staged.display()))?;
+            }
+
+            util::copy_dir(
+                self.src_dir(),
+                &staged,
+                |relative| dirs.contains(&self.src_dir().join(relative)),
+                |_| {},
+            )?;
+            // A translation with a summary of its own keeps only that one
+            if SUMMARY_FILE_NAMES
+                .iter()
+                .any(|name| dir.join(name).exists())
+            {
+                for name in SUMMARY_FILE_NAMES {
+                    let summary = staged.join(name);
+
+                    if summary.exists() {
+                        fs::remove_file(&summary)
+                            .with_context(|| anyhow!("Failed to remove {}", summary.display()))?;
+                    }
+                }
+            }
+            util::copy_dir(&dir, &staged, |_| false, |_| {})?;
+
+            let mut config = self.config.clone();
+            config.general.src_dir = staged;
+            config.general.build_dir = self.config.general.build_dir.join(&code);
+            config.general.language = code.clone();
+            config.general.site_url = config
+                .general
+                .site_url
+                .map(|site_url| format!("{}/{code}", site_url.trim_end_matches('/')));
+            let translated = Vault {
+                config,
+                path: self.path.clone(),
+                live_reload: self.live_reload,
+                base_path: format!("{}/{code}", self.base_path),
+                profile: self.profile,
+                with_solutions: self.with_solutions,
+                no_cache: self.no_cache,
+                audience: self.audience.clone(),
+                content_cache: None,
+                translation: Some((dir, self.src_dir())),
+                languages: Vec::new(),
+            };
+            fs::create_dir_all(translated.build_dir()).with_context(|| {
+                anyhow!("Failed to create {}", translated.build_dir().display())
+            })?;
+
+            translations.push((language.clone(), translated));
+        }
+
+        Ok(translations)
+    }
+
+    // The default language and the translations, with the pages each one has. There's nothing to
+    // switch to without translations.
+    fn site_languages(&self, translations: &[(Language, Vault)]) -> Result<Vec<SiteLanguage>> {
+        if translations.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let name = self
+            .config
+            .languages
+            .iter()
+            .find(|language| self.is_default(language))
+            .map(|language| language.name.clone())
+            .unwrap_or_else(|| String::from("Default"));
+        let mut languages = vec![SiteLanguage {
+            name,
+            base_path: self.base_path.clone(),
+            pages: self.site_pages()?,
+        }];
+
+        for (language, translated) in translations {
+            languages.push(SiteLanguage {
+                name: language.name.clone(),
+                base_path: translated.base_path.clone(),
+                pages: translated.site_pages()?,
+            });
+        }
+
+        Ok(languages)
+    }
+
+    // The pages the chapters become, relative to the build dir
+    fn site_pages(&self) -> Result<Vec<PathBuf>> {
+        self.without_private(self.content()?)?
+            .all_chapters()
+            .into_iter()
+            .filter(|chapter| chapter.content.exists())
+            .map(|chapter| renderer::output_path(&chapter.content, self.src_dir()))
+            .collect()
+    }
+
+    // Where a translation is laid over the source dir (e.g. `.src.pt-br` for `src`)
+    fn staged_dir(&self, code: &str) -> PathBuf {
+        let src_dir = self.src_dir();
+        let name = src_dir.file_name().unwrap_or_default().to_string_lossy();
+
+        src_dir.with_file_name(format!(".{name}.{code}"))
+    }
+
+    /// Where a file of the source dir really comes from. Translations are built from a copy, but
+    /// their history is the one of the files that were copied.
+    pub(super) fn origin(&self, path: &Path) -> PathBuf {
+        let Some((dir, src_dir)) = &self.translation else {
+            return path.to_path_buf();
+        };
+        let Ok(relative) = path.strip_prefix(self.src_dir()) else {
+            return path.to_path_buf();
+        };
+
+        match dir.join(relative).exists() {
+            true => dir.join(relative),
+            false => src_dir.join(relative),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::vault::CONFIG_FILE;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_build_each_language_with_the_default_as_a_fallback() -> Result<(), Box<dyn Error>>
+    {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.config.general.multiple_language = true;
+        vault.config.languages = vec![
+            Language {
+                name: String::from("English"),
+                path: String::from("src"),
+            },
+            Language {
+                name: String::from("Português"),
+                path: String::from("pt-br"),
+            },
+        ];
+
+        fs::write(vault.src_dir().join("intro.md"), "# Hello there")?;
+        fs::write(vault.src_dir().join("setup.md"), "# General Kenobi")?;
+        fs::create_dir(temp_dir.path().join("pt-br"))?;
+        fs::write(temp_dir.path().join("pt-br/intro.md"), "# Olá")?;
+
+        let report = vault.build()?;
+        assert_eq!(report.chapters, 4);
+
+        let build_dir = vault.build_dir();
+        assert!(fs::read_to_string(build_dir.join("intro.html"))?.contains("Hello there"));
+        assert!(fs::read_to_string(build_dir.join("intro.html"))?.contains("<html lang=\"en\""));
+        assert!(fs::read_to_string(build_dir.join("pt-br/intro.html"))?.contains("Olá"));
+        assert!(fs::read_to_string(build_dir.join("pt-br/intro.html"))?
+            .contains("<html lang=\"pt-br\""));
+        assert!(fs::read_to_string(build_dir.join("pt-br/setup.html"))?.contains("General Kenobi"));
+        assert!(fs::read_to_string(build_dir.join("pt-br/intro.html"))?
+            .contains("href=\"/pt-br/setup.html\""));
+        assert!(fs::read_to_string(build_dir.join("setup.html"))?.contains(
+            "<a class=\"language-switcher__item\" href=\"/pt-br/setup.html\">Português</a>"
+        ));
+        assert!(!temp_dir.path().join(".src.pt-br").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_record_moves_only_in_the_config_of_the_default_language(
+    ) -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.config.general.multiple_language = true;
+        vault.config.languages = vec![
+            Language {
+                name: String::from("English"),
+                path: String::from("src"),
+            },
+            Language {
+                name: String::from("Português"),
+                path: String::from("pt-br"),
+            },
+        ];
+
+        fs::write(vault.src_dir().join("guide.md"), "# Guide")?;
+        fs::create_dir(temp_dir.path().join("pt-br"))?;
+        fs::write(temp_dir.path().join("pt-br/guide.md"), "# Guia")?;
+        vault.build()?;
+
+        fs::rename(
+            vault.src_dir().join("guide.md"),
+            vault.src_dir().join("tutorial.md"),
+        )?;
+        fs::rename(
+            temp_dir.path().join("pt-br/guide.md"),
+            temp_dir.path().join("pt-br/tutorial.md"),
+        )?;
+        vault.build()?;
+
+        let config = Config::from_disk(temp_dir.path().join(CONFIG_FILE))?;
+        assert_eq!(config.general.src_dir, vault.config.general.src_dir);
+        assert_eq!(config.general.build_dir, vault.config.general.build_dir);
+        assert_eq!(config.general.language, vault.config.general.language);
+        assert_eq!(
+            config.redirects.into_iter().collect::<Vec<_>>(),
+            vec![("guide.html".to_string(), "tutorial.html".to_string())]
+        );
+        assert!(vault.build_dir().join("pt-br/guide.html").exists());
+
+        Ok(())
+    }
+}
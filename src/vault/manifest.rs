@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = ".cahlter-manifest.json";
+
+/// A record of what the last build produced. It's written to the build dir, so later commands
+/// can compare the vault against it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Manifest {
+    pub chapters: Vec<ManifestChapter>,
+    /// Hash of everything, other than its own file, a page depends on (config, summary, etc.)
+    #[serde(default)]
+    pub inputs: String,
+}
+
+/// A single chapter written by the build
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ManifestChapter {
+    /// The chapter's file, relative to the source dir
+    pub source: PathBuf,
+    /// The generated page, relative to the build dir
+    pub output: PathBuf,
+    pub words: usize,
+    /// How many minutes the chapter takes to read
+    #[serde(default)]
+    pub reading_time: usize,
+    /// Hash of the chapter's file
+    #[serde(default)]
+    pub hash: String,
+    /// The anchors of the headings in the page, so renamed ones can be found later
+    #[serde(default)]
+    pub anchors: Vec<String>,
+}
+
+impl Manifest {
+    pub fn new(chapters: Vec<ManifestChapter>, inputs: String) -> Self {
+        Self { chapters, inputs }
+    }
+
+    /// Reads the manifest left in the given build dir. It returns `None` if the vault was never built
+    pub fn from_disk<P>(build_dir: P) -> Result<Option<Manifest>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = build_dir.as_ref().join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = fs::read_to_string(&path)
+            .with_context(|| anyhow!("Failed to read {}", path.display()))?;
+        let manifest = serde_json::from_str(&file)
+            .with_context(|| anyhow!("Failed to parse {}", path.display()))?;
+
+        Ok(Some(manifest))
+    }
+
+    pub fn save<P>(&self, build_dir: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = build_dir.as_ref().join(MANIFEST_FILE);
+        let serialized = serde_json::to_string_pretty(self)?;
+
+        fs::write(&path, serialized).with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+
+    /// Looks for a chapter by its file, relative to the source dir
+    pub fn chapter<P>(&self, source: P) -> Option<&ManifestChapter>
+    where
+        P: AsRef<Path>,
+    {
+        self.chapters
+            .iter()
+            .find(|chapter| chapter.source == source.as_ref())
+    }
+
+    pub fn words(&self) -> usize {
+        self.chapters.iter().map(|chapter| chapter.words).sum()
+    }
+}
@@ -0,0 +1,337 @@
+use super::manifest::Manifest;
+use super::{Vault, CONFIG_FILE};
+use crate::renderer::{self, markdown};
+use crate::{util, Chapter, Content, FrontMatter};
+use anyhow::{anyhow, Context, Result};
+use log::info;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A chapter whose file moved since the last build, as its pages before and after
+#[derive(Debug, Clone, PartialEq)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// A relative link to a chapter that isn't there anymore, and where it should point now
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovedLink {
+    /// The chapter with the link, relative to the vault
+    pub path: PathBuf,
+    pub line: usize,
+    pub url: String,
+    pub replacement: String,
+}
+
+impl fmt::Display for MovedLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} -> {}",
+            self.path.display(),
+            self.line,
+            self.url,
+            self.replacement
+        )
+    }
+}
+
+impl Vault {
+    /// Compares the chapters with the last build. A chapter whose file is gone, but whose contents
+    /// showed up in a file the last build didn't know about, was moved there.
+    pub(super) fn find_moves(&self, content: &Content, previous: &Manifest) -> Result<Vec<Move>> {
+        let mut added = Vec::new();
+
+        for chapter in content.all_chapters().iter() {
+            let source: PathBuf = chapter
+                .content
+                .strip_prefix(self.src_dir())?
+                .components()
+                .collect();
+
+            if chapter.content.exists() && previous.chapter(&source).is_none() {
+                added.push((
+                    renderer::output_path(&chapter.content, self.src_dir())?,
+                    util::content_hash(fs::read(&chapter.content)?),
+                ));
+            }
+        }
+
+        let mut moves = Vec::new();
+        for built in previous.chapters.iter() {
+            // Manifests written before hashes were recorded can't tell
+            if built.hash.is_empty() || self.src_dir().join(&built.source).exists() {
+                continue;
+            }
+
+            if let Some((output, _)) = added.iter().find(|(_, hash)| *hash == built.hash) {
+                // Number prefixes aren't part of the page, so renumbered chapters didn't move
+                if *output != built.output {
+                    moves.push(Move {
+                        from: built.output.clone(),
+                        to: output.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(moves)
+    }
+
+    /// Adds the moves to the redirects of the config and saves it, so the old pages keep working
+    pub(super) fn record_moves(&mut self, moves: &[Move]) -> Result<()> {
+        if moves.is_empty() {
+            return Ok(());
+        }
+
+        for moved in moves.iter() {
+            let from = util::url_path(&moved.from);
+            let to = util::url_path(&moved.to);
+
+            // Pages that already redirected to the old one go straight to the new one
+            for target in self.config.redirects.values_mut() {
+                if *target == from {
+                    *target = to.clone();
+                }
+            }
+
+            info!("{from} moved to {to}. Recorded a redirect");
+            self.config.redirects.insert(from, to);
+        }
+
+        // A page that moved back to where it was doesn't need one
+        self.config.redirects.retain(|from, to| from != to);
+        self.config.save(self.path.join(CONFIG_FILE))
+    }
+
+    /// Finds the relative links to chapters that aren't where the links expect them anymore. The
+    /// redirects of the config, or a chapter with the same page, tell where they are now.
+    /// Reference-style links are left out.
+    pub fn moved_links(&self) -> Result<Vec<MovedLink>> {
+        let chapters = self.chapters_with_content()?;
+        let pages = self.pages(&chapters)?;
+        let mut links = Vec::new();
+
+        for chapter in chapters.iter() {
+            let markdown = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+
+            links.extend(
+                self.find_moved_links(chapter, &markdown, &pages)?
+                    .into_iter()
+                    .map(|(_, link)| link),
+            );
+        }
+
+        Ok(links)
+    }
+
+    /// Rewrites the links found by [`Vault::moved_links`] in the source files and returns them
+    pub fn relink(&self) -> Result<Vec<MovedLink>> {
+        let chapters = self.chapters_with_content()?;
+        let pages = self.pages(&chapters)?;
+        let mut links = Vec::new();
+
+        for chapter in chapters.iter() {
+            let mut markdown = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+            let found = self.find_moved_links(chapter, &markdown, &pages)?;
+
+            if found.is_empty() {
+                continue;
+            }
+
+            // From the end, so the ranges that are left stay valid
+            for (range, link) in found.iter().rev() {
+                markdown.replace_range(range.clone(), &link.replacement);
+            }
+
+            fs::write(&chapter.content, markdown)
+                .with_context(|| anyhow!("Failed to write {}", chapter.content.display()))?;
+            links.extend(found.into_iter().map(|(_, link)| link));
+        }
+
+        Ok(links)
+    }
+
+    pub(super) fn chapters_with_content(&self) -> Result<Vec<Chapter>> {
+        Ok(self
+            .content()?
+            .all_chapters()
+            .into_iter()
+            .filter(|chapter| chapter.content.exists())
+            .collect())
+    }
+
+    // Each page and the file it comes from, relative to the source dir
+    fn pages(&self, chapters: &[Chapter]) -> Result<HashMap<String, PathBuf>> {
+        let mut pages = HashMap::new();
+
+        for chapter in chapters.iter() {
+            pages.insert(
+                util::url_path(renderer::output_path(&chapter.content, self.src_dir())?),
+                chapter.content.strip_prefix(self.src_dir())?.to_path_buf(),
+            );
+        }
+
+        Ok(pages)
+    }
+
+    // The ranges are where the urls are in the markdown
+    fn find_moved_links(
+        &self,
+        chapter: &Chapter,
+        markdown: &str,
+        pages: &HashMap<String, PathBuf>,
+    ) -> Result<Vec<(Range<usize>, MovedLink)>> {
+        let body = FrontMatter::extract(markdown)
+            .map(|(_, body)| body)
+            .unwrap_or(markdown);
+        let offset = markdown.len() - body.len();
+        let dir = chapter.content.parent().unwrap_or(Path::new(""));
+        let relative_dir = dir.strip_prefix(self.src_dir()).unwrap_or(Path::new(""));
+        let options = markdown::options(&self.config.markdown.extensions);
+        let mut links = Vec::new();
+
+        for (event, range) in Parser::new_ext(body, options).into_offset_iter() {
+            let Event::Start(Tag::Link(_, url, _)) = event else {
+                continue;
+            };
+
+            let (path, suffix) = url.split_at(url.find(['#', '?']).unwrap_or(url.len()));
+            if !markdown::is_relative_url(&url) || !path.ends_with(".md") || dir.join(path).exists()
+            {
+                continue;
+            }
+
+            let Some(target) = util::normalize(&relative_dir.join(path)) else {
+                continue;
+            };
+            let Some(source) = pages.get(&self.follow_redirects(&target)) else {
+                continue;
+            };
+            let Some(start) = markdown[offset + range.start..offset + range.end]
+                .find(&format!("]({path}"))
+                .map(|i| offset + range.start + i + 2)
+            else {
+                continue;
+            };
+
+            let mut replacement = util::relative_path(relative_dir, source)
+                .to_string_lossy()
+                .replace('\\', "/");
+            if path.starts_with("./") && !replacement.starts_with("..") {
+                replacement.insert_str(0, "./");
+            }
+            replacement.push_str(suffix);
+
+            links.push((
+                start..start + path.len() + suffix.len(),
+                MovedLink {
+                    path: self.relative(&chapter.content),
+                    line: markdown[..start].matches('\n').count() + 1,
+                    url: url.to_string(),
+                    replacement,
+                },
+            ));
+        }
+
+        Ok(links)
+    }
+
+    // The page the chapter would have, after all the redirects it went through
+    fn follow_redirects(&self, source: &Path) -> String {
+        let mut page = util::url_path(util::strip_number_prefixes(source).with_extension("html"));
+
+        // Each redirect is followed once at most, in case they go in circles
+        for _ in 0..self.config.redirects.len() {
+            match self.config.redirects.get(&page) {
+                Some(next) => page = next.clone(),
+                None => break,
+            }
+        }
+
+        page
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_record_the_chapters_that_moved() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("setup.md"), "# Setup")?;
+        fs::write(vault.src_dir().join("01-intro.md"), "# Intro")?;
+        vault.build()?;
+
+        fs::create_dir(vault.src_dir().join("guide"))?;
+        fs::rename(
+            vault.src_dir().join("setup.md"),
+            vault.src_dir().join("guide/setup.md"),
+        )?;
+        fs::rename(
+            vault.src_dir().join("01-intro.md"),
+            vault.src_dir().join("02-intro.md"),
+        )?;
+        fs::write(vault.src_dir().join("guide/index.md"), "# Guide")?;
+        vault.build()?;
+
+        let config = crate::config::Config::from_disk(temp_dir.path().join(CONFIG_FILE))?;
+        assert_eq!(
+            config.redirects.into_iter().collect::<Vec<_>>(),
+            vec![("setup.html".to_string(), "guide/setup.html".to_string())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_rewrite_the_links_to_moved_chapters() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::create_dir(vault.src_dir().join("guide"))?;
+        fs::write(vault.src_dir().join("guide/index.md"), "# Guide")?;
+        fs::write(vault.src_dir().join("guide/install.md"), "# Install")?;
+        fs::write(vault.src_dir().join("02-faq.md"), "# FAQ")?;
+        fs::write(
+            vault.src_dir().join("intro.md"),
+            "# Intro\n\nSee [install](./install.md#cargo), [the faq](01-faq.md) and [setup](setup.md).\n",
+        )?;
+        vault
+            .config
+            .redirects
+            .insert("install.html".to_string(), "guide/install.html".to_string());
+
+        let links = vault.moved_links()?;
+        assert_eq!(links.len(), 2);
+        assert_eq!(
+            links[0].to_string(),
+            "src/intro.md:3: ./install.md#cargo -> ./guide/install.md#cargo"
+        );
+
+        vault.relink()?;
+        assert_eq!(
+            fs::read_to_string(vault.src_dir().join("intro.md"))?,
+            "# Intro\n\nSee [install](./guide/install.md#cargo), [the faq](02-faq.md) and [setup](setup.md).\n"
+        );
+        assert!(vault.moved_links()?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use super::{Content, Vault};
+use crate::renderer::{self, AskamaRenderer, THEME_PREVIEW_FILE};
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use std::fs;
+use std::path::Path;
+
+impl Vault {
+    /// Writes the hidden page that shows every element a chapter can have under the current
+    /// appearance, unless a chapter already takes its place.
+    pub(super) fn write_theme_preview(
+        &self,
+        content: &Content,
+        renderer: &AskamaRenderer,
+    ) -> Result<()> {
+        for chapter in content.all_chapters().iter() {
+            if renderer::output_path(&chapter.content, self.src_dir())?
+                == Path::new(THEME_PREVIEW_FILE)
+            {
+                warn!("{THEME_PREVIEW_FILE} is a chapter, so the theme preview isn't written");
+                return Ok(());
+            }
+        }
+
+        let path = self.build_dir().join(THEME_PREVIEW_FILE);
+        let html = renderer
+            .render_theme_preview()
+            .with_context(|| anyhow!("Failed to render the theme preview"))?;
+
+        fs::write(&path, html).with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_write_a_hidden_theme_preview() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.appearance.highlight_theme = String::from("catppuccin");
+        vault.init()?;
+        vault.build()?;
+
+        let preview = fs::read_to_string(vault.build_dir().join(THEME_PREVIEW_FILE))?;
+        assert!(preview.contains("<meta name=\"robots\" content=\"noindex\" />"));
+        assert!(preview.contains("<blockquote>"));
+        assert!(preview.contains("<h3>gruvbox</h3>"));
+        assert!(preview.contains("<h3>catppuccin (current)</h3>"));
+        assert!(preview.contains("class=\"highlight highlight--gruvbox\""));
+        assert!(preview.contains("class=\"metadata\""));
+        assert!(preview.contains("class=\"prerequisites\""));
+        assert!(preview.contains("Obi-Wan Kenobi"));
+        assert!(!fs::read_to_string(vault.build_dir().join("sitemap.html"))?
+            .contains(THEME_PREVIEW_FILE));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use super::backends::PRINT_BACKEND;
+use super::{Content, Vault};
+use crate::renderer::{self, AskamaRenderer, PRINT_FILE};
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use std::fs;
+use std::path::Path;
+
+impl Vault {
+    /// Writes the page with every chapter for printing, unless a chapter already takes its place.
+    /// It's removed once the config turns it off.
+    pub(super) fn write_print(&self, content: &Content, renderer: &AskamaRenderer) -> Result<()> {
+        for chapter in content.all_chapters().iter() {
+            if renderer::output_path(&chapter.content, self.src_dir())? == Path::new(PRINT_FILE) {
+                if self.config.print.enabled {
+                    warn!("{PRINT_FILE} is a chapter, so the page for printing isn't written");
+                }
+                return Ok(());
+            }
+        }
+
+        let path = self.build_dir().join(PRINT_FILE);
+        if !self.config.print.enabled {
+            return match path.exists() {
+                true => fs::remove_file(&path)
+                    .with_context(|| anyhow!("Failed to remove {}", path.display())),
+                false => Ok(()),
+            };
+        }
+
+        let html = renderer
+            .render_print(&self.content_for(content.clone(), PRINT_BACKEND)?)
+            .with_context(|| anyhow!("Failed to render the page for printing"))?;
+
+        fs::write(&path, html).with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_print_every_chapter_in_one_page() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Intro](./intro.md)\n\n# Guide\n\n- [Setup](./setup.md)\n  - [Linux](./linux.md)\n- [Draft]()\n- [Docs](https://example.com)\n",
+        )?;
+        fs::write(vault.src_dir().join("intro.md"), "# Intro\n\nHello there")?;
+        fs::write(
+            vault.src_dir().join("setup.md"),
+            "# Setup\n\nGeneral Kenobi",
+        )?;
+        fs::write(
+            vault.src_dir().join("linux.md"),
+            "# Linux\n\nYou are a bold one",
+        )?;
+        vault.build()?;
+
+        let print = fs::read_to_string(vault.build_dir().join(PRINT_FILE))?;
+        let position = |text: &str| print.find(text).unwrap_or(usize::MAX);
+        assert!(position("Hello there") < position("<h1 class=\"print__section\">Guide</h1>"));
+        assert!(position("Guide</h1>") < position("General Kenobi"));
+        assert!(position("General Kenobi") < position("You are a bold one"));
+        assert_eq!(
+            print.matches("<article class=\"print__chapter\">").count(),
+            3
+        );
+        assert!(print.contains("<meta name=\"robots\" content=\"noindex\" />"));
+        assert!(fs::read_to_string(vault.build_dir().join("intro.html"))?
+            .contains("href=\"/print.html\""));
+
+        vault.config.print.enabled = false;
+        vault.build()?;
+        assert!(!vault.build_dir().join(PRINT_FILE).exists());
+
+        Ok(())
+    }
+}
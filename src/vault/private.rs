@@ -0,0 +1,198 @@
+use super::{Content, Vault};
+use crate::config::PrivateMode;
+use crate::FrontMatter;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::env;
+use std::path::PathBuf;
+
+impl Vault {
+    /// What happens to the private chapters in builds of the profile of the vault
+    pub(super) fn private_mode(&self) -> PrivateMode {
+        self.config.private.mode(self.profile)
+    }
+
+    /// The passphrase of the encrypted chapters, if its environment variable is set
+    pub(super) fn passphrase(&self) -> Option<String> {
+        env::var(&self.config.private.passphrase_env)
+            .ok()
+            .filter(|passphrase| !passphrase.is_empty())
+    }
+
+    /// Builds that exclude the private chapters don't know they exist, so they're taken out of
+    /// the summary and everything made from it (e.g. the sidebar, the search index or the feed)
+    pub(super) fn without_private(&self, mut content: Content) -> Result<Content> {
+        if self.private_mode() != PrivateMode::Exclude {
+            return Ok(content);
+        }
+
+        let private = self.private_chapters(&content)?;
+        content.retain(|chapter| !private.contains(&chapter.content));
+
+        Ok(content)
+    }
+
+    /// The private chapters whose text can only be in their encrypted pages, by their file. It's
+    /// empty unless the build encrypts them.
+    pub(super) fn encrypted_chapters(&self, content: &Content) -> Result<HashSet<PathBuf>> {
+        match self.private_mode() {
+            PrivateMode::Encrypt => self.private_chapters(content),
+            PrivateMode::Plain | PrivateMode::Exclude => Ok(HashSet::new()),
+        }
+    }
+
+    fn private_chapters(&self, content: &Content) -> Result<HashSet<PathBuf>> {
+        let mut private = HashSet::new();
+
+        for chapter in content.all_chapters() {
+            if chapter.content.exists() && FrontMatter::from_disk(&chapter.content)?.private {
+                private.insert(chapter.content);
+            }
+        }
+
+        Ok(private)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Profile;
+    use crate::renderer::exercises::SOLUTIONS_FILE;
+    use crate::vault::manifest::Manifest;
+    use std::error::Error;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_leave_the_private_chapters_out_of_release_builds() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("01_intro.md"), "# Intro")?;
+        fs::write(
+            vault.src_dir().join("02_notes.md"),
+            "---\nprivate: true\n---\n# Order 66",
+        )?;
+        fs::write(vault.src_dir().join("03_usage.md"), "# Usage")?;
+        let report = vault.build()?;
+
+        assert_eq!(report.chapters, 2);
+        assert!(!vault.build_dir().join("notes.html").exists());
+        let sitemap = fs::read_to_string(vault.build_dir().join("sitemap.html"))?;
+        assert!(!sitemap.contains("Notes"));
+        // Nothing gives away there was a chapter in between
+        let numbers: Vec<String> = vault
+            .without_private(vault.content()?)?
+            .chapters()
+            .into_iter()
+            .map(|chapter| chapter.number)
+            .collect();
+        assert_eq!(numbers, ["1", "2"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_encrypt_the_private_chapters() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.profile = Profile::Preview;
+        vault.config.private.passphrase_env = String::from("CAHLTER_TEST_PASSPHRASE");
+        vault.config.search.enabled = true;
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("notes.md"),
+            "---\nprivate: true\n---\n# Order 66",
+        )?;
+        assert!(vault.build().is_err());
+
+        env::set_var("CAHLTER_TEST_PASSPHRASE", "kenobi");
+        vault.build()?;
+
+        let page = fs::read_to_string(vault.build_dir().join("notes.html"))?;
+        assert!(page.contains("class=\"private\""));
+        assert!(!page.contains("Order 66"));
+        let search_index = fs::read_to_string(vault.build_dir().join("search-index.json"))?;
+        assert!(!search_index.contains("Order 66"));
+
+        Ok(())
+    }
+
+    // An encrypting build of a vault with a single private chapter
+    fn encrypting_vault(path: &Path, passphrase_env: &str, chapter: &str) -> Result<Vault> {
+        let mut vault = Vault::new(path);
+        vault.profile = Profile::Preview;
+        vault.config.private.passphrase_env = passphrase_env.to_string();
+        vault.init()?;
+        env::set_var(passphrase_env, "kenobi");
+
+        fs::write(
+            vault.src_dir().join("notes.md"),
+            format!("---\nprivate: true\n---\n{chapter}"),
+        )?;
+        fs::write(vault.src_dir().join("intro.md"), "# Intro")?;
+
+        Ok(vault)
+    }
+
+    #[test]
+    fn it_should_leave_encrypted_chapters_out_of_the_solutions() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = encrypting_vault(
+            temp_dir.path(),
+            "CAHLTER_TEST_SOLUTIONS_PASSPHRASE",
+            "# Orders\n\n::: exercise\nWhich order?\n:::\n\n::: solution\nOrder 66\n:::\n",
+        )?;
+        vault.with_solutions = true;
+        vault.build()?;
+
+        assert!(!vault.build_dir().join(SOLUTIONS_FILE).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_leave_what_encrypted_chapters_include_out_of_the_source_pages(
+    ) -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = encrypting_vault(
+            temp_dir.path(),
+            "CAHLTER_TEST_SOURCES_PASSPHRASE",
+            "# Orders\n\n```rust\n{{#include ../order.rs}}\n```\n",
+        )?;
+        vault.config.code_blocks.source_view = true;
+        fs::write(temp_dir.path().join("order.rs"), "// Order 66")?;
+        vault.build()?;
+
+        assert!(!vault.build_dir().join("source/order.rs.html").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_leave_the_text_of_encrypted_chapters_out_of_the_manifest(
+    ) -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = encrypting_vault(
+            temp_dir.path(),
+            "CAHLTER_TEST_MANIFEST_PASSPHRASE",
+            "# Orders\n\n## Order 66",
+        )?;
+        vault.build()?;
+
+        let manifest = Manifest::from_disk(vault.build_dir())?.unwrap_or_default();
+        let notes = manifest
+            .chapters
+            .iter()
+            .find(|chapter| chapter.source == Path::new("notes.md"))
+            .expect("The chapter should be in the manifest");
+        assert!(notes.anchors.is_empty());
+        assert!(notes.hash.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use super::{Content, Vault};
+use crate::renderer::{self, AskamaRenderer};
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+impl Vault {
+    /// Writes a page at the old location of each redirect of the config that sends readers to the
+    /// new one. Old locations without an extension (e.g. `setup/`) get an index.html.
+    pub(super) fn write_redirects(
+        &self,
+        content: &Content,
+        renderer: &AskamaRenderer,
+    ) -> Result<()> {
+        let mut pages = Vec::new();
+        for chapter in content.all_chapters().iter() {
+            pages.push(renderer::output_path(&chapter.content, self.src_dir())?);
+        }
+
+        for (from, to) in self.config.redirects.iter() {
+            let page = redirect_path(from)
+                .with_context(|| anyhow!("The redirect from {from} leaves the build dir"))?;
+
+            // A redirect would hide the chapter that took its place
+            if pages.contains(&page) {
+                warn!("{from} is a chapter now, so it doesn't redirect to {to}");
+                continue;
+            }
+
+            let path = self.build_dir().join(&page);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| anyhow!("Failed to create {}", parent.display()))?;
+            }
+
+            let html = renderer
+                .render_redirect(to)
+                .with_context(|| anyhow!("Failed to render the redirect from {from}"))?;
+            fs::write(&path, html)
+                .with_context(|| anyhow!("Failed to write {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+// Where the redirect is written, relative to the build dir. It returns `None` if that's outside
+// of it.
+fn redirect_path(from: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+
+    for component in Path::new(from.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(name) => path.push(name),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    if from.ends_with('/') || path.extension().is_none() {
+        path.push("index.html");
+    }
+
+    Some(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_write_a_page_for_each_redirect() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.base_path = "docs".to_string();
+
+        fs::write(vault.src_dir().join("intro.md"), "# Intro")?;
+        for (from, to) in [
+            ("/setup.html", "guide/setup.html#cargo"),
+            ("old/", "https://example.com/new"),
+            ("intro.html", "guide/setup.html"),
+        ] {
+            vault
+                .config
+                .redirects
+                .insert(from.to_string(), to.to_string());
+        }
+        vault.build()?;
+
+        let setup = fs::read_to_string(vault.build_dir().join("setup.html"))?;
+        assert!(setup.contains("content=\"0; url=/docs/guide/setup.html#cargo\""));
+        assert!(
+            fs::read_to_string(vault.build_dir().join("old/index.html"))?
+                .contains("<link rel=\"canonical\" href=\"https://example.com/new\" />")
+        );
+        assert!(!fs::read_to_string(vault.build_dir().join("intro.html"))?.contains("url="));
+
+        vault
+            .config
+            .redirects
+            .insert("../outside.html".to_string(), "intro.html".to_string());
+        assert!(vault.build().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use super::Vault;
+use crate::renderer::markdown;
+use crate::{util, FrontMatter};
+use anyhow::{anyhow, Context, Result};
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::HashSet;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// The replacements made in a chapter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replacement {
+    /// The chapter, relative to the vault
+    pub path: PathBuf,
+    pub count: usize,
+    /// The lines that changed, like in [`util::diff_lines`]
+    pub diff: String,
+}
+
+impl Vault {
+    /// Finds where `from` would be replaced by `to` in the chapters, without touching them. Code
+    /// blocks and inline code are left alone, since code rarely wants the same replacement as prose.
+    pub fn replacements(&self, from: &str, to: &str) -> Result<Vec<Replacement>> {
+        Ok(self
+            .find_replacements(from, to)?
+            .into_iter()
+            .map(|(_, replacement)| replacement)
+            .collect())
+    }
+
+    /// Makes the replacements found by [`Vault::replacements`] in the source files and returns them
+    pub fn replace(&self, from: &str, to: &str) -> Result<Vec<Replacement>> {
+        let mut replacements = Vec::new();
+
+        for (markdown, replacement) in self.find_replacements(from, to)? {
+            let path = self.path.join(&replacement.path);
+
+            fs::write(&path, markdown)
+                .with_context(|| anyhow!("Failed to write {}", path.display()))?;
+            replacements.push(replacement);
+        }
+
+        Ok(replacements)
+    }
+
+    // Each chapter that changes, as its new markdown
+    fn find_replacements(&self, from: &str, to: &str) -> Result<Vec<(String, Replacement)>> {
+        if from.is_empty() {
+            anyhow::bail!("There's nothing to replace");
+        }
+
+        let mut seen = HashSet::new();
+        let mut replacements = Vec::new();
+
+        for chapter in self.chapters_with_content()?.iter() {
+            // The same file can be in the summary more than once
+            if !seen.insert(chapter.content.clone()) {
+                continue;
+            }
+
+            let markdown = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+            let code = self.code_ranges(&markdown);
+            let found: Vec<usize> = markdown
+                .match_indices(from)
+                .map(|(start, _)| start)
+                .filter(|start| {
+                    let end = start + from.len();
+                    !code
+                        .iter()
+                        .any(|range| *start < range.end && range.start < end)
+                })
+                .collect();
+
+            if found.is_empty() {
+                continue;
+            }
+
+            let mut replaced = markdown.clone();
+            // From the end, so the positions that are left stay valid
+            for start in found.iter().rev() {
+                replaced.replace_range(*start..start + from.len(), to);
+            }
+
+            replacements.push((
+                replaced.clone(),
+                Replacement {
+                    path: self.relative(&chapter.content),
+                    count: found.len(),
+                    diff: util::diff_lines(&markdown, &replaced),
+                },
+            ));
+        }
+
+        Ok(replacements)
+    }
+
+    // Where the code blocks and inline code are in the markdown
+    fn code_ranges(&self, markdown: &str) -> Vec<Range<usize>> {
+        let body = FrontMatter::extract(markdown)
+            .map(|(_, body)| body)
+            .unwrap_or(markdown);
+        let offset = markdown.len() - body.len();
+        let options = markdown::options(&self.config.markdown.extensions);
+
+        Parser::new_ext(body, options)
+            .into_offset_iter()
+            .filter(|(event, _)| matches!(event, Event::Start(Tag::CodeBlock(_)) | Event::Code(_)))
+            .map(|(_, range)| offset + range.start..offset + range.end)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_replace_outside_of_code() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        let markdown =
+            "# Foo v1\n\nInstall Foo v1 with `Foo v1`.\n\n```sh\necho Foo v1\n```\n\nFoo v1 is done.";
+        fs::write(vault.src_dir().join("intro.md"), markdown)?;
+        fs::write(vault.src_dir().join("other.md"), "# Other")?;
+
+        let replacements = vault.replacements("Foo v1", "Foo v2")?;
+        assert_eq!(replacements.len(), 1);
+        assert_eq!(replacements[0].path, PathBuf::from("src/intro.md"));
+        assert_eq!(replacements[0].count, 3);
+        assert!(replacements[0].diff.starts_with("-# Foo v1\n+# Foo v2\n"));
+        assert_eq!(
+            fs::read_to_string(vault.src_dir().join("intro.md"))?,
+            markdown
+        );
+
+        vault.replace("Foo v1", "Foo v2")?;
+        assert_eq!(
+            fs::read_to_string(vault.src_dir().join("intro.md"))?,
+            "# Foo v2\n\nInstall Foo v2 with `Foo v1`.\n\n```sh\necho Foo v1\n```\n\nFoo v2 is done."
+        );
+        assert!(vault.replacements("Foo v1", "Foo v2")?.is_empty());
+        assert!(vault.replace("", "Foo").is_err());
+
+        Ok(())
+    }
+}
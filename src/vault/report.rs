@@ -0,0 +1,121 @@
+use super::Vault;
+use crate::FrontMatter;
+use anyhow::Result;
+use chrono::{Days, NaiveDate};
+use std::fmt;
+use std::path::PathBuf;
+
+/// A chapter that should be reviewed again
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverdueReview {
+    /// The chapter's file, relative to the vault
+    pub path: PathBuf,
+    pub title: String,
+    pub owner: Option<String>,
+    /// When it was last reviewed. `None` means it was never reviewed
+    pub reviewed: Option<NaiveDate>,
+}
+
+impl fmt::Display for OverdueReview {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: \"{}\"", self.path.display(), self.title)?;
+
+        match self.reviewed {
+            Some(reviewed) => write!(f, " was last reviewed on {reviewed}")?,
+            None => write!(f, " was never reviewed")?,
+        }
+
+        match &self.owner {
+            Some(owner) => write!(f, " (owner: {owner})"),
+            None => write!(f, " (no owner)"),
+        }
+    }
+}
+
+impl Vault {
+    /// Lists the chapters that were never reviewed or whose last review is older than
+    /// `review.max_age` days. Missing chapters are left to [`Vault::check`].
+    pub fn overdue_reviews(&self, today: NaiveDate) -> Result<Vec<OverdueReview>> {
+        // Nothing is older than the oldest date there is
+        let deadline = today
+            .checked_sub_days(Days::new(self.config.review.max_age.into()))
+            .unwrap_or(NaiveDate::MIN);
+        let mut overdue = Vec::new();
+
+        for chapter in self.content()?.all_chapters() {
+            if !chapter.content.exists() {
+                continue;
+            }
+
+            let front_matter = FrontMatter::from_disk(&chapter.content)?;
+            if front_matter
+                .reviewed
+                .is_some_and(|reviewed| reviewed >= deadline)
+            {
+                continue;
+            }
+
+            overdue.push(OverdueReview {
+                path: self.relative(&chapter.content),
+                title: chapter.title,
+                owner: front_matter.owner,
+                reviewed: front_matter.reviewed,
+            });
+        }
+
+        Ok(overdue)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_list_the_overdue_reviews() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.review.max_age = 30;
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Recent](./recent.md)\n- [Old](./old.md)\n- [Never](./never.md)",
+        )?;
+        fs::write(
+            vault.src_dir().join("recent.md"),
+            "---\nreviewed: 2024-05-04\n---\n",
+        )?;
+        fs::write(
+            vault.src_dir().join("old.md"),
+            "---\nowner: Obi-Wan\nreviewed: 2024-04-03\n---\n",
+        )?;
+        fs::write(vault.src_dir().join("never.md"), "# Hello there")?;
+
+        let expected = vec![
+            OverdueReview {
+                path: PathBuf::from("src/old.md"),
+                title: "Old".to_string(),
+                owner: Some("Obi-Wan".to_string()),
+                reviewed: NaiveDate::from_ymd_opt(2024, 4, 3),
+            },
+            OverdueReview {
+                path: PathBuf::from("src/never.md"),
+                title: "Never".to_string(),
+                owner: None,
+                reviewed: None,
+            },
+        ];
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+
+        assert_eq!(vault.overdue_reviews(today)?, expected);
+
+        vault.config.review.max_age = u32::MAX;
+        assert_eq!(vault.overdue_reviews(today)?, expected[1..]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+use super::{Content, Vault};
+use crate::config::Search;
+use crate::renderer::{self, audiences, exercises, markdown};
+use crate::{Chapter, FrontMatter};
+use anyhow::{anyhow, Context, Result};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the index is written, inside the build dir. The search box in the header fetches it.
+pub const SEARCH_INDEX_FILE: &str = "search-index.json";
+
+/// Everything the search box needs to find chapters without a server
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct SearchIndex {
+    pub documents: Vec<SearchDocument>,
+}
+
+/// A chapter, as the search sees it
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SearchDocument {
+    pub title: String,
+    /// The page of the chapter, relative to the build dir
+    pub url: PathBuf,
+    /// The beginning of the text, shown under the title in the results
+    pub excerpt: String,
+    /// Every word in the chapter, lowercased and without the stop words
+    pub words: Vec<String>,
+}
+
+impl SearchIndex {
+    /// Reads the index left in the given build dir. It returns `None` if it was never written.
+    pub fn from_disk<P>(build_dir: P) -> Result<Option<SearchIndex>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = build_dir.as_ref().join(SEARCH_INDEX_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = fs::read(&path).with_context(|| anyhow!("Failed to read {}", path.display()))?;
+        let index = serde_json::from_slice(&file)
+            .with_context(|| anyhow!("Failed to parse {}", path.display()))?;
+
+        Ok(Some(index))
+    }
+
+    /// The documents with every term of the query, best matches first, just like the search box
+    /// finds them. A term matches the words it starts, and matching the title counts for more.
+    pub fn search(&self, query: &str) -> Vec<&SearchDocument> {
+        let terms = query_terms(query);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut results: Vec<(usize, &SearchDocument)> = self
+            .documents
+            .iter()
+            .filter_map(|document| {
+                let title = document.title.to_lowercase();
+                let mut score = 0;
+
+                for term in terms.iter() {
+                    let matches = document
+                        .words
+                        .iter()
+                        .filter(|word| word.starts_with(term.as_str()))
+                        .count();
+
+                    if matches == 0 {
+                        return None;
+                    }
+
+                    score += matches + if title.contains(term.as_str()) { 10 } else { 0 };
+                }
+
+                Some((score, document))
+            })
+            .collect();
+
+        // Stable, so documents with the same score keep the order of the summary
+        results.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        results.into_iter().map(|(_, document)| document).collect()
+    }
+}
+
+impl SearchDocument {
+    pub fn new(chapter: &Chapter, url: PathBuf, text: &str, options: &Search) -> Self {
+        let words = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() > 1)
+            .map(|word| word.to_lowercase())
+            .filter(|word| !options.stop_words.contains(word))
+            .collect::<BTreeSet<String>>();
+
+        Self {
+            title: chapter.title.clone(),
+            url,
+            excerpt: excerpt(text, options.max_excerpt_length),
+            words: words.into_iter().collect(),
+        }
+    }
+}
+
+impl Vault {
+    /// Looks for the query in the index of the last build or, if there is none, in one made on
+    /// the spot
+    pub fn search(&self, query: &str) -> Result<Vec<SearchDocument>> {
+        let index = match SearchIndex::from_disk(self.build_dir())? {
+            Some(index) => index,
+            None => self.search_index(&self.content()?)?,
+        };
+
+        Ok(index.search(query).into_iter().cloned().collect())
+    }
+
+    /// Writes the search index of every chapter to the build dir
+    pub(super) fn write_search_index(&self, content: &Content) -> Result<()> {
+        let index = self.search_index(content)?;
+        let path = self.build_dir().join(SEARCH_INDEX_FILE);
+
+        fs::write(&path, serde_json::to_vec(&index)?)
+            .with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+
+    fn search_index(&self, content: &Content) -> Result<SearchIndex> {
+        let options = markdown::options(&self.config.markdown.extensions);
+        let mut index = SearchIndex::default();
+        let encrypted = self.encrypted_chapters(content)?;
+
+        for chapter in content.all_chapters().iter() {
+            if !chapter.content.exists() || encrypted.contains(&chapter.content) {
+                continue;
+            }
+
+            let markdown = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+            let body = FrontMatter::extract(&markdown)
+                .map(|(_, body)| body)
+                .unwrap_or(&markdown);
+            // Like the pages, it only has what's for the audience of the build
+            let body = audiences::audiences(body, self.audience.as_deref())?;
+            // Solutions would give the answers away in the results
+            let body = exercises::exercises(&body, None)?;
+
+            index.documents.push(SearchDocument::new(
+                chapter,
+                renderer::output_path(&chapter.content, self.src_dir())?,
+                &plain_text(&body, options),
+                &self.config.search,
+            ));
+        }
+
+        Ok(index)
+    }
+}
+
+/// The words of a query, lowercased. Anything that isn't a letter or a number separates them.
+pub fn query_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+        .collect()
+}
+
+/// The text of the markdown, without any formatting. Blocks are separated by new lines.
+pub fn plain_text(markdown: &str, options: Options) -> String {
+    let mut text = String::new();
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(
+                Tag::Paragraph | Tag::Heading(..) | Tag::Item | Tag::CodeBlock(_) | Tag::TableCell,
+            ) => text.push('\n'),
+            _ => {}
+        }
+    }
+
+    text.trim().to_string()
+}
+
+// Cut between words, so the excerpt doesn't end in half of one
+pub(super) fn excerpt(text: &str, max_length: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    if text.chars().count() <= max_length {
+        return text;
+    }
+
+    let mut excerpt = String::new();
+    for word in text.split(' ') {
+        if excerpt.chars().count() + word.chars().count() + 1 > max_length {
+            break;
+        }
+
+        if !excerpt.is_empty() {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(word);
+    }
+
+    excerpt + "…"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_write_the_search_index() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.config.search.max_excerpt_length = 20;
+
+        fs::write(
+            vault.src_dir().join("01-install.md"),
+            "---\nowner: Obi-Wan\n---\n# Install\n\nRun `cargo install` and **the** build is done.",
+        )?;
+        vault.build()?;
+
+        let index: SearchIndex =
+            serde_json::from_slice(&fs::read(vault.build_dir().join(SEARCH_INDEX_FILE))?)?;
+        let document = &index.documents[0];
+
+        assert_eq!(document.url, PathBuf::from("install.html"));
+        assert_eq!(document.excerpt, "Install Run cargo…");
+        assert_eq!(
+            document.words,
+            vec!["build", "cargo", "done", "install", "run"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_search_the_index() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("01-lifetimes.md"),
+            "# Lifetimes\n\nLifetime elision saves some typing.",
+        )?;
+        fs::write(
+            vault.src_dir().join("02-traits.md"),
+            "# Traits\n\nTrait objects have a lifetime too, but no elision here.",
+        )?;
+
+        // Nothing was built yet, so the index is made on the spot
+        let titles = |documents: Vec<SearchDocument>| -> Vec<String> {
+            documents
+                .into_iter()
+                .map(|document| document.title)
+                .collect()
+        };
+        assert_eq!(
+            titles(vault.search("lifetime elision")?),
+            vec!["Lifetimes", "Traits"]
+        );
+
+        vault.build()?;
+        assert_eq!(titles(vault.search("TRAIT")?), vec!["Traits"]);
+        assert!(vault.search("borrow")?.is_empty());
+        assert!(vault.search("  ")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_write_the_search_index_when_disabled() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.config.search.enabled = false;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "Hello there")?;
+        vault.build()?;
+
+        assert!(!vault.build_dir().join(SEARCH_INDEX_FILE).exists());
+        assert!(
+            !fs::read_to_string(vault.build_dir().join("chapter1.html"))?
+                .contains("class=\"search\"")
+        );
+
+        Ok(())
+    }
+}
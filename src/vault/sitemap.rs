@@ -0,0 +1,115 @@
+use super::{Content, Vault, SITEMAP_FILE};
+use crate::{renderer, util};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::fs;
+
+/// Where the sitemap for search engines is written, inside the build dir
+pub const SITEMAP_XML_FILE: &str = "sitemap.xml";
+
+/// Tells crawlers what they may index and where the sitemap is. It's written next to the sitemap.
+pub const ROBOTS_FILE: &str = "robots.txt";
+
+impl Vault {
+    /// Writes a sitemap.xml with the absolute url of every page to the build dir. Chapters are
+    /// dated by the last time their file was modified.
+    pub(super) fn write_sitemap_xml(&self, content: &Content, site_url: &str) -> Result<()> {
+        let site_url = site_url.trim_end_matches('/');
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+
+        for chapter in content.all_chapters().iter() {
+            if !chapter.content.exists() {
+                continue;
+            }
+
+            let page = renderer::output_path(&chapter.content, self.src_dir())?;
+            let modified: DateTime<Utc> = fs::metadata(&chapter.content)?.modified()?.into();
+
+            xml.push_str(&format!(
+                "  <url>\n    <loc>{}</loc>\n    <lastmod>{}</lastmod>\n  </url>\n",
+                util::absolute_url(site_url, &page),
+                modified.format("%Y-%m-%d")
+            ));
+        }
+
+        xml.push_str(&format!(
+            "  <url>\n    <loc>{}</loc>\n  </url>\n</urlset>\n",
+            util::absolute_url(site_url, SITEMAP_FILE)
+        ));
+
+        let path = self.build_dir().join(SITEMAP_XML_FILE);
+        fs::write(&path, xml).with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+
+    /// Writes a robots.txt that lets crawlers index everything or, if the robots config doesn't
+    /// allow it for the profile of the build, nothing
+    pub(super) fn write_robots(&self, site_url: &str) -> Result<()> {
+        let rule = match self.config.robots.allows_indexing(self.profile) {
+            true => "Allow: /",
+            false => "Disallow: /",
+        };
+        let robots = format!(
+            "User-agent: *\n{rule}\n\nSitemap: {}\n",
+            util::absolute_url(site_url.trim_end_matches('/'), SITEMAP_XML_FILE)
+        );
+
+        let path = self.build_dir().join(ROBOTS_FILE);
+        fs::write(&path, robots).with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Profile;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_write_the_sitemap_xml_and_robots_txt() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::create_dir(vault.src_dir().join("02-q&a"))?;
+        fs::write(vault.src_dir().join("02-q&a/index.md"), "# Q&A")?;
+        fs::write(vault.src_dir().join("01-intro.md"), "# Intro")?;
+        vault.build()?;
+        assert!(!vault.build_dir().join(SITEMAP_XML_FILE).exists());
+
+        vault.config.general.site_url = Some("https://example.com/docs/".to_string());
+        vault.build()?;
+
+        let xml = fs::read_to_string(vault.build_dir().join(SITEMAP_XML_FILE))?;
+        let today = Utc::now().format("%Y-%m-%d");
+
+        assert!(xml.contains(&format!(
+            "<loc>https://example.com/docs/intro.html</loc>\n    <lastmod>{today}</lastmod>"
+        )));
+        assert!(xml.contains("<loc>https://example.com/docs/q&amp;a/index.html</loc>"));
+        assert!(xml.contains("<loc>https://example.com/docs/sitemap.html</loc>"));
+        assert!(xml.ends_with("</urlset>\n"));
+        assert_eq!(
+            fs::read_to_string(vault.build_dir().join(ROBOTS_FILE))?,
+            "User-agent: *\nAllow: /\n\nSitemap: https://example.com/docs/sitemap.xml\n"
+        );
+        assert!(
+            fs::read_to_string(vault.build_dir().join("q&a/index.html"))?.contains(
+                "<link rel=\"canonical\" href=\"https://example.com/docs/q&amp;a/index.html\" />"
+            )
+        );
+
+        vault.profile = Profile::Dev;
+        vault.build()?;
+        assert!(fs::read_to_string(vault.build_dir().join(ROBOTS_FILE))?.contains("Disallow: /"));
+
+        vault.config.robots.dev = true;
+        vault.build()?;
+        assert!(fs::read_to_string(vault.build_dir().join(ROBOTS_FILE))?.contains("Allow: /"));
+
+        Ok(())
+    }
+}
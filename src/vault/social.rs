@@ -0,0 +1,102 @@
+use super::Vault;
+use crate::renderer::{self, social, social::CardStyle};
+use crate::{util, Chapter};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Component, Path};
+
+impl Vault {
+    /// Draws the social card of the chapter into the build dir
+    pub(super) fn write_social_card(&self, chapter: &Chapter) -> Result<()> {
+        let options = &self.config.social_cards;
+        if !Path::new(&options.dir)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            anyhow::bail!("The social cards dir {} leaves the build dir", options.dir);
+        }
+
+        let template = self.social_card_template()?;
+        let page = renderer::output_path(&chapter.content, self.src_dir())?;
+        let destination = self
+            .build_dir()
+            .join(renderer::social_card_path(&options.dir, page));
+
+        if let Some(parent) = destination.parent() {
+            util::create_dir_if_not_exists(parent)?;
+        }
+
+        let theme = CardStyle::of_theme(&self.config.appearance.default_theme);
+        let style = CardStyle {
+            font: options.font.as_deref(),
+            background: options.background.as_deref().unwrap_or(theme.background),
+            foreground: options.foreground.as_deref().unwrap_or(theme.foreground),
+            accent: options.accent.as_deref().unwrap_or(theme.accent),
+        };
+
+        let png = social::render_card(
+            &template,
+            &chapter.title,
+            &self.config.general.title,
+            &style,
+        )
+        .with_context(|| anyhow!("Failed to draw the social card of {}", chapter.title))?;
+        fs::write(&destination, png)
+            .with_context(|| anyhow!("Failed to write {}", destination.display()))
+    }
+
+    pub(super) fn social_card_template(&self) -> Result<String> {
+        match &self.config.social_cards.template {
+            Some(template) => {
+                let path = self.path.join(template);
+                fs::read_to_string(&path).with_context(|| {
+                    anyhow!("Failed to read the social card template {}", path.display())
+                })
+            }
+            None => Ok(social::DEFAULT_TEMPLATE.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_draw_a_social_card_for_each_chapter() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.config.social_cards.enabled = true;
+
+        fs::create_dir(vault.src_dir().join("guide"))?;
+        fs::write(vault.src_dir().join("guide/index.md"), "# Guide")?;
+        fs::write(vault.src_dir().join("guide/01-install.md"), "# Install")?;
+        vault.build()?;
+
+        let card = fs::read(vault.build_dir().join("social/guide/install.png"))?;
+        assert!(card.starts_with(b"\x89PNG"));
+        assert!(
+            fs::read_to_string(vault.build_dir().join("guide/install.html"))?
+                .contains("<meta property=\"og:image\" content=\"/social/guide/install.png\" />")
+        );
+
+        vault.config.social_cards.dir = "cards".to_string();
+        vault.config.social_cards.background = Some("#ffffff".to_string());
+        vault.config.social_cards.font = Some("DejaVu Serif".to_string());
+        vault.build()?;
+        assert!(vault.build_dir().join("cards/guide/install.png").exists());
+
+        vault.config.social_cards.dir = "../cards".to_string();
+        assert!(vault.build().is_err());
+
+        vault.config.social_cards.dir = "social".to_string();
+        fs::write(temp_dir.path().join("card.svg"), "<svg>{{title}}")?;
+        vault.config.social_cards.template = Some("card.svg".into());
+        assert!(vault.build().is_err());
+
+        Ok(())
+    }
+}
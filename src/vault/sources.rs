@@ -0,0 +1,95 @@
+use super::{Content, Vault};
+use crate::renderer::includes;
+use crate::renderer::AskamaRenderer;
+use crate::{util, FrontMatter};
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+impl Vault {
+    /// Writes a page with the whole file for each file that chapters pull into code blocks with
+    /// `{{#include path}}`. What encrypted chapters pull in stays out, like their text.
+    pub(super) fn write_source_pages(
+        &self,
+        content: &Content,
+        renderer: &AskamaRenderer,
+    ) -> Result<()> {
+        // Several chapters may include the same file
+        let mut pages = BTreeMap::new();
+        let encrypted = self.encrypted_chapters(content)?;
+
+        for chapter in content.all_chapters().iter() {
+            if !chapter.content.exists() || encrypted.contains(&chapter.content) {
+                continue;
+            }
+
+            let markdown = fs::read_to_string(&chapter.content).with_context(|| {
+                anyhow!("Failed to read contents of {}", chapter.content.display())
+            })?;
+            let (_, body) = FrontMatter::extract(&markdown)?;
+            let dir = chapter.content.parent().unwrap_or(Path::new(""));
+
+            for include in includes::find_includes(body, dir)? {
+                if include.in_code_block {
+                    pages.insert(
+                        includes::source_page(&self.path, &include.path),
+                        include.path,
+                    );
+                }
+            }
+        }
+
+        for (page, file) in pages {
+            let destination = self.build_dir().join(&page);
+            if let Some(parent) = destination.parent() {
+                util::create_dir_if_not_exists(parent)?;
+            }
+
+            let html = renderer
+                .render_source(&file, page)
+                .with_context(|| anyhow!("Failed to render the source of {}", file.display()))?;
+            fs::write(&destination, html)
+                .with_context(|| anyhow!("Failed to write {}", destination.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_write_the_source_of_included_code() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        vault.config.code_blocks.source_view = true;
+
+        fs::create_dir(temp_dir.path().join("examples"))?;
+        fs::write(
+            temp_dir.path().join("examples/hello.rs"),
+            "// Says hi\nfn main() {\n    println!(\"hi\");\n}\n",
+        )?;
+        fs::write(
+            vault.src_dir().join("intro.md"),
+            "# Intro\n\n```rust\n{{#include ../examples/hello.rs:2:4}}\n```\n",
+        )?;
+        vault.build()?;
+
+        let page = fs::read_to_string(vault.build_dir().join("intro.html"))?;
+        let source = fs::read_to_string(vault.build_dir().join("source/examples/hello.rs.html"))?;
+
+        assert!(page.contains("<span class=\"hl-entity hl-name hl-function hl-rust\">main</span>"));
+        assert!(!page.contains("Says hi"));
+        assert!(page.contains("href=\"/source/examples/hello.rs.html#L2\">View full source</a>"));
+        assert!(source.contains("<span class=\"code-block__line\" id=\"L1\"><span class=\"hl-comment hl-line hl-double-slash hl-rust\">"));
+        assert!(source.contains("id=\"L4\""));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use super::backends::HTML_BACKEND;
+use super::Vault;
+use crate::renderer::{self, markdown, Renderer};
+use crate::static_files;
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where `export html` writes the page with every chapter, inside the vault. It stays out of the
+/// build dir, which only holds the site. The page of a single chapter is named after it, with this
+/// extension.
+pub const STANDALONE_FILE: &str = "standalone.html";
+
+impl Vault {
+    /// Exports the vault, or only `chapter` (relative to the vault), as a single html file with
+    /// its stylesheets, scripts and images inside of it, so it can be sent or archived as it is.
+    /// It's written to `destination` or, without one, to the vault. The vault is built first,
+    /// since the files come from there. Chapters that exclude `html` are left out. Anything from
+    /// another site (e.g. the icons) stays a link, and the search doesn't work without the rest of
+    /// the site.
+    pub fn export_html(
+        &mut self,
+        chapter: Option<&Path>,
+        destination: Option<&Path>,
+    ) -> Result<PathBuf> {
+        self.build()?;
+
+        let content = self.content_for(self.without_private(self.content()?)?, HTML_BACKEND)?;
+        let renderer = self.renderer(
+            &content,
+            &self.last_updated(&content)?,
+            &self.contributors(&content)?,
+        )?;
+
+        let (html, page) = match chapter {
+            Some(path) => {
+                let path = self.path.join(path);
+                let chapter = content
+                    .find_by_path(&path)
+                    .filter(|chapter| chapter.content.is_file())
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "{} isn't a chapter of the vault",
+                            self.relative(&path).display()
+                        )
+                    })?;
+                let html = renderer
+                    .render(chapter)
+                    .with_context(|| anyhow!("Failed to render the chapter {}", chapter.title))?;
+
+                (
+                    html,
+                    renderer::output_path(&chapter.content, self.src_dir())?,
+                )
+            }
+            None => (
+                renderer
+                    .render_all_chapters(&content)
+                    .with_context(|| anyhow!("Failed to render the chapters"))?,
+                PathBuf::from(STANDALONE_FILE),
+            ),
+        };
+
+        let page_dir = self
+            .build_dir()
+            .join(page.parent().unwrap_or(Path::new("")));
+        let html = self.inline(&html, &page_dir)?;
+        let destination = match (destination, chapter) {
+            (Some(destination), _) => destination.to_path_buf(),
+            (None, Some(_)) => self.path.join(
+                Path::new(page.file_name().unwrap_or_default()).with_extension(STANDALONE_FILE),
+            ),
+            (None, None) => self.path.join(STANDALONE_FILE),
+        };
+        fs::write(&destination, html)
+            .with_context(|| anyhow!("Failed to write {}", destination.display()))?;
+
+        Ok(destination)
+    }
+
+    // Every stylesheet, script, icon and image of the page found in the build dir goes inside of
+    // it. Only the tags are looked at, so text that looks like a tag is left alone.
+    fn inline(&self, html: &str, page_dir: &Path) -> Result<String> {
+        let mut inlined = String::with_capacity(html.len());
+        let mut rest = html;
+
+        while let Some(start) = rest.find('<') {
+            inlined.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            inlined.push_str(&self.inline_tag(&rest[..end], page_dir)?);
+            rest = &rest[end..];
+        }
+        inlined.push_str(rest);
+
+        Ok(inlined)
+    }
+
+    fn inline_tag(&self, tag: &str, page_dir: &Path) -> Result<String> {
+        let name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or_default();
+        let url = match name {
+            "link" => attribute(tag, "href"),
+            "script" | "img" => attribute(tag, "src"),
+            _ => None,
+        };
+        let Some((url, file)) = url.and_then(|url| Some((url, self.local_file(url, page_dir)?)))
+        else {
+            return Ok(tag.to_string());
+        };
+
+        let read = || {
+            fs::read(&file)
+                .with_context(|| anyhow!("Failed to read contents of {}", file.display()))
+        };
+        let rel = attribute(tag, "rel").unwrap_or_default();
+
+        Ok(match name {
+            "link" if rel == "stylesheet" => {
+                let css = String::from_utf8_lossy(&read()?).replace("</style", "<\\/style");
+                format!("<style>\n{css}\n</style>")
+            }
+            "link" if rel.split_whitespace().any(|rel| rel == "icon") => {
+                tag.replacen(url, &data_url(&file, &read()?), 1)
+            }
+            "link" => tag.to_string(),
+            // The closing tag is still there, right after
+            "script" => {
+                let js = String::from_utf8_lossy(&read()?).replace("</script", "<\\/script");
+                format!(
+                    "{}\n{js}\n",
+                    tag.replacen(&format!(" src=\"{url}\""), "", 1)
+                )
+            }
+            _ => tag.replacen(url, &data_url(&file, &read()?), 1),
+        })
+    }
+
+    // Urls starting with `/` start at the base path. Files outside of the build dir are left out.
+    fn local_file(&self, url: &str, page_dir: &Path) -> Option<PathBuf> {
+        let path = url.split(['#', '?']).next()?;
+        let file = match path.strip_prefix(&self.base_path) {
+            Some(absolute) if path.starts_with('/') && !path.starts_with("//") => {
+                self.build_dir().join(absolute.trim_start_matches('/'))
+            }
+            _ if markdown::is_relative_url(path) => page_dir.join(path),
+            _ => return None,
+        };
+
+        let build_dir = fs::canonicalize(self.build_dir()).ok()?;
+        fs::canonicalize(&file)
+            .ok()
+            .filter(|file| file.starts_with(&build_dir) && file.is_file())
+    }
+}
+
+// The value of the attribute, as the templates and the markdown write it (e.g. `src="a.png"`)
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {name}=\""))? + name.len() + 3;
+    let end = tag[start..].find('"')?;
+
+    Some(&tag[start..start + end])
+}
+
+fn data_url(path: &Path, data: &[u8]) -> String {
+    format!(
+        "data:{};base64,{}",
+        static_files::content_type(path),
+        STANDARD.encode(data)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_export_the_vault_as_a_single_html_file() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+        fs::create_dir_all(vault.src_dir().join("02_guide/images"))?;
+        fs::write(
+            vault.src_dir().join("01_intro.md"),
+            "# Intro\n\nHello there",
+        )?;
+        fs::write(vault.src_dir().join("02_guide/index.md"), "# Guide")?;
+        fs::write(
+            vault.src_dir().join("02_guide/01_setup.md"),
+            "# Setup\n\n![Kenobi](images/kenobi.png)\n\n![Logo](https://example.com/logo.png)",
+        )?;
+        fs::write(
+            vault.src_dir().join("03_playground.md"),
+            "---\nexclude_from: [html]\n---\n# Playground\n\nGeneral Kenobi",
+        )?;
+        fs::write(
+            vault.src_dir().join("02_guide/images/kenobi.png"),
+            [0x89, b'P', b'N', b'G'],
+        )?;
+
+        let path = vault.export_html(None, None)?;
+        let html = fs::read_to_string(&path)?;
+        assert_eq!(path, vault.path.join(STANDALONE_FILE));
+        assert!(html.contains("Hello there"));
+        assert!(!html.contains("General Kenobi"));
+        assert!(vault.build_dir().join("playground.html").exists());
+        assert!(html.contains("src=\"data:image/png;base64,iVBORw==\""));
+        assert!(html.contains("src=\"https://example.com/logo.png\""));
+        assert!(!html.contains("href=\"/main.css\""));
+        assert!(!html.contains("src=\"/index.js\""));
+        assert!(!html.contains("window.print()"));
+
+        let path = vault.export_html(Some(Path::new("src/02_guide/01_setup.md")), None)?;
+        let html = fs::read_to_string(&path)?;
+        assert_eq!(path, vault.path.join("setup.standalone.html"));
+        assert!(html.contains("src=\"data:image/png;base64,iVBORw==\""));
+        assert!(!html.contains("Hello there"));
+        assert!(vault
+            .export_html(Some(Path::new("src/missing.md")), None)
+            .is_err());
+
+        Ok(())
+    }
+}
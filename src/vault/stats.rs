@@ -0,0 +1,255 @@
+use super::manifest::Manifest;
+use super::Vault;
+use crate::{Chapter, FrontMatter, Item};
+use anyhow::{anyhow, Context, Result};
+use pulldown_cmark::{Event, Parser, Tag};
+use serde::Serialize;
+use std::fs;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+
+/// How much there is in a piece of markdown
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Counts {
+    /// Words outside of code blocks
+    pub words: usize,
+    pub images: usize,
+    pub links: usize,
+}
+
+impl Counts {
+    pub fn from_markdown(markdown: &str) -> Counts {
+        let mut counts = Counts::default();
+        // Neither code blocks nor alt texts are part of the prose
+        let mut skip_text = false;
+
+        for event in Parser::new(markdown) {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) => skip_text = true,
+                Event::End(Tag::CodeBlock(_)) | Event::End(Tag::Image(..)) => skip_text = false,
+                Event::Start(Tag::Image(..)) => {
+                    counts.images += 1;
+                    skip_text = true;
+                }
+                Event::Start(Tag::Link(..)) => counts.links += 1,
+                Event::Text(text) | Event::Code(text) if !skip_text => {
+                    counts.words += text
+                        .split_whitespace()
+                        .filter(|word| word.chars().any(char::is_alphanumeric))
+                        .count()
+                }
+                _ => {}
+            }
+        }
+
+        counts
+    }
+
+    /// How many minutes the words take to read, rounded up. Anything with words takes at least
+    /// a minute.
+    pub fn reading_time(&self, words_per_minute: usize) -> usize {
+        self.words.div_ceil(words_per_minute.max(1))
+    }
+
+    /// Counts a chapter's file, ignoring its front matter
+    pub fn from_disk<P>(path: P) -> Result<Counts>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let markdown = fs::read_to_string(path)
+            .with_context(|| anyhow!("Failed to read contents of {}", path.display()))?;
+        let (_, body) = FrontMatter::extract(&markdown)
+            .with_context(|| anyhow!("Invalid front matter in {}", path.display()))?;
+
+        Ok(Counts::from_markdown(body))
+    }
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Counts) {
+        self.words += other.words;
+        self.images += other.images;
+        self.links += other.links;
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChapterStats {
+    pub title: String,
+    /// The chapter's file, relative to the vault
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+/// The chapters that follow a section, up to the next one
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SectionStats {
+    pub title: String,
+    pub chapters: usize,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+/// How much the vault changed since the last build
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Growth {
+    pub chapters: i64,
+    pub words: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub chapters: Vec<ChapterStats>,
+    pub sections: Vec<SectionStats>,
+    pub total: Counts,
+    /// `None` if the vault was never built
+    pub growth: Option<Growth>,
+}
+
+impl Vault {
+    /// Counts the words, images and links of every chapter. Missing chapters are left to
+    /// [`Vault::check`].
+    pub fn stats(&self) -> Result<Stats> {
+        let content = self.content()?;
+        let mut chapters = Vec::new();
+        let mut sections: Vec<SectionStats> = Vec::new();
+        let mut total = Counts::default();
+
+        for item in content.summary.items.iter() {
+            let (mut section, top) = match item {
+                Item::Section(section) => (
+                    Some(SectionStats {
+                        title: section.title.clone(),
+                        chapters: 0,
+                        counts: Counts::default(),
+                    }),
+                    section.chapters.as_slice(),
+                ),
+                Item::Chapter(chapter) => (None, std::slice::from_ref(chapter)),
+            };
+
+            for chapter in top.iter().flat_map(|chapter| chapter.flatten()) {
+                let Some(stats) = self.chapter_stats(chapter)? else {
+                    continue;
+                };
+
+                if let Some(section) = section.as_mut() {
+                    section.chapters += 1;
+                    section.counts += stats.counts;
+                }
+
+                total += stats.counts;
+                chapters.push(stats);
+            }
+
+            sections.extend(section);
+        }
+
+        let growth = Manifest::from_disk(self.build_dir())?.map(|manifest| Growth {
+            chapters: chapters.len() as i64 - manifest.chapters.len() as i64,
+            words: total.words as i64 - manifest.words() as i64,
+        });
+
+        Ok(Stats {
+            chapters,
+            sections,
+            total,
+            growth,
+        })
+    }
+
+    fn chapter_stats(&self, chapter: Chapter) -> Result<Option<ChapterStats>> {
+        if !chapter.content.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(ChapterStats {
+            counts: Counts::from_disk(&chapter.content)?,
+            path: self.relative(&chapter.content),
+            title: chapter.title,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_count_the_markdown() {
+        let markdown = r#"
+# Hello there
+
+General `Kenobi`, you are a [bold](https://example.com) one.
+
+![diagram](diagram.png)
+
+```rust
+let ignored = "code";
+```
+"#;
+        let expected = Counts {
+            words: 9,
+            images: 1,
+            links: 1,
+        };
+
+        assert_eq!(Counts::from_markdown(markdown), expected);
+        assert_eq!(expected.reading_time(200), 1);
+        assert_eq!(expected.reading_time(4), 3);
+        assert_eq!(Counts::default().reading_time(200), 0);
+    }
+
+    #[test]
+    fn it_should_collect_the_stats_of_the_vault() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "[Intro](./intro.md)\n# Part 1\n- [Chapter 1](./chapter1.md)\n    - [Chapter 1.1](./chapter1.1.md)",
+        )?;
+        fs::write(vault.src_dir().join("intro.md"), "Hello there")?;
+        fs::write(
+            vault.src_dir().join("chapter1.md"),
+            "---\nowner: Obi-Wan\n---\nOne two three",
+        )?;
+        fs::write(vault.src_dir().join("chapter1.1.md"), "![four](four.png)")?;
+
+        let stats = vault.stats()?;
+
+        assert_eq!(stats.chapters.len(), 3);
+        assert_eq!(
+            stats.sections,
+            vec![SectionStats {
+                title: "Part 1".to_string(),
+                chapters: 2,
+                counts: Counts {
+                    words: 3,
+                    images: 1,
+                    links: 0
+                }
+            }]
+        );
+        assert_eq!(stats.total.words, 5);
+        assert_eq!(stats.growth, None);
+
+        vault.build()?;
+        fs::write(vault.src_dir().join("intro.md"), "Hello")?;
+
+        assert_eq!(
+            vault.stats()?.growth,
+            Some(Growth {
+                chapters: 0,
+                words: -1
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use super::Vault;
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use serde::Serialize;
+use std::fs;
+
+/// Holds the themes of the vault, next to the config. Each one is a dir named after it
+pub const THEMES_DIR: &str = "themes";
+/// The stylesheet every theme has. It styles the pages when the `<html>` has the class of the
+/// theme, like the ones built in do (e.g. `.nord { --background: #2e3440; }`)
+pub const THEME_CSS: &str = "theme.css";
+/// A script a theme may have, loaded by every page after the default one
+pub const THEME_JS: &str = "theme.js";
+
+/// A theme found in the themes dir
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Does it have a script of its own?
+    pub script: bool,
+}
+
+impl Vault {
+    /// The themes in the themes dir, by name. Dirs without a stylesheet aren't themes, so they're
+    /// left out.
+    pub fn themes(&self) -> Result<Vec<Theme>> {
+        let dir = self.themes_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut themes = Vec::new();
+        for entry in dir
+            .read_dir()
+            .with_context(|| anyhow!("Failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if !path.is_dir() || !path.join(THEME_CSS).is_file() {
+                continue;
+            }
+
+            themes.push(Theme {
+                name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                script: path.join(THEME_JS).is_file(),
+            });
+        }
+        themes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(themes)
+    }
+
+    /// Copies the themes to the build dir, along with anything their stylesheets use (e.g. fonts
+    /// or images)
+    pub(super) fn write_themes(&self, themes: &[Theme]) -> Result<()> {
+        let appearance = &self.config.appearance;
+        if !appearance.themes.contains(&appearance.default_theme)
+            && !themes
+                .iter()
+                .any(|theme| theme.name == appearance.default_theme)
+        {
+            warn!(
+                "The default theme {} is neither built in nor in {THEMES_DIR}",
+                appearance.default_theme
+            );
+        }
+
+        let build_dir = self.build_dir().join(THEMES_DIR);
+        if build_dir.exists() {
+            fs::remove_dir_all(&build_dir)
+                .with_context(|| anyhow!("Failed to remove {}", build_dir.display()))?;
+        }
+
+        for theme in themes {
+            let dir = self.themes_dir().join(&theme.name);
+
+            util::copy_dir(&dir, build_dir.join(&theme.name), |_| false, |_| {})
+                .with_context(|| anyhow!("Failed to copy {}", dir.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_build_with_the_themes_of_the_vault() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.config.appearance.default_theme = String::from("nord");
+        vault.init()?;
+
+        let nord = vault.themes_dir().join("nord");
+        fs::create_dir_all(nord.join("fonts"))?;
+        fs::write(nord.join(THEME_CSS), ".nord { --background: #2e3440; }")?;
+        fs::write(nord.join(THEME_JS), "console.log('nord')")?;
+        fs::write(nord.join("fonts/inter.woff2"), "")?;
+        fs::create_dir_all(vault.themes_dir().join("unfinished"))?;
+
+        assert_eq!(
+            vault.themes()?,
+            vec![Theme {
+                name: String::from("nord"),
+                script: true
+            }]
+        );
+
+        vault.build()?;
+        let page = fs::read_to_string(vault.build_dir().join("sitemap.html"))?;
+        assert!(page.contains("<html lang=\"en\" class=\"nord\" data-theme=\"nord\">"));
+        assert!(page.contains("<link href=\"/themes/nord/theme.css\" rel=\"stylesheet\" />"));
+        assert!(page.contains("<script src=\"/themes/nord/theme.js\"></script>"));
+        assert!(page.contains("<li class=\"theme-popup__item\">nord</li>"));
+        assert!(page
+            .contains("<li class=\"theme-popup__item\" data-light=\"gruvbox-light\">gruvbox</li>"));
+        assert!(vault
+            .build_dir()
+            .join("themes/nord/fonts/inter.woff2")
+            .exists());
+        assert!(!vault.build_dir().join("themes/unfinished").exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use super::{Content, Vault};
+use crate::renderer;
+use crate::{Chapter, Item};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Where the table of contents is written, inside the build dir, for anything that wants to show
+/// the navigation of the vault somewhere else
+pub const TOC_FILE: &str = "toc.json";
+
+/// Where the pages are listed in the order they're meant to be read, inside the build dir, for
+/// anything that goes through the whole vault (e.g. text-to-speech)
+pub const READING_ORDER_FILE: &str = "reading-order.json";
+
+/// A page of the vault, as it ends up in [`READING_ORDER_FILE`]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ReadingOrderPage {
+    pub title: String,
+    /// The page, relative to the build dir
+    pub url: String,
+}
+
+/// The summary of the vault, as it ends up in [`TOC_FILE`]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TocItem {
+    Chapter(TocChapter),
+    Section {
+        title: String,
+        chapters: Vec<TocChapter>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TocChapter {
+    pub title: String,
+    pub number: String,
+    /// The page of the chapter, relative to the build dir, or where it points to if it's outside of
+    /// the vault. Drafts have none.
+    pub url: Option<String>,
+    /// How deep it is in the summary. Chapters at the top, or right inside a section, are at 1
+    pub depth: usize,
+    pub external: bool,
+    /// Chapters and sections under it, like in the summary
+    pub subchapters: Vec<TocItem>,
+}
+
+impl Vault {
+    pub(super) fn write_toc(&self, content: &Content) -> Result<()> {
+        let toc = self.toc(content)?;
+        let path = self.build_dir().join(TOC_FILE);
+
+        fs::write(&path, serde_json::to_vec(&toc)?)
+            .with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+
+    /// Writes the pages that were built, in the order of the summary. Drafts and external
+    /// chapters aren't pages, and a page split into parts is listed once.
+    pub(super) fn write_reading_order(&self, content: &Content) -> Result<()> {
+        let pages = content
+            .all_chapters()
+            .into_iter()
+            .filter(|chapter| chapter.content.exists())
+            .map(|chapter| {
+                Ok(ReadingOrderPage {
+                    url: renderer::output_path(&chapter.content, self.src_dir())?
+                        .to_string_lossy()
+                        .replace('\\', "/"),
+                    title: chapter.title,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let path = self.build_dir().join(READING_ORDER_FILE);
+
+        fs::write(&path, serde_json::to_vec(&pages)?)
+            .with_context(|| anyhow!("Failed to write {}", path.display()))
+    }
+
+    // The summary with the pages of the chapters, ready to be turned into navigation
+    fn toc(&self, content: &Content) -> Result<Vec<TocItem>> {
+        self.toc_items(&content.summary.items, 1)
+    }
+
+    // Chapters right inside a section are as deep as the section
+    fn toc_items(&self, items: &[Item], depth: usize) -> Result<Vec<TocItem>> {
+        items
+            .iter()
+            .map(|item| match item {
+                Item::Chapter(chapter) => Ok(TocItem::Chapter(self.toc_chapter(chapter, depth)?)),
+                Item::Section(section) => Ok(TocItem::Section {
+                    title: section.title.clone(),
+                    chapters: section
+                        .chapters
+                        .iter()
+                        .map(|chapter| self.toc_chapter(chapter, depth))
+                        .collect::<Result<_>>()?,
+                }),
+            })
+            .collect()
+    }
+
+    fn toc_chapter(&self, chapter: &Chapter, depth: usize) -> Result<TocChapter> {
+        let url = match (&chapter.url, chapter.draft) {
+            (Some(url), _) => Some(url.clone()),
+            (None, true) => None,
+            (None, false) => {
+                let page = renderer::output_path(&chapter.content, self.src_dir())?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+
+                match &chapter.anchor {
+                    Some(anchor) => Some(format!("{page}#{anchor}")),
+                    None => Some(page),
+                }
+            }
+        };
+
+        Ok(TocChapter {
+            title: chapter.title.clone(),
+            number: chapter.number.clone(),
+            url,
+            depth,
+            external: chapter.url.is_some(),
+            subchapters: self.toc_items(&chapter.subchapters, depth + 1)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_write_the_table_of_contents() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Intro](./intro.md)\n    - [Install](./intro.md#install)\n- [Future]()\n\n# Reference\n\n- [Rust Book](https://doc.rust-lang.org/book)",
+        )?;
+        fs::write(vault.src_dir().join("intro.md"), "# Install")?;
+        vault.build()?;
+
+        let toc: Vec<TocItem> =
+            serde_json::from_slice(&fs::read(vault.build_dir().join(TOC_FILE))?)?;
+        let TocItem::Chapter(intro) = &toc[0] else {
+            panic!("Expected a chapter, got {:?}", toc[0]);
+        };
+        let TocItem::Chapter(install) = &intro.subchapters[0] else {
+            panic!("Expected a chapter, got {:?}", intro.subchapters[0]);
+        };
+
+        assert_eq!(toc.len(), 3);
+        assert_eq!(intro.url.as_deref(), Some("intro.html"));
+        assert_eq!(install.url.as_deref(), Some("intro.html#install"));
+        assert_eq!(install.depth, 2);
+        assert!(matches!(&toc[1], TocItem::Chapter(future) if future.url.is_none()));
+        assert!(matches!(&toc[2], TocItem::Section { title, chapters }
+            if title == "Reference" && chapters[0].external));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_write_the_reading_order() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(
+            vault.src_dir().join("summary.md"),
+            "- [Intro](./intro.md)\n    - [Install](./intro.md#install)\n- [Future]()\n- [Setup](./guide/setup.md)\n- [Rust Book](https://doc.rust-lang.org/book)",
+        )?;
+        fs::write(vault.src_dir().join("intro.md"), "# Install")?;
+        fs::create_dir(vault.src_dir().join("guide"))?;
+        fs::write(vault.src_dir().join("guide/setup.md"), "# Setup")?;
+        vault.build()?;
+
+        let pages: Vec<ReadingOrderPage> =
+            serde_json::from_slice(&fs::read(vault.build_dir().join(READING_ORDER_FILE))?)?;
+        assert_eq!(
+            pages,
+            vec![
+                ReadingOrderPage {
+                    title: String::from("Intro"),
+                    url: String::from("intro.html")
+                },
+                ReadingOrderPage {
+                    title: String::from("Setup"),
+                    url: String::from("guide/setup.html")
+                },
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use super::{BuildReport, Vault};
+use std::ops::ControlFlow;
+use std::thread;
+use std::time::Duration;
+
+/// What happens while a vault is being watched
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// Something in the source dir or the config changed
+    ChangeDetected,
+    RebuildStarted,
+    RebuildFinished(BuildReport),
+    /// Checking for changes or rebuilding failed. The vault is still watched.
+    Error(anyhow::Error),
+}
+
+impl Vault {
+    /// Polls the vault every `interval` and rebuilds it whenever something changes, telling
+    /// `callback` about every step. The config is read again on each rebuild, since it may have
+    /// changed too. Watching goes on until `callback` returns [`ControlFlow::Break`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use cahlter::vault::Vault;
+    /// use std::ops::ControlFlow;
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut vault = Vault::from_disk("docs")?;
+    ///
+    /// vault.watch(Duration::from_millis(500), |event| {
+    ///     println!("{event:?}");
+    ///     ControlFlow::Continue(())
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch<F>(&mut self, interval: Duration, mut callback: F)
+    where
+        F: FnMut(WatchEvent) -> ControlFlow<()>,
+    {
+        let mut last_build = self.last_modified().ok();
+
+        loop {
+            thread::sleep(interval);
+
+            let modified = match self.last_modified() {
+                Ok(modified) => modified,
+                Err(e) => match callback(WatchEvent::Error(e)) {
+                    ControlFlow::Continue(()) => continue,
+                    ControlFlow::Break(()) => return,
+                },
+            };
+
+            if last_build.is_some_and(|last_build| modified <= last_build) {
+                continue;
+            }
+
+            last_build = Some(modified);
+
+            let events = [WatchEvent::ChangeDetected, WatchEvent::RebuildStarted];
+            for event in events {
+                if callback(event).is_break() {
+                    return;
+                }
+            }
+
+            let event = match self.rebuild() {
+                Ok(report) => WatchEvent::RebuildFinished(report),
+                Err(e) => WatchEvent::Error(e),
+            };
+
+            if callback(event).is_break() {
+                return;
+            }
+        }
+    }
+
+    /// Reads the vault from disk again, keeping how it's being built and served (e.g. the base
+    /// path and the profile), and builds it
+    pub fn rebuild(&mut self) -> anyhow::Result<BuildReport> {
+        let mut rebuilt = Vault::from_disk(&self.path)?;
+        rebuilt.live_reload = self.live_reload;
+        rebuilt.base_path = self.base_path.clone();
+        rebuilt.profile = self.profile;
+        rebuilt.with_solutions = self.with_solutions;
+        rebuilt.no_cache = self.no_cache;
+        rebuilt.audience = self.audience.clone();
+        rebuilt.content_cache = self.content_cache.take();
+
+        let report = rebuilt.build()?;
+        *self = rebuilt;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_should_rebuild_when_the_vault_changes() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempdir()?;
+        let mut vault = Vault::new(temp_dir.path());
+        vault.init()?;
+
+        fs::write(vault.src_dir().join("chapter1.md"), "# Hello there")?;
+        vault.build()?;
+
+        let chapter = vault.src_dir().join("chapter1.md");
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            fs::write(chapter, "# General Kenobi")
+        });
+
+        let mut events = Vec::new();
+        vault.watch(Duration::from_millis(10), |event| {
+            let finished = matches!(event, WatchEvent::RebuildFinished(_));
+            events.push(event);
+
+            match finished {
+                true => ControlFlow::Break(()),
+                false => ControlFlow::Continue(()),
+            }
+        });
+        writer.join().unwrap()?;
+
+        assert!(matches!(
+            events.as_slice(),
+            [
+                WatchEvent::ChangeDetected,
+                WatchEvent::RebuildStarted,
+                WatchEvent::RebuildFinished(BuildReport { chapters: 1, .. })
+            ]
+        ));
+        assert!(fs::read_to_string(vault.build_dir().join("chapter1.html"))?.contains("Kenobi"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+//! Lets a git host tell a served vault that its repository changed. With
+//! `cahlter serve --webhook-secret <secret>`, POST requests to [`WEBHOOK_ENDPOINT`] pull the
+//! repository (with `--git-pull`) and rebuild the vault.
+//!
+//! Requests have to prove they know the secret, either by signing the body like GitHub and Gitea
+//! do (an HMAC-SHA256 in `X-Hub-Signature-256`) or by sending the secret itself in one of the
+//! [`TOKEN_HEADERS`], like GitLab does.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+/// Path, relative to where a vault is served from, of the endpoint that triggers the rebuilds
+pub const WEBHOOK_ENDPOINT: &str = "/__webhook";
+
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+/// Headers that may carry the secret as it is
+pub const TOKEN_HEADERS: [&str; 2] = ["X-Gitlab-Token", "X-Webhook-Secret"];
+
+/// Does the request know the secret? `signature` is the value of the [`SIGNATURE_HEADER`] and
+/// `token` the value of any of the [`TOKEN_HEADERS`].
+pub fn is_authorized(
+    secret: &str,
+    signature: Option<&str>,
+    token: Option<&str>,
+    body: &[u8],
+) -> bool {
+    if secret.is_empty() {
+        return false;
+    }
+
+    match (signature, token) {
+        (Some(signature), _) => signature
+            .strip_prefix("sha256=")
+            .and_then(|hmac| hex::decode(hmac).ok())
+            .is_some_and(|hmac| mac(secret, body).verify_slice(&hmac).is_ok()),
+        // Comparing byte by byte would tell how much of a guess was right by how long it took
+        (None, Some(token)) => token.as_bytes().ct_eq(secret.as_bytes()).into(),
+        (None, None) => false,
+    }
+}
+
+/// The signature of the body, as the [`SIGNATURE_HEADER`] carries it (e.g. `sha256=5bdc...`)
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    format!(
+        "sha256={}",
+        hex::encode(mac(secret, body).finalize().into_bytes())
+    )
+}
+
+fn mac(secret: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body);
+
+    mac
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_sign_the_body() {
+        // From the test cases of RFC 4231
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn it_should_only_authorize_requests_that_know_the_secret() {
+        let body = b"{\"ref\": \"refs/heads/main\"}";
+        let signature = sign("hello there", body);
+
+        assert!(is_authorized("hello there", Some(&signature), None, body));
+        assert!(is_authorized(
+            "hello there",
+            None,
+            Some("hello there"),
+            body
+        ));
+        assert!(!is_authorized("hello there", Some(&signature), None, b"{}"));
+        assert!(!is_authorized("hello there", Some("sha256=zz"), None, body));
+        assert!(!is_authorized(
+            "hello there",
+            None,
+            Some("general kenobi"),
+            body
+        ));
+        assert!(!is_authorized("hello there", None, None, body));
+        assert!(!is_authorized("", None, Some(""), body));
+    }
+}
@@ -1,8 +1,54 @@
-function changeColorscheme(colorscheme) {
+// The theme readers picked, or else the default one
+function currentColorscheme() {
+    return localStorage.getItem("colorscheme") || document.querySelector("html").dataset.theme;
+}
+
+// Light or dark, as readers chose it or, if they never did, as their system prefers
+function currentScheme() {
+    let prefersLight = window.matchMedia("(prefers-color-scheme: light)").matches;
+
+    return localStorage.getItem("scheme") || (prefersLight ? "light" : "dark");
+}
+
+// Themes are dark, so the light scheme only changes those with a light variant
+function applyColorscheme() {
     let html = document.querySelector("html");
+    let colorscheme = currentColorscheme();
+    let item = [...document.querySelectorAll(".theme-popup__item")].find(
+        (item) => item.textContent === colorscheme,
+    );
+    let light = item?.dataset.light;
+
+    html.className = currentScheme() === "light" && light ? light : colorscheme;
+}
 
+function changeColorscheme(colorscheme) {
     localStorage.setItem("colorscheme", colorscheme);
-    html.className = colorscheme;
+    applyColorscheme();
+}
+
+function toggleScheme() {
+    localStorage.setItem("scheme", currentScheme() === "light" ? "dark" : "light");
+    applyColorscheme();
+}
+
+function setupSchemeButton() {
+    let button = document.querySelector(".scheme-button");
+
+    // Until readers choose, the scheme follows the system, even while the page is open
+    window.matchMedia("(prefers-color-scheme: light)").addEventListener("change", applyColorscheme);
+
+    if (!button) {
+        return;
+    }
+
+    button.addEventListener("click", toggleScheme);
+    button.addEventListener("keydown", (event) => {
+        if (event.key === "Enter" || event.key === " ") {
+            event.preventDefault();
+            toggleScheme();
+        }
+    });
 }
 
 function setupThemeButton() {
@@ -35,6 +81,13 @@ function setupThemeButton() {
     }
 
     button.addEventListener("click", handleButtonClick);
+    // It's an icon, so it doesn't answer to the keyboard like a real button would
+    button.addEventListener("keydown", (event) => {
+        if (event.key === "Enter" || event.key === " ") {
+            event.preventDefault();
+            handleButtonClick();
+        }
+    });
     document.addEventListener("click", handleDocumentClick);
     window.addEventListener("resize", () => (popup.style.display = "none"));
 }
@@ -50,9 +103,322 @@ function highlightSummaryitem() {
     }
 }
 
-if (localStorage.getItem("colorscheme")) {
-    changeColorscheme(localStorage.getItem("colorscheme"));
+// Remember which sections the reader collapsed, so they stay that way from page to page
+function setupSectionGroups() {
+    let groups = document.querySelectorAll(".table-of-contents__group");
+    let collapsed = JSON.parse(localStorage.getItem("collapsedSections") || "[]");
+
+    for (let group of groups) {
+        let title = group.dataset.section;
+        let active = group.querySelector(`a[href="${window.location.pathname}"]`);
+
+        if (collapsed.includes(title) && !active) {
+            group.open = false;
+        }
+
+        group.addEventListener("toggle", () => {
+            collapsed = collapsed.filter((other) => other !== title);
+
+            if (!group.open) {
+                collapsed.push(title);
+            }
+
+            localStorage.setItem("collapsedSections", JSON.stringify(collapsed));
+        });
+    }
+}
+
+// The index is only fetched the first time the reader types something
+function setupSearch() {
+    let search = document.querySelector(".search");
+
+    if (!search) {
+        return;
+    }
+
+    let input = search.querySelector("input");
+    let results = search.querySelector(".search__results");
+    let basePath = search.dataset.basePath;
+    let index = null;
+
+    async function loadIndex() {
+        if (!index) {
+            let response = await fetch(`${basePath}/search-index.json`);
+            index = (await response.json()).documents;
+        }
+
+        return index;
+    }
+
+    // Every word of the query has to start one of the words of the chapter
+    function find(documents, query) {
+        let terms = query.toLowerCase().split(/[^\p{L}\p{N}]+/u).filter((term) => term);
+
+        if (terms.length === 0) {
+            return [];
+        }
+
+        return documents
+            .map((document) => {
+                let score = 0;
+
+                for (let term of terms) {
+                    let matches = document.words.filter((word) => word.startsWith(term)).length;
+
+                    if (matches === 0) {
+                        return null;
+                    }
+
+                    score += matches + (document.title.toLowerCase().includes(term) ? 10 : 0);
+                }
+
+                return { document, score };
+            })
+            .filter((result) => result)
+            .sort((a, b) => b.score - a.score)
+            .slice(0, 10)
+            .map((result) => result.document);
+    }
+
+    function show(documents) {
+        results.replaceChildren();
+
+        for (let document_ of documents) {
+            let item = document.createElement("li");
+            let link = document.createElement("a");
+            let title = document.createElement("p");
+            let excerpt = document.createElement("p");
+
+            item.className = "search__result";
+            link.href = `${basePath}/${document_.url}`;
+            title.className = "search__title";
+            title.textContent = document_.title;
+            excerpt.className = "search__excerpt";
+            excerpt.textContent = document_.excerpt;
+
+            link.append(title, excerpt);
+            item.append(link);
+            results.append(item);
+        }
+
+        if (documents.length === 0 && input.value.trim()) {
+            let item = document.createElement("li");
+
+            item.className = "search__result";
+            item.textContent = "Nothing found";
+            results.append(item);
+        }
+
+        results.classList.toggle("search__results--open", input.value.trim() !== "");
+    }
+
+    input.addEventListener("input", async () => show(find(await loadIndex(), input.value)));
+    input.addEventListener("keydown", (event) => {
+        if (event.key === "Escape") {
+            input.value = "";
+            show([]);
+        } else if (event.key === "Enter") {
+            let first = results.querySelector("a");
+
+            if (first) {
+                window.location.href = first.href;
+            }
+        }
+    });
+    document.addEventListener("click", (event) => {
+        if (!search.contains(event.target)) {
+            results.classList.remove("search__results--open");
+        }
+    });
+}
+
+function setupCopyButtons() {
+    let buttons = document.querySelectorAll(".code-block__copy");
+
+    for (let button of buttons) {
+        button.addEventListener("click", async () => {
+            let code = button.parentElement.querySelector("code");
+            let icon = button.querySelector("i");
+
+            await navigator.clipboard.writeText(code.innerText);
+            icon.className = "ti ti-check";
+            setTimeout(() => (icon.className = "ti ti-copy"), 2000);
+        });
+    }
+}
+
+// Shows a short message at the bottom of the screen, which goes away by itself
+function showToast(message) {
+    let toast = document.createElement("div");
+
+    toast.className = "toast";
+    toast.setAttribute("role", "status");
+    toast.textContent = message;
+    document.body.appendChild(toast);
+    setTimeout(() => toast.remove(), 2000);
+}
+
+// Clicking the permalink of a heading copies the url of its section, besides going there
+function setupPermalinks() {
+    let permalinks = document.querySelectorAll(".heading__permalink");
+
+    for (let permalink of permalinks) {
+        permalink.addEventListener("click", async (event) => {
+            // Without a secure context there is no clipboard, so it's only a link
+            if (!navigator.clipboard) {
+                return;
+            }
+
+            event.preventDefault();
+            history.replaceState(null, "", permalink.hash);
+            permalink.parentElement.scrollIntoView();
+
+            try {
+                await navigator.clipboard.writeText(permalink.href);
+                showToast("Link copied");
+            } catch {
+                showToast("Couldn't copy the link");
+            }
+        });
+    }
+}
+
+// The right choices are in the page, in each question, so quizzes are checked without a server
+function setupQuizzes() {
+    let quizzes = document.querySelectorAll(".quiz");
+
+    for (let quiz of quizzes) {
+        quiz.addEventListener("submit", (event) => {
+            let questions = quiz.querySelectorAll(".quiz__question");
+            let right = 0;
+
+            event.preventDefault();
+
+            for (let question of questions) {
+                let answer = question.dataset.answer.split(",");
+                let inputs = question.querySelectorAll("input");
+                let picked = [...inputs]
+                    .filter((input) => input.checked)
+                    .map((input) => input.value);
+                let correct =
+                    picked.length === answer.length &&
+                    picked.every((choice) => answer.includes(choice));
+
+                for (let input of inputs) {
+                    let choice = input.parentElement;
+
+                    choice.classList.toggle("quiz__choice--right", answer.includes(input.value));
+                }
+
+                question.classList.toggle("quiz__question--right", correct);
+                question.classList.toggle("quiz__question--wrong", !correct);
+                question.querySelector(".quiz__explanation")?.removeAttribute("hidden");
+
+                if (correct) {
+                    right += 1;
+                }
+            }
+
+            let result = quiz.querySelector(".quiz__result");
+
+            result.textContent = `${right} of ${questions.length} right`;
+        });
+    }
+}
+
+// Highlights the section of the page that's being read in its table of contents
+function setupPageToc() {
+    let links = document.querySelectorAll(".page-toc__link");
+    let headings = [...links]
+        .map((link) => document.getElementById(decodeURIComponent(link.hash.slice(1))))
+        .filter((heading) => heading);
+
+    if (headings.length === 0) {
+        return;
+    }
+
+    function highlight() {
+        // The last heading that went past the top of the screen, or the first one
+        let current = headings.filter((heading) => heading.getBoundingClientRect().top < 100).pop();
+        let hash = `#${(current || headings[0]).id}`;
+
+        for (let link of links) {
+            link.classList.toggle("page-toc__link--active", decodeURIComponent(link.hash) === hash);
+        }
+    }
+
+    document.addEventListener("scroll", highlight, { passive: true });
+    highlight();
+}
+
+// Only the keys listed in the help do something, since it only lists the features the page has
+function setupShortcuts() {
+    let help = document.querySelector(".shortcuts");
+
+    if (!help) {
+        return;
+    }
+
+    // Moves from the section being read to the one before or after it
+    function jumpToSection(offset) {
+        let headings = [...document.querySelectorAll(".page-toc__link")]
+            .map((link) => document.getElementById(decodeURIComponent(link.hash.slice(1))))
+            .filter((heading) => heading);
+        let current = headings.filter((heading) => heading.getBoundingClientRect().top < 100).length - 1;
+        let target = headings[Math.min(Math.max(current + offset, 0), headings.length - 1)];
+
+        target?.scrollIntoView();
+    }
+
+    let actions = {
+        help: () => (help.open ? help.close() : help.showModal()),
+        search: () => document.querySelector(".search input")?.focus(),
+        "next-section": () => jumpToSection(1),
+        "previous-section": () => jumpToSection(-1),
+        sitemap: () => (window.location.href = help.dataset.sitemap),
+        theme: () => {
+            let themes = [...document.querySelectorAll(".theme-popup__item")].map(
+                (item) => item.textContent,
+            );
+            let current = themes.indexOf(currentColorscheme());
+
+            changeColorscheme(themes[(current + 1) % themes.length]);
+        },
+        scheme: toggleScheme,
+        language: () => {
+            let switcher = document.querySelector(".language-switcher");
+
+            switcher.open = true;
+            switcher.querySelector(".language-switcher__item")?.focus();
+        },
+    };
+    let keys = {};
+
+    for (let row of help.querySelectorAll("[data-action]")) {
+        keys[row.dataset.key] = actions[row.dataset.action];
+    }
+
+    document.addEventListener("keydown", (event) => {
+        let typing = event.target.closest("input, textarea, select, [contenteditable]");
+        let action = keys[event.key];
+
+        if (typing || event.ctrlKey || event.metaKey || event.altKey || !action) {
+            return;
+        }
+
+        event.preventDefault();
+        action();
+    });
 }
 
+applyColorscheme();
 setupThemeButton();
+setupSchemeButton();
+setupCopyButtons();
+setupPermalinks();
+setupQuizzes();
+setupPageToc();
+setupShortcuts();
+setupSectionGroups();
+setupSearch();
 window.addEventListener("load", highlightSummaryitem());
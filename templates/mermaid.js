@@ -0,0 +1,14 @@
+// Written as mermaid.min.js when the vault uses the default files. It loads mermaid from its CDN
+// and draws the diagrams. A mermaid.min.js in the static dir takes its place, for sites that
+// host mermaid themselves.
+(function () {
+    let script = document.createElement("script");
+
+    script.src = "https://cdn.jsdelivr.net/npm/mermaid@10.9.1/dist/mermaid.min.js";
+    script.crossOrigin = "anonymous";
+    script.onload = () => {
+        mermaid.initialize({ startOnLoad: false });
+        mermaid.run({ querySelector: ".mermaid" });
+    };
+    document.head.appendChild(script);
+})();